@@ -1,23 +1,48 @@
-//! BAM/SAM alignment batch reader backed by noodles.
+//! BAM/SAM/CRAM alignment batch reader and writer backed by noodles.
 //!
-//! Provides a stateful reader that owns a noodles BAM or SAM reader,
-//! reads the header on open, and returns batches of parsed records in a
-//! struct-of-arrays layout. No FFI dependencies — both the napi and wasm
-//! adapters wrap this with their respective type conversions.
+//! Provides a stateful reader that owns a noodles BAM, SAM or CRAM
+//! reader, reads the header on open, and returns batches of parsed
+//! records in a struct-of-arrays layout. CRAM slices are decoded against
+//! a local reference FASTA. BAM inputs opened with a BAI or CSI index
+//! can be restricted to a genomic region. The writer accepts the same
+//! layout and serializes it back to BAM or SAM. No FFI dependencies —
+//! both the napi and wasm adapters wrap this with their respective type
+//! conversions.
 
 use std::{
     fs::File,
-    io::{BufReader, Cursor, Read, Seek, Write},
+    io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
 };
 
-use noodles_bam as bam;
+use noodles_bam::{self as bam, bai};
 use noodles_bgzf as bgzf;
+use noodles_core::{region::Interval, Position, Region};
+use noodles_cram as cram;
+use noodles_csi::{
+    self as csi, binning_index::index::reference_sequence::bin::Chunk, BinningIndex,
+};
+use noodles_fasta::{self as fasta, fai, repository::adapters::IndexedReader};
 use noodles_sam::{
     self as sam,
-    alignment::{record::cigar::op::Kind, record_buf::Cigar},
+    alignment::{
+        io::Write as _,
+        record::{
+            cigar::{op::Kind, Op},
+            data::field::Tag,
+            Flags, MappingQuality,
+        },
+        record_buf::{
+            data::field::{value::Array, Value},
+            Cigar,
+        },
+    },
 };
 
-use crate::EngineError;
+use crate::{
+    codec::{self, ByteCounter, Counted, CountedBytes},
+    field_at, validate_offsets, BytesConsumed, EngineError, ParseError, ParseErrorKind,
+    ReaderCheckpoint, RecordFormat,
+};
 
 /// Information about a reference sequence from the SAM/BAM header.
 pub struct ReferenceSequenceInfo {
@@ -45,125 +70,646 @@ pub struct AlignmentBatch {
     pub rname_data: Vec<u8>,
     pub rname_offsets: Vec<u32>,
 
+    pub rnext_data: Vec<u8>,
+    pub rnext_offsets: Vec<u32>,
+
     pub flags: Vec<u16>,
     pub positions: Vec<i32>,
     pub mapping_qualities: Vec<u8>,
+    pub next_positions: Vec<i32>,
+    pub template_lengths: Vec<i32>,
+
+    /// One typed column per tag requested via `AuxTagOptions::tags`.
+    pub tags: Vec<AuxTagColumn>,
+
+    /// Every tag of every record as tab-separated SAM text
+    /// (`NM:i:1\tRG:Z:grp1`). Empty (no offsets) unless
+    /// `AuxTagOptions::raw` is set.
+    pub raw_tag_data: Vec<u8>,
+    pub raw_tag_offsets: Vec<u32>,
+}
+
+/// Value type of an extracted auxiliary tag column.
+///
+/// Fixed by the tag's SAM type when it is requested, so a column keeps
+/// its type from batch to batch whatever values the records carry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuxTagKind {
+    /// Integer values (SAM types `i`, `c`, `C`, `s`, `S`, `I`).
+    Int,
+    /// Floating-point values (SAM type `f`).
+    Float,
+    /// Characters, strings and hex strings (SAM types `A`, `Z`, `H`).
+    String,
+    /// Integer arrays (SAM type `B` with subtype `c`, `C`, `s`, `S`, `i`
+    /// or `I`).
+    IntArray,
+    /// Floating-point arrays (SAM type `B` with subtype `f`).
+    FloatArray,
+}
+
+impl AuxTagKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::String => "string",
+            Self::IntArray => "int_array",
+            Self::FloatArray => "float_array",
+        }
+    }
+
+    /// Parse a SAM type code: `i`, `f`, `Z` and the other scalar codes,
+    /// or `B:` followed by an array subtype.
+    fn from_sam_type(code: &[u8]) -> Option<Self> {
+        match code {
+            b"c" | b"C" | b"s" | b"S" | b"i" | b"I" => Some(Self::Int),
+            b"f" => Some(Self::Float),
+            b"A" | b"Z" | b"H" => Some(Self::String),
+            [b'B', b':', b'c' | b'C' | b's' | b'S' | b'i' | b'I'] => Some(Self::IntArray),
+            b"B:f" => Some(Self::FloatArray),
+            _ => None,
+        }
+    }
+
+    /// The type of a tag predefined by the SAM optional fields
+    /// specification.
+    fn predefined(tag: [u8; 2]) -> Option<Self> {
+        match &tag {
+            b"AM" | b"AS" | b"CM" | b"CP" | b"FI" | b"H0" | b"H1" | b"H2" | b"HI" | b"IH"
+            | b"MN" | b"MQ" | b"NH" | b"NM" | b"OP" | b"PQ" | b"SM" | b"TC" | b"UQ" => {
+                Some(Self::Int)
+            }
+            b"BC" | b"BQ" | b"BZ" | b"CB" | b"CC" | b"CO" | b"CQ" | b"CR" | b"CS" | b"CT"
+            | b"CY" | b"E2" | b"FS" | b"LB" | b"MC" | b"MD" | b"MI" | b"MM" | b"OA" | b"OC"
+            | b"OQ" | b"OX" | b"PG" | b"PT" | b"PU" | b"Q2" | b"QT" | b"QX" | b"R2" | b"RG"
+            | b"RX" | b"SA" | b"TS" | b"U2" => Some(Self::String),
+            b"CG" | b"FZ" | b"ML" => Some(Self::IntArray),
+            _ => None,
+        }
+    }
+}
+
+/// A single auxiliary tag extracted across a batch.
+///
+/// `present[i]` is 1 when record `i` carries the tag. Only the value
+/// storage matching `kind` is populated: `int_values` or `float_values`
+/// hold one entry per record (0 where absent), string columns use CSR
+/// `string_data`/`string_offsets` (empty where absent), and array
+/// columns hold every element in `int_values` or `float_values` with
+/// CSR `array_offsets` (empty where absent).
+#[derive(Debug, PartialEq)]
+pub struct AuxTagColumn {
+    pub tag: String,
+    pub kind: AuxTagKind,
+    pub present: Vec<u8>,
+    pub int_values: Vec<i64>,
+    pub float_values: Vec<f64>,
+    pub string_data: Vec<u8>,
+    pub string_offsets: Vec<u32>,
+    pub array_offsets: Vec<u32>,
+}
+
+/// Auxiliary tag extraction settings for `AlignmentReader`.
+#[derive(Clone, Debug, Default)]
+pub struct AuxTagOptions {
+    /// Tags to extract as typed columns. A tag predefined by the SAM
+    /// specification may be given by name alone (`NM`); any other tag
+    /// needs its SAM type after a colon (`XS:i`, `XA:Z`, `ZB:B:c`).
+    pub tags: Vec<String>,
+    /// Also emit all tags of each record as SAM text.
+    pub raw: bool,
 }
 
 enum ReaderInner {
-    BamFile(bam::io::Reader<bgzf::io::Reader<BufReader<File>>>),
-    BamBytes(bam::io::Reader<bgzf::io::Reader<Cursor<Vec<u8>>>>),
-    SamFile(sam::io::Reader<BufReader<File>>),
-    SamBytes(sam::io::Reader<BufReader<Cursor<Vec<u8>>>>),
+    BamFile(BamInput<BufReader<File>>),
+    BamBytes(BamInput<Cursor<Vec<u8>>>),
+    SamFile(sam::io::Reader<Counted<BufReader<File>>>),
+    SamBytes(sam::io::Reader<Counted<BufReader<Cursor<Vec<u8>>>>>),
+    CramFile(Box<CramInput<Counted<BufReader<File>>>>),
+    CramBytes(Box<CramInput<CountedBytes>>),
+    BamStream(BamInput<Box<dyn BufRead + Send>>),
+    SamStream(sam::io::Reader<Counted<Box<dyn BufRead + Send>>>),
+    CramStream(Box<CramInput<Counted<Box<dyn BufRead + Send>>>>),
+}
+
+/// A BAM reader counting the decompressed bytes it consumes.
+type BamInput<R> = bam::io::Reader<Counted<bgzf::io::Reader<R>>>;
+
+fn bam_input<R: Read>(inner: R, decoded: &ByteCounter) -> BamInput<R> {
+    bam::io::Reader::from(Counted::new(bgzf::io::Reader::new(inner), decoded))
+}
+
+/// A CRAM reader plus the records decoded from its current container.
+///
+/// CRAM decodes a whole container at a time, so records are buffered
+/// here and handed out one by one.
+struct CramInput<R> {
+    reader: cram::io::Reader<R>,
+    repository: fasta::Repository,
+    container: cram::io::reader::Container,
+    pending: std::vec::IntoIter<sam::alignment::RecordBuf>,
+    eof: bool,
+}
+
+impl<R: Read> CramInput<R> {
+    fn new(inner: R, repository: fasta::Repository) -> Self {
+        let reader = cram::io::reader::Builder::default()
+            .set_reference_sequence_repository(repository.clone())
+            .build_from_reader(inner);
+
+        Self {
+            reader,
+            repository,
+            container: cram::io::reader::Container::default(),
+            pending: Vec::new().into_iter(),
+            eof: false,
+        }
+    }
+
+    fn read_record_buf(
+        &mut self,
+        header: &sam::Header,
+        record: &mut sam::alignment::RecordBuf,
+    ) -> std::io::Result<usize> {
+        loop {
+            if let Some(next) = self.pending.next() {
+                *record = next;
+                return Ok(1);
+            }
+
+            // The EOF container reads as empty; nothing may follow it.
+            if self.eof || self.reader.read_container(&mut self.container)? == 0 {
+                self.eof = true;
+                return Ok(0);
+            }
+
+            self.pending = self.decode_container(header)?.into_iter();
+        }
+    }
+
+    fn decode_container(
+        &self,
+        header: &sam::Header,
+    ) -> std::io::Result<Vec<sam::alignment::RecordBuf>> {
+        let compression_header = self.container.compression_header()?;
+        let mut records = Vec::new();
+
+        for slice in self.container.slices() {
+            let slice = slice?;
+            let (core_data_src, external_data_srcs) = slice.decode_blocks()?;
+
+            for record in slice.records(
+                self.repository.clone(),
+                header,
+                &compression_header,
+                &core_data_src,
+                &external_data_srcs,
+            )? {
+                records.push(sam::alignment::RecordBuf::try_from_alignment_record(
+                    header, &record,
+                )?);
+            }
+        }
+
+        Ok(records)
+    }
 }
 
-#[derive(Clone, Copy)]
-enum Format {
+/// Alignment container format.
+///
+/// CRAM is read-only; `AlignmentWriter` takes an `AlignmentOutputFormat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlignmentFormat {
     Bam,
     Sam,
+    Cram,
 }
 
-impl Format {
-    fn as_str(self) -> &'static str {
+impl AlignmentFormat {
+    /// The lowercase format name, as reported in `AlignmentBatch::format`.
+    pub fn as_str(self) -> &'static str {
         match self {
             Self::Bam => "bam",
             Self::Sam => "sam",
+            Self::Cram => "cram",
         }
     }
 }
 
+/// Alignment format written by `AlignmentWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlignmentOutputFormat {
+    Bam,
+    Sam,
+}
+
 /// Stateful alignment file reader.
 ///
-/// Wraps a noodles BAM or SAM reader. The file handle (or in-memory
+/// Wraps a noodles BAM, SAM or CRAM reader. The file handle (or in-memory
 /// buffer), decompression state (for BAM), and parsed header are owned
-/// by this struct.
+/// by this struct. `bytes_consumed` and `checkpoint` report and save
+/// progress through the input.
 pub struct AlignmentReader {
     inner: ReaderInner,
     header: sam::Header,
-    format: Format,
+    format: AlignmentFormat,
     reference_names: Vec<String>,
     record_buf: sam::alignment::RecordBuf,
+    index: Option<Box<dyn BinningIndex + Send>>,
+    query: Option<QueryState>,
+    requested_tags: Vec<(Tag, AuxTagKind)>,
+    raw_tags: bool,
+    /// Records read since open, or since the current query started.
+    records_read: u64,
+    /// SAM header line count, for reporting record line numbers.
+    header_lines: u64,
+    /// Decompressed bytes consumed for BAM; raw bytes for SAM and CRAM.
+    source: ByteCounter,
+}
+
+/// Position within an active region query.
+///
+/// Holds the index chunks that may contain overlapping records and the
+/// end of the chunk currently being read. Records outside the region
+/// are skipped as they are decoded.
+struct QueryState {
+    chunks: Vec<Chunk>,
+    next_chunk: usize,
+    chunk_end: Option<bgzf::VirtualPosition>,
+    reference_sequence_id: usize,
+    interval: Interval,
 }
 
 impl AlignmentReader {
-    /// Open a BAM or SAM file by path.
+    /// Open a BAM, SAM or CRAM file by path.
     ///
-    /// Format is detected from the first two bytes (BGZF magic). The
-    /// header is read immediately; records are read lazily via
-    /// `read_batch`.
+    /// Format is detected from the leading magic bytes (BGZF or `CRAM`).
+    /// The header is read immediately; records are read lazily via
+    /// `read_batch`. CRAM files opened this way have no reference, so
+    /// only reference-free or embedded-reference slices decode; use
+    /// `open_cram` to supply a FASTA.
     ///
     /// # Errors
     ///
-    /// Returns `EngineError::InvalidArgument` if the file cannot be
-    /// opened or the header cannot be parsed.
+    /// Returns `EngineError::Io` if the file cannot be opened, or
+    /// `EngineError::Parse` if the header cannot be parsed.
     pub fn open_from_path(path: &str) -> Result<Self, EngineError> {
         let mut file = File::open(path)
             .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
 
-        let mut magic = [0u8; 2];
+        let mut magic = [0u8; 4];
         let n = file.read(&mut magic).map_err(|e| {
             EngineError::Io(format!("failed to read magic bytes from '{path}': {e}"))
         })?;
         file.seek(std::io::SeekFrom::Start(0))
             .map_err(|e| EngineError::Io(format!("failed to seek in '{path}': {e}")))?;
 
-        let is_bgzf = n >= 2 && magic[0] == 0x1f && magic[1] == 0x8b;
-
-        if is_bgzf {
-            Self::open_bam_from_file(file, path)
-        } else {
-            Self::open_sam_from_file(file, path)
+        match detect_format(&magic[..n]) {
+            AlignmentFormat::Bam => Self::open_bam_from_file(file, path),
+            AlignmentFormat::Sam => Self::open_sam_from_file(file, path),
+            AlignmentFormat::Cram => {
+                Self::open_cram_from_file(file, path, fasta::Repository::default())
+            }
         }
     }
 
-    /// Open a BAM or SAM dataset from an in-memory buffer.
+    /// Open a BAM, SAM or CRAM stream from any reader, such as stdin, a
+    /// pipe or a socket.
+    ///
+    /// The reader is buffered internally and the format is detected from
+    /// its leading bytes. Stream readers cannot `query`, and CRAM streams
+    /// decode without a reference, as in `open_from_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if the header cannot be read.
+    pub fn open_from_reader(reader: Box<dyn Read + Send>) -> Result<Self, EngineError> {
+        Self::open_from_buf_reader(Box::new(BufReader::new(reader)))
+    }
+
+    /// Open a BAM, SAM or CRAM stream from a buffered reader.
+    ///
+    /// The format is detected from the stream's leading bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if the header cannot be read.
+    pub fn open_from_buf_reader(reader: Box<dyn BufRead + Send>) -> Result<Self, EngineError> {
+        let (magic, reader) = codec::peek_magic(reader, codec::BGZF_HEADER_LEN)
+            .map_err(|e| EngineError::Io(format!("failed to read alignment stream: {e}")))?;
+        let format = detect_format(&magic);
+
+        let source = ByteCounter::default();
+        let (inner, header) = match format {
+            AlignmentFormat::Bam => {
+                let mut reader = bam_input(reader, &source);
+                let header = reader
+                    .read_header()
+                    .map_err(|e| ParseError::header(RecordFormat::Bam, "stream", &e))?;
+                (ReaderInner::BamStream(reader), header)
+            }
+            AlignmentFormat::Sam => {
+                let mut reader = sam::io::Reader::new(Counted::new(reader, &source));
+                let header = reader
+                    .read_header()
+                    .map_err(|e| ParseError::header(RecordFormat::Sam, "stream", &e))?;
+                (ReaderInner::SamStream(reader), header)
+            }
+            AlignmentFormat::Cram => {
+                let mut input =
+                    CramInput::new(Counted::new(reader, &source), fasta::Repository::default());
+                let header = input
+                    .reader
+                    .read_header()
+                    .map_err(|e| ParseError::header(RecordFormat::Cram, "stream", &e))?;
+                (ReaderInner::CramStream(Box::new(input)), header)
+            }
+        };
+
+        Ok(Self::from_parts(inner, header, format, source))
+    }
+
+    /// Open a BAM, SAM or CRAM dataset from an in-memory buffer.
     ///
-    /// Format is detected from the first two bytes, same as
+    /// Format is detected from the leading bytes, same as
     /// `open_from_path`. This is the cross-runtime primitive — it works
     /// in Node/Bun, browsers, and wasm.
     ///
     /// # Errors
     ///
-    /// Returns `EngineError::InvalidArgument` if the header cannot be
-    /// parsed.
+    /// Returns `EngineError::Parse` if the header cannot be parsed.
     pub fn open_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
-        let is_bgzf = bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b;
+        let format = detect_format(&bytes);
 
-        if is_bgzf {
-            let cursor = Cursor::new(bytes);
-            let mut reader = bam::io::Reader::new(cursor);
-
-            let header = reader.read_header().map_err(|e| {
-                EngineError::Io(format!("failed to read BAM header from buffer: {e}"))
-            })?;
+        if format == AlignmentFormat::Cram {
+            Self::open_cram_from_cursor(Cursor::new(bytes), fasta::Repository::default())
+        } else if format == AlignmentFormat::Bam {
+            let source = ByteCounter::default();
+            let mut reader = bam_input(Cursor::new(bytes), &source);
 
-            let reference_names = resolve_reference_names(&header);
+            let header = reader
+                .read_header()
+                .map_err(|e| ParseError::header(RecordFormat::Bam, "buffer", &e))?;
 
-            Ok(Self {
-                inner: ReaderInner::BamBytes(reader),
+            Ok(Self::from_parts(
+                ReaderInner::BamBytes(reader),
                 header,
-                format: Format::Bam,
-                reference_names,
-                record_buf: sam::alignment::RecordBuf::default(),
-            })
+                AlignmentFormat::Bam,
+                source,
+            ))
         } else {
-            let cursor = Cursor::new(bytes);
-            let mut reader = sam::io::Reader::new(BufReader::new(cursor));
+            let source = ByteCounter::default();
+            let cursor = Counted::new(BufReader::new(Cursor::new(bytes)), &source);
+            let mut reader = sam::io::Reader::new(cursor);
+
+            let header = reader
+                .read_header()
+                .map_err(|e| ParseError::header(RecordFormat::Sam, "buffer", &e))?;
+
+            Ok(Self::from_parts(
+                ReaderInner::SamBytes(reader),
+                header,
+                AlignmentFormat::Sam,
+                source,
+            ))
+        }
+    }
+
+    /// Open a BAM, SAM or CRAM file by path and resume at a checkpoint
+    /// taken by an earlier reader over the same file.
+    ///
+    /// BAM resumes by seeking to a BGZF virtual offset and SAM to a byte
+    /// offset. CRAM records are decoded and discarded up to the
+    /// checkpoint, since containers cannot be entered part-way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the checkpoint was taken
+    /// from another format, `EngineError::Io` if the file cannot be read
+    /// up to it, or `EngineError::Parse` if the header cannot be parsed.
+    pub fn open_from_path_at(
+        path: &str,
+        checkpoint: &ReaderCheckpoint,
+    ) -> Result<Self, EngineError> {
+        let mut reader = Self::open_from_path(path)?;
+        reader.resume(checkpoint)?;
+        Ok(reader)
+    }
 
-            let header = reader.read_header().map_err(|e| {
-                EngineError::Io(format!("failed to read SAM header from buffer: {e}"))
+    /// Open a BAM, SAM or CRAM dataset from an in-memory buffer and
+    /// resume at a checkpoint, as for `open_from_path_at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the checkpoint was taken
+    /// from another format, `EngineError::Io` if the buffer ends before
+    /// it, or `EngineError::Parse` if the header cannot be parsed.
+    pub fn open_from_bytes_at(
+        bytes: Vec<u8>,
+        checkpoint: &ReaderCheckpoint,
+    ) -> Result<Self, EngineError> {
+        let mut reader = Self::open_from_bytes(bytes)?;
+        reader.resume(checkpoint)?;
+        Ok(reader)
+    }
+
+    /// Open a CRAM file, decoding against a local reference FASTA.
+    ///
+    /// `reference_path` must have a samtools-style `.fai` index next to
+    /// it (`<reference_path>.fai`); bgzipped references (`.gz`/`.bgz`)
+    /// also need their `.gzi`. Reference sequences are loaded on demand
+    /// as slices refer to them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if either file cannot be opened,
+    /// `EngineError::Parse` if the CRAM header cannot be read, and
+    /// `EngineError::InvalidArgument` if the input is not CRAM.
+    pub fn open_cram(path: &str, reference_path: &str) -> Result<Self, EngineError> {
+        let reference = fasta::io::indexed_reader::Builder::default()
+            .build_from_path(reference_path)
+            .map_err(|e| {
+                EngineError::Io(format!(
+                    "failed to open reference '{reference_path}' (is it indexed?): {e}"
+                ))
+            })?;
+        let repository = fasta::Repository::new(IndexedReader::new(reference));
+
+        let mut file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        let mut magic = [0u8; 4];
+        let n = file.read(&mut magic).map_err(|e| {
+            EngineError::Io(format!("failed to read magic bytes from '{path}': {e}"))
+        })?;
+        file.seek(std::io::SeekFrom::Start(0))
+            .map_err(|e| EngineError::Io(format!("failed to seek in '{path}': {e}")))?;
+
+        if detect_format(&magic[..n]) != AlignmentFormat::Cram {
+            return Err(EngineError::InvalidArgument(format!(
+                "'{path}' is not a CRAM file"
+            )));
+        }
+
+        Self::open_cram_from_file(file, path, repository)
+    }
+
+    /// Open a CRAM dataset from memory, decoding against an in-memory
+    /// reference.
+    ///
+    /// `reference` is uncompressed FASTA text and `reference_index` the
+    /// contents of its `.fai`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the data is not CRAM or
+    /// the index cannot be parsed, or `EngineError::Parse` if the CRAM
+    /// header cannot be read.
+    pub fn open_cram_from_bytes(
+        bytes: Vec<u8>,
+        reference: Vec<u8>,
+        reference_index: &[u8],
+    ) -> Result<Self, EngineError> {
+        if detect_format(&bytes) != AlignmentFormat::Cram {
+            return Err(EngineError::InvalidArgument(
+                "buffer is not CRAM data".to_string(),
+            ));
+        }
+
+        let index = fai::io::Reader::new(reference_index)
+            .read_index()
+            .map_err(|e| {
+                EngineError::InvalidArgument(format!("failed to read reference index: {e}"))
             })?;
+        let reference = fasta::io::IndexedReader::new(Cursor::new(reference), index);
+        let repository = fasta::Repository::new(IndexedReader::new(reference));
 
-            let reference_names = resolve_reference_names(&header);
+        Self::open_cram_from_cursor(Cursor::new(bytes), repository)
+    }
 
-            Ok(Self {
-                inner: ReaderInner::SamBytes(reader),
-                header,
-                format: Format::Sam,
-                reference_names,
-                record_buf: sam::alignment::RecordBuf::default(),
-            })
+    /// Open an indexed BAM file by path.
+    ///
+    /// `index_path` may point to a BAI or CSI index; the format is
+    /// detected from its magic bytes. Until `query` is called, records
+    /// are read sequentially from the start of the file as usual.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the file is not BAM,
+    /// or `EngineError::Io` if either file cannot be read.
+    pub fn open_indexed(path: &str, index_path: &str) -> Result<Self, EngineError> {
+        let index_bytes = std::fs::read(index_path)
+            .map_err(|e| EngineError::Io(format!("failed to read '{index_path}': {e}")))?;
+        let index = read_index(&index_bytes)?;
+
+        let mut reader = Self::open_from_path(path)?;
+        if !matches!(reader.format, AlignmentFormat::Bam) {
+            return Err(EngineError::InvalidArgument(format!(
+                "indexed queries require BAM input, but '{path}' is {}",
+                reader.format.as_str()
+            )));
+        }
+        reader.index = Some(index);
+
+        Ok(reader)
+    }
+
+    /// Open an indexed BAM dataset from in-memory BAM and index buffers.
+    ///
+    /// The in-memory counterpart of `open_indexed`, for runtimes without
+    /// filesystem access.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the data is not BAM or
+    /// the index cannot be parsed.
+    pub fn open_indexed_from_bytes(
+        bytes: Vec<u8>,
+        index_bytes: &[u8],
+    ) -> Result<Self, EngineError> {
+        let index = read_index(index_bytes)?;
+
+        let mut reader = Self::open_from_bytes(bytes)?;
+        if !matches!(reader.format, AlignmentFormat::Bam) {
+            return Err(EngineError::InvalidArgument(format!(
+                "indexed queries require BAM input, but the buffer is {}",
+                reader.format.as_str()
+            )));
         }
+        reader.index = Some(index);
+
+        Ok(reader)
+    }
+
+    /// Restrict subsequent reads to records overlapping `region`.
+    ///
+    /// `region` uses samtools syntax (`chr2`, `chr2:1000`, or
+    /// `chr2:1000-5000`, 1-based and inclusive). The reader seeks to the
+    /// BGZF blocks the index lists for the region; `read_batch` then
+    /// returns only overlapping records and yields `None` once the
+    /// region is exhausted. Calling `query` again starts a new region.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the reader was not
+    /// opened with an index, the region cannot be parsed, or the
+    /// reference sequence is not in the header.
+    pub fn query(&mut self, region: &str) -> Result<(), EngineError> {
+        let index = self.index.as_ref().ok_or_else(|| {
+            EngineError::InvalidArgument(
+                "region queries require a reader opened with an index".to_string(),
+            )
+        })?;
+
+        let region: Region = region
+            .parse()
+            .map_err(|e| EngineError::InvalidArgument(format!("invalid region '{region}': {e}")))?;
+
+        let reference_sequence_id = self
+            .header
+            .reference_sequences()
+            .get_index_of(region.name())
+            .ok_or_else(|| {
+                EngineError::InvalidArgument(format!(
+                    "reference sequence '{}' is not in the header",
+                    region.name()
+                ))
+            })?;
+
+        let chunks = index
+            .query(reference_sequence_id, region.interval())
+            .map_err(|e| EngineError::Io(format!("index query error: {e}")))?;
+
+        self.query = Some(QueryState {
+            chunks,
+            next_chunk: 0,
+            chunk_end: None,
+            reference_sequence_id,
+            interval: region.interval(),
+        });
+        self.records_read = 0;
+
+        Ok(())
+    }
+
+    /// Configure which auxiliary tags `read_batch` extracts.
+    ///
+    /// By default no optional fields are read. Each requested tag yields
+    /// an `AuxTagColumn` in every subsequent batch, in request order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a tag name is not two
+    /// characters matching `[A-Za-z][A-Za-z0-9]`, its type is not a SAM
+    /// type code, or it is not a predefined tag and has no type.
+    pub fn set_tag_options(&mut self, options: &AuxTagOptions) -> Result<(), EngineError> {
+        self.requested_tags = options
+            .tags
+            .iter()
+            .map(|spec| parse_tag_spec(spec))
+            .collect::<Result<_, _>>()?;
+        self.raw_tags = options.raw;
+        Ok(())
     }
 
     /// Read the next batch of alignment records.
@@ -173,19 +719,38 @@ impl AlignmentReader {
     ///
     /// # Errors
     ///
-    /// Returns `EngineError::InvalidArgument` if a record cannot be
-    /// parsed.
-    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number (and line, for SAM).
     pub fn read_batch(&mut self, max_records: u32) -> Result<Option<AlignmentBatch>, EngineError> {
+        self.read_batch_bounded(max_records, u32::MAX)
+    }
+
+    /// Read the next batch of alignment records, stopping early once the
+    /// batch holds `max_bytes` of sequence and quality data.
+    ///
+    /// The record that crosses the budget is kept, so a batch always
+    /// holds at least one record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number (and line, for SAM).
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    pub fn read_batch_bounded(
+        &mut self,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> Result<Option<AlignmentBatch>, EngineError> {
         let max = max_records as usize;
+        let max_bytes = max_bytes as usize;
 
         let mut qname_bytes: Vec<u8> = Vec::with_capacity(max * 32);
         let mut qname_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
-        let mut seq_bytes: Vec<u8> = Vec::with_capacity(max * 150);
+        let mut seq_bytes: Vec<u8> = Vec::with_capacity((max * 150).min(max_bytes / 2));
         let mut seq_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
-        let mut qual_bytes: Vec<u8> = Vec::with_capacity(max * 150);
+        let mut qual_bytes: Vec<u8> = Vec::with_capacity((max * 150).min(max_bytes / 2));
         let mut qual_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
         let mut cigar_bytes: Vec<u8> = Vec::with_capacity(max * 20);
@@ -194,9 +759,16 @@ impl AlignmentReader {
         let mut rname_bytes: Vec<u8> = Vec::with_capacity(max * 10);
         let mut rname_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
+        let mut rnext_bytes: Vec<u8> = Vec::with_capacity(max * 10);
+        let mut rnext_offsets: Vec<u32> = Vec::with_capacity(max + 1);
+
         let mut flags: Vec<u16> = Vec::with_capacity(max);
         let mut positions: Vec<i32> = Vec::with_capacity(max);
         let mut mapqs: Vec<u8> = Vec::with_capacity(max);
+        let mut next_positions: Vec<i32> = Vec::with_capacity(max);
+        let mut template_lengths: Vec<i32> = Vec::with_capacity(max);
+
+        let mut tags = TagCollector::new(self.requested_tags.clone(), self.raw_tags, max);
 
         let mut count: u32 = 0;
 
@@ -205,6 +777,7 @@ impl AlignmentReader {
         qual_offsets.push(0);
         cigar_offsets.push(0);
         rname_offsets.push(0);
+        rnext_offsets.push(0);
 
         for _ in 0..max {
             let bytes_read = self.read_one_record()?;
@@ -222,14 +795,7 @@ impl AlignmentReader {
             seq_bytes.extend_from_slice(seq);
             seq_offsets.push(seq_bytes.len() as u32);
 
-            let qual_scores: &[u8] = record.quality_scores().as_ref();
-            if qual_scores.is_empty() || qual_scores.iter().all(|&s| s == 255) {
-                qual_bytes.extend(std::iter::repeat_n(b'*', seq.len()));
-            } else {
-                for &score in qual_scores {
-                    qual_bytes.push(score.saturating_add(33));
-                }
-            }
+            push_quality(record.quality_scores().as_ref(), seq.len(), &mut qual_bytes);
             qual_offsets.push(qual_bytes.len() as u32);
 
             format_cigar(record.cigar(), &mut cigar_bytes);
@@ -250,13 +816,33 @@ impl AlignmentReader {
             let mapq = record.mapping_quality().map_or(255, |m| m.get());
             mapqs.push(mapq);
 
+            let rnext: &[u8] = record
+                .mate_reference_sequence_id()
+                .and_then(|id| self.reference_names.get(id))
+                .map_or(&b"*"[..], String::as_bytes);
+            rnext_bytes.extend_from_slice(rnext);
+            rnext_offsets.push(rnext_bytes.len() as u32);
+
+            let pnext = record.mate_alignment_start().map_or(0, |p| p.get() as i32);
+            next_positions.push(pnext);
+
+            template_lengths.push(record.template_length());
+
+            tags.push(record.data())
+                .map_err(|message| self.tag_error(message))?;
+
             count += 1;
+            if seq_bytes.len() + qual_bytes.len() >= max_bytes {
+                break;
+            }
         }
 
         if count == 0 {
             return Ok(None);
         }
 
+        let (tags, raw_tag_data, raw_tag_offsets) = tags.finish();
+
         Ok(Some(AlignmentBatch {
             count,
             format: self.format.as_str(),
@@ -270,9 +856,16 @@ impl AlignmentReader {
             cigar_offsets,
             rname_data: rname_bytes,
             rname_offsets,
+            rnext_data: rnext_bytes,
+            rnext_offsets,
             flags,
             positions,
             mapping_qualities: mapqs,
+            next_positions,
+            template_lengths,
+            tags,
+            raw_tag_data,
+            raw_tag_offsets,
         }))
     }
 
@@ -313,86 +906,1748 @@ impl AlignmentReader {
     }
 
     fn open_bam_from_file(file: File, path: &str) -> Result<Self, EngineError> {
-        let mut reader = bam::io::Reader::new(BufReader::new(file));
-
-        let header = reader.read_header().map_err(|e| {
-            EngineError::Io(format!("failed to read BAM header from '{path}': {e}"))
-        })?;
+        let source = ByteCounter::default();
+        let mut reader = bam_input(BufReader::new(file), &source);
 
-        let reference_names = resolve_reference_names(&header);
+        let header = reader
+            .read_header()
+            .map_err(|e| ParseError::header(RecordFormat::Bam, &format!("'{path}'"), &e))?;
 
-        Ok(Self {
-            inner: ReaderInner::BamFile(reader),
+        Ok(Self::from_parts(
+            ReaderInner::BamFile(reader),
             header,
-            format: Format::Bam,
-            reference_names,
-            record_buf: sam::alignment::RecordBuf::default(),
-        })
+            AlignmentFormat::Bam,
+            source,
+        ))
     }
 
     fn open_sam_from_file(file: File, path: &str) -> Result<Self, EngineError> {
-        let mut reader = sam::io::Reader::new(BufReader::new(file));
+        let source = ByteCounter::default();
+        let mut reader = sam::io::Reader::new(Counted::new(BufReader::new(file), &source));
 
-        let header = reader.read_header().map_err(|e| {
-            EngineError::Io(format!("failed to read SAM header from '{path}': {e}"))
-        })?;
+        let header = reader
+            .read_header()
+            .map_err(|e| ParseError::header(RecordFormat::Sam, &format!("'{path}'"), &e))?;
+
+        Ok(Self::from_parts(
+            ReaderInner::SamFile(reader),
+            header,
+            AlignmentFormat::Sam,
+            source,
+        ))
+    }
+
+    fn open_cram_from_file(
+        file: File,
+        path: &str,
+        repository: fasta::Repository,
+    ) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        let mut input = CramInput::new(Counted::new(BufReader::new(file), &source), repository);
+
+        let header = input
+            .reader
+            .read_header()
+            .map_err(|e| ParseError::header(RecordFormat::Cram, &format!("'{path}'"), &e))?;
+
+        Ok(Self::from_parts(
+            ReaderInner::CramFile(Box::new(input)),
+            header,
+            AlignmentFormat::Cram,
+            source,
+        ))
+    }
+
+    fn open_cram_from_cursor(
+        cursor: Cursor<Vec<u8>>,
+        repository: fasta::Repository,
+    ) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        let mut input = CramInput::new(Counted::new(cursor, &source), repository);
+
+        let header = input
+            .reader
+            .read_header()
+            .map_err(|e| ParseError::header(RecordFormat::Cram, "buffer", &e))?;
 
+        Ok(Self::from_parts(
+            ReaderInner::CramBytes(Box::new(input)),
+            header,
+            AlignmentFormat::Cram,
+            source,
+        ))
+    }
+
+    fn from_parts(
+        inner: ReaderInner,
+        header: sam::Header,
+        format: AlignmentFormat,
+        source: ByteCounter,
+    ) -> Self {
         let reference_names = resolve_reference_names(&header);
+        let header_lines = match format {
+            AlignmentFormat::Sam => count_header_lines(&header),
+            AlignmentFormat::Bam | AlignmentFormat::Cram => 0,
+        };
 
-        Ok(Self {
-            inner: ReaderInner::SamFile(reader),
+        Self {
+            inner,
             header,
-            format: Format::Sam,
+            format,
             reference_names,
             record_buf: sam::alignment::RecordBuf::default(),
-        })
+            index: None,
+            query: None,
+            requested_tags: Vec::new(),
+            raw_tags: false,
+            records_read: 0,
+            header_lines,
+            source,
+        }
     }
 
-    fn read_one_record(&mut self) -> Result<usize, EngineError> {
-        match &mut self.inner {
-            ReaderInner::BamFile(r) => r
-                .read_record_buf(&self.header, &mut self.record_buf)
-                .map_err(|e| EngineError::Io(format!("BAM read error: {e}"))),
-            ReaderInner::BamBytes(r) => r
-                .read_record_buf(&self.header, &mut self.record_buf)
-                .map_err(|e| EngineError::Io(format!("BAM read error: {e}"))),
-            ReaderInner::SamFile(r) => r
-                .read_record_buf(&self.header, &mut self.record_buf)
-                .map_err(|e| EngineError::Io(format!("SAM read error: {e}"))),
-            ReaderInner::SamBytes(r) => r
-                .read_record_buf(&self.header, &mut self.record_buf)
-                .map_err(|e| EngineError::Io(format!("SAM read error: {e}"))),
+    /// Number of records returned since open, or since the current
+    /// region query started. Resuming at a checkpoint counts the
+    /// records before it.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Progress through the input. For BAM, `compressed` is the offset
+    /// into the BGZF file and `uncompressed` the decompressed bytes
+    /// consumed; SAM and CRAM report the bytes read from the input for
+    /// both.
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        let compressed = match &self.inner {
+            ReaderInner::BamFile(r) => r.get_ref().get_ref().position(),
+            ReaderInner::BamBytes(r) => r.get_ref().get_ref().position(),
+            ReaderInner::BamStream(r) => r.get_ref().get_ref().position(),
+            _ => self.source.get(),
+        };
+        BytesConsumed {
+            compressed,
+            uncompressed: self.source.get(),
         }
     }
-}
 
-fn format_cigar(cigar: &Cigar, out: &mut Vec<u8>) {
-    let ops: &[sam::alignment::record::cigar::Op] = cigar.as_ref();
-    if ops.is_empty() {
-        out.push(b'*');
-        return;
+    /// Save the reader's position after the last record returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` while a region query is
+    /// active.
+    pub fn checkpoint(&self) -> Result<ReaderCheckpoint, EngineError> {
+        if self.query.is_some() {
+            return Err(EngineError::InvalidArgument(
+                "cannot checkpoint during a region query".to_string(),
+            ));
+        }
+        let virtual_offset = match &self.inner {
+            ReaderInner::BamFile(r) => Some(r.get_ref().get_ref().virtual_position()),
+            ReaderInner::BamBytes(r) => Some(r.get_ref().get_ref().virtual_position()),
+            ReaderInner::BamStream(r) => Some(r.get_ref().get_ref().virtual_position()),
+            _ => None,
+        };
+        Ok(ReaderCheckpoint::new(
+            self.record_format(),
+            self.records_read,
+            self.source.get(),
+            virtual_offset.map(u64::from),
+        ))
     }
 
-    for op in ops {
-        let ch = match op.kind() {
-            Kind::Match => b'M',
-            Kind::Insertion => b'I',
-            Kind::Deletion => b'D',
-            Kind::Skip => b'N',
-            Kind::SoftClip => b'S',
-            Kind::HardClip => b'H',
-            Kind::Pad => b'P',
-            Kind::SequenceMatch => b'=',
-            Kind::SequenceMismatch => b'X',
+    fn resume(&mut self, checkpoint: &ReaderCheckpoint) -> Result<(), EngineError> {
+        checkpoint.expect_format(self.record_format())?;
+        let offset = checkpoint.uncompressed_offset();
+        // Stream readers only move forward; they skip from where the
+        // header left off.
+        let ahead = offset.saturating_sub(self.source.get());
+        let result = match (&mut self.inner, checkpoint.virtual_offset()) {
+            (ReaderInner::BamFile(r), Some(pos)) => {
+                r.get_mut().get_mut().seek(pos.into()).map(drop)
+            }
+            (ReaderInner::BamBytes(r), Some(pos)) => {
+                r.get_mut().get_mut().seek(pos.into()).map(drop)
+            }
+            (ReaderInner::BamStream(r), _) => codec::skip_to(r.get_mut(), ahead),
+            (ReaderInner::BamFile(_) | ReaderInner::BamBytes(_), None) => {
+                return Err(EngineError::InvalidArgument(
+                    "BAM checkpoint has no virtual offset".to_string(),
+                ));
+            }
+            (ReaderInner::SamFile(r), _) => r.get_mut().seek(SeekFrom::Start(offset)).map(drop),
+            (ReaderInner::SamBytes(r), _) => r.get_mut().seek(SeekFrom::Start(offset)).map(drop),
+            (ReaderInner::SamStream(r), _) => codec::skip_to(r.get_mut(), ahead),
+            (
+                ReaderInner::CramFile(_) | ReaderInner::CramBytes(_) | ReaderInner::CramStream(_),
+                _,
+            ) => {
+                for _ in 0..checkpoint.records_read() {
+                    if self.read_one_record()? == 0 {
+                        return Err(EngineError::Io(format!(
+                            "checkpoint at record {} is past the end of the input",
+                            checkpoint.records_read()
+                        )));
+                    }
+                }
+                Ok(())
+            }
         };
-        let _ = write!(out, "{}{}", op.len(), ch as char);
+        result.map_err(|e| EngineError::Io(format!("failed to resume alignment input: {e}")))?;
+        self.source.set(offset.max(self.source.get()));
+        self.records_read = checkpoint.records_read();
+        Ok(())
     }
-}
 
-fn resolve_reference_names(header: &sam::Header) -> Vec<String> {
-    header
-        .reference_sequences()
-        .iter()
-        .map(|(name, _)| name.to_string())
-        .collect()
+    fn record_format(&self) -> RecordFormat {
+        match self.format {
+            AlignmentFormat::Bam => RecordFormat::Bam,
+            AlignmentFormat::Sam => RecordFormat::Sam,
+            AlignmentFormat::Cram => RecordFormat::Cram,
+        }
+    }
+
+    fn read_one_record(&mut self) -> Result<usize, EngineError> {
+        match self.read_record_buf() {
+            Ok(0) => Ok(0),
+            Ok(n) => {
+                self.records_read += 1;
+                Ok(n)
+            }
+            Err(e) => Err(self.parse_error(&e).into()),
+        }
+    }
+
+    fn read_record_buf(&mut self) -> std::io::Result<usize> {
+        if let Some(query) = &mut self.query {
+            return match &mut self.inner {
+                ReaderInner::BamFile(r) => {
+                    read_query_record(r, &self.header, query, &mut self.record_buf)
+                }
+                ReaderInner::BamBytes(r) => {
+                    read_query_record(r, &self.header, query, &mut self.record_buf)
+                }
+                ReaderInner::SamFile(_)
+                | ReaderInner::SamBytes(_)
+                | ReaderInner::CramFile(_)
+                | ReaderInner::CramBytes(_)
+                | ReaderInner::BamStream(_)
+                | ReaderInner::SamStream(_)
+                | ReaderInner::CramStream(_) => Ok(0),
+            };
+        }
+
+        match &mut self.inner {
+            ReaderInner::BamFile(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::BamBytes(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::SamFile(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::SamBytes(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::CramFile(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::CramBytes(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::BamStream(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::SamStream(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+            ReaderInner::CramStream(r) => r.read_record_buf(&self.header, &mut self.record_buf),
+        }
+    }
+
+    /// A parse error for the record being read. SAM records carry their
+    /// line number outside of region queries.
+    fn parse_error(&self, e: &std::io::Error) -> ParseError {
+        let record = self.records_read + 1;
+        match self.format {
+            AlignmentFormat::Bam => ParseError::from_io(RecordFormat::Bam, record, e),
+            AlignmentFormat::Cram => ParseError::from_io(RecordFormat::Cram, record, e),
+            AlignmentFormat::Sam => {
+                let line = self.query.is_none().then_some(self.header_lines + record);
+                ParseError::from_io(RecordFormat::Sam, record, e).at(None, line)
+            }
+        }
+    }
+
+    /// A parse error for the record just read, whose optional fields do
+    /// not match the requested tag types.
+    fn tag_error(&self, message: String) -> ParseError {
+        let error = |format| {
+            ParseError::new(
+                format,
+                ParseErrorKind::InvalidRecord,
+                self.records_read,
+                message,
+            )
+            .field("tags")
+        };
+        match self.format {
+            AlignmentFormat::Bam => error(RecordFormat::Bam),
+            AlignmentFormat::Cram => error(RecordFormat::Cram),
+            AlignmentFormat::Sam => {
+                let line = self
+                    .query
+                    .is_none()
+                    .then_some(self.header_lines + self.records_read);
+                error(RecordFormat::Sam).at(None, line)
+            }
+        }
+    }
+}
+
+/// Append quality scores as Phred+33 text, or `*` per base when the
+/// record has none.
+fn push_quality(scores: &[u8], sequence_len: usize, out: &mut Vec<u8>) {
+    if scores.is_empty() || scores.iter().all(|&s| s == 255) {
+        out.extend(std::iter::repeat_n(b'*', sequence_len));
+    } else {
+        out.extend(scores.iter().map(|&score| score.saturating_add(33)));
+    }
+}
+
+/// Number of lines the header takes up when written as SAM text.
+#[allow(clippy::naive_bytecount)]
+fn count_header_lines(header: &sam::Header) -> u64 {
+    let mut buf = Vec::new();
+    if sam::io::Writer::new(&mut buf).write_header(header).is_err() {
+        return 0;
+    }
+    buf.iter().filter(|&&b| b == b'\n').count() as u64
+}
+
+/// Detect the alignment format from leading bytes: BGZF magic means BAM,
+/// `CRAM` means CRAM, anything else is treated as SAM text.
+fn detect_format(magic: &[u8]) -> AlignmentFormat {
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        AlignmentFormat::Bam
+    } else if magic.starts_with(b"CRAM") {
+        AlignmentFormat::Cram
+    } else {
+        AlignmentFormat::Sam
+    }
+}
+
+fn read_index(bytes: &[u8]) -> Result<Box<dyn BinningIndex + Send>, EngineError> {
+    if bytes.starts_with(b"BAI\x01") {
+        let index = bai::io::Reader::new(bytes)
+            .read_index()
+            .map_err(|e| EngineError::InvalidArgument(format!("failed to read BAI index: {e}")))?;
+        Ok(Box::new(index))
+    } else if bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b {
+        let index = csi::io::Reader::new(bytes)
+            .read_index()
+            .map_err(|e| EngineError::InvalidArgument(format!("failed to read CSI index: {e}")))?;
+        Ok(Box::new(index))
+    } else {
+        Err(EngineError::InvalidArgument(
+            "unrecognized index format (expected BAI or CSI)".to_string(),
+        ))
+    }
+}
+
+fn read_query_record<R: Read + Seek>(
+    reader: &mut BamInput<R>,
+    header: &sam::Header,
+    query: &mut QueryState,
+    record: &mut sam::alignment::RecordBuf,
+) -> std::io::Result<usize> {
+    loop {
+        let in_chunk = query
+            .chunk_end
+            .is_some_and(|end| reader.get_ref().get_ref().virtual_position() < end);
+
+        if !in_chunk {
+            let Some(chunk) = query.chunks.get(query.next_chunk) else {
+                return Ok(0);
+            };
+            reader.get_mut().get_mut().seek(chunk.start())?;
+            query.chunk_end = Some(chunk.end());
+            query.next_chunk += 1;
+            continue;
+        }
+
+        let n = reader.read_record_buf(header, record)?;
+        if n == 0 {
+            query.next_chunk = query.chunks.len();
+            query.chunk_end = None;
+            return Ok(0);
+        }
+
+        if intersects(record, query.reference_sequence_id, query.interval) {
+            return Ok(n);
+        }
+    }
+}
+
+fn intersects(
+    record: &sam::alignment::RecordBuf,
+    reference_sequence_id: usize,
+    interval: Interval,
+) -> bool {
+    if record.reference_sequence_id() != Some(reference_sequence_id) {
+        return false;
+    }
+
+    if interval.start().is_none() && interval.end().is_none() {
+        return true;
+    }
+
+    match (record.alignment_start(), record.alignment_end()) {
+        (Some(start), Some(end)) => interval.intersects((start..=end).into()),
+        _ => false,
+    }
+}
+
+fn parse_tag(name: &[u8]) -> Result<Tag, EngineError> {
+    match name {
+        [a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphanumeric() => Ok(Tag::from([*a, *b])),
+        _ => Err(EngineError::InvalidArgument(format!(
+            "invalid auxiliary tag name '{}'",
+            String::from_utf8_lossy(name)
+        ))),
+    }
+}
+
+/// Parse a requested tag: a name, optionally followed by `:` and its
+/// SAM type.
+fn parse_tag_spec(spec: &str) -> Result<(Tag, AuxTagKind), EngineError> {
+    let (name, code) = match spec.split_once(':') {
+        Some((name, code)) => (name, Some(code)),
+        None => (spec, None),
+    };
+    let tag = parse_tag(name.as_bytes())?;
+    let kind = match code {
+        Some(code) => AuxTagKind::from_sam_type(code.as_bytes()).ok_or_else(|| {
+            EngineError::InvalidArgument(format!(
+                "invalid SAM type '{code}' for auxiliary tag '{name}'"
+            ))
+        })?,
+        None => AuxTagKind::predefined(tag.into()).ok_or_else(|| {
+            EngineError::InvalidArgument(format!(
+                "auxiliary tag '{name}' is not predefined by the SAM specification; \
+                 give its type, e.g. '{name}:i'"
+            ))
+        })?,
+    };
+    Ok((tag, kind))
+}
+
+/// Accumulates requested and raw auxiliary tags while a batch is read.
+struct TagCollector {
+    tags: Vec<Tag>,
+    columns: Vec<AuxTagColumn>,
+    raw: Option<(Vec<u8>, Vec<u32>)>,
+}
+
+impl TagCollector {
+    fn new(tags: Vec<(Tag, AuxTagKind)>, raw: bool, max: usize) -> Self {
+        let columns = tags
+            .iter()
+            .map(|&(tag, kind)| empty_tag_column(tag, kind, max))
+            .collect();
+        let raw = raw.then(|| {
+            let mut offsets = Vec::with_capacity(max + 1);
+            offsets.push(0);
+            (Vec::with_capacity(max * 32), offsets)
+        });
+        Self {
+            tags: tags.into_iter().map(|(tag, _)| tag).collect(),
+            columns,
+            raw,
+        }
+    }
+
+    /// Append a record's tags, failing if a requested tag holds a value
+    /// of another type.
+    #[allow(clippy::cast_possible_truncation)]
+    fn push(&mut self, data: &sam::alignment::record_buf::Data) -> Result<(), String> {
+        for (tag, column) in self.tags.iter().zip(&mut self.columns) {
+            push_tag_value(column, data.get(tag))?;
+        }
+
+        if let Some((bytes, offsets)) = &mut self.raw {
+            format_raw_tags(data, bytes);
+            offsets.push(bytes.len() as u32);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> (Vec<AuxTagColumn>, Vec<u8>, Vec<u32>) {
+        let (raw_data, raw_offsets) = self.raw.unwrap_or_default();
+        (self.columns, raw_data, raw_offsets)
+    }
+}
+
+fn empty_tag_column(tag: Tag, kind: AuxTagKind, max: usize) -> AuxTagColumn {
+    let tag_bytes: [u8; 2] = tag.into();
+    let mut column = AuxTagColumn {
+        tag: String::from_utf8_lossy(&tag_bytes).into_owned(),
+        kind,
+        present: Vec::with_capacity(max),
+        int_values: Vec::new(),
+        float_values: Vec::new(),
+        string_data: Vec::new(),
+        string_offsets: Vec::new(),
+        array_offsets: Vec::new(),
+    };
+    match kind {
+        AuxTagKind::Int => column.int_values.reserve(max),
+        AuxTagKind::Float => column.float_values.reserve(max),
+        AuxTagKind::String => column.string_offsets.push(0),
+        AuxTagKind::IntArray | AuxTagKind::FloatArray => column.array_offsets.push(0),
+    }
+    column
+}
+
+/// Append one record's value, or its absence, to a tag column.
+#[allow(clippy::cast_possible_truncation)]
+fn push_tag_value(column: &mut AuxTagColumn, value: Option<&Value>) -> Result<(), String> {
+    match (column.kind, value) {
+        (AuxTagKind::Int, _) => {
+            let n = value.map(|v| aux_int(v).ok_or_else(|| tag_mismatch(column, v)));
+            column.int_values.push(n.transpose()?.unwrap_or(0));
+        }
+        (AuxTagKind::Float, _) => {
+            let n = value.map(|v| aux_float(v).ok_or_else(|| tag_mismatch(column, v)));
+            column.float_values.push(n.transpose()?.unwrap_or(0.0));
+        }
+        (AuxTagKind::String, Some(Value::Character(c))) => column.string_data.push(*c),
+        (AuxTagKind::String, Some(Value::String(s) | Value::Hex(s))) => {
+            column.string_data.extend_from_slice(s);
+        }
+        (AuxTagKind::IntArray, Some(Value::Array(array))) if !matches!(array, Array::Float(_)) => {
+            push_int_array(array, &mut column.int_values);
+        }
+        (AuxTagKind::FloatArray, Some(Value::Array(array))) => {
+            push_float_array(array, &mut column.float_values);
+        }
+        (_, Some(v)) => return Err(tag_mismatch(column, v)),
+        (_, None) => {}
+    }
+    match column.kind {
+        AuxTagKind::Int | AuxTagKind::Float => {}
+        AuxTagKind::String => column.string_offsets.push(column.string_data.len() as u32),
+        AuxTagKind::IntArray => column.array_offsets.push(column.int_values.len() as u32),
+        AuxTagKind::FloatArray => column.array_offsets.push(column.float_values.len() as u32),
+    }
+    column.present.push(u8::from(value.is_some()));
+    Ok(())
+}
+
+fn tag_mismatch(column: &AuxTagColumn, value: &Value) -> String {
+    format!(
+        "tag {} is requested as {} but holds a '{}' value",
+        column.tag,
+        column.kind.as_str(),
+        char::from(aux_type_code(value))
+    )
+}
+
+fn push_int_array(array: &Array, out: &mut Vec<i64>) {
+    match array {
+        Array::Int8(v) => out.extend(v.iter().map(|&n| i64::from(n))),
+        Array::UInt8(v) => out.extend(v.iter().map(|&n| i64::from(n))),
+        Array::Int16(v) => out.extend(v.iter().map(|&n| i64::from(n))),
+        Array::UInt16(v) => out.extend(v.iter().map(|&n| i64::from(n))),
+        Array::Int32(v) => out.extend(v.iter().map(|&n| i64::from(n))),
+        Array::UInt32(v) => out.extend(v.iter().map(|&n| i64::from(n))),
+        Array::Float(_) => {}
+    }
+}
+
+/// Append a `B` array as floats; integer arrays are widened, as scalar
+/// integers are for `Float` columns.
+#[allow(clippy::cast_precision_loss)]
+fn push_float_array(array: &Array, out: &mut Vec<f64>) {
+    if let Array::Float(v) = array {
+        out.extend(v.iter().map(|&n| f64::from(n)));
+    } else {
+        let mut ints = Vec::new();
+        push_int_array(array, &mut ints);
+        out.extend(ints.into_iter().map(|n| n as f64));
+    }
+}
+
+fn aux_int(value: &Value) -> Option<i64> {
+    match *value {
+        Value::Int8(n) => Some(i64::from(n)),
+        Value::UInt8(n) => Some(i64::from(n)),
+        Value::Int16(n) => Some(i64::from(n)),
+        Value::UInt16(n) => Some(i64::from(n)),
+        Value::Int32(n) => Some(i64::from(n)),
+        Value::UInt32(n) => Some(i64::from(n)),
+        _ => None,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn aux_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(n) => Some(f64::from(*n)),
+        _ => aux_int(value).map(|n| n as f64),
+    }
+}
+
+fn aux_type_code(value: &Value) -> u8 {
+    match value {
+        Value::Character(_) => b'A',
+        Value::Float(_) => b'f',
+        Value::String(_) => b'Z',
+        Value::Hex(_) => b'H',
+        Value::Array(_) => b'B',
+        _ => b'i',
+    }
+}
+
+fn format_aux_value(value: &Value, out: &mut Vec<u8>) {
+    fn join<T: std::fmt::Display>(subtype: u8, values: &[T], out: &mut Vec<u8>) {
+        out.push(subtype);
+        for v in values {
+            let _ = write!(out, ",{v}");
+        }
+    }
+
+    match value {
+        Value::Character(c) => out.push(*c),
+        Value::Float(n) => {
+            let _ = write!(out, "{n}");
+        }
+        Value::String(s) | Value::Hex(s) => out.extend_from_slice(s),
+        Value::Array(array) => match array {
+            Array::Int8(v) => join(b'c', v, out),
+            Array::UInt8(v) => join(b'C', v, out),
+            Array::Int16(v) => join(b's', v, out),
+            Array::UInt16(v) => join(b'S', v, out),
+            Array::Int32(v) => join(b'i', v, out),
+            Array::UInt32(v) => join(b'I', v, out),
+            Array::Float(v) => join(b'f', v, out),
+        },
+        _ => {
+            let _ = write!(out, "{}", aux_int(value).unwrap_or(0));
+        }
+    }
+}
+
+fn format_raw_tags(data: &sam::alignment::record_buf::Data, out: &mut Vec<u8>) {
+    for (i, (tag, value)) in data.iter().enumerate() {
+        if i > 0 {
+            out.push(b'\t');
+        }
+        let tag_bytes: [u8; 2] = tag.into();
+        out.extend_from_slice(&tag_bytes);
+        out.push(b':');
+        out.push(aux_type_code(value));
+        out.push(b':');
+        format_aux_value(value, out);
+    }
+}
+
+/// Parse tab-separated SAM text tags (`NM:i:1\tRG:Z:grp1`) into `data`.
+fn parse_raw_tags(
+    text: &[u8],
+    data: &mut sam::alignment::record_buf::Data,
+) -> Result<(), EngineError> {
+    data.clear();
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    for field in text.split(|&b| b == b'\t') {
+        let invalid = || {
+            EngineError::InvalidArgument(format!(
+                "invalid auxiliary field '{}'",
+                String::from_utf8_lossy(field)
+            ))
+        };
+
+        let [t0, t1, b':', ty, b':', rest @ ..] = field else {
+            return Err(invalid());
+        };
+        let tag = parse_tag(&[*t0, *t1])?;
+        let value = parse_aux_value(*ty, rest).ok_or_else(invalid)?;
+        data.insert(tag, value);
+    }
+
+    Ok(())
+}
+
+fn parse_aux_value(ty: u8, text: &[u8]) -> Option<Value> {
+    fn parse<T: std::str::FromStr>(text: &[u8]) -> Option<T> {
+        std::str::from_utf8(text).ok()?.parse().ok()
+    }
+
+    fn parse_list<T: std::str::FromStr>(items: &[&[u8]]) -> Option<Vec<T>> {
+        items.iter().map(|item| parse(item)).collect()
+    }
+
+    match ty {
+        b'A' => match text {
+            [c] => Some(Value::Character(*c)),
+            _ => None,
+        },
+        b'i' => {
+            // Pick the narrowest BAM integer type, as htslib does.
+            let n: i64 = parse(text)?;
+            Some(if n >= 0 {
+                if let Ok(n) = u8::try_from(n) {
+                    Value::UInt8(n)
+                } else if let Ok(n) = u16::try_from(n) {
+                    Value::UInt16(n)
+                } else {
+                    Value::UInt32(u32::try_from(n).ok()?)
+                }
+            } else if let Ok(n) = i8::try_from(n) {
+                Value::Int8(n)
+            } else if let Ok(n) = i16::try_from(n) {
+                Value::Int16(n)
+            } else {
+                Value::Int32(i32::try_from(n).ok()?)
+            })
+        }
+        b'f' => parse(text).map(Value::Float),
+        b'Z' => Some(Value::String(text.into())),
+        b'H' => Some(Value::Hex(text.into())),
+        b'B' => {
+            let mut parts = text.split(|&b| b == b',');
+            let subtype = parts.next()?;
+            let items: Vec<&[u8]> = parts.collect();
+            let array = match subtype {
+                b"c" => Array::Int8(parse_list(&items)?),
+                b"C" => Array::UInt8(parse_list(&items)?),
+                b"s" => Array::Int16(parse_list(&items)?),
+                b"S" => Array::UInt16(parse_list(&items)?),
+                b"i" => Array::Int32(parse_list(&items)?),
+                b"I" => Array::UInt32(parse_list(&items)?),
+                b"f" => Array::Float(parse_list(&items)?),
+                _ => return None,
+            };
+            Some(Value::Array(array))
+        }
+        _ => None,
+    }
+}
+
+enum WriterInner {
+    BamFile(bam::io::Writer<bgzf::io::Writer<BufWriter<File>>>),
+    BamBytes(bam::io::Writer<bgzf::io::Writer<Vec<u8>>>),
+    SamFile(sam::io::Writer<BufWriter<File>>),
+    SamBytes(sam::io::Writer<Vec<u8>>),
+}
+
+/// Stateful alignment batch writer.
+///
+/// Accepts batches of records in the same struct-of-arrays layout that
+/// `AlignmentReader` produces and writes them as BAM (BGZF-compressed)
+/// or SAM text. The header is parsed from SAM header text and written
+/// on open. Call `finish()` to flush and close — for bytes mode this
+/// returns the accumulated output.
+pub struct AlignmentWriter {
+    inner: WriterInner,
+    header: sam::Header,
+    record_buf: sam::alignment::RecordBuf,
+}
+
+impl AlignmentWriter {
+    /// Open a writer to a file path.
+    ///
+    /// `header_text` is SAM header text, e.g. the output of
+    /// `AlignmentReader::header_text`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the header cannot be
+    /// parsed, or `EngineError::Io` if the file cannot be created or the
+    /// header cannot be written.
+    pub fn open_to_path(
+        path: &str,
+        header_text: &str,
+        format: AlignmentOutputFormat,
+    ) -> Result<Self, EngineError> {
+        let header = parse_header(header_text)?;
+        let file = File::create(path)
+            .map_err(|e| EngineError::Io(format!("failed to create '{path}': {e}")))?;
+        let buf = BufWriter::new(file);
+
+        let inner = match format {
+            AlignmentOutputFormat::Bam => WriterInner::BamFile(bam::io::Writer::new(buf)),
+            AlignmentOutputFormat::Sam => WriterInner::SamFile(sam::io::Writer::new(buf)),
+        };
+
+        Self::with_header(inner, header)
+    }
+
+    /// Open a writer to an in-memory buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the header cannot be
+    /// parsed.
+    pub fn open_to_bytes(
+        header_text: &str,
+        format: AlignmentOutputFormat,
+    ) -> Result<Self, EngineError> {
+        let header = parse_header(header_text)?;
+
+        let inner = match format {
+            AlignmentOutputFormat::Bam => WriterInner::BamBytes(bam::io::Writer::new(Vec::new())),
+            AlignmentOutputFormat::Sam => WriterInner::SamBytes(sam::io::Writer::new(Vec::new())),
+        };
+
+        Self::with_header(inner, header)
+    }
+
+    /// Write a batch of alignment records.
+    ///
+    /// Reference names are resolved against the header's reference
+    /// sequence dictionary; `*` marks an unplaced record. Positions are
+    /// 1-based with 0 meaning unset, a mapping quality of 255 means
+    /// missing, and a quality string of all `*` means absent scores.
+    /// Optional fields are taken from `raw_tag_data` when the batch
+    /// carries it, then from the typed `tags` columns, which replace a
+    /// raw tag of the same name. `String` columns are written as `Z`
+    /// values and integers use the smallest SAM type that holds them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidOffsets` or
+    /// `EngineError::InvalidArgument` if the batch is malformed or
+    /// references an unknown sequence, and `EngineError::Io` if writing
+    /// fails.
+    pub fn write_batch(&mut self, batch: &AlignmentBatch) -> Result<(), EngineError> {
+        validate_batch(batch)?;
+
+        for i in 0..batch.count as usize {
+            fill_record(&self.header, batch, i, &mut self.record_buf)?;
+
+            let result = match &mut self.inner {
+                WriterInner::BamFile(w) => w.write_alignment_record(&self.header, &self.record_buf),
+                WriterInner::BamBytes(w) => {
+                    w.write_alignment_record(&self.header, &self.record_buf)
+                }
+                WriterInner::SamFile(w) => w.write_alignment_record(&self.header, &self.record_buf),
+                WriterInner::SamBytes(w) => {
+                    w.write_alignment_record(&self.header, &self.record_buf)
+                }
+            };
+            result.map_err(|e| EngineError::Io(format!("alignment write error: {e}")))?;
+        }
+
+        Ok(())
+    }
+
+    /// Flush and close the writer.
+    ///
+    /// For file mode, returns `None`. For bytes mode, returns the
+    /// accumulated output bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if flushing fails.
+    pub fn finish(self) -> Result<Option<Vec<u8>>, EngineError> {
+        match self.inner {
+            WriterInner::BamFile(w) => {
+                let mut buf = w
+                    .into_inner()
+                    .finish()
+                    .map_err(|e| EngineError::Io(format!("BGZF finish error: {e}")))?;
+                buf.flush()
+                    .map_err(|e| EngineError::Io(format!("flush error: {e}")))?;
+                Ok(None)
+            }
+            WriterInner::BamBytes(w) => {
+                let bytes = w
+                    .into_inner()
+                    .finish()
+                    .map_err(|e| EngineError::Io(format!("BGZF finish error: {e}")))?;
+                Ok(Some(bytes))
+            }
+            WriterInner::SamFile(w) => {
+                w.into_inner()
+                    .flush()
+                    .map_err(|e| EngineError::Io(format!("flush error: {e}")))?;
+                Ok(None)
+            }
+            WriterInner::SamBytes(w) => Ok(Some(w.into_inner())),
+        }
+    }
+
+    fn with_header(mut inner: WriterInner, header: sam::Header) -> Result<Self, EngineError> {
+        let result = match &mut inner {
+            WriterInner::BamFile(w) => w.write_header(&header),
+            WriterInner::BamBytes(w) => w.write_header(&header),
+            WriterInner::SamFile(w) => w.write_header(&header),
+            WriterInner::SamBytes(w) => w.write_header(&header),
+        };
+        result.map_err(|e| EngineError::Io(format!("failed to write header: {e}")))?;
+
+        Ok(Self {
+            inner,
+            header,
+            record_buf: sam::alignment::RecordBuf::default(),
+        })
+    }
+}
+
+fn parse_header(header_text: &str) -> Result<sam::Header, EngineError> {
+    header_text
+        .parse()
+        .map_err(|e| EngineError::InvalidArgument(format!("failed to parse SAM header: {e}")))
+}
+
+fn validate_batch(batch: &AlignmentBatch) -> Result<(), EngineError> {
+    let count = batch.count as usize;
+
+    let columns: [(&str, &[u8], &[u32]); 6] = [
+        ("qname", &batch.qname_data, &batch.qname_offsets),
+        ("sequence", &batch.sequence_data, &batch.sequence_offsets),
+        ("quality", &batch.quality_data, &batch.quality_offsets),
+        ("cigar", &batch.cigar_data, &batch.cigar_offsets),
+        ("rname", &batch.rname_data, &batch.rname_offsets),
+        ("rnext", &batch.rnext_data, &batch.rnext_offsets),
+    ];
+    for (field, data, offsets) in columns {
+        if offsets.len() != count + 1 {
+            return Err(EngineError::InvalidArgument(format!(
+                "alignment batch: {field} offsets length ({}) != count + 1 ({})",
+                offsets.len(),
+                count + 1
+            )));
+        }
+        validate_offsets(offsets, data.len())?;
+    }
+
+    for column in &batch.tags {
+        validate_tag_column(column, count)?;
+    }
+
+    if !batch.raw_tag_offsets.is_empty() {
+        if batch.raw_tag_offsets.len() != count + 1 {
+            return Err(EngineError::InvalidArgument(format!(
+                "alignment batch: raw_tag offsets length ({}) != count + 1 ({})",
+                batch.raw_tag_offsets.len(),
+                count + 1
+            )));
+        }
+        validate_offsets(&batch.raw_tag_offsets, batch.raw_tag_data.len())?;
+    }
+
+    let lengths = [
+        ("flags", batch.flags.len()),
+        ("positions", batch.positions.len()),
+        ("mapping_qualities", batch.mapping_qualities.len()),
+        ("next_positions", batch.next_positions.len()),
+        ("template_lengths", batch.template_lengths.len()),
+    ];
+    for (field, len) in lengths {
+        if len != count {
+            return Err(EngineError::InvalidArgument(format!(
+                "alignment batch: {field} length ({len}) != count ({count})"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_tag_column(column: &AuxTagColumn, count: usize) -> Result<(), EngineError> {
+    parse_tag(column.tag.as_bytes())?;
+    let mismatch = |field: &str, len: usize, expected: usize| {
+        EngineError::InvalidArgument(format!(
+            "alignment batch: tag {} {field} length ({len}) != {expected}",
+            column.tag
+        ))
+    };
+    if column.present.len() != count {
+        return Err(mismatch("present", column.present.len(), count));
+    }
+    let (values, offsets) = match column.kind {
+        AuxTagKind::Int => (column.int_values.len(), None),
+        AuxTagKind::Float => (column.float_values.len(), None),
+        AuxTagKind::String => (column.string_data.len(), Some(&column.string_offsets)),
+        AuxTagKind::IntArray => (column.int_values.len(), Some(&column.array_offsets)),
+        AuxTagKind::FloatArray => (column.float_values.len(), Some(&column.array_offsets)),
+    };
+    match offsets {
+        None if values != count => Err(mismatch("values", values, count)),
+        None => Ok(()),
+        Some(offsets) if offsets.len() != count + 1 => {
+            Err(mismatch("offsets", offsets.len(), count + 1))
+        }
+        Some(offsets) => validate_offsets(offsets, values),
+    }
+}
+
+fn fill_record(
+    header: &sam::Header,
+    batch: &AlignmentBatch,
+    i: usize,
+    record: &mut sam::alignment::RecordBuf,
+) -> Result<(), EngineError> {
+    let name = field_at(&batch.qname_data, &batch.qname_offsets, i);
+    *record.name_mut() = if name == b"*" {
+        None
+    } else {
+        Some(name.into())
+    };
+
+    *record.flags_mut() = Flags::from(batch.flags[i]);
+
+    let rname = field_at(&batch.rname_data, &batch.rname_offsets, i);
+    let reference_sequence_id = resolve_reference_id(header, rname)?;
+    *record.reference_sequence_id_mut() = reference_sequence_id;
+
+    // RNEXT may use the SAM shorthand `=` for "same as RNAME".
+    let rnext = field_at(&batch.rnext_data, &batch.rnext_offsets, i);
+    *record.mate_reference_sequence_id_mut() = if rnext == b"=" {
+        reference_sequence_id
+    } else {
+        resolve_reference_id(header, rnext)?
+    };
+
+    *record.mate_alignment_start_mut() = usize::try_from(batch.next_positions[i])
+        .ok()
+        .and_then(Position::new);
+
+    *record.template_length_mut() = batch.template_lengths[i];
+
+    *record.alignment_start_mut() = usize::try_from(batch.positions[i])
+        .ok()
+        .and_then(Position::new);
+
+    *record.mapping_quality_mut() = MappingQuality::new(batch.mapping_qualities[i]);
+
+    let cigar = field_at(&batch.cigar_data, &batch.cigar_offsets, i);
+    *record.cigar_mut() = parse_cigar(cigar)?;
+
+    let seq = field_at(&batch.sequence_data, &batch.sequence_offsets, i);
+    let seq_buf: &mut Vec<u8> = record.sequence_mut().as_mut();
+    seq_buf.clear();
+    if seq != b"*" {
+        seq_buf.extend_from_slice(seq);
+    }
+
+    let qual = field_at(&batch.quality_data, &batch.quality_offsets, i);
+    let qual_buf: &mut Vec<u8> = record.quality_scores_mut().as_mut();
+    qual_buf.clear();
+    if qual != b"*" {
+        qual_buf.extend(qual.iter().map(|&q| q.saturating_sub(33)));
+    }
+
+    if batch.raw_tag_offsets.is_empty() {
+        record.data_mut().clear();
+    } else {
+        let tags = field_at(&batch.raw_tag_data, &batch.raw_tag_offsets, i);
+        parse_raw_tags(tags, record.data_mut())?;
+    }
+    for column in &batch.tags {
+        if column.present[i] != 0 {
+            let tag = parse_tag(column.tag.as_bytes())?;
+            record.data_mut().insert(tag, tag_value(column, i)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Record `i`'s value in a typed tag column, using the smallest SAM
+/// integer type that holds integer values.
+#[allow(clippy::cast_possible_truncation)]
+fn tag_value(column: &AuxTagColumn, i: usize) -> Result<Value, EngineError> {
+    let out_of_range = || {
+        EngineError::InvalidArgument(format!(
+            "tag {} value for record {i} does not fit a SAM integer",
+            column.tag
+        ))
+    };
+    let array = || column.array_offsets[i] as usize..column.array_offsets[i + 1] as usize;
+    Ok(match column.kind {
+        AuxTagKind::Int => int_value(column.int_values[i]).ok_or_else(out_of_range)?,
+        AuxTagKind::Float => Value::Float(column.float_values[i] as f32),
+        AuxTagKind::String => {
+            Value::String(field_at(&column.string_data, &column.string_offsets, i).into())
+        }
+        AuxTagKind::IntArray => {
+            Value::Array(int_array(&column.int_values[array()]).ok_or_else(out_of_range)?)
+        }
+        AuxTagKind::FloatArray => Value::Array(Array::Float(
+            column.float_values[array()]
+                .iter()
+                .map(|&x| x as f32)
+                .collect(),
+        )),
+    })
+}
+
+fn int_value(n: i64) -> Option<Value> {
+    if let Ok(n) = i8::try_from(n) {
+        Some(Value::Int8(n))
+    } else if let Ok(n) = u8::try_from(n) {
+        Some(Value::UInt8(n))
+    } else if let Ok(n) = i16::try_from(n) {
+        Some(Value::Int16(n))
+    } else if let Ok(n) = u16::try_from(n) {
+        Some(Value::UInt16(n))
+    } else if let Ok(n) = i32::try_from(n) {
+        Some(Value::Int32(n))
+    } else {
+        u32::try_from(n).ok().map(Value::UInt32)
+    }
+}
+
+fn int_array(values: &[i64]) -> Option<Array> {
+    fn convert<T: TryFrom<i64>>(values: &[i64]) -> Option<Vec<T>> {
+        values.iter().map(|&n| T::try_from(n).ok()).collect()
+    }
+    let (min, max) = (
+        values.iter().copied().min().unwrap_or(0),
+        values.iter().copied().max().unwrap_or(0),
+    );
+    if min >= 0 {
+        if max <= i64::from(u8::MAX) {
+            convert(values).map(Array::UInt8)
+        } else if max <= i64::from(u16::MAX) {
+            convert(values).map(Array::UInt16)
+        } else {
+            convert(values).map(Array::UInt32)
+        }
+    } else if min >= i64::from(i8::MIN) && max <= i64::from(i8::MAX) {
+        convert(values).map(Array::Int8)
+    } else if min >= i64::from(i16::MIN) && max <= i64::from(i16::MAX) {
+        convert(values).map(Array::Int16)
+    } else {
+        convert(values).map(Array::Int32)
+    }
+}
+
+fn resolve_reference_id(header: &sam::Header, name: &[u8]) -> Result<Option<usize>, EngineError> {
+    if name == b"*" {
+        return Ok(None);
+    }
+
+    header
+        .reference_sequences()
+        .get_index_of(name)
+        .map(Some)
+        .ok_or_else(|| {
+            EngineError::InvalidArgument(format!(
+                "reference sequence '{}' is not in the header",
+                String::from_utf8_lossy(name)
+            ))
+        })
+}
+
+fn parse_cigar(text: &[u8]) -> Result<Cigar, EngineError> {
+    let invalid = || {
+        EngineError::InvalidArgument(format!(
+            "invalid CIGAR string '{}'",
+            String::from_utf8_lossy(text)
+        ))
+    };
+
+    if text == b"*" {
+        return Ok(Cigar::default());
+    }
+
+    let mut ops = Vec::new();
+    let mut len: usize = 0;
+    let mut has_len = false;
+
+    for &b in text {
+        if b.is_ascii_digit() {
+            len = len
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(usize::from(b - b'0')))
+                .ok_or_else(invalid)?;
+            has_len = true;
+            continue;
+        }
+
+        let kind = match b {
+            b'M' => Kind::Match,
+            b'I' => Kind::Insertion,
+            b'D' => Kind::Deletion,
+            b'N' => Kind::Skip,
+            b'S' => Kind::SoftClip,
+            b'H' => Kind::HardClip,
+            b'P' => Kind::Pad,
+            b'=' => Kind::SequenceMatch,
+            b'X' => Kind::SequenceMismatch,
+            _ => return Err(invalid()),
+        };
+        if !has_len {
+            return Err(invalid());
+        }
+        ops.push(Op::new(kind, len));
+        len = 0;
+        has_len = false;
+    }
+
+    if has_len {
+        return Err(invalid());
+    }
+
+    Ok(Cigar::from(ops))
+}
+
+fn format_cigar(cigar: &Cigar, out: &mut Vec<u8>) {
+    let ops: &[sam::alignment::record::cigar::Op] = cigar.as_ref();
+    if ops.is_empty() {
+        out.push(b'*');
+        return;
+    }
+
+    for op in ops {
+        let ch = match op.kind() {
+            Kind::Match => b'M',
+            Kind::Insertion => b'I',
+            Kind::Deletion => b'D',
+            Kind::Skip => b'N',
+            Kind::SoftClip => b'S',
+            Kind::HardClip => b'H',
+            Kind::Pad => b'P',
+            Kind::SequenceMatch => b'=',
+            Kind::SequenceMismatch => b'X',
+        };
+        let _ = write!(out, "{}{}", op.len(), ch as char);
+    }
+}
+
+fn resolve_reference_names(header: &sam::Header) -> Vec<String> {
+    header
+        .reference_sequences()
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::ParseErrorKind;
+
+    const SAM: &[u8] = b"@HD\tVN:1.6\tSO:unsorted\n\
+@SQ\tSN:chr1\tLN:1000\n\
+@SQ\tSN:chr2\tLN:500\n\
+read1\t99\tchr1\t10\t60\t4M\t=\t200\t194\tACGT\tIIII\n\
+read2\t16\tchr2\t20\t30\t2S2M\tchr1\t50\t0\tGGCC\t*\n\
+read3\t4\t*\t0\t255\t*\t*\t0\t0\tTTAA\tJJJJ\n";
+
+    fn read_all(reader: &mut AlignmentReader) -> AlignmentBatch {
+        reader.read_batch(100).unwrap().unwrap()
+    }
+
+    fn assert_same_records(a: &AlignmentBatch, b: &AlignmentBatch) {
+        assert_eq!(a.count, b.count);
+        assert_eq!(a.qname_data, b.qname_data);
+        assert_eq!(a.sequence_data, b.sequence_data);
+        assert_eq!(a.quality_data, b.quality_data);
+        assert_eq!(a.cigar_data, b.cigar_data);
+        assert_eq!(a.rname_data, b.rname_data);
+        assert_eq!(a.rnext_data, b.rnext_data);
+        assert_eq!(a.rnext_offsets, b.rnext_offsets);
+        assert_eq!(a.flags, b.flags);
+        assert_eq!(a.positions, b.positions);
+        assert_eq!(a.mapping_qualities, b.mapping_qualities);
+        assert_eq!(a.next_positions, b.next_positions);
+        assert_eq!(a.template_lengths, b.template_lengths);
+    }
+
+    #[test]
+    fn mate_fields_are_read() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        let batch = read_all(&mut reader);
+        assert_eq!(batch.rnext_data, b"chr1chr1*");
+        assert_eq!(batch.rnext_offsets, vec![0, 4, 8, 9]);
+        assert_eq!(batch.next_positions, vec![200, 50, 0]);
+        assert_eq!(batch.template_lengths, vec![194, 0, 0]);
+    }
+
+    #[test]
+    fn sam_batch_round_trips_through_bam() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        let header = reader.header_text().unwrap();
+        let original = read_all(&mut reader);
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        writer.write_batch(&original).unwrap();
+        let bam_bytes = writer.finish().unwrap().unwrap();
+
+        let mut reader = AlignmentReader::open_from_bytes(bam_bytes).unwrap();
+        let round_tripped = read_all(&mut reader);
+        assert_eq!(round_tripped.format, "bam");
+        assert_same_records(&original, &round_tripped);
+        assert_eq!(reader.reference_sequences().len(), 2);
+    }
+
+    #[test]
+    fn sam_batch_round_trips_through_sam() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        let header = reader.header_text().unwrap();
+        let original = read_all(&mut reader);
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Sam).unwrap();
+        writer.write_batch(&original).unwrap();
+        let sam_bytes = writer.finish().unwrap().unwrap();
+
+        let text = String::from_utf8(sam_bytes.clone()).unwrap();
+        assert!(text.contains("read1\t99\tchr1\t10\t60\t4M\t=\t200\t194\tACGT\tIIII"));
+        assert!(text.contains("read2\t16\tchr2\t20\t30\t2S2M\tchr1\t50\t0\tGGCC\t*"));
+
+        let mut reader = AlignmentReader::open_from_bytes(sam_bytes).unwrap();
+        assert_same_records(&original, &read_all(&mut reader));
+    }
+
+    #[test]
+    fn unknown_reference_is_rejected() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        let mut batch = read_all(&mut reader);
+        batch.rname_data = b"chr1chrXchr2".to_vec();
+        batch.rname_offsets = vec![0, 4, 8, 12];
+        batch.count = 3;
+
+        let header = reader.header_text().unwrap();
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Sam).unwrap();
+        let err = writer.write_batch(&batch).unwrap_err().to_string();
+        assert!(
+            err.contains("chrX"),
+            "error should name the reference: {err}"
+        );
+    }
+
+    fn indexed_fixture() -> (std::path::PathBuf, Vec<u8>) {
+        let sam = b"@HD\tVN:1.6\tSO:coordinate\n\
+@SQ\tSN:chr1\tLN:100000\n\
+@SQ\tSN:chr2\tLN:100000\n\
+a\t0\tchr1\t100\t60\t10M\t*\t0\t0\tACGTACGTAC\t*\n\
+b\t0\tchr1\t5000\t60\t10M\t*\t0\t0\tACGTACGTAC\t*\n\
+c\t0\tchr2\t995\t60\t10M\t*\t0\t0\tACGTACGTAC\t*\n\
+d\t0\tchr2\t3000\t60\t10M\t*\t0\t0\tACGTACGTAC\t*\n\
+e\t0\tchr2\t9000\t60\t10M\t*\t0\t0\tACGTACGTAC\t*\n";
+
+        let mut reader = AlignmentReader::open_from_bytes(sam.to_vec()).unwrap();
+        let header = reader.header_text().unwrap();
+        let batch = read_all(&mut reader);
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let bam_bytes = writer.finish().unwrap().unwrap();
+
+        let path = crate::test_dir("indexed").join("sample.bam");
+        std::fs::write(&path, &bam_bytes).unwrap();
+        let index = bam::fs::index(&path).unwrap();
+
+        let mut index_bytes = Vec::new();
+        bai::io::Writer::new(&mut index_bytes)
+            .write_index(&index)
+            .unwrap();
+
+        (path, index_bytes)
+    }
+
+    fn qnames(batch: &AlignmentBatch) -> Vec<&[u8]> {
+        batch
+            .qname_offsets
+            .windows(2)
+            .map(|w| &batch.qname_data[w[0] as usize..w[1] as usize])
+            .collect()
+    }
+
+    #[test]
+    fn indexed_query_returns_only_overlapping_records() {
+        let (path, index_bytes) = indexed_fixture();
+        let bam_bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let mut reader = AlignmentReader::open_indexed_from_bytes(bam_bytes, &index_bytes).unwrap();
+
+        reader.query("chr2:1000-5000").unwrap();
+        let batch = reader.read_batch(100).unwrap().unwrap();
+        assert_eq!(qnames(&batch), vec![&b"c"[..], &b"d"[..]]);
+        assert!(reader.read_batch(100).unwrap().is_none());
+
+        reader.query("chr1").unwrap();
+        let batch = reader.read_batch(1).unwrap().unwrap();
+        assert_eq!(qnames(&batch), vec![&b"a"[..]]);
+        let batch = reader.read_batch(1).unwrap().unwrap();
+        assert_eq!(qnames(&batch), vec![&b"b"[..]]);
+        assert!(reader.read_batch(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn indexed_query_from_path() {
+        let (path, index_bytes) = indexed_fixture();
+        let index_path = path.with_extension("bam.bai");
+        std::fs::write(&index_path, &index_bytes).unwrap();
+
+        let mut reader =
+            AlignmentReader::open_indexed(path.to_str().unwrap(), index_path.to_str().unwrap())
+                .unwrap();
+        reader.query("chr2:8000-20000").unwrap();
+        let batch = reader.read_batch(100).unwrap().unwrap();
+        assert_eq!(qnames(&batch), vec![&b"e"[..]]);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn query_without_index_is_rejected() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        assert!(reader.query("chr1:1-10").is_err());
+    }
+
+    #[test]
+    fn malformed_cigar_is_rejected() {
+        assert!(parse_cigar(b"10M5").is_err());
+        assert!(parse_cigar(b"M").is_err());
+        assert!(parse_cigar(b"3Q").is_err());
+        assert_eq!(parse_cigar(b"*").unwrap().as_ref().len(), 0);
+        assert_eq!(parse_cigar(b"3S10M2I").unwrap().as_ref().len(), 3);
+    }
+
+    const TAGGED_SAM: &[u8] = b"@HD\tVN:1.6\n\
+@SQ\tSN:chr1\tLN:1000\n\
+r1\t0\tchr1\t10\t60\t4M\t*\t0\t0\tACGT\t*\tNM:i:1\tRG:Z:grp1\tXS:f:1.5\n\
+r2\t0\tchr1\t20\t60\t4M\t*\t0\t0\tACGT\t*\tNM:i:-300\tXS:i:2\tZB:B:c,-1,2\n\
+r3\t0\tchr1\t30\t60\t4M\t*\t0\t0\tACGT\t*\n";
+
+    #[test]
+    fn requested_tags_become_typed_columns() {
+        let mut reader = AlignmentReader::open_from_bytes(TAGGED_SAM.to_vec()).unwrap();
+        reader
+            .set_tag_options(&AuxTagOptions {
+                tags: vec![
+                    "NM".into(),
+                    "XS:f".into(),
+                    "RG".into(),
+                    "ZB:B:c".into(),
+                    "XX:i".into(),
+                ],
+                raw: false,
+            })
+            .unwrap();
+        let batch = read_all(&mut reader);
+        assert!(batch.raw_tag_offsets.is_empty());
+
+        let [nm, xs, rg, zb, xx] = &batch.tags[..] else {
+            panic!("expected five tag columns");
+        };
+
+        assert_eq!(nm.tag, "NM");
+        assert_eq!(nm.kind, AuxTagKind::Int);
+        assert_eq!(nm.present, vec![1, 1, 0]);
+        assert_eq!(nm.int_values, vec![1, -300, 0]);
+
+        assert_eq!(xs.kind, AuxTagKind::Float);
+        assert_eq!(xs.float_values, vec![1.5, 2.0, 0.0]);
+
+        assert_eq!(rg.kind, AuxTagKind::String);
+        assert_eq!(rg.present, vec![1, 0, 0]);
+        assert_eq!(rg.string_data, b"grp1");
+        assert_eq!(rg.string_offsets, vec![0, 4, 4, 4]);
+
+        assert_eq!(zb.kind, AuxTagKind::IntArray);
+        assert_eq!(zb.present, vec![0, 1, 0]);
+        assert_eq!(zb.int_values, vec![-1, 2]);
+        assert_eq!(zb.array_offsets, vec![0, 0, 2, 2]);
+
+        assert_eq!(xx.kind, AuxTagKind::Int);
+        assert_eq!(xx.present, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn tag_value_of_another_type_is_a_parse_error() {
+        let mut reader = AlignmentReader::open_from_bytes(TAGGED_SAM.to_vec()).unwrap();
+        reader
+            .set_tag_options(&AuxTagOptions {
+                tags: vec!["XS:i".into()],
+                raw: false,
+            })
+            .unwrap();
+        let Err(EngineError::Parse(e)) = reader.read_batch(16) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(e.kind, ParseErrorKind::InvalidRecord);
+        assert_eq!((e.record, e.line), (1, Some(3)));
+        assert_eq!(e.field, Some("tags"));
+    }
+
+    #[test]
+    fn invalid_tag_name_is_rejected() {
+        let mut reader = AlignmentReader::open_from_bytes(TAGGED_SAM.to_vec()).unwrap();
+        for bad in ["N", "NMX", "1M", "XX", "XX:q", "ZB:B:q"] {
+            let options = AuxTagOptions {
+                tags: vec![bad.into()],
+                raw: false,
+            };
+            assert!(reader.set_tag_options(&options).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn raw_tags_round_trip_through_bam() {
+        let mut reader = AlignmentReader::open_from_bytes(TAGGED_SAM.to_vec()).unwrap();
+        let options = AuxTagOptions {
+            tags: Vec::new(),
+            raw: true,
+        };
+        reader.set_tag_options(&options).unwrap();
+        let header = reader.header_text().unwrap();
+        let original = read_all(&mut reader);
+        assert_eq!(
+            field_at(&original.raw_tag_data, &original.raw_tag_offsets, 0),
+            b"NM:i:1\tRG:Z:grp1\tXS:f:1.5"
+        );
+        assert_eq!(
+            field_at(&original.raw_tag_data, &original.raw_tag_offsets, 2),
+            b""
+        );
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        writer.write_batch(&original).unwrap();
+        let bam_bytes = writer.finish().unwrap().unwrap();
+
+        let mut reader = AlignmentReader::open_from_bytes(bam_bytes).unwrap();
+        reader.set_tag_options(&options).unwrap();
+        let round_tripped = read_all(&mut reader);
+        assert_same_records(&original, &round_tripped);
+        assert_eq!(original.raw_tag_data, round_tripped.raw_tag_data);
+        assert_eq!(original.raw_tag_offsets, round_tripped.raw_tag_offsets);
+    }
+
+    #[test]
+    fn typed_tags_are_written_back() {
+        let mut reader = AlignmentReader::open_from_bytes(TAGGED_SAM.to_vec()).unwrap();
+        let header = reader.header_text().unwrap();
+        let options = AuxTagOptions {
+            tags: vec!["NM".into(), "XS:f".into(), "RG".into(), "ZB:B:c".into()],
+            raw: true,
+        };
+        reader.set_tag_options(&options).unwrap();
+        let mut original = read_all(&mut reader);
+        original.tags[0].int_values[0] = 70_000;
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        writer.write_batch(&original).unwrap();
+        let bam_bytes = writer.finish().unwrap().unwrap();
+
+        let mut reader = AlignmentReader::open_from_bytes(bam_bytes).unwrap();
+        reader.set_tag_options(&options).unwrap();
+        let round_tripped = read_all(&mut reader);
+        assert_same_records(&original, &round_tripped);
+        assert_eq!(original.tags, round_tripped.tags);
+        assert_eq!(
+            field_at(
+                &round_tripped.raw_tag_data,
+                &round_tripped.raw_tag_offsets,
+                1
+            ),
+            b"NM:i:-300\tXS:f:2\tZB:B:c,-1,2"
+        );
+
+        original.tags[0].int_values[0] = i64::MAX;
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        assert!(writer.write_batch(&original).is_err());
+        original.tags[0].int_values.pop();
+        assert!(writer.write_batch(&original).is_err());
+    }
+
+    #[test]
+    fn malformed_raw_tag_is_rejected() {
+        let mut data = sam::alignment::record_buf::Data::default();
+        assert!(parse_raw_tags(b"NM:i:x", &mut data).is_err());
+        assert!(parse_raw_tags(b"NM:q:1", &mut data).is_err());
+        assert!(parse_raw_tags(b"NMi1", &mut data).is_err());
+        parse_raw_tags(b"NM:i:70000\tXA:A:c", &mut data).unwrap();
+        assert_eq!(data.get(&Tag::from(*b"NM")), Some(&Value::UInt32(70000)));
+    }
+
+    const CRAM_REFERENCE: &[u8] = b">chr1\n\
+ACGTTGCAACGTTGCAACGTTGCAACGTTGCAACGTTGCA\n\
+ACGTTGCAACGTTGCAACGTTGCAACGTTGCAACGTTGCA\n";
+
+    const CRAM_REFERENCE_FAI: &[u8] = b"chr1\t80\t6\t40\t41\n";
+
+    const CRAM_SAM: &[u8] = b"@HD\tVN:1.6\tSO:coordinate\n\
+@SQ\tSN:chr1\tLN:80\n\
+r1\t0\tchr1\t1\t60\t8M\t*\t0\t0\tACGTTGCA\tIIIIIIII\tNM:i:0\n\
+r2\t16\tchr1\t9\t60\t4M1I3M\t*\t0\t0\tACGTGTGC\tIIIIHHHH\n\
+r3\t4\t*\t0\t255\t*\t*\t0\t0\tGGGG\tJJJJ\n";
+
+    fn cram_fixture() -> Vec<u8> {
+        let reference = fasta::io::IndexedReader::new(
+            Cursor::new(CRAM_REFERENCE.to_vec()),
+            fai::io::Reader::new(CRAM_REFERENCE_FAI)
+                .read_index()
+                .unwrap(),
+        );
+        let repository = fasta::Repository::new(IndexedReader::new(reference));
+
+        let mut sam_reader = sam::io::Reader::new(CRAM_SAM);
+        let header = sam_reader.read_header().unwrap();
+
+        let mut writer = cram::io::writer::Builder::default()
+            .set_reference_sequence_repository(repository)
+            .build_from_writer(Vec::new());
+        writer.write_header(&header).unwrap();
+        for record in sam_reader.record_bufs(&header) {
+            writer
+                .write_alignment_record(&header, &record.unwrap())
+                .unwrap();
+        }
+        writer.try_finish(&header).unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn cram_batch_matches_sam() {
+        let mut reader = AlignmentReader::open_from_bytes(CRAM_SAM.to_vec()).unwrap();
+        reader
+            .set_tag_options(&AuxTagOptions {
+                tags: Vec::new(),
+                raw: true,
+            })
+            .unwrap();
+        let expected = read_all(&mut reader);
+
+        let mut reader = AlignmentReader::open_cram_from_bytes(
+            cram_fixture(),
+            CRAM_REFERENCE.to_vec(),
+            CRAM_REFERENCE_FAI,
+        )
+        .unwrap();
+        reader
+            .set_tag_options(&AuxTagOptions {
+                tags: Vec::new(),
+                raw: true,
+            })
+            .unwrap();
+        let batch = read_all(&mut reader);
+
+        assert_eq!(batch.format, "cram");
+        assert_same_records(&expected, &batch);
+        assert_eq!(expected.raw_tag_data, batch.raw_tag_data);
+        assert!(reader.read_batch(100).unwrap().is_none());
+    }
+
+    #[test]
+    fn cram_from_path_uses_reference_fai() {
+        let dir = crate::test_dir("cram");
+        let cram_path = dir.join("sample.cram");
+        let reference_path = dir.join("ref.fa");
+        std::fs::write(&cram_path, cram_fixture()).unwrap();
+        std::fs::write(&reference_path, CRAM_REFERENCE).unwrap();
+
+        let cram_path = cram_path.to_str().unwrap();
+        let reference_path = reference_path.to_str().unwrap();
+
+        let err = AlignmentReader::open_cram(cram_path, reference_path);
+        assert!(err.is_err(), "missing .fai should be reported");
+
+        std::fs::write(format!("{reference_path}.fai"), CRAM_REFERENCE_FAI).unwrap();
+        let mut reader = AlignmentReader::open_cram(cram_path, reference_path).unwrap();
+        let batch = read_all(&mut reader);
+        assert_eq!(batch.count, 3);
+        assert_eq!(batch.sequence_data, b"ACGTTGCAACGTGTGCGGGG");
+
+        let reader = AlignmentReader::open_from_path(cram_path).unwrap();
+        assert_eq!(reader.reference_sequences().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cram_magic_is_detected() {
+        let reader = AlignmentReader::open_from_bytes(cram_fixture()).unwrap();
+        assert_eq!(reader.format, AlignmentFormat::Cram);
+        assert!(reader.header_text().unwrap().contains("SN:chr1"));
+
+        assert!(AlignmentReader::open_cram_from_bytes(SAM.to_vec(), Vec::new(), b"").is_err());
+    }
+
+    #[test]
+    fn bounded_batches_count_sequence_and_quality_bytes() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        assert_eq!(reader.read_batch_bounded(100, 8).unwrap().unwrap().count, 1);
+        let batch = reader.read_batch_bounded(100, 9).unwrap().unwrap();
+        assert_eq!(batch.count, 2);
+        assert_eq!(batch.quality_data, b"****JJJJ");
+    }
+
+    #[test]
+    fn checkpoints_resume_sam_and_bam() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        let header = reader.header_text().unwrap();
+        let original = read_all(&mut reader);
+        assert_eq!(reader.records_read(), 3);
+        assert_eq!(reader.bytes_consumed().uncompressed, SAM.len() as u64);
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        writer.write_batch(&original).unwrap();
+        let bam = writer.finish().unwrap().unwrap();
+
+        for data in [SAM.to_vec(), bam] {
+            let mut reader = AlignmentReader::open_from_bytes(data.clone()).unwrap();
+            reader.read_batch(1).unwrap().unwrap();
+            let checkpoint =
+                ReaderCheckpoint::from_bytes(&reader.checkpoint().unwrap().to_bytes()).unwrap();
+            let rest = read_all(&mut reader);
+            assert!(reader.read_batch(1).unwrap().is_none());
+            assert_eq!(reader.bytes_consumed().compressed, data.len() as u64);
+
+            let mut resumed = AlignmentReader::open_from_bytes_at(data, &checkpoint).unwrap();
+            assert_eq!(resumed.records_read(), 1);
+            assert_same_records(&rest, &read_all(&mut resumed));
+            assert_eq!(resumed.records_read(), 3);
+        }
+    }
+
+    #[test]
+    fn sam_parse_errors_carry_record_and_line() {
+        let mut sam = SAM.to_vec();
+        sam.extend_from_slice(b"read4\tx\tchr1\t1\t60\t4M\t*\t0\t0\tACGT\tIIII\n");
+        let mut reader = AlignmentReader::open_from_bytes(sam).unwrap();
+        let Err(EngineError::Parse(err)) = reader.read_batch(100) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.format, RecordFormat::Sam);
+        assert_eq!((err.record, err.line), (4, Some(7)));
+
+        let Err(EngineError::Parse(err)) =
+            AlignmentReader::open_from_bytes(b"@SQ\tSN:chr1\tLN:x\n".to_vec())
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((err.kind, err.record), (ParseErrorKind::BadHeader, 0));
+    }
+
+    #[test]
+    fn generic_reader_streams_bam_and_sam() {
+        let mut reader = AlignmentReader::open_from_bytes(SAM.to_vec()).unwrap();
+        let header = reader.header_text().unwrap();
+        let original = read_all(&mut reader);
+
+        let mut writer =
+            AlignmentWriter::open_to_bytes(&header, AlignmentOutputFormat::Bam).unwrap();
+        writer.write_batch(&original).unwrap();
+        let bam_bytes = writer.finish().unwrap().unwrap();
+
+        let mut reader =
+            AlignmentReader::open_from_reader(Box::new(Cursor::new(bam_bytes.clone()))).unwrap();
+        let streamed = read_all(&mut reader);
+        assert_eq!(streamed.format, "bam");
+        assert_same_records(&original, &streamed);
+        assert!(reader.query("chr1:1-100").is_err());
+
+        // A reader that buffers one byte at a time is still sniffed as BAM.
+        let trickle = BufReader::with_capacity(1, Cursor::new(bam_bytes));
+        let mut reader = AlignmentReader::open_from_buf_reader(Box::new(trickle)).unwrap();
+        assert_same_records(&original, &read_all(&mut reader));
+
+        let mut reader = AlignmentReader::open_from_buf_reader(Box::new(SAM)).unwrap();
+        assert_same_records(&original, &read_all(&mut reader));
+    }
 }
@@ -0,0 +1,519 @@
+//! Arrow IPC export of record batches.
+//!
+//! FASTQ, FASTA and alignment batches are already laid out the way Arrow
+//! stores variable-width columns: contiguous data plus offsets. An
+//! `ArrowTable` takes ownership of a batch and wraps its data buffers as
+//! Arrow `Utf8`/`Binary` columns without copying, alongside primitive
+//! columns for flags, positions and MAPQ. Per-record metrics from
+//! `sequence_metrics_batch` can be appended as extra columns before the
+//! table is serialized as an Arrow IPC stream or file, for consumption by
+//! `DuckDB`, Polars, arrow-js and other Arrow readers.
+
+use std::{collections::HashMap, sync::Arc};
+
+use arrow_array::{
+    ArrayRef, BinaryArray, Float64Array, Int32Array, Int64Array, ListArray, RecordBatch,
+    StringArray, UInt16Array, UInt32Array, UInt8Array,
+};
+use arrow_buffer::{Buffer, NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_ipc::writer::{FileWriter, StreamWriter};
+use arrow_schema::{ArrowError, Field, Schema};
+
+use crate::{
+    alignment::{AlignmentBatch, AuxTagColumn, AuxTagKind},
+    fasta::FastaBatch,
+    fastq::FastqBatch,
+    metrics::SequenceMetricsResult,
+    validate_offsets, EngineError,
+};
+
+/// Arrow IPC framing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpcFormat {
+    /// The streaming format (`.arrows`), read front to back.
+    #[default]
+    Stream,
+    /// The random-access file format (`.arrow`, Feather v2).
+    File,
+}
+
+/// A batch converted to Arrow columns, ready to serialize.
+///
+/// The schema carries the source format (`"fastq"`, `"fasta"`, or the
+/// alignment batch's `"sam"`, `"bam"` or `"cram"`) under the
+/// `genotype:format` metadata key.
+#[derive(Debug)]
+pub struct ArrowTable {
+    format: &'static str,
+    rows: usize,
+    fields: Vec<Field>,
+    columns: Vec<ArrayRef>,
+}
+
+impl ArrowTable {
+    fn new(format: &'static str, rows: u32) -> Self {
+        Self {
+            format,
+            rows: rows as usize,
+            fields: Vec::new(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// Columns `name`, `description` and `sequence` (`Utf8`) and
+    /// `quality` (`Binary`, the raw quality bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidOffsets` if an offset array does not
+    /// match `count` or its data, or `EngineError::InvalidArgument` if a
+    /// text column is not valid UTF-8.
+    pub fn from_fastq(batch: FastqBatch) -> Result<Self, EngineError> {
+        let mut table = Self::new("fastq", batch.count);
+        table.push_packed("name", batch.name_data, &batch.name_offsets, true)?;
+        table.push_packed(
+            "description",
+            batch.description_data,
+            &batch.description_offsets,
+            true,
+        )?;
+        table.push_packed(
+            "sequence",
+            batch.sequence_data,
+            &batch.sequence_offsets,
+            true,
+        )?;
+        table.push_packed("quality", batch.quality_data, &batch.quality_offsets, false)?;
+        Ok(table)
+    }
+
+    /// Columns `name`, `description` and `sequence` (`Utf8`).
+    ///
+    /// # Errors
+    ///
+    /// As for `from_fastq`.
+    pub fn from_fasta(batch: FastaBatch) -> Result<Self, EngineError> {
+        let mut table = Self::new("fasta", batch.count);
+        table.push_packed("name", batch.name_data, &batch.name_offsets, true)?;
+        table.push_packed(
+            "description",
+            batch.description_data,
+            &batch.description_offsets,
+            true,
+        )?;
+        table.push_packed(
+            "sequence",
+            batch.sequence_data,
+            &batch.sequence_offsets,
+            true,
+        )?;
+        Ok(table)
+    }
+
+    /// The SAM columns in SAM order: `qname`, `flag` (`UInt16`), `rname`,
+    /// `pos` (`Int32`), `mapq` (`UInt8`), `cigar`, `rnext`, `pnext` and
+    /// `tlen` (`Int32`), `sequence`, and `quality` (`Binary`). Each typed
+    /// tag column follows under its tag name (`Int64`, `Float64`, `Utf8`,
+    /// or a `List` of `Int64` or `Float64` for `B` arrays, null where
+    /// absent), then `raw_tags` when the batch carries raw tag text.
+    ///
+    /// # Errors
+    ///
+    /// As for `from_fastq`, or `EngineError::InvalidArgument` if a
+    /// primitive or tag column does not have `count` entries.
+    pub fn from_alignment(batch: AlignmentBatch) -> Result<Self, EngineError> {
+        let mut table = Self::new(batch.format, batch.count);
+        table.push_packed("qname", batch.qname_data, &batch.qname_offsets, true)?;
+        table.push_primitive("flag", UInt16Array::from(batch.flags))?;
+        table.push_packed("rname", batch.rname_data, &batch.rname_offsets, true)?;
+        table.push_primitive("pos", Int32Array::from(batch.positions))?;
+        table.push_primitive("mapq", UInt8Array::from(batch.mapping_qualities))?;
+        table.push_packed("cigar", batch.cigar_data, &batch.cigar_offsets, true)?;
+        table.push_packed("rnext", batch.rnext_data, &batch.rnext_offsets, true)?;
+        table.push_primitive("pnext", Int32Array::from(batch.next_positions))?;
+        table.push_primitive("tlen", Int32Array::from(batch.template_lengths))?;
+        table.push_packed(
+            "sequence",
+            batch.sequence_data,
+            &batch.sequence_offsets,
+            true,
+        )?;
+        table.push_packed("quality", batch.quality_data, &batch.quality_offsets, false)?;
+        for tag in batch.tags {
+            table.push_tag(tag)?;
+        }
+        if !batch.raw_tag_offsets.is_empty() {
+            table.push_packed("raw_tags", batch.raw_tag_data, &batch.raw_tag_offsets, true)?;
+        }
+        Ok(table)
+    }
+
+    /// Append each computed metric as a nullable column: `length`
+    /// (`UInt32`), `gc`, `at`, `gc_skew`, `at_skew`, `entropy` and
+    /// `avg_qual` (`Float64`), `alphabet_mask` (`UInt32`), and `min_qual`
+    /// and `max_qual` (`Int32`). Metrics that were not requested are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a metric does not have
+    /// one value per row or its name is already a column.
+    pub fn append_metrics(&mut self, metrics: SequenceMetricsResult) -> Result<(), EngineError> {
+        let columns: [(&str, Option<ArrayRef>); 10] = [
+            ("length", wrap(metrics.lengths, UInt32Array::from)),
+            ("gc", wrap(metrics.gc, Float64Array::from)),
+            ("at", wrap(metrics.at, Float64Array::from)),
+            ("gc_skew", wrap(metrics.gc_skew, Float64Array::from)),
+            ("at_skew", wrap(metrics.at_skew, Float64Array::from)),
+            ("entropy", wrap(metrics.entropy, Float64Array::from)),
+            (
+                "alphabet_mask",
+                wrap(metrics.alphabet_mask, UInt32Array::from),
+            ),
+            ("avg_qual", wrap(metrics.avg_qual, Float64Array::from)),
+            ("min_qual", wrap(metrics.min_qual, Int32Array::from)),
+            ("max_qual", wrap(metrics.max_qual, Int32Array::from)),
+        ];
+        for (name, column) in columns {
+            if let Some(column) = column {
+                self.push_column(name, column, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of rows (records).
+    pub fn num_rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Column names, in schema order.
+    pub fn column_names(&self) -> Vec<String> {
+        self.fields.iter().map(|f| f.name().clone()).collect()
+    }
+
+    /// Serialize the table as a single record batch in the given IPC
+    /// framing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if encoding fails.
+    pub fn to_ipc(&self, format: IpcFormat) -> Result<Vec<u8>, EngineError> {
+        let metadata = HashMap::from([("genotype:format".to_owned(), self.format.to_owned())]);
+        let schema = Arc::new(Schema::new_with_metadata(self.fields.clone(), metadata));
+        let batch = RecordBatch::try_new(schema.clone(), self.columns.clone()).map_err(ipc_err)?;
+
+        match format {
+            IpcFormat::Stream => {
+                let mut writer = StreamWriter::try_new(Vec::new(), &schema).map_err(ipc_err)?;
+                writer.write(&batch).map_err(ipc_err)?;
+                writer.into_inner().map_err(ipc_err)
+            }
+            IpcFormat::File => {
+                let mut writer = FileWriter::try_new(Vec::new(), &schema).map_err(ipc_err)?;
+                writer.write(&batch).map_err(ipc_err)?;
+                writer.into_inner().map_err(ipc_err)
+            }
+        }
+    }
+
+    fn push_column(
+        &mut self,
+        name: &str,
+        column: ArrayRef,
+        nullable: bool,
+    ) -> Result<(), EngineError> {
+        if column.len() != self.rows {
+            return Err(EngineError::InvalidArgument(format!(
+                "arrow: column '{name}' has {} values for {} rows",
+                column.len(),
+                self.rows
+            )));
+        }
+        if self.fields.iter().any(|f| f.name() == name) {
+            return Err(EngineError::InvalidArgument(format!(
+                "arrow: column '{name}' already exists"
+            )));
+        }
+        self.fields
+            .push(Field::new(name, column.data_type().clone(), nullable));
+        self.columns.push(column);
+        Ok(())
+    }
+
+    fn push_primitive<A: arrow_array::Array + 'static>(
+        &mut self,
+        name: &str,
+        column: A,
+    ) -> Result<(), EngineError> {
+        self.push_column(name, Arc::new(column), false)
+    }
+
+    /// Wrap a packed `(data, offsets)` column as `Utf8` or `Binary`,
+    /// moving `data` into Arrow without copying.
+    fn push_packed(
+        &mut self,
+        name: &str,
+        data: Vec<u8>,
+        offsets: &[u32],
+        utf8: bool,
+    ) -> Result<(), EngineError> {
+        let offsets = arrow_offsets(name, offsets, data.len(), self.rows)?;
+        let values = Buffer::from_vec(data);
+        let column: ArrayRef = if utf8 {
+            Arc::new(StringArray::try_new(offsets, values, None).map_err(|e| {
+                EngineError::InvalidArgument(format!("arrow: column '{name}': {e}"))
+            })?)
+        } else {
+            Arc::new(BinaryArray::new(offsets, values, None))
+        };
+        self.push_column(name, column, false)
+    }
+
+    fn push_tag(&mut self, tag: AuxTagColumn) -> Result<(), EngineError> {
+        let nulls = NullBuffer::from(tag.present.iter().map(|&p| p != 0).collect::<Vec<_>>());
+        let column: ArrayRef = match tag.kind {
+            AuxTagKind::Int => Arc::new(
+                Int64Array::try_new(ScalarBuffer::from(tag.int_values), Some(nulls))
+                    .map_err(|e| tag_err(&tag.tag, &e))?,
+            ),
+            AuxTagKind::Float => Arc::new(
+                Float64Array::try_new(ScalarBuffer::from(tag.float_values), Some(nulls))
+                    .map_err(|e| tag_err(&tag.tag, &e))?,
+            ),
+            AuxTagKind::String => {
+                let offsets = arrow_offsets(
+                    &tag.tag,
+                    &tag.string_offsets,
+                    tag.string_data.len(),
+                    self.rows,
+                )?;
+                Arc::new(
+                    StringArray::try_new(offsets, Buffer::from_vec(tag.string_data), Some(nulls))
+                        .map_err(|e| tag_err(&tag.tag, &e))?,
+                )
+            }
+            AuxTagKind::IntArray => {
+                let len = tag.int_values.len();
+                let values = Arc::new(Int64Array::from(tag.int_values));
+                self.tag_list(&tag.tag, &tag.array_offsets, values, len, nulls)?
+            }
+            AuxTagKind::FloatArray => {
+                let len = tag.float_values.len();
+                let values = Arc::new(Float64Array::from(tag.float_values));
+                self.tag_list(&tag.tag, &tag.array_offsets, values, len, nulls)?
+            }
+        };
+        self.push_column(&tag.tag, column, true)
+    }
+
+    fn tag_list(
+        &self,
+        name: &str,
+        offsets: &[u32],
+        values: ArrayRef,
+        len: usize,
+        nulls: NullBuffer,
+    ) -> Result<ArrayRef, EngineError> {
+        let offsets = arrow_offsets(name, offsets, len, self.rows)?;
+        let item = Arc::new(Field::new("item", values.data_type().clone(), false));
+        Ok(Arc::new(
+            ListArray::try_new(item, offsets, values, Some(nulls))
+                .map_err(|e| tag_err(name, &e))?,
+        ))
+    }
+}
+
+fn wrap<T, A>(values: Option<Vec<T>>, array: impl Fn(Vec<T>) -> A) -> Option<ArrayRef>
+where
+    A: arrow_array::Array + 'static,
+{
+    values.map(|v| Arc::new(array(v)) as ArrayRef)
+}
+
+/// Validate a batch's `u32` offsets and convert them to Arrow's `i32`.
+fn arrow_offsets(
+    name: &str,
+    offsets: &[u32],
+    data_len: usize,
+    rows: usize,
+) -> Result<OffsetBuffer<i32>, EngineError> {
+    if offsets.len() != rows + 1 {
+        return Err(EngineError::InvalidOffsets(format!(
+            "arrow: column '{name}' has {} offsets for {rows} rows",
+            offsets.len()
+        )));
+    }
+    validate_offsets(offsets, data_len)?;
+    let offsets = offsets
+        .iter()
+        .map(|&o| i32::try_from(o))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| {
+            EngineError::InvalidOffsets(format!(
+                "arrow: column '{name}' exceeds the 2 GiB Utf8/Binary limit"
+            ))
+        })?;
+    Ok(OffsetBuffer::new(ScalarBuffer::from(offsets)))
+}
+
+fn tag_err(tag: &str, e: &ArrowError) -> EngineError {
+    EngineError::InvalidArgument(format!("arrow: tag column '{tag}': {e}"))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn ipc_err(e: ArrowError) -> EngineError {
+    EngineError::Io(format!("Arrow IPC write error: {e}"))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::Int64Type, Array};
+    use arrow_ipc::reader::{FileReader, StreamReader};
+
+    use super::*;
+    use crate::{alignment::AlignmentReader, fastq::FastqReader, sequence_metrics_batch};
+
+    fn read_stream(bytes: &[u8]) -> RecordBatch {
+        let mut reader = StreamReader::try_new(bytes, None).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        batch
+    }
+
+    #[test]
+    fn fastq_batch_round_trips_with_metrics() {
+        let data = b"@r1 first\nACGT\n+\nIIII\n@r2\nGG\n+\n#5\n".to_vec();
+        let batch = FastqReader::open_from_bytes(data)
+            .unwrap()
+            .read_batch(10)
+            .unwrap()
+            .unwrap();
+        let metrics = sequence_metrics_batch(
+            &batch.sequence_data,
+            &batch.sequence_offsets,
+            &batch.quality_data,
+            &batch.quality_offsets,
+            crate::metrics::METRIC_LENGTH | crate::metrics::METRIC_GC,
+            33,
+        )
+        .unwrap();
+
+        let mut table = ArrowTable::from_fastq(batch).unwrap();
+        table.append_metrics(metrics).unwrap();
+        assert_eq!(table.num_rows(), 2);
+        assert_eq!(
+            table.column_names(),
+            ["name", "description", "sequence", "quality", "length", "gc"]
+        );
+
+        let batch = read_stream(&table.to_ipc(IpcFormat::Stream).unwrap());
+        assert_eq!(batch.schema().metadata()["genotype:format"], "fastq");
+        let names = batch.column(0).as_string::<i32>();
+        assert_eq!(names.value(1), "r2");
+        assert_eq!(batch.column(1).as_string::<i32>().value(0), "first");
+        assert_eq!(batch.column(3).as_binary::<i32>().value(1), b"#5");
+        let gc = batch
+            .column(5)
+            .as_primitive::<arrow_array::types::Float64Type>();
+        assert!((gc.value(0) - 50.0).abs() < f64::EPSILON);
+
+        let bytes = table.to_ipc(IpcFormat::File).unwrap();
+        let mut reader = FileReader::try_new(std::io::Cursor::new(bytes), None).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap().num_rows(), 2);
+    }
+
+    #[test]
+    fn alignment_batch_exports_primitive_and_tag_columns() {
+        let sam = b"@SQ\tSN:chr1\tLN:100\n\
+r1\t0\tchr1\t5\t60\t4M\t*\t0\t0\tACGT\tIIII\tNM:i:1\n\
+r2\t16\tchr1\t9\t12\t2M\t*\t0\t0\tGG\t##\n"
+            .to_vec();
+        let mut reader = AlignmentReader::open_from_bytes(sam).unwrap();
+        reader
+            .set_tag_options(&crate::alignment::AuxTagOptions {
+                tags: vec!["NM".to_owned()],
+                raw: false,
+            })
+            .unwrap();
+        let table = ArrowTable::from_alignment(reader.read_batch(10).unwrap().unwrap()).unwrap();
+        assert_eq!(
+            table.column_names(),
+            [
+                "qname", "flag", "rname", "pos", "mapq", "cigar", "rnext", "pnext", "tlen",
+                "sequence", "quality", "NM"
+            ]
+        );
+
+        let batch = read_stream(&table.to_ipc(IpcFormat::Stream).unwrap());
+        assert_eq!(batch.schema().metadata()["genotype:format"], "sam");
+        let flags = batch
+            .column(1)
+            .as_primitive::<arrow_array::types::UInt16Type>();
+        assert_eq!(flags.values().to_vec(), vec![0, 16]);
+        let mapq = batch
+            .column(4)
+            .as_primitive::<arrow_array::types::UInt8Type>();
+        assert_eq!(mapq.values().to_vec(), vec![60, 12]);
+        assert_eq!(batch.column(5).as_string::<i32>().value(0), "4M");
+        let nm = batch.column(11).as_primitive::<Int64Type>();
+        assert_eq!(nm.value(0), 1);
+        assert!(nm.is_null(1));
+    }
+
+    #[test]
+    fn rejects_malformed_columns() {
+        let batch = FastaBatch {
+            count: 2,
+            name_data: b"ab".to_vec(),
+            name_offsets: vec![0, 1],
+            description_data: Vec::new(),
+            description_offsets: vec![0, 0, 0],
+            sequence_data: b"AC".to_vec(),
+            sequence_offsets: vec![0, 1, 2],
+        };
+        assert!(matches!(
+            ArrowTable::from_fasta(batch),
+            Err(EngineError::InvalidOffsets(_))
+        ));
+
+        let batch = FastaBatch {
+            count: 1,
+            name_data: vec![0xff],
+            name_offsets: vec![0, 1],
+            description_data: Vec::new(),
+            description_offsets: vec![0, 0],
+            sequence_data: b"A".to_vec(),
+            sequence_offsets: vec![0, 1],
+        };
+        assert!(matches!(
+            ArrowTable::from_fasta(batch),
+            Err(EngineError::InvalidArgument(_))
+        ));
+
+        let batch = FastaBatch {
+            count: 1,
+            name_data: b"a".to_vec(),
+            name_offsets: vec![0, 1],
+            description_data: Vec::new(),
+            description_offsets: vec![0, 0],
+            sequence_data: b"A".to_vec(),
+            sequence_offsets: vec![0, 1],
+        };
+        let mut table = ArrowTable::from_fasta(batch).unwrap();
+        let metrics = SequenceMetricsResult {
+            lengths: Some(vec![1, 2]),
+            gc: None,
+            at: None,
+            gc_skew: None,
+            at_skew: None,
+            entropy: None,
+            alphabet_mask: None,
+            avg_qual: None,
+            min_qual: None,
+            max_qual: None,
+        };
+        assert!(table.append_metrics(metrics).is_err());
+    }
+}
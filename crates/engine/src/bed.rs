@@ -0,0 +1,809 @@
+//! BED batch reader and writer.
+//!
+//! `BedReader` parses BED3 through BED12 intervals into struct-of-arrays
+//! batches, skipping blank, `#` comment, `track` and `browser` lines.
+//! Fields are tab-separated; lines without a tab are split on runs of
+//! spaces, as many tools emit. Gzip (including BGZF) and Zstandard input
+//! is detected from the leading magic bytes.
+//!
+//! BED columns come in groups: name, score and strand may each be
+//! present, thickStart/thickEnd only together, then itemRgb, and the
+//! three block columns only together. A batch records how many columns
+//! each interval carried in `field_counts`, rounding down to the last
+//! complete group (a seven-column line counts as BED6, a ten- or
+//! eleven-column line as BED9); columns past the twelfth are ignored.
+//! An optional column is present in a batch if any of its intervals
+//! carries it, with defaults filled in for the intervals that don't.
+//!
+//! `BedWriter` writes the same batches back out, emitting
+//! `field_counts[i]` columns for interval `i`.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Write},
+};
+
+use crate::{
+    codec::{self, BoxBufRead, ByteCounter, Counted, Output},
+    field_at,
+    text::{invalid, parse_field, parse_number, push_packed},
+    validate_offsets, BytesConsumed, CompressionMode, EngineError, ParseError, RecordFault,
+    RecordFormat,
+};
+
+/// A batch of BED intervals in struct-of-arrays layout.
+///
+/// Coordinates are zero-based and half-open, as in the file.
+#[derive(Debug, Default)]
+pub struct BedBatch {
+    pub count: u32,
+    /// Columns carried by each interval: 3, 4, 5, 6, 8, 9 or 12.
+    pub field_counts: Vec<u8>,
+
+    pub chrom_data: Vec<u8>,
+    pub chrom_offsets: Vec<u32>,
+    pub starts: Vec<u64>,
+    pub ends: Vec<u64>,
+
+    /// Empty for intervals without a name.
+    pub name_data: Option<Vec<u8>>,
+    pub name_offsets: Option<Vec<u32>>,
+    /// `NaN` for a `.` score; 0 for intervals without one.
+    pub scores: Option<Vec<f64>>,
+    /// `+`, `-` or `.`; `.` for intervals without a strand.
+    pub strands: Option<Vec<u8>>,
+    /// The whole interval for intervals without a thick region.
+    pub thick_starts: Option<Vec<u64>>,
+    pub thick_ends: Option<Vec<u64>>,
+    /// `0xRRGGBB`; 0 for intervals without a colour.
+    pub item_rgbs: Option<Vec<u32>>,
+    /// CSR offsets into `block_sizes` and `block_starts`; intervals
+    /// without blocks have none.
+    pub block_offsets: Option<Vec<u32>>,
+    pub block_sizes: Option<Vec<u32>>,
+    /// Block starts relative to the interval start.
+    pub block_starts: Option<Vec<u32>>,
+}
+
+/// One parsed line, borrowing from the reader's buffers.
+struct BedRecord<'a> {
+    field_count: u8,
+    chrom: &'a [u8],
+    start: u64,
+    end: u64,
+    name: Option<&'a [u8]>,
+    score: Option<f64>,
+    strand: Option<u8>,
+    thick: Option<(u64, u64)>,
+    item_rgb: Option<u32>,
+    blocks: Option<(&'a [u32], &'a [u32])>,
+}
+
+/// Append `value` to an optional column, creating the column with
+/// `default` for the `len` earlier rows the first time a value appears.
+fn push_column<T: Clone>(column: &mut Option<Vec<T>>, len: usize, value: Option<T>, default: T) {
+    match (column.as_mut(), value) {
+        (Some(values), value) => values.push(value.unwrap_or(default)),
+        (None, Some(value)) => {
+            let mut values = vec![default; len];
+            values.push(value);
+            *column = Some(values);
+        }
+        (None, None) => {}
+    }
+}
+
+/// Append a CSR row to an optional column pair, as for `push_column`.
+fn push_packed_column<T: Copy>(
+    data: &mut Option<Vec<T>>,
+    offsets: &mut Option<Vec<u32>>,
+    len: usize,
+    value: Option<&[T]>,
+) {
+    if offsets.is_none() && value.is_some() {
+        *data = Some(Vec::new());
+        *offsets = Some(vec![0; len + 1]);
+    }
+    if let (Some(data), Some(offsets)) = (data.as_mut(), offsets.as_mut()) {
+        push_packed(data, offsets, value.unwrap_or_default());
+    }
+}
+
+impl BedBatch {
+    fn empty() -> Self {
+        Self {
+            chrom_offsets: vec![0],
+            ..Self::default()
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn push(&mut self, record: &BedRecord<'_>) {
+        let n = self.count as usize;
+        self.field_counts.push(record.field_count);
+        self.chrom_data.extend_from_slice(record.chrom);
+        self.chrom_offsets.push(self.chrom_data.len() as u32);
+        self.starts.push(record.start);
+        self.ends.push(record.end);
+
+        push_packed_column(&mut self.name_data, &mut self.name_offsets, n, record.name);
+        push_column(&mut self.scores, n, record.score, 0.0);
+        push_column(&mut self.strands, n, record.strand, b'.');
+        // Thick defaults depend on each row's own interval, so backfill
+        // from the coordinates already pushed.
+        if self.thick_starts.is_none() && record.thick.is_some() {
+            self.thick_starts = Some(self.starts[..n].to_vec());
+            self.thick_ends = Some(self.ends[..n].to_vec());
+        }
+        if let (Some(starts), Some(ends)) = (&mut self.thick_starts, &mut self.thick_ends) {
+            let (start, end) = record.thick.unwrap_or((record.start, record.end));
+            starts.push(start);
+            ends.push(end);
+        }
+        push_column(&mut self.item_rgbs, n, record.item_rgb, 0);
+        let (sizes, starts) = record.blocks.unzip();
+        push_packed_column(&mut self.block_sizes, &mut self.block_offsets, n, sizes);
+        if self.block_starts.is_none() && self.block_sizes.is_some() {
+            self.block_starts = Some(Vec::new());
+        }
+        if let Some(block_starts) = &mut self.block_starts {
+            block_starts.extend_from_slice(starts.unwrap_or_default());
+        }
+
+        self.count += 1;
+    }
+}
+
+/// Parse a comma-separated list of block values, allowing the trailing
+/// comma UCSC tools write.
+fn parse_list(field: &[u8], out: &mut Vec<u32>) -> Option<()> {
+    out.clear();
+    let field = field.strip_suffix(b",").unwrap_or(field);
+    if field.is_empty() {
+        return Some(());
+    }
+    for value in field.split(|&b| b == b',') {
+        out.push(parse_number(value)?);
+    }
+    Some(())
+}
+
+fn parse_rgb(field: &[u8]) -> Option<u32> {
+    if field == b"0" || field == b"." {
+        return Some(0);
+    }
+    let mut parts = field.split(|&b| b == b',');
+    let mut rgb = 0;
+    for _ in 0..3 {
+        rgb = (rgb << 8) | u32::from(parse_number::<u8>(parts.next()?)?);
+    }
+    parts.next().is_none().then_some(rgb)
+}
+
+/// Split a line into at most 12 fields, returning them with the total
+/// field count.
+fn split_fields(line: &[u8]) -> ([&[u8]; 12], usize) {
+    let mut fields = [&line[..0]; 12];
+    let mut n = 0;
+    let mut push = |field| {
+        if n < fields.len() {
+            fields[n] = field;
+        }
+        n += 1;
+    };
+    if line.contains(&b'\t') {
+        line.split(|&b| b == b'\t').for_each(&mut push);
+    } else {
+        line.split(|&b| b == b' ')
+            .filter(|field| !field.is_empty())
+            .for_each(&mut push);
+    }
+    (fields, n)
+}
+
+/// Parse the blockCount, blockSizes and blockStarts columns into `sizes`
+/// and `starts`.
+fn parse_blocks(
+    fields: &[&[u8]],
+    sizes: &mut Vec<u32>,
+    starts: &mut Vec<u32>,
+) -> Result<(), RecordFault> {
+    let count: usize = parse_field(fields[0], "block_count", "a non-negative integer", |f| {
+        parse_number(f)
+    })?;
+    for (list, name, out) in [
+        (fields[1], "block_sizes", sizes),
+        (fields[2], "block_starts", starts),
+    ] {
+        parse_field(list, name, "comma-separated integers", |f| {
+            parse_list(f, out)
+        })?;
+        if out.len() != count {
+            let message = format!("blockCount is {count} but {name} has {} values", out.len());
+            return Err(invalid(name, message));
+        }
+    }
+    Ok(())
+}
+
+/// Parse one data line. `sizes` and `starts` receive the block lists.
+fn parse_record<'a>(
+    line: &'a [u8],
+    sizes: &'a mut Vec<u32>,
+    starts: &'a mut Vec<u32>,
+) -> Result<BedRecord<'a>, RecordFault> {
+    let (fields, n) = split_fields(line);
+    let field_count: u8 = match n {
+        0..=2 => {
+            let message = format!("expected at least 3 fields, found {n}");
+            return Err(invalid("end", message));
+        }
+        3 => 3,
+        4 => 4,
+        5 => 5,
+        6 | 7 => 6,
+        8 => 8,
+        9..=11 => 9,
+        _ => 12,
+    };
+
+    let coordinate = |i: usize, name| {
+        parse_field(
+            fields[i],
+            name,
+            "a non-negative integer",
+            parse_number::<u64>,
+        )
+    };
+    let start = coordinate(1, "start")?;
+    let end = coordinate(2, "end")?;
+    if end < start {
+        let message = format!("end ({end}) is before start ({start})");
+        return Err(invalid("end", message));
+    }
+
+    let score = (field_count >= 5)
+        .then(|| {
+            parse_field(fields[4], "score", "a number or '.'", |f| {
+                if f == b"." {
+                    Some(f64::NAN)
+                } else {
+                    parse_number(f)
+                }
+            })
+        })
+        .transpose()?;
+    let strand = (field_count >= 6)
+        .then(|| {
+            parse_field(fields[5], "strand", "'+', '-' or '.'", |f| match f {
+                [b @ (b'+' | b'-' | b'.')] => Some(*b),
+                _ => None,
+            })
+        })
+        .transpose()?;
+    let thick = (field_count >= 8)
+        .then(|| Ok((coordinate(6, "thick_start")?, coordinate(7, "thick_end")?)))
+        .transpose()?;
+    let item_rgb = (field_count >= 9)
+        .then(|| parse_field(fields[8], "item_rgb", "0 or 'r,g,b'", parse_rgb))
+        .transpose()?;
+    let blocks = if field_count == 12 {
+        parse_blocks(&fields[9..12], sizes, starts)?;
+        Some((&sizes[..], &starts[..]))
+    } else {
+        None
+    };
+
+    Ok(BedRecord {
+        field_count,
+        chrom: fields[0],
+        start,
+        end,
+        name: (field_count >= 4).then_some(fields[3]),
+        score,
+        strand,
+        thick,
+        item_rgb,
+        blocks,
+    })
+}
+
+/// Stateful BED batch reader.
+pub struct BedReader {
+    inner: BoxBufRead,
+    line: Vec<u8>,
+    block_sizes: Vec<u32>,
+    block_starts: Vec<u32>,
+    records_read: u64,
+    lines_read: u64,
+    /// Decompressed bytes the parser has consumed.
+    bytes_read: u64,
+    /// Raw bytes pulled from the input.
+    source: ByteCounter,
+}
+
+impl BedReader {
+    /// Open a BED file by path.
+    ///
+    /// Gzip, BGZF and Zstandard compression are detected from the leading
+    /// magic bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be opened or read.
+    pub fn open_from_path(path: &str) -> Result<Self, EngineError> {
+        let file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        let source = ByteCounter::default();
+        let reader = Box::new(BufReader::new(Counted::new(file, &source)));
+        Self::from_source(reader, source)
+            .map_err(|e| EngineError::Io(format!("failed to read from '{path}': {e}")))
+    }
+
+    /// Open a BED dataset from an in-memory buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the buffer cannot be decompressed.
+    pub fn open_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        let reader = Box::new(Counted::new(Cursor::new(bytes), &source));
+        Self::from_source(reader, source)
+            .map_err(|e| EngineError::Io(format!("failed to read BED input: {e}")))
+    }
+
+    /// Open a BED stream from any reader, such as stdin, a pipe or a
+    /// socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the leading bytes cannot be read.
+    pub fn open_from_reader(reader: Box<dyn Read + Send>) -> Result<Self, EngineError> {
+        Self::open_from_buf_reader(Box::new(BufReader::new(reader)))
+    }
+
+    /// Open a BED stream from a buffered reader without adding another
+    /// layer of buffering to plain input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the leading bytes cannot be read.
+    pub fn open_from_buf_reader(reader: Box<dyn BufRead + Send>) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        Self::from_source(Box::new(Counted::new(reader, &source)), source)
+            .map_err(|e| EngineError::Io(format!("failed to read BED stream: {e}")))
+    }
+
+    fn from_source(reader: BoxBufRead, source: ByteCounter) -> std::io::Result<Self> {
+        Ok(Self {
+            inner: codec::decode_buf_read(reader)?,
+            line: Vec::new(),
+            block_sizes: Vec::new(),
+            block_starts: Vec::new(),
+            records_read: 0,
+            lines_read: 0,
+            bytes_read: 0,
+            source,
+        })
+    }
+
+    /// Number of intervals returned so far.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Progress through the input, as raw and decompressed bytes.
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        BytesConsumed {
+            compressed: self.source.get(),
+            uncompressed: self.bytes_read,
+        }
+    }
+
+    /// Read the next batch of up to `max_records` intervals, or `None`
+    /// at end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the input cannot be read, or
+    /// `EngineError::Parse` if a line is malformed, locating it by
+    /// record number, line and byte offset in the decompressed input.
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<BedBatch>, EngineError> {
+        let mut batch = BedBatch::empty();
+
+        while batch.count < max_records {
+            self.line.clear();
+            let n = self
+                .inner
+                .read_until(b'\n', &mut self.line)
+                .map_err(|e| EngineError::Io(format!("BED read error: {e}")))?;
+            if n == 0 {
+                break;
+            }
+            let offset = self.bytes_read;
+            self.bytes_read += n as u64;
+            self.lines_read += 1;
+
+            let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.is_empty()
+                || line.starts_with(b"#")
+                || line.starts_with(b"track")
+                || line.starts_with(b"browser")
+            {
+                continue;
+            }
+
+            let record = parse_record(line, &mut self.block_sizes, &mut self.block_starts)
+                .map_err(|(kind, field, message)| {
+                    ParseError::new(RecordFormat::Bed, kind, self.records_read + 1, message)
+                        .at(Some(offset), Some(self.lines_read))
+                        .field(field)
+                })?;
+            batch.push(&record);
+            self.records_read += 1;
+        }
+
+        Ok((batch.count > 0).then_some(batch))
+    }
+}
+
+/// Stateful BED batch writer.
+///
+/// Accepts batches in the layout `BedReader` produces. Output may be
+/// gzip-, BGZF- or zstd-compressed, with gzip and BGZF optionally
+/// compressed in parallel blocks.
+pub struct BedWriter {
+    inner: Output,
+    line: Vec<u8>,
+}
+
+impl BedWriter {
+    /// Open a writer to a file path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be created, or
+    /// `EngineError::InvalidArgument` if a compression level is out of
+    /// range.
+    pub fn open_to_path(path: &str, compression: CompressionMode) -> Result<Self, EngineError> {
+        Ok(Self::new(Output::open_to_path(path, compression)?))
+    }
+
+    /// Open a writer to an in-memory buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a compression level is
+    /// out of range.
+    pub fn open_to_bytes(compression: CompressionMode) -> Result<Self, EngineError> {
+        Ok(Self::new(Output::open_to_bytes(compression)?))
+    }
+
+    fn new(inner: Output) -> Self {
+        Self {
+            inner,
+            line: Vec::new(),
+        }
+    }
+
+    /// Write a batch of intervals, one tab-separated line each.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a column does not have
+    /// `count` values or an interval's `field_counts` entry needs a
+    /// column the batch lacks, `EngineError::InvalidOffsets` if an offset
+    /// array is malformed, or `EngineError::Io` if writing fails. Nothing
+    /// is written for a rejected batch.
+    pub fn write_batch(&mut self, batch: &BedBatch) -> Result<(), EngineError> {
+        check_batch(batch)?;
+        self.line.clear();
+        for i in 0..batch.count as usize {
+            format_record(batch, i, &mut self.line)?;
+        }
+        self.inner
+            .write_all(&self.line)
+            .map_err(|e| EngineError::Io(format!("BED write error: {e}")))
+    }
+
+    /// Flush and close the writer.
+    ///
+    /// For file mode, returns `None`. For bytes mode, returns the
+    /// accumulated output bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if flushing fails.
+    pub fn finish(self) -> Result<Option<Vec<u8>>, EngineError> {
+        self.inner.finish()
+    }
+}
+
+/// Check every column of `batch` against its `count`.
+fn check_batch(batch: &BedBatch) -> Result<(), EngineError> {
+    let count = batch.count as usize;
+    let check_len = |name: &str, len: usize| {
+        if len == count {
+            return Ok(());
+        }
+        Err(EngineError::InvalidArgument(format!(
+            "BedWriter: the {name} column has {len} values for {count} intervals"
+        )))
+    };
+    let check_rows = |name: &str, offsets: &[u32], len: usize| {
+        if offsets.len() != count + 1 {
+            return Err(EngineError::InvalidOffsets(format!(
+                "BedWriter: the {name} offsets describe {} intervals, not {count}",
+                offsets.len().saturating_sub(1)
+            )));
+        }
+        validate_offsets(offsets, len)
+    };
+
+    check_rows("chrom", &batch.chrom_offsets, batch.chrom_data.len())?;
+    check_len("start", batch.starts.len())?;
+    check_len("end", batch.ends.len())?;
+    if let Some(offsets) = &batch.name_offsets {
+        check_rows(
+            "name",
+            offsets,
+            batch.name_data.as_ref().map_or(0, Vec::len),
+        )?;
+    }
+    for (name, len) in [
+        ("score", batch.scores.as_ref().map(Vec::len)),
+        ("strand", batch.strands.as_ref().map(Vec::len)),
+        ("thick_start", batch.thick_starts.as_ref().map(Vec::len)),
+        ("thick_end", batch.thick_ends.as_ref().map(Vec::len)),
+        ("item_rgb", batch.item_rgbs.as_ref().map(Vec::len)),
+    ] {
+        if let Some(len) = len {
+            check_len(name, len)?;
+        }
+    }
+    if let Some(offsets) = &batch.block_offsets {
+        let sizes = batch.block_sizes.as_ref().map_or(0, Vec::len);
+        let starts = batch.block_starts.as_ref().map_or(0, Vec::len);
+        check_rows("block", offsets, sizes.min(starts))?;
+    }
+    Ok(())
+}
+
+/// Column `i` of an optional batch column, or an error naming it.
+fn column<'a, T>(values: Option<&'a [T]>, name: &str, i: usize) -> Result<&'a T, EngineError> {
+    values.and_then(|v| v.get(i)).ok_or_else(|| {
+        EngineError::InvalidArgument(format!(
+            "BedWriter: interval {i} needs a {name} column the batch does not have"
+        ))
+    })
+}
+
+fn join_list(out: &mut Vec<u8>, values: &[u32]) {
+    for (j, value) in values.iter().enumerate() {
+        if j > 0 {
+            out.push(b',');
+        }
+        out.extend_from_slice(value.to_string().as_bytes());
+    }
+}
+
+/// Format interval `i` of `batch` as a BED line.
+fn format_record(batch: &BedBatch, i: usize, out: &mut Vec<u8>) -> Result<(), EngineError> {
+    let fields = batch.field_counts.get(i).copied().unwrap_or(3);
+    out.extend_from_slice(field_at(&batch.chrom_data, &batch.chrom_offsets, i));
+    write!(out, "\t{}\t{}", batch.starts[i], batch.ends[i])
+        .map_err(|e| EngineError::Io(e.to_string()))?;
+
+    if fields >= 4 {
+        let offsets = batch.name_offsets.as_deref();
+        let start = *column(offsets, "name", i)? as usize;
+        let end = *column(offsets, "name", i + 1)? as usize;
+        let name = batch.name_data.as_deref().and_then(|d| d.get(start..end));
+        let Some(name) = name else {
+            return Err(EngineError::InvalidArgument(format!(
+                "BedWriter: interval {i} needs a name column the batch does not have"
+            )));
+        };
+        out.push(b'\t');
+        out.extend_from_slice(name);
+    }
+    if fields >= 5 {
+        let score = *column(batch.scores.as_deref(), "score", i)?;
+        out.push(b'\t');
+        if score.is_nan() {
+            out.push(b'.');
+        } else {
+            out.extend_from_slice(score.to_string().as_bytes());
+        }
+    }
+    if fields >= 6 {
+        out.push(b'\t');
+        out.push(*column(batch.strands.as_deref(), "strand", i)?);
+    }
+    if fields >= 8 {
+        let start = column(batch.thick_starts.as_deref(), "thick_start", i)?;
+        let end = column(batch.thick_ends.as_deref(), "thick_end", i)?;
+        write!(out, "\t{start}\t{end}").map_err(|e| EngineError::Io(e.to_string()))?;
+    }
+    if fields >= 9 {
+        let rgb = *column(batch.item_rgbs.as_deref(), "item_rgb", i)?;
+        if rgb == 0 {
+            out.extend_from_slice(b"\t0");
+        } else {
+            let [_, r, g, b] = rgb.to_be_bytes();
+            write!(out, "\t{r},{g},{b}").map_err(|e| EngineError::Io(e.to_string()))?;
+        }
+    }
+    if fields >= 12 {
+        let offsets = batch.block_offsets.as_deref();
+        let start = *column(offsets, "block", i)? as usize;
+        let end = *column(offsets, "block", i + 1)? as usize;
+        let sizes = batch.block_sizes.as_deref().and_then(|s| s.get(start..end));
+        let starts = batch
+            .block_starts
+            .as_deref()
+            .and_then(|s| s.get(start..end));
+        let (Some(sizes), Some(starts)) = (sizes, starts) else {
+            return Err(EngineError::InvalidArgument(format!(
+                "BedWriter: interval {i} needs block columns the batch does not have"
+            )));
+        };
+        write!(out, "\t{}\t", sizes.len()).map_err(|e| EngineError::Io(e.to_string()))?;
+        join_list(out, sizes);
+        out.push(b'\t');
+        join_list(out, starts);
+    }
+    out.push(b'\n');
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const BED: &[u8] = b"track name=peaks\n\
+        # comment\n\
+        chr1\t10\t20\n\
+        \n\
+        chr1\t30\t45\tpeak2\t500\t-\n\
+        chr2 5 9 spaced\n\
+        chr2\t100\t200\tgene\t0\t+\t110\t190\t255,0,0\t2\t10,20,\t0,80,\r\n";
+
+    fn read_all(data: &[u8]) -> BedBatch {
+        BedReader::open_from_bytes(data.to_vec())
+            .unwrap()
+            .read_batch(100)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_mixed_widths_with_defaults() {
+        let batch = read_all(BED);
+        assert_eq!(batch.count, 4);
+        assert_eq!(batch.field_counts, vec![3, 6, 4, 12]);
+        assert_eq!(batch.chrom_data, b"chr1chr1chr2chr2");
+        assert_eq!(batch.starts, vec![10, 30, 5, 100]);
+        assert_eq!(batch.ends, vec![20, 45, 9, 200]);
+        assert_eq!(batch.name_data.as_deref().unwrap(), b"peak2spacedgene");
+        assert_eq!(batch.name_offsets.as_deref().unwrap(), &[0, 0, 5, 11, 15]);
+        assert_eq!(batch.scores.as_deref().unwrap(), &[0.0, 500.0, 0.0, 0.0]);
+        assert_eq!(batch.strands.as_deref().unwrap(), b".-.+");
+        assert_eq!(batch.thick_starts.as_deref().unwrap(), &[10, 30, 5, 110]);
+        assert_eq!(batch.thick_ends.as_deref().unwrap(), &[20, 45, 9, 190]);
+        assert_eq!(batch.item_rgbs.as_deref().unwrap(), &[0, 0, 0, 0xff_00_00]);
+        assert_eq!(batch.block_offsets.as_deref().unwrap(), &[0, 0, 0, 0, 2]);
+        assert_eq!(batch.block_sizes.as_deref().unwrap(), &[10, 20]);
+        assert_eq!(batch.block_starts.as_deref().unwrap(), &[0, 80]);
+    }
+
+    #[test]
+    fn bed3_batches_have_no_optional_columns() {
+        let mut reader =
+            BedReader::open_from_bytes(b"c\t1\t2\nc\t3\t4\nc\t5\t6\n".to_vec()).unwrap();
+        let first = reader.read_batch(2).unwrap().unwrap();
+        assert_eq!(first.count, 2);
+        assert!(first.name_data.is_none() && first.scores.is_none());
+        assert!(first.block_offsets.is_none());
+        assert_eq!(reader.read_batch(2).unwrap().unwrap().starts, vec![5]);
+        assert!(reader.read_batch(2).unwrap().is_none());
+        assert_eq!(reader.records_read(), 3);
+    }
+
+    #[test]
+    fn malformed_lines_are_located() {
+        let cases: [(&[u8], &str, u64); 5] = [
+            (b"chr1\t10\n", "end", 1),
+            (b"chr1\t10\t20\nchr1\tx\t20\n", "start", 2),
+            (b"chr1\t30\t20\n", "end", 1),
+            (b"chr1\t1\t2\tn\t0\t*\n", "strand", 1),
+            (
+                b"chr1\t1\t9\tn\t0\t+\t1\t9\t0\t2\t4\t0,4\n",
+                "block_sizes",
+                1,
+            ),
+        ];
+        for (input, field, line) in cases {
+            let mut reader = BedReader::open_from_bytes(input.to_vec()).unwrap();
+            let Err(EngineError::Parse(error)) = reader.read_batch(10) else {
+                panic!("expected a parse error for {input:?}");
+            };
+            assert_eq!(error.format, RecordFormat::Bed);
+            assert_eq!(error.field, Some(field), "{input:?}");
+            assert_eq!(error.line, Some(line), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn writer_round_trips_through_compression() {
+        let batch = read_all(BED);
+        for compression in [
+            CompressionMode::None,
+            CompressionMode::Gzip,
+            CompressionMode::Bgzf,
+        ] {
+            let mut writer = BedWriter::open_to_bytes(compression).unwrap();
+            writer.write_batch(&batch).unwrap();
+            let bytes = writer.finish().unwrap().unwrap();
+            if compression == CompressionMode::None {
+                assert_eq!(
+                    bytes,
+                    b"chr1\t10\t20\n\
+                      chr1\t30\t45\tpeak2\t500\t-\n\
+                      chr2\t5\t9\tspaced\n\
+                      chr2\t100\t200\tgene\t0\t+\t110\t190\t255,0,0\t2\t10,20\t0,80\n"
+                );
+            }
+
+            let again = read_all(&bytes);
+            assert_eq!(again.field_counts, batch.field_counts);
+            assert_eq!(again.name_data, batch.name_data);
+            assert_eq!(again.block_starts, batch.block_starts);
+        }
+
+        let mut writer = BedWriter::open_to_bytes(CompressionMode::None).unwrap();
+        let bare = read_all(b"chr1\t1\t2\n");
+        let widened = BedBatch {
+            field_counts: vec![5],
+            ..bare
+        };
+        assert!(matches!(
+            writer.write_batch(&widened),
+            Err(EngineError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn writer_rejects_truncated_batches_before_writing() {
+        let mut writer = BedWriter::open_to_bytes(CompressionMode::None).unwrap();
+
+        let mut batch = read_all(BED);
+        batch.ends.pop();
+        assert!(matches!(
+            writer.write_batch(&batch),
+            Err(EngineError::InvalidArgument(_))
+        ));
+
+        let mut batch = read_all(BED);
+        batch.chrom_offsets.pop();
+        assert!(matches!(
+            writer.write_batch(&batch),
+            Err(EngineError::InvalidOffsets(_))
+        ));
+
+        let mut batch = read_all(BED);
+        batch.block_sizes.as_mut().unwrap().pop();
+        assert!(matches!(
+            writer.write_batch(&batch),
+            Err(EngineError::InvalidOffsets(_))
+        ));
+
+        let mut batch = read_all(BED);
+        batch.count += 1;
+        assert!(writer.write_batch(&batch).is_err());
+
+        assert!(writer.finish().unwrap().unwrap().is_empty());
+    }
+}
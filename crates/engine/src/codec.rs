@@ -0,0 +1,1072 @@
+//! Compression sniffing, encoder setup and background decompression
+//! shared by the sequence readers and writers.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use flate2::{write::DeflateEncoder, write::GzEncoder, Compression, Crc};
+use noodles_bgzf::{self as bgzf, gzi};
+use rayon::prelude::*;
+
+use crate::{CompressionMode, EngineError};
+
+/// Compression of a reader's input, sniffed from its leading bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InputCompression {
+    None,
+    /// Gzip, including multi-member and BGZF streams.
+    Gzip,
+    Zstd,
+}
+
+/// Streaming zstd decoder over a raw reader; concatenated frames are
+/// decoded back to back.
+#[cfg(feature = "zstd")]
+pub(crate) type ZstdDecoder<R> = zstd::Decoder<'static, BufReader<R>>;
+#[cfg(not(feature = "zstd"))]
+pub(crate) type ZstdDecoder<R> = NoZstd<R>;
+
+#[cfg(feature = "zstd")]
+pub(crate) type ZstdEncoder<W> = zstd::Encoder<'static, W>;
+#[cfg(not(feature = "zstd"))]
+pub(crate) type ZstdEncoder<W> = NoZstd<W>;
+
+#[cfg(feature = "zstd")]
+type ZstdChunkDecoder = zstd::stream::write::Decoder<'static, Vec<u8>>;
+#[cfg(not(feature = "zstd"))]
+type ZstdChunkDecoder = NoZstd<Vec<u8>>;
+
+/// Start a zstd decoder over `inner`.
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd_decoder<R: Read>(inner: R) -> io::Result<ZstdDecoder<R>> {
+    zstd::Decoder::new(inner)
+}
+
+/// Zstandard input needs the `zstd` feature.
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn zstd_decoder<R: Read>(_: R) -> io::Result<ZstdDecoder<R>> {
+    Err(NoZstd::<R>::error())
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_chunk_decoder() -> io::Result<ZstdChunkDecoder> {
+    ZstdChunkDecoder::new(Vec::new())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_chunk_decoder() -> io::Result<ZstdChunkDecoder> {
+    Err(NoZstd::<Vec<u8>>::error())
+}
+
+/// Stand-in for the zstd codec types when the `zstd` feature is off.
+/// It cannot be constructed, so the zstd variants it fills in the
+/// reader and writer enums are never reached.
+#[cfg(not(feature = "zstd"))]
+pub(crate) struct NoZstd<T>(std::convert::Infallible, std::marker::PhantomData<T>);
+
+#[cfg(not(feature = "zstd"))]
+impl<T> NoZstd<T> {
+    fn error() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "zstd support is not enabled in this build",
+        )
+    }
+
+    pub(crate) fn finish(self) -> io::Result<T> {
+        match self.0 {}
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        match self.0 {}
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+impl<T> Read for NoZstd<T> {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        match self.0 {}
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+impl<T> Write for NoZstd<T> {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        match self.0 {}
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0 {}
+    }
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Number of leading bytes `detect` needs to tell every format apart.
+pub(crate) const MAGIC_LEN: usize = ZSTD_MAGIC.len();
+
+/// Classify input from its first bytes. Shorter inputs than `MAGIC_LEN`
+/// are fine; anything unrecognised is treated as uncompressed text.
+pub(crate) fn detect(magic: &[u8]) -> InputCompression {
+    if magic.starts_with(&GZIP_MAGIC) {
+        InputCompression::Gzip
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        InputCompression::Zstd
+    } else {
+        InputCompression::None
+    }
+}
+
+/// Boxed buffered source taken by the generic reader constructors.
+pub(crate) type BoxBufRead = Box<dyn BufRead + Send>;
+
+/// Read the first `len` bytes of `reader` (fewer only at EOF), returning
+/// them alongside a reader that still yields them.
+pub(crate) fn peek_magic(mut reader: BoxBufRead, len: usize) -> io::Result<(Vec<u8>, BoxBufRead)> {
+    let buffered = reader.fill_buf()?;
+    if buffered.len() >= len {
+        let magic = buffered[..len].to_vec();
+        return Ok((magic, reader));
+    }
+    let mut magic = Vec::with_capacity(len);
+    (&mut reader).take(len as u64).read_to_end(&mut magic)?;
+    let replay = Cursor::new(magic.clone()).chain(reader);
+    Ok((magic, Box::new(replay)))
+}
+
+/// Sniff compression from the first `MAGIC_LEN` bytes of `reader` and
+/// wrap it in the matching decoder. Plain input is returned as is.
+pub(crate) fn decode_buf_read(reader: BoxBufRead) -> io::Result<BoxBufRead> {
+    let (magic, reader) = peek_magic(reader, MAGIC_LEN)?;
+    Ok(match detect(&magic) {
+        InputCompression::None => reader,
+        InputCompression::Gzip => {
+            Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))
+        }
+        InputCompression::Zstd => Box::new(BufReader::new(zstd_decoder(reader)?)),
+    })
+}
+
+/// Length of a BGZF block header, enough for `is_bgzf` to decide.
+pub(crate) const BGZF_HEADER_LEN: usize = 18;
+
+/// Whether a gzip stream opens with a BGZF block: the FEXTRA flag is set
+/// and the first extra subfield is `BC`.
+pub(crate) fn is_bgzf(header: &[u8]) -> bool {
+    header.len() >= BGZF_HEADER_LEN
+        && header.starts_with(&GZIP_MAGIC)
+        && header[3] & 0x04 != 0
+        && header[12..14] == *b"BC"
+}
+
+/// Resolve a requested thread count, where `0` means one per core.
+pub(crate) fn worker_count(threads: u32) -> NonZeroUsize {
+    NonZeroUsize::new(threads as usize)
+        .unwrap_or_else(|| thread::available_parallelism().unwrap_or(NonZeroUsize::MIN))
+}
+
+/// Build a Zstandard encoder, rejecting levels libzstd does not support.
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd_encoder<W: Write>(inner: W, level: i32) -> Result<ZstdEncoder<W>, EngineError> {
+    let range = zstd::compression_level_range();
+    if !range.contains(&level) {
+        return Err(EngineError::InvalidArgument(format!(
+            "zstd level {level} is outside {}..={}",
+            range.start(),
+            range.end()
+        )));
+    }
+    zstd::Encoder::new(inner, level)
+        .map_err(|e| EngineError::Io(format!("failed to start zstd stream: {e}")))
+}
+
+/// Zstandard output needs the `zstd` feature.
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn zstd_encoder<W: Write>(_: W, _: i32) -> Result<ZstdEncoder<W>, EngineError> {
+    Err(EngineError::InvalidArgument(
+        NoZstd::<W>::error().to_string(),
+    ))
+}
+
+/// Validate a deflate level for the block writers.
+pub(crate) fn deflate_level(level: i32) -> Result<Compression, EngineError> {
+    u32::try_from(level)
+        .ok()
+        .filter(|&level| level <= 9)
+        .map(Compression::new)
+        .ok_or_else(|| {
+            EngineError::InvalidArgument(format!("deflate level {level} is outside 0..=9"))
+        })
+}
+
+/// Where a writer's output goes: a file or an in-memory buffer.
+pub(crate) enum Sink {
+    File(BufWriter<File>),
+    Bytes(Vec<u8>),
+}
+
+impl Sink {
+    /// Flush a file, or hand back the buffered bytes.
+    fn finish(self) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            Self::File(mut w) => w.flush().map(|()| None),
+            Self::Bytes(v) => Ok(Some(v)),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(w) => w.write(buf),
+            Self::Bytes(v) => v.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(w) => w.flush(),
+            Self::Bytes(v) => v.flush(),
+        }
+    }
+}
+
+/// A writer's output stream, compressed as its `CompressionMode` asks.
+/// Shared by the FASTQ, FASTA, BED and VCF writers.
+pub(crate) enum Output {
+    Plain(Sink),
+    Gzip(GzEncoder<Sink>),
+    Bgzf(BgzfWriter<Sink>),
+    Zstd(ZstdEncoder<Sink>),
+    Block(BlockWriter<Sink>),
+}
+
+impl Output {
+    /// Create `path` and write to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be created, or
+    /// `EngineError::InvalidArgument` if a compression level is out of
+    /// range.
+    pub(crate) fn open_to_path(
+        path: &str,
+        compression: CompressionMode,
+    ) -> Result<Self, EngineError> {
+        let file = File::create(path)
+            .map_err(|e| EngineError::Io(format!("failed to create '{path}': {e}")))?;
+        Self::new(Sink::File(BufWriter::new(file)), compression)
+    }
+
+    /// Write to an in-memory buffer, returned by `finish`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a compression level is
+    /// out of range.
+    pub(crate) fn open_to_bytes(compression: CompressionMode) -> Result<Self, EngineError> {
+        Self::new(Sink::Bytes(Vec::new()), compression)
+    }
+
+    fn new(sink: Sink, compression: CompressionMode) -> Result<Self, EngineError> {
+        Ok(match compression {
+            CompressionMode::None => Self::Plain(sink),
+            CompressionMode::Gzip => Self::Gzip(GzEncoder::new(sink, Compression::default())),
+            CompressionMode::Bgzf => Self::Bgzf(BgzfWriter::new(sink)),
+            CompressionMode::Zstd(level) => Self::Zstd(zstd_encoder(sink, level)?),
+            CompressionMode::ParallelGzip(level) => Self::Block(BlockWriter::new(
+                sink,
+                BlockFormat::Gzip,
+                deflate_level(level)?,
+            )),
+            CompressionMode::ParallelBgzf(level) => Self::Block(BlockWriter::new(
+                sink,
+                BlockFormat::Bgzf,
+                deflate_level(level)?,
+            )),
+        })
+    }
+
+    /// Whether the output is BGZF, serial or parallel.
+    pub(crate) fn is_bgzf(&self) -> bool {
+        match self {
+            Self::Bgzf(_) => true,
+            Self::Block(w) => w.format() == BlockFormat::Bgzf,
+            Self::Plain(_) | Self::Gzip(_) | Self::Zstd(_) => false,
+        }
+    }
+
+    /// Finish the compressed stream and flush it. Returns `None` for a
+    /// file and the output bytes for a buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if finishing or flushing fails.
+    pub(crate) fn finish(self) -> Result<Option<Vec<u8>>, EngineError> {
+        self.finish_with_gzi().map(|(data, _)| data)
+    }
+
+    /// Like `finish`, also returning the `.gzi` of BGZF output.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if finishing or flushing fails.
+    pub(crate) fn finish_with_gzi(
+        self,
+    ) -> Result<(Option<Vec<u8>>, Option<gzi::Index>), EngineError> {
+        let (finished, context) = match self {
+            Self::Plain(sink) => (Ok((sink, None)), "flush"),
+            Self::Gzip(w) => (w.finish().map(|sink| (sink, None)), "gzip finish"),
+            Self::Bgzf(w) => (
+                w.finish().map(|(sink, gzi)| (sink, Some(gzi))),
+                "BGZF finish",
+            ),
+            Self::Zstd(w) => (w.finish().map(|sink| (sink, None)), "zstd finish"),
+            Self::Block(w) => {
+                let bgzf = w.format() == BlockFormat::Bgzf;
+                let finished = w.finish().map(|(sink, gzi)| (sink, bgzf.then_some(gzi)));
+                (finished, "block compression")
+            }
+        };
+        finished
+            .and_then(|(sink, blocks)| Ok((sink.finish()?, blocks)))
+            .map_err(|e| EngineError::Io(format!("{context} error: {e}")))
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(w) => w.write(buf),
+            Self::Gzip(w) => w.write(buf),
+            Self::Bgzf(w) => w.write(buf),
+            Self::Zstd(w) => w.write(buf),
+            Self::Block(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(w) => w.flush(),
+            Self::Gzip(w) => w.flush(),
+            Self::Bgzf(w) => w.flush(),
+            Self::Zstd(w) => w.flush(),
+            Self::Block(w) => w.flush(),
+        }
+    }
+}
+
+/// Container written by `BlockWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BlockFormat {
+    /// Concatenated gzip members, one per block.
+    Gzip,
+    /// BGZF blocks followed by the standard EOF marker.
+    Bgzf,
+}
+
+impl BlockFormat {
+    fn block_size(self) -> usize {
+        match self {
+            // pigz's default block size.
+            Self::Gzip => 128 * 1024,
+            // htslib's payload size, which still fits a 64 KiB block
+            // when stored uncompressed.
+            Self::Bgzf => 0xff00,
+        }
+    }
+}
+
+const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// noodles' BGZF writer, noting the compressed and uncompressed start
+/// of each block after the first from its virtual position, as the
+/// `.gzi` records them.
+pub(crate) struct BgzfWriter<W: Write> {
+    inner: bgzf::io::Writer<W>,
+    /// Uncompressed bytes written so far.
+    position: u64,
+    blocks: Vec<(u64, u64)>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner: bgzf::io::Writer::new(inner),
+            position: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Note a block boundary if the last write or flush closed a block.
+    fn note_block(&mut self) {
+        let virtual_position = self.inner.virtual_position();
+        let compressed = virtual_position.compressed();
+        if virtual_position.uncompressed() == 0
+            && compressed > 0
+            && self
+                .blocks
+                .last()
+                .is_none_or(|&(last, _)| last != compressed)
+        {
+            self.blocks.push((compressed, self.position));
+        }
+    }
+
+    /// Finish the stream, returning the inner writer and the `.gzi` of
+    /// the blocks written.
+    pub(crate) fn finish(mut self) -> io::Result<(W, gzi::Index)> {
+        // A boundary noted at the very end starts only the EOF block.
+        if self
+            .blocks
+            .last()
+            .is_some_and(|&(_, start)| start == self.position)
+        {
+            self.blocks.pop();
+        }
+        let inner = self.inner.finish()?;
+        Ok((inner, gzi::Index::from(self.blocks)))
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // noodles closes at most one block per call.
+        let n = self.inner.write(buf)?;
+        self.position += n as u64;
+        self.note_block();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        self.note_block();
+        Ok(())
+    }
+}
+
+/// pigz-style writer: input is cut into fixed-size blocks, a batch of
+/// blocks is compressed at once on the rayon pool, and the compressed
+/// blocks are written in input order.
+pub(crate) struct BlockWriter<W: Write> {
+    inner: W,
+    format: BlockFormat,
+    level: Compression,
+    /// Full blocks waiting for the next parallel pass.
+    pending: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    /// Compressed and uncompressed start of each block after the first.
+    blocks: Vec<(u64, u64)>,
+    /// Compressed and uncompressed bytes written so far.
+    written: (u64, u64),
+}
+
+impl<W: Write> BlockWriter<W> {
+    pub(crate) fn new(inner: W, format: BlockFormat, level: Compression) -> Self {
+        Self {
+            inner,
+            format,
+            level,
+            pending: Vec::new(),
+            current: Vec::with_capacity(format.block_size()),
+            blocks: Vec::new(),
+            written: (0, 0),
+        }
+    }
+
+    pub(crate) fn format(&self) -> BlockFormat {
+        self.format
+    }
+
+    /// Compress and write everything buffered, then return the inner
+    /// writer and the `.gzi` of the blocks written. BGZF output gains its
+    /// EOF marker block.
+    pub(crate) fn finish(mut self) -> io::Result<(W, gzi::Index)> {
+        self.write_pending(true)?;
+        if self.format == BlockFormat::Bgzf {
+            self.inner.write_all(&BGZF_EOF)?;
+        }
+        Ok((self.inner, gzi::Index::from(self.blocks)))
+    }
+
+    fn write_pending(&mut self, include_partial: bool) -> io::Result<()> {
+        if include_partial && !self.current.is_empty() {
+            let block = std::mem::take(&mut self.current);
+            self.pending.push(block);
+        }
+        let (format, level) = (self.format, self.level);
+        let compressed = self
+            .pending
+            .par_iter()
+            .map(|block| compress_block(format, level, block))
+            .collect::<io::Result<Vec<_>>>()?;
+        for (block, raw) in compressed.iter().zip(self.pending.drain(..)) {
+            self.inner.write_all(block)?;
+            if self.written.0 > 0 {
+                self.blocks.push(self.written);
+            }
+            self.written.0 += block.len() as u64;
+            self.written.1 += raw.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BlockWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let block_size = self.format.block_size();
+        let n = buf.len().min(block_size - self.current.len());
+        self.current.extend_from_slice(&buf[..n]);
+
+        if self.current.len() == block_size {
+            let block = std::mem::replace(&mut self.current, Vec::with_capacity(block_size));
+            self.pending.push(block);
+            // A few blocks per worker keeps the pool busy between passes.
+            if self.pending.len() >= rayon::current_num_threads() * 4 {
+                self.write_pending(false)?;
+            }
+        }
+
+        Ok(n)
+    }
+
+    /// Writes only the full blocks, so flushing mid-stream does not
+    /// scatter small blocks through the output.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending(false)?;
+        self.inner.flush()
+    }
+}
+
+fn compress_block(format: BlockFormat, level: Compression, block: &[u8]) -> io::Result<Vec<u8>> {
+    match format {
+        BlockFormat::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::with_capacity(block.len() / 2), level);
+            encoder.write_all(block)?;
+            encoder.finish()
+        }
+        BlockFormat::Bgzf => {
+            let mut cdata = deflate(block, level)?;
+            if cdata.len() + 26 > 0x10000 {
+                cdata = deflate(block, Compression::none())?;
+            }
+            let mut crc = Crc::new();
+            crc.update(block);
+
+            let block_size = u16::try_from(cdata.len() + 25)
+                .map_err(|_| io::Error::other("BGZF block exceeds 64 KiB"))?;
+            let uncompressed = u32::try_from(block.len())
+                .map_err(|_| io::Error::other("BGZF block exceeds 64 KiB"))?;
+
+            let mut out = Vec::with_capacity(cdata.len() + 26);
+            out.extend_from_slice(&[
+                0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43,
+                0x02, 0x00,
+            ]);
+            out.extend_from_slice(&block_size.to_le_bytes());
+            out.extend_from_slice(&cdata);
+            out.extend_from_slice(&crc.sum().to_le_bytes());
+            out.extend_from_slice(&uncompressed.to_le_bytes());
+            Ok(out)
+        }
+    }
+}
+
+fn deflate(data: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len() / 2), level);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Push-based decompressor for input that arrives in chunks.
+///
+/// The format is sniffed once enough leading bytes have arrived; decoder
+/// state then carries across chunk boundaries.
+pub(crate) struct ChunkDecoder {
+    state: ChunkState,
+}
+
+enum ChunkState {
+    /// Waiting for `MAGIC_LEN` bytes to decide the format.
+    Sniffing(Vec<u8>),
+    Plain,
+    Gzip(Box<flate2::write::MultiGzDecoder<Vec<u8>>>),
+    Zstd(Box<ZstdChunkDecoder>),
+}
+
+impl ChunkDecoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: ChunkState::Sniffing(Vec::new()),
+        }
+    }
+
+    /// Decode `chunk`, appending whatever output it completes to `out`.
+    pub(crate) fn push(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        if let ChunkState::Sniffing(head) = &mut self.state {
+            head.extend_from_slice(chunk);
+            if head.len() < MAGIC_LEN {
+                return Ok(());
+            }
+            let head = std::mem::take(head);
+            self.start(&head)?;
+            return self.decode(&head, out);
+        }
+        self.decode(chunk, out)
+    }
+
+    /// Signal end of input, flushing any buffered output to `out`.
+    pub(crate) fn finish(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        if let ChunkState::Sniffing(head) = &mut self.state {
+            let head = std::mem::take(head);
+            self.start(&head)?;
+            self.decode(&head, out)?;
+        }
+        match &mut self.state {
+            ChunkState::Sniffing(_) | ChunkState::Plain => {}
+            ChunkState::Gzip(decoder) => {
+                decoder.try_finish()?;
+                out.append(decoder.get_mut());
+            }
+            ChunkState::Zstd(decoder) => {
+                decoder.flush()?;
+                out.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+
+    fn start(&mut self, head: &[u8]) -> io::Result<()> {
+        self.state = match detect(head) {
+            InputCompression::None => ChunkState::Plain,
+            InputCompression::Gzip => {
+                ChunkState::Gzip(Box::new(flate2::write::MultiGzDecoder::new(Vec::new())))
+            }
+            InputCompression::Zstd => ChunkState::Zstd(Box::new(zstd_chunk_decoder()?)),
+        };
+        Ok(())
+    }
+
+    fn decode(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        match &mut self.state {
+            ChunkState::Sniffing(_) => {}
+            ChunkState::Plain => out.extend_from_slice(chunk),
+            ChunkState::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                out.append(decoder.get_mut());
+            }
+            ChunkState::Zstd(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                out.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Size of each decoded chunk handed over by `ReadAhead`.
+const READ_AHEAD_CHUNK: usize = 1 << 20;
+/// Decoded chunks buffered ahead of the consumer.
+const READ_AHEAD_DEPTH: usize = 4;
+
+/// Runs a decoder on a background thread and hands decoded chunks to the
+/// consumer through a bounded queue, so inflating the next chunk overlaps
+/// with parsing the current one. Bytes come out in stream order.
+pub(crate) struct ReadAhead {
+    receiver: Option<Receiver<io::Result<Vec<u8>>>>,
+    handle: Option<JoinHandle<()>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ReadAhead {
+    pub(crate) fn spawn<R: Read + Send + 'static>(mut inner: R) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD_DEPTH);
+        let handle = thread::spawn(move || loop {
+            let mut chunk = vec![0; READ_AHEAD_CHUNK];
+            let mut filled = 0;
+            let result = loop {
+                match inner.read(&mut chunk[filled..]) {
+                    Ok(0) => break Ok(()),
+                    Ok(n) => {
+                        filled += n;
+                        if filled == chunk.len() {
+                            break Ok(());
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => break Err(e),
+                }
+            };
+            let message = match result {
+                Ok(()) if filled == 0 => break,
+                Ok(()) => {
+                    chunk.truncate(filled);
+                    Ok(chunk)
+                }
+                Err(e) => Err(e),
+            };
+            let failed = message.is_err();
+            // A send error means the reader was dropped; stop decoding.
+            if sender.send(message).is_err() || failed {
+                break;
+            }
+        });
+
+        Self {
+            receiver: Some(receiver),
+            handle: Some(handle),
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for ReadAhead {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.chunk.len() {
+            let Some(receiver) = &self.receiver else {
+                return Ok(&[]);
+            };
+            if let Ok(message) = receiver.recv() {
+                self.chunk = message?;
+                self.pos = 0;
+            } else {
+                // The decoder thread hung up: either end of stream or a panic.
+                self.receiver = None;
+                if let Some(handle) = self.handle.take() {
+                    handle
+                        .join()
+                        .map_err(|_| io::Error::other("decompression thread panicked"))?;
+                }
+            }
+        }
+        Ok(&self.chunk[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.chunk.len());
+    }
+}
+
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        // Dropping the receiver makes the worker's next send fail, so the
+        // join below waits for at most one chunk of decoding.
+        self.receiver = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Shared count of bytes pulled from a reader's raw input. Clones share
+/// one counter, so it can be read while a background thread owns the
+/// source.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, n: usize) {
+        self.0.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set(&self, n: u64) {
+        self.0.store(n, Ordering::Relaxed);
+    }
+}
+
+/// Wraps a raw source and counts the bytes read from it. Seeking moves
+/// the count to the new position, so it always reads as an offset into
+/// the source.
+pub(crate) struct Counted<R> {
+    inner: R,
+    counter: ByteCounter,
+}
+
+/// In-memory input counted as it is read.
+pub(crate) type CountedBytes = Counted<Cursor<Vec<u8>>>;
+
+impl<R> Counted<R> {
+    pub(crate) fn new(inner: R, counter: &ByteCounter) -> Self {
+        Self {
+            inner,
+            counter: counter.clone(),
+        }
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.counter.add(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counted<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.counter.add(amt);
+    }
+}
+
+impl<R: Seek> Seek for Counted<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.counter.set(position);
+        Ok(position)
+    }
+}
+
+/// Read and discard `offset` bytes, for resuming a stream that cannot
+/// seek. Fails if the input ends first.
+pub(crate) fn skip_to<R: BufRead>(reader: &mut R, offset: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(offset), &mut io::sink())?;
+    if skipped < offset {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("checkpoint at byte {offset} is past the end of the input ({skipped} bytes)"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_buf_read_sniffs_across_short_fills() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(b"hello\n").unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        for (input, expected) in [(gzip, &b"hello\n"[..]), (b"ab".to_vec(), b"ab")] {
+            let trickle = BufReader::with_capacity(1, Cursor::new(input));
+            let mut decoded = Vec::new();
+            decode_buf_read(Box::new(trickle))
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, expected);
+        }
+    }
+
+    #[test]
+    fn detect_recognises_magic_bytes() {
+        assert_eq!(detect(&[0x1f, 0x8b, 0x08, 0x04]), InputCompression::Gzip);
+        assert_eq!(detect(&[0x28, 0xb5, 0x2f, 0xfd]), InputCompression::Zstd);
+        assert_eq!(detect(b">chr1"), InputCompression::None);
+        assert_eq!(detect(&[0x28, 0xb5]), InputCompression::None);
+        assert_eq!(detect(&[]), InputCompression::None);
+    }
+
+    #[test]
+    fn bgzf_is_told_apart_from_plain_gzip() {
+        let mut bgzf = noodles_bgzf::io::Writer::new(Vec::new());
+        bgzf.write_all(b"ACGT").unwrap();
+        assert!(is_bgzf(&bgzf.finish().unwrap()));
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"ACGT").unwrap();
+        assert!(!is_bgzf(&gzip.finish().unwrap()));
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn read_ahead_preserves_stream_order() {
+        let data: Vec<u8> = (0..READ_AHEAD_CHUNK * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let mut reader = ReadAhead::spawn(io::Cursor::new(data.clone()));
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn read_ahead_surfaces_decoder_errors() {
+        let mut reader = ReadAhead::spawn(flate2::read::GzDecoder::new(&b"\x1f\x8bnot gzip"[..]));
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn block_writer_output_decodes_in_order() {
+        let data: Vec<u8> = (0..1_000_000)
+            .map(|i| b"ACGT"[(i * 7 + i / 13) % 4])
+            .collect();
+
+        for format in [BlockFormat::Gzip, BlockFormat::Bgzf] {
+            let mut writer = BlockWriter::new(Vec::new(), format, Compression::new(6));
+            for piece in data.chunks(10_007) {
+                writer.write_all(piece).unwrap();
+            }
+            let (compressed, _) = writer.finish().unwrap();
+
+            let mut out = Vec::new();
+            flate2::read::MultiGzDecoder::new(&compressed[..])
+                .read_to_end(&mut out)
+                .unwrap();
+            assert_eq!(out, data, "{format:?}");
+
+            if format == BlockFormat::Bgzf {
+                assert!(is_bgzf(&compressed));
+                assert!(compressed.ends_with(&BGZF_EOF));
+                let mut reader = noodles_bgzf::io::Reader::new(&compressed[..]);
+                let mut out = Vec::new();
+                reader.read_to_end(&mut out).unwrap();
+                assert_eq!(out, data);
+            }
+        }
+    }
+
+    #[test]
+    fn bgzf_output_gzi_locates_every_block() {
+        let data: Vec<u8> = (0..1_000_000)
+            .map(|i| b"ACGT"[(i * 7 + i / 13) % 4])
+            .collect();
+
+        for mode in [CompressionMode::Bgzf, CompressionMode::ParallelBgzf(6)] {
+            let mut output = Output::open_to_bytes(mode).unwrap();
+            for piece in data.chunks(10_007) {
+                output.write_all(piece).unwrap();
+            }
+            let (compressed, gzi) = output.finish_with_gzi().unwrap();
+            let (compressed, gzi) = (compressed.unwrap(), gzi.unwrap());
+
+            assert_eq!(gzi.as_ref().len(), data.len().div_ceil(0xff00) - 1);
+            for &(c, _) in gzi.as_ref() {
+                assert!(is_bgzf(&compressed[usize::try_from(c).unwrap()..]));
+            }
+            let mut reader = noodles_bgzf::io::Reader::new(Cursor::new(&compressed));
+            for pos in [0, 0xff00 - 1, 0xff00, 300_000, 999_999] {
+                reader.seek(gzi.query(pos).unwrap()).unwrap();
+                let mut byte = [0];
+                reader.read_exact(&mut byte).unwrap();
+                assert_eq!(byte[0], data[usize::try_from(pos).unwrap()], "{mode:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn incompressible_bgzf_blocks_fall_back_to_stored() {
+        let mut state = 0x2545_f491_u32;
+        let data: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_le_bytes()[0]
+            })
+            .collect();
+        let mut writer = BlockWriter::new(Vec::new(), BlockFormat::Bgzf, Compression::best());
+        writer.write_all(&data).unwrap();
+        let (compressed, _) = writer.finish().unwrap();
+
+        let mut out = Vec::new();
+        noodles_bgzf::io::Reader::new(&compressed[..])
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn chunk_decoder_carries_state_across_tiny_chunks() {
+        let data = b"@r1\nACGT\n+\nIIII\n".repeat(500);
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&data).unwrap();
+        let gzip = gzip.finish().unwrap();
+        #[cfg(feature = "zstd")]
+        let inputs = [&data, &gzip, &zstd::encode_all(&data[..], 0).unwrap()];
+        #[cfg(not(feature = "zstd"))]
+        let inputs = [&data, &gzip];
+
+        for input in inputs {
+            let mut decoder = ChunkDecoder::new();
+            let mut out = Vec::new();
+            for piece in input.chunks(3) {
+                decoder.push(piece, &mut out).unwrap();
+            }
+            decoder.finish(&mut out).unwrap();
+            assert_eq!(out, data);
+        }
+
+        let mut decoder = ChunkDecoder::new();
+        let mut out = Vec::new();
+        decoder.push(b">a", &mut out).unwrap();
+        decoder.finish(&mut out).unwrap();
+        assert_eq!(out, b">a");
+    }
+
+    #[test]
+    fn deflate_level_is_validated() {
+        assert!(deflate_level(0).is_ok());
+        assert!(deflate_level(9).is_ok());
+        assert!(deflate_level(10).is_err());
+        assert!(deflate_level(-1).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_level_is_validated() {
+        assert!(zstd_encoder(Vec::new(), 3).is_ok());
+        assert!(zstd_encoder(Vec::new(), 1000).is_err());
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_needs_the_feature() {
+        assert!(matches!(
+            Output::open_to_bytes(CompressionMode::Zstd(3)),
+            Err(EngineError::InvalidArgument(_))
+        ));
+        let err = ChunkDecoder::new()
+            .push(&ZSTD_MAGIC, &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
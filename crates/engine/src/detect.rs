@@ -0,0 +1,461 @@
+//! Compression and record-format sniffing for arbitrary input.
+//!
+//! `from_bytes` and `from_path` look at the first `SNIFF_LEN` bytes of
+//! input and report how it is compressed, which record format it holds,
+//! and hints about its content: the sequence alphabet of FASTA and FASTQ
+//! data and the quality encoding of FASTQ data. Gzip, BGZF and zstd input
+//! is decompressed far enough to sniff the records inside; bzip2 and xz
+//! are recognised but not decoded, so their format is left unknown.
+//!
+//! Every answer is a best guess from a prefix of the input. The readers
+//! do their own detection when opening a file; this module is for routing
+//! input before choosing a reader.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::{
+    classify::{self, NUM_CLASSES},
+    codec::{self, InputCompression},
+    EngineError, RecordFormat,
+};
+
+/// Number of leading bytes inspected, both raw and after decompression.
+pub const SNIFF_LEN: usize = 16 * 1024;
+
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Compression of the inspected input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bgzf,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Bgzf => "bgzf",
+            Self::Zstd => "zstd",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+        }
+    }
+}
+
+/// Likely alphabet of the sequences in FASTA or FASTQ input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alphabet {
+    Dna,
+    Rna,
+    Protein,
+}
+
+impl Alphabet {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Dna => "dna",
+            Self::Rna => "rna",
+            Self::Protein => "protein",
+        }
+    }
+}
+
+/// Likely ASCII encoding of FASTQ quality scores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QualityEncoding {
+    Phred33,
+    Phred64,
+    Solexa,
+}
+
+impl QualityEncoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Phred33 => "phred33",
+            Self::Phred64 => "phred64",
+            Self::Solexa => "solexa",
+        }
+    }
+}
+
+/// What `from_bytes` and `from_path` found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Detection {
+    pub compression: Compression,
+    /// `None` if the content is not a recognised format or could not be
+    /// decompressed.
+    pub format: Option<RecordFormat>,
+    /// Set for FASTA and FASTQ input with at least one sequence line.
+    pub alphabet: Option<Alphabet>,
+    /// Set for FASTQ input with at least one quality line.
+    pub quality_encoding: Option<QualityEncoding>,
+}
+
+/// Sniff a file by path, reading at most `SNIFF_LEN` bytes of it.
+///
+/// # Errors
+///
+/// Returns `EngineError::Io` if the file cannot be opened or read.
+pub fn from_path(path: &str) -> Result<Detection, EngineError> {
+    let file =
+        File::open(path).map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| EngineError::Io(format!("failed to read from '{path}': {e}")))?;
+    Ok(from_bytes(&head))
+}
+
+/// Sniff a buffer. Only its first `SNIFF_LEN` bytes are inspected.
+pub fn from_bytes(data: &[u8]) -> Detection {
+    let head = &data[..data.len().min(SNIFF_LEN)];
+    let compression = compression(head);
+    let decoded = match compression {
+        Compression::None => Some(head.to_vec()),
+        Compression::Gzip | Compression::Bgzf => Some(inflate_prefix(MultiGzDecoder::new(head))),
+        Compression::Zstd => codec::zstd_decoder(head).ok().map(inflate_prefix),
+        Compression::Bzip2 | Compression::Xz => None,
+    };
+
+    let mut detection = Detection {
+        compression,
+        format: None,
+        alphabet: None,
+        quality_encoding: None,
+    };
+    let Some(text) = decoded else {
+        return detection;
+    };
+    detection.format = record_format(&text);
+    match detection.format {
+        Some(RecordFormat::Fasta) => {
+            detection.alphabet = alphabet(fasta_sequence_lines(&text));
+        }
+        Some(RecordFormat::Fastq) => {
+            detection.alphabet = alphabet(fastq_lines(&text, 1));
+            detection.quality_encoding = quality_encoding(fastq_lines(&text, 3));
+        }
+        _ => {}
+    }
+    detection
+}
+
+fn compression(head: &[u8]) -> Compression {
+    match codec::detect(head) {
+        InputCompression::Gzip if codec::is_bgzf(head) => Compression::Bgzf,
+        InputCompression::Gzip => Compression::Gzip,
+        InputCompression::Zstd => Compression::Zstd,
+        InputCompression::None if head.starts_with(BZIP2_MAGIC) => Compression::Bzip2,
+        InputCompression::None if head.starts_with(XZ_MAGIC) => Compression::Xz,
+        InputCompression::None => Compression::None,
+    }
+}
+
+/// Decompress up to `SNIFF_LEN` bytes. The compressed input is itself a
+/// prefix, so the stream usually ends in an error; whatever decoded
+/// before it is kept.
+fn inflate_prefix<R: Read>(decoder: R) -> Vec<u8> {
+    let mut out = vec![0; SNIFF_LEN];
+    let mut filled = 0;
+    let mut decoder = decoder;
+    while filled < out.len() {
+        match decoder.read(&mut out[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+    out.truncate(filled);
+    out
+}
+
+/// Lines of `text`, without line endings. When `text` fills the whole
+/// sniff window its last line may be cut short, so it is dropped.
+fn lines(text: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let complete = match text.iter().rposition(|&b| b == b'\n') {
+        Some(i) if text.len() >= SNIFF_LEN => &text[..=i],
+        _ => text,
+    };
+    complete
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+fn record_format(text: &[u8]) -> Option<RecordFormat> {
+    if text.starts_with(b"BAM\x01") {
+        return Some(RecordFormat::Bam);
+    }
+    if text.starts_with(b"BCF\x02") {
+        return Some(RecordFormat::Bcf);
+    }
+    if text.starts_with(b"CRAM") {
+        return Some(RecordFormat::Cram);
+    }
+
+    let mut lines = lines(text).filter(|line| !line.is_empty()).peekable();
+    let first = *lines.peek()?;
+    if first.starts_with(b"##fileformat=VCF") || first.starts_with(b"#CHROM\tPOS") {
+        return Some(RecordFormat::Vcf);
+    }
+    if first.starts_with(b"##gff-version") {
+        return Some(RecordFormat::Gff);
+    }
+    if [b"@HD\t", b"@SQ\t", b"@RG\t", b"@PG\t", b"@CO\t"]
+        .iter()
+        .any(|tag| first.starts_with(*tag))
+    {
+        return Some(RecordFormat::Sam);
+    }
+    if first.starts_with(b">") || first.starts_with(b";") {
+        return Some(RecordFormat::Fasta);
+    }
+    if first.starts_with(b"@") {
+        return lines
+            .nth(2)
+            .is_some_and(|plus| plus.starts_with(b"+"))
+            .then_some(RecordFormat::Fastq);
+    }
+
+    // Headerless tabular formats: judge by the first data line.
+    let mut data = lines.filter(|line| {
+        !(line.starts_with(b"#") || line.starts_with(b"track") || line.starts_with(b"browser"))
+    });
+    tabular_format(data.next()?)
+}
+
+fn tabular_format(line: &[u8]) -> Option<RecordFormat> {
+    let fields: Vec<&[u8]> = line.split(|&b| b == b'\t').collect();
+    let is_int = |field: &[u8]| !field.is_empty() && field.iter().all(u8::is_ascii_digit);
+
+    // BED12 also has integers in these columns, but never a CIGAR string.
+    if fields.len() >= 11
+        && is_int(fields[1])
+        && is_int(fields[3])
+        && is_int(fields[4])
+        && is_cigar(fields[5])
+    {
+        return Some(RecordFormat::Sam);
+    }
+    if fields.len() == 9
+        && is_int(fields[3])
+        && is_int(fields[4])
+        && matches!(fields[6], b"+" | b"-" | b"." | b"?")
+    {
+        let attributes = fields[8];
+        let gtf = attributes.contains(&b'"') && !attributes.contains(&b'=');
+        return Some(if gtf {
+            RecordFormat::Gtf
+        } else {
+            RecordFormat::Gff
+        });
+    }
+    if fields.len() >= 8 && is_int(fields[1]) && matches!(fields[6], b"PASS" | b".") {
+        return Some(RecordFormat::Vcf);
+    }
+    if fields.len() >= 3 && is_int(fields[1]) && is_int(fields[2]) {
+        return Some(RecordFormat::Bed);
+    }
+    None
+}
+
+/// Whether `field` is `*` or a run of `<length><op>` CIGAR operations.
+fn is_cigar(field: &[u8]) -> bool {
+    if field == b"*" {
+        return true;
+    }
+    let mut length = false;
+    for &b in field {
+        if b.is_ascii_digit() {
+            length = true;
+        } else if length && b"MIDNSHP=X".contains(&b) {
+            length = false;
+        } else {
+            return false;
+        }
+    }
+    !field.is_empty() && !length
+}
+
+fn fasta_sequence_lines(text: &[u8]) -> impl Iterator<Item = &[u8]> {
+    lines(text).filter(|line| !line.starts_with(b">") && !line.starts_with(b";"))
+}
+
+/// Line `offset` (0 = header) of each four-line FASTQ record.
+fn fastq_lines(text: &[u8], offset: usize) -> impl Iterator<Item = &[u8]> {
+    lines(text)
+        .filter(|line| !line.is_empty())
+        .skip(offset)
+        .step_by(4)
+}
+
+fn alphabet<'a>(sequences: impl Iterator<Item = &'a [u8]>) -> Option<Alphabet> {
+    let mut counts = [0u32; NUM_CLASSES];
+    let mut seen = false;
+    for line in sequences {
+        classify::classify(line, &mut counts);
+        seen |= !line.is_empty();
+    }
+    if !seen {
+        return None;
+    }
+
+    let nucleotides = counts[classify::CLASS_A]
+        + counts[classify::CLASS_C]
+        + counts[classify::CLASS_G]
+        + counts[classify::CLASS_T]
+        + counts[classify::CLASS_U]
+        + counts[classify::CLASS_N];
+    let residues: u32 = counts.iter().sum::<u32>() - counts[classify::CLASS_GAP];
+    // Protein sequences use A, C, G, T and N too, but rarely for 90% of
+    // residues; anything outside the IUPAC nucleotide codes settles it.
+    if counts[classify::CLASS_OTHER] > 0 || u64::from(nucleotides) * 10 < u64::from(residues) * 9 {
+        Some(Alphabet::Protein)
+    } else if counts[classify::CLASS_U] > 0 && counts[classify::CLASS_T] == 0 {
+        Some(Alphabet::Rna)
+    } else {
+        Some(Alphabet::Dna)
+    }
+}
+
+fn quality_encoding<'a>(qualities: impl Iterator<Item = &'a [u8]>) -> Option<QualityEncoding> {
+    let (min, max) = qualities
+        .flat_map(|line| line.iter().copied())
+        .fold(None, |range, q| match range {
+            None => Some((q, q)),
+            Some((min, max)) => Some((q.min(min), q.max(max))),
+        })?;
+    // Phred+33 tops out at 'J' (or 'K' for some newer instruments), so
+    // only a score above that rules it out; a range that fits both
+    // encodings is taken as the modern default.
+    if min < b';' || max <= b'K' {
+        return Some(QualityEncoding::Phred33);
+    }
+    Some(if min < b'@' {
+        QualityEncoding::Solexa
+    } else {
+        QualityEncoding::Phred64
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn format(data: &[u8]) -> Option<RecordFormat> {
+        from_bytes(data).format
+    }
+
+    #[test]
+    fn high_quality_phred33_is_not_mistaken_for_older_encodings() {
+        let q26_floor = from_bytes(b"@r1\nACGT\n+\n;?@I\n@r2\nACGT\n+\nFFFJ\n");
+        assert_eq!(q26_floor.quality_encoding, Some(QualityEncoding::Phred33));
+
+        let all_high = from_bytes(b"@r1\nACGT\n+\nIIII\n");
+        assert_eq!(all_high.quality_encoding, Some(QualityEncoding::Phred33));
+    }
+
+    #[test]
+    fn sequence_formats_with_hints() {
+        let fastq = from_bytes(b"@r1\nACGTN\n+\nIIII#\n@r2\nGGCA\n+\nIIII\n");
+        assert_eq!(fastq.compression, Compression::None);
+        assert_eq!(fastq.format, Some(RecordFormat::Fastq));
+        assert_eq!(fastq.alphabet, Some(Alphabet::Dna));
+        assert_eq!(fastq.quality_encoding, Some(QualityEncoding::Phred33));
+
+        let legacy = from_bytes(b"@r1\nACGU\n+\nhhh@\n");
+        assert_eq!(legacy.alphabet, Some(Alphabet::Rna));
+        assert_eq!(legacy.quality_encoding, Some(QualityEncoding::Phred64));
+
+        let solexa = from_bytes(b"@r1\nACGT\n+\nhh;h\n");
+        assert_eq!(solexa.quality_encoding, Some(QualityEncoding::Solexa));
+
+        let protein = from_bytes(b">sp|P1\nMKVLIEQWPF\nGAS\n");
+        assert_eq!(protein.format, Some(RecordFormat::Fasta));
+        assert_eq!(protein.alphabet, Some(Alphabet::Protein));
+        assert_eq!(protein.quality_encoding, None);
+    }
+
+    #[test]
+    fn alignment_and_annotation_formats() {
+        assert_eq!(format(b"@HD\tVN:1.6\n"), Some(RecordFormat::Sam));
+        assert_eq!(
+            format(b"r1\t0\tchr1\t100\t60\t4M\t*\t0\t0\tACGT\tIIII\n"),
+            Some(RecordFormat::Sam)
+        );
+        assert_eq!(format(b"CRAM\x03\x00"), Some(RecordFormat::Cram));
+        assert_eq!(format(b"##fileformat=VCFv4.3\n"), Some(RecordFormat::Vcf));
+        assert_eq!(
+            format(b"##gff-version 3\nchr1\t.\tgene\t1\t9\t.\t+\t.\tID=g1\n"),
+            Some(RecordFormat::Gff)
+        );
+        assert_eq!(
+            format(b"chr1\tsrc\texon\t1\t9\t.\t+\t.\tgene_id \"g1\"; transcript_id \"t1\";\n"),
+            Some(RecordFormat::Gtf)
+        );
+        assert_eq!(
+            format(b"track name=x\nchr1\t10\t20\tpeak\n"),
+            Some(RecordFormat::Bed)
+        );
+        assert_eq!(
+            format(b"chr1\t100\t200\t7\t0\t+\t110\t190\t0\t2\t10,20,\t0,80,\n"),
+            Some(RecordFormat::Bed)
+        );
+        assert_eq!(format(b"hello world\n"), None);
+        assert_eq!(format(b""), None);
+    }
+
+    #[test]
+    fn compressed_input_is_sniffed_through() {
+        let gzipped = from_bytes(&gzip(b">chr1\nACGT\n"));
+        assert_eq!(gzipped.compression, Compression::Gzip);
+        assert_eq!(gzipped.format, Some(RecordFormat::Fasta));
+
+        let mut bgzf = noodles_bgzf::io::Writer::new(Vec::new());
+        bgzf.write_all(b"BAM\x01\x00\x00\x00\x00").unwrap();
+        let bam = from_bytes(&bgzf.finish().unwrap());
+        assert_eq!(bam.compression, Compression::Bgzf);
+        assert_eq!(bam.format, Some(RecordFormat::Bam));
+
+        #[cfg(feature = "zstd")]
+        {
+            let zstd = zstd::encode_all(&b"@r1\nACGT\n+\nIIII\n"[..], 0).unwrap();
+            assert_eq!(from_bytes(&zstd).format, Some(RecordFormat::Fastq));
+        }
+
+        // A long file cut off mid-stream still sniffs from what decoded.
+        let mut records = Vec::new();
+        for i in 0..20_000 {
+            records.extend_from_slice(format!("@read{i}\nACGT{i}\n+\nIIII\n").as_bytes());
+        }
+        let long = gzip(&records);
+        assert!(long.len() > SNIFF_LEN);
+        assert_eq!(from_bytes(&long).format, Some(RecordFormat::Fastq));
+
+        let xz = from_bytes(&[0xfd, b'7', b'z', b'X', b'Z', 0x00, 0x00]);
+        assert_eq!(xz.compression, Compression::Xz);
+        assert_eq!(xz.format, None);
+        assert_eq!(from_bytes(b"BZh91AY&SY").compression, Compression::Bzip2);
+    }
+}
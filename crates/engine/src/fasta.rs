@@ -2,21 +2,42 @@
 //!
 //! Provides a stateful reader that owns a noodles FASTA reader and
 //! returns batches of parsed records in a struct-of-arrays layout.
-//! Handles gzip-compressed input transparently via flate2.
+//! Handles gzip-compressed input (including multi-member and BGZF
+//! streams) via flate2 and Zstandard input via zstd, detected from the
+//! leading magic bytes.
 //!
 //! FASTA sequences can span multiple lines. The noodles reader handles
 //! this correctly — it concatenates continuation lines into a single
-//! sequence before returning the record.
+//! sequence before returning the record. For records too large to hold
+//! at once, `FastaReader::read_sequence_chunks` returns the sequence in
+//! fixed-size pieces instead.
+//!
+//! `FastaChunkReader` parses input pushed in chunks, such as a stream
+//! from the browser, without buffering the whole file.
+//!
+//! `FastaIndex` adds samtools-compatible `.fai` indexing and random
+//! access to subsequences of uncompressed FASTA files. `FastaWriter` can
+//! emit BGZF output together with its `.fai` and `.gzi` sidecars.
 
 use std::{
+    collections::HashMap,
     fs::File,
-    io::{BufReader, BufWriter, Cursor, Read, Seek, Write},
+    io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write},
 };
 
-use flate2::{read::GzDecoder, write::GzEncoder};
-use noodles_fasta as fasta;
+use flate2::read::MultiGzDecoder;
+use noodles_bgzf::{self as bgzf, gzi};
+use noodles_fasta::{self as fasta, fai};
 
-use crate::EngineError;
+use crate::{
+    codec::{
+        self, BoxBufRead, ByteCounter, ChunkDecoder, Counted, CountedBytes, InputCompression,
+        Output, ZstdDecoder,
+    },
+    fastq::{next_lines, split_definition},
+    transform, BytesConsumed, CompressionMode, EngineError, ParseError, ParseErrorKind,
+    ReaderCheckpoint, RecordFormat,
+};
 
 /// A batch of parsed FASTA records in struct-of-arrays layout.
 pub struct FastaBatch {
@@ -32,6 +53,79 @@ pub struct FastaBatch {
     pub sequence_offsets: Vec<u32>,
 }
 
+impl FastaBatch {
+    pub(crate) fn empty() -> Self {
+        Self {
+            count: 0,
+            name_data: Vec::new(),
+            name_offsets: vec![0],
+            description_data: Vec::new(),
+            description_offsets: vec![0],
+            sequence_data: Vec::new(),
+            sequence_offsets: vec![0],
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn push(&mut self, name: &[u8], description: &[u8], sequence: &[u8]) {
+        self.name_data.extend_from_slice(name);
+        self.name_offsets.push(self.name_data.len() as u32);
+        self.description_data.extend_from_slice(description);
+        self.description_offsets
+            .push(self.description_data.len() as u32);
+        self.sequence_data.extend_from_slice(sequence);
+        self.sequence_offsets.push(self.sequence_data.len() as u32);
+        self.count += 1;
+    }
+}
+
+/// A run of sequence chunks from `FastaReader::read_sequence_chunks`.
+///
+/// Entry `i` is a slice of record `record_indices[i]` (0-based, in file
+/// order) starting at base `chunk_starts[i]`; `is_last[i]` is 1 on the
+/// record's final chunk. Every chunk repeats its record's name.
+#[derive(Debug)]
+pub struct FastaSequenceChunkBatch {
+    pub count: u32,
+
+    pub record_indices: Vec<u64>,
+    pub chunk_starts: Vec<u64>,
+    pub is_last: Vec<u8>,
+
+    pub name_data: Vec<u8>,
+    pub name_offsets: Vec<u32>,
+
+    pub sequence_data: Vec<u8>,
+    pub sequence_offsets: Vec<u32>,
+}
+
+impl FastaSequenceChunkBatch {
+    fn empty() -> Self {
+        Self {
+            count: 0,
+            record_indices: Vec::new(),
+            chunk_starts: Vec::new(),
+            is_last: Vec::new(),
+            name_data: Vec::new(),
+            name_offsets: vec![0],
+            sequence_data: Vec::new(),
+            sequence_offsets: vec![0],
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn push(&mut self, record_index: u64, start: u64, is_last: bool, name: &[u8], sequence: &[u8]) {
+        self.record_indices.push(record_index);
+        self.chunk_starts.push(start);
+        self.is_last.push(u8::from(is_last));
+        self.name_data.extend_from_slice(name);
+        self.name_offsets.push(self.name_data.len() as u32);
+        self.sequence_data.extend_from_slice(sequence);
+        self.sequence_offsets.push(self.sequence_data.len() as u32);
+        self.count += 1;
+    }
+}
+
 /// Stateful FASTA file reader.
 ///
 /// Wraps a noodles FASTA reader with optional gzip decompression.
@@ -48,65 +142,261 @@ pub struct FastaReader {
     // For large files, we use the lower-level read_definition +
     // read_sequence approach.
     inner: FastaReaderInner,
+    records_read: u64,
+    /// Name and next base offset of a record `read_sequence_chunks` has
+    /// not finished.
+    open_record: Option<(Vec<u8>, u64)>,
+    /// Raw bytes pulled from the input.
+    source: ByteCounter,
+    /// Decompressed bytes the parser has consumed.
+    parsed: ByteCounter,
 }
 
+/// A noodles FASTA reader over decompressed input, counting the bytes it
+/// consumes.
+type Input<R> = fasta::io::Reader<Counted<R>>;
+
 enum FastaReaderInner {
-    PlainFile(fasta::io::Reader<BufReader<File>>),
-    GzipFile(fasta::io::Reader<BufReader<GzDecoder<File>>>),
-    PlainBytes(fasta::io::Reader<BufReader<Cursor<Vec<u8>>>>),
-    GzipBytes(fasta::io::Reader<BufReader<GzDecoder<Cursor<Vec<u8>>>>>),
+    PlainFile(Input<BufReader<Counted<File>>>),
+    GzipFile(Input<BufReader<MultiGzDecoder<Counted<File>>>>),
+    BgzfFile(Input<bgzf::io::Reader<Counted<File>>>),
+    PlainBytes(Input<BufReader<CountedBytes>>),
+    GzipBytes(Input<BufReader<MultiGzDecoder<CountedBytes>>>),
+    BgzfBytes(Input<bgzf::io::Reader<CountedBytes>>),
+    ZstdFile(Input<BufReader<ZstdDecoder<Counted<File>>>>),
+    ZstdBytes(Input<BufReader<ZstdDecoder<CountedBytes>>>),
+    Stream(Input<BoxBufRead>),
 }
 
 impl FastaReader {
     /// Open a FASTA file by path.
     ///
-    /// Gzip compression is detected from the first two bytes (gzip magic).
+    /// Gzip and Zstandard compression are detected from the leading magic
+    /// bytes.
     ///
     /// # Errors
     ///
     /// Returns `EngineError::Io` if the file cannot be opened or read.
     pub fn open_from_path(path: &str) -> Result<Self, EngineError> {
-        let mut file = File::open(path)
+        let file = File::open(path)
             .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        let (source, parsed) = (ByteCounter::default(), ByteCounter::default());
+        let mut file = Counted::new(file, &source);
 
-        let mut magic = [0u8; 2];
-        let n = file
-            .read(&mut magic)
+        let mut header = Vec::with_capacity(codec::BGZF_HEADER_LEN);
+        (&mut file)
+            .take(codec::BGZF_HEADER_LEN as u64)
+            .read_to_end(&mut header)
             .map_err(|e| EngineError::Io(format!("failed to read from '{path}': {e}")))?;
 
-        file.seek(std::io::SeekFrom::Start(0))
+        file.seek(SeekFrom::Start(0))
             .map_err(|e| EngineError::Io(format!("failed to seek in '{path}': {e}")))?;
 
-        let is_gzip = n >= 2 && magic[0] == 0x1f && magic[1] == 0x8b;
-
-        let inner = if is_gzip {
-            FastaReaderInner::GzipFile(fasta::io::Reader::new(BufReader::new(GzDecoder::new(file))))
-        } else {
-            FastaReaderInner::PlainFile(fasta::io::Reader::new(BufReader::new(file)))
+        let inner = match codec::detect(&header) {
+            InputCompression::Gzip if codec::is_bgzf(&header) => {
+                FastaReaderInner::BgzfFile(input(bgzf::io::Reader::new(file), &parsed))
+            }
+            InputCompression::Gzip => FastaReaderInner::GzipFile(input(
+                BufReader::new(MultiGzDecoder::new(file)),
+                &parsed,
+            )),
+            InputCompression::Zstd => {
+                let decoder = codec::zstd_decoder(file).map_err(|e| {
+                    EngineError::Io(format!("failed to start zstd stream for '{path}': {e}"))
+                })?;
+                FastaReaderInner::ZstdFile(input(BufReader::new(decoder), &parsed))
+            }
+            InputCompression::None => {
+                FastaReaderInner::PlainFile(input(BufReader::new(file), &parsed))
+            }
         };
 
-        Ok(Self { inner })
+        Ok(Self::from_inner(inner, source, parsed))
     }
 
     /// Open a FASTA dataset from an in-memory buffer.
     ///
-    /// Gzip compression is detected from the first two bytes.
+    /// Gzip and Zstandard compression are detected from the leading magic
+    /// bytes.
     ///
     /// # Errors
     ///
     /// Returns `EngineError::Io` if the buffer cannot be parsed.
     pub fn open_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
-        let is_gzip = bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b;
+        let compression = codec::detect(&bytes);
+        let is_bgzf = codec::is_bgzf(&bytes[..bytes.len().min(codec::BGZF_HEADER_LEN)]);
+        let (source, parsed) = (ByteCounter::default(), ByteCounter::default());
+        let bytes = Counted::new(Cursor::new(bytes), &source);
 
-        let inner = if is_gzip {
-            FastaReaderInner::GzipBytes(fasta::io::Reader::new(BufReader::new(GzDecoder::new(
-                Cursor::new(bytes),
-            ))))
-        } else {
-            FastaReaderInner::PlainBytes(fasta::io::Reader::new(BufReader::new(Cursor::new(bytes))))
+        let inner = match compression {
+            InputCompression::Gzip if is_bgzf => {
+                FastaReaderInner::BgzfBytes(input(bgzf::io::Reader::new(bytes), &parsed))
+            }
+            InputCompression::Gzip => FastaReaderInner::GzipBytes(input(
+                BufReader::new(MultiGzDecoder::new(bytes)),
+                &parsed,
+            )),
+            InputCompression::Zstd => {
+                let decoder = codec::zstd_decoder(bytes)
+                    .map_err(|e| EngineError::Io(format!("failed to start zstd stream: {e}")))?;
+                FastaReaderInner::ZstdBytes(input(BufReader::new(decoder), &parsed))
+            }
+            InputCompression::None => {
+                FastaReaderInner::PlainBytes(input(BufReader::new(bytes), &parsed))
+            }
         };
 
-        Ok(Self { inner })
+        Ok(Self::from_inner(inner, source, parsed))
+    }
+
+    /// Open a FASTA file by path and resume at a checkpoint taken by an
+    /// earlier reader over the same file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the checkpoint is not a
+    /// FASTA checkpoint, or `EngineError::Io` if the file cannot be read
+    /// up to it.
+    pub fn open_from_path_at(
+        path: &str,
+        checkpoint: &ReaderCheckpoint,
+    ) -> Result<Self, EngineError> {
+        let mut reader = Self::open_from_path(path)?;
+        reader.resume(checkpoint)?;
+        Ok(reader)
+    }
+
+    /// Open a FASTA dataset from an in-memory buffer and resume at a
+    /// checkpoint, as for `open_from_path_at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the checkpoint is not a
+    /// FASTA checkpoint, or `EngineError::Io` if the buffer ends before
+    /// it.
+    pub fn open_from_bytes_at(
+        bytes: Vec<u8>,
+        checkpoint: &ReaderCheckpoint,
+    ) -> Result<Self, EngineError> {
+        let mut reader = Self::open_from_bytes(bytes)?;
+        reader.resume(checkpoint)?;
+        Ok(reader)
+    }
+
+    /// Open a FASTA stream from any reader, such as stdin, a pipe or a
+    /// socket.
+    ///
+    /// The reader is buffered internally; gzip and Zstandard compression
+    /// are detected from its leading bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the leading bytes cannot be read.
+    pub fn open_from_reader(reader: Box<dyn Read + Send>) -> Result<Self, EngineError> {
+        Self::open_from_buf_reader(Box::new(BufReader::new(reader)))
+    }
+
+    /// Open a FASTA stream from a buffered reader without adding another
+    /// layer of buffering to plain input.
+    ///
+    /// Compression is detected from the stream's leading bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the leading bytes cannot be read.
+    pub fn open_from_buf_reader(reader: Box<dyn BufRead + Send>) -> Result<Self, EngineError> {
+        let (source, parsed) = (ByteCounter::default(), ByteCounter::default());
+        let reader = codec::decode_buf_read(Box::new(Counted::new(reader, &source)))
+            .map_err(|e| EngineError::Io(format!("failed to read FASTA stream: {e}")))?;
+        Ok(Self::from_inner(
+            FastaReaderInner::Stream(input(reader, &parsed)),
+            source,
+            parsed,
+        ))
+    }
+
+    fn from_inner(inner: FastaReaderInner, source: ByteCounter, parsed: ByteCounter) -> Self {
+        Self {
+            inner,
+            records_read: 0,
+            open_record: None,
+            source,
+            parsed,
+        }
+    }
+
+    /// Number of records read to the end so far, counting any skipped
+    /// by resuming at a checkpoint. A record `read_sequence_chunks` is
+    /// part-way through is not counted.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Progress through the input, as raw and decompressed bytes.
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        BytesConsumed {
+            compressed: self.source.get(),
+            uncompressed: self.parsed.get(),
+        }
+    }
+
+    /// Save the reader's position after the last record read.
+    ///
+    /// BGZF input records the block's virtual offset, so resuming seeks
+    /// straight to it; other input resumes from the decompressed byte
+    /// offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if `read_sequence_chunks`
+    /// stopped part-way through a record.
+    pub fn checkpoint(&self) -> Result<ReaderCheckpoint, EngineError> {
+        if self.open_record.is_some() {
+            return Err(EngineError::InvalidArgument(
+                "cannot checkpoint part-way through a chunked FASTA record".to_string(),
+            ));
+        }
+        let virtual_offset = match &self.inner {
+            FastaReaderInner::BgzfFile(r) => Some(r.get_ref().get_ref().virtual_position()),
+            FastaReaderInner::BgzfBytes(r) => Some(r.get_ref().get_ref().virtual_position()),
+            _ => None,
+        };
+        Ok(ReaderCheckpoint::new(
+            RecordFormat::Fasta,
+            self.records_read,
+            self.parsed.get(),
+            virtual_offset.map(u64::from),
+        ))
+    }
+
+    fn resume(&mut self, checkpoint: &ReaderCheckpoint) -> Result<(), EngineError> {
+        checkpoint.expect_format(RecordFormat::Fasta)?;
+        let offset = checkpoint.uncompressed_offset();
+        let result = match (&mut self.inner, checkpoint.virtual_offset()) {
+            (FastaReaderInner::BgzfFile(r), Some(pos)) => {
+                r.get_mut().get_mut().seek(pos.into()).map(drop)
+            }
+            (FastaReaderInner::BgzfBytes(r), Some(pos)) => {
+                r.get_mut().get_mut().seek(pos.into()).map(drop)
+            }
+            (FastaReaderInner::PlainFile(r), _) => {
+                r.get_mut().seek(SeekFrom::Start(offset)).map(drop)
+            }
+            (FastaReaderInner::PlainBytes(r), _) => {
+                r.get_mut().seek(SeekFrom::Start(offset)).map(drop)
+            }
+            (FastaReaderInner::GzipFile(r), _) => codec::skip_to(r.get_mut(), offset),
+            (FastaReaderInner::BgzfFile(r), None) => codec::skip_to(r.get_mut(), offset),
+            (FastaReaderInner::GzipBytes(r), _) => codec::skip_to(r.get_mut(), offset),
+            (FastaReaderInner::BgzfBytes(r), None) => codec::skip_to(r.get_mut(), offset),
+            (FastaReaderInner::ZstdFile(r), _) => codec::skip_to(r.get_mut(), offset),
+            (FastaReaderInner::ZstdBytes(r), _) => codec::skip_to(r.get_mut(), offset),
+            (FastaReaderInner::Stream(r), _) => codec::skip_to(r.get_mut(), offset),
+        };
+        result.map_err(|e| EngineError::Io(format!("failed to resume FASTA input: {e}")))?;
+        self.records_read = checkpoint.records_read();
+        self.parsed.set(offset);
+        Ok(())
     }
 
     /// Read the next batch of FASTA records.
@@ -116,10 +406,37 @@ impl FastaReader {
     ///
     /// # Errors
     ///
-    /// Returns `EngineError::Io` if a record cannot be parsed.
-    #[allow(clippy::cast_possible_truncation)]
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number.
     pub fn read_batch(&mut self, max_records: u32) -> Result<Option<FastaBatch>, EngineError> {
+        self.read_batch_bounded(max_records, u32::MAX)
+    }
+
+    /// Read the next batch of FASTA records, stopping early once the
+    /// batch holds `max_bytes` of sequence data.
+    ///
+    /// The record that crosses the budget is kept, so a batch always
+    /// holds at least one record. Use `read_sequence_chunks` to stream a
+    /// record that is itself too large.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number, or `EngineError::InvalidArgument` if
+    /// `read_sequence_chunks` stopped part-way through a record.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn read_batch_bounded(
+        &mut self,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> Result<Option<FastaBatch>, EngineError> {
+        if self.open_record.is_some() {
+            return Err(EngineError::InvalidArgument(
+                "read_batch called part-way through a chunked FASTA record".to_string(),
+            ));
+        }
         let max = max_records as usize;
+        let max_bytes = max_bytes as usize;
 
         let mut name_bytes: Vec<u8> = Vec::with_capacity(max * 32);
         let mut name_offsets: Vec<u32> = Vec::with_capacity(max + 1);
@@ -127,7 +444,7 @@ impl FastaReader {
         let mut desc_bytes: Vec<u8> = Vec::with_capacity(max * 32);
         let mut desc_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
-        let mut seq_bytes: Vec<u8> = Vec::with_capacity(max * 500);
+        let mut seq_bytes: Vec<u8> = Vec::with_capacity((max * 500).min(max_bytes));
         let mut seq_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
         name_offsets.push(0);
@@ -147,19 +464,9 @@ impl FastaReader {
                 break;
             }
 
+            let (name, desc) = self.parse_definition(&def_buf)?;
             self.read_sequence(&mut seq_buf)?;
 
-            // Parse definition line: ">name description"
-            // read_definition returns the full line including the '>' prefix.
-            let def_trimmed = def_buf
-                .trim_end()
-                .strip_prefix('>')
-                .unwrap_or(def_buf.trim_end());
-            let (name, desc) = match def_trimmed.find(char::is_whitespace) {
-                Some(pos) => (&def_trimmed[..pos], &def_trimmed[pos..].trim_start()),
-                None => (def_trimmed, &""),
-            };
-
             name_bytes.extend_from_slice(name.as_bytes());
             name_offsets.push(name_bytes.len() as u32);
 
@@ -170,6 +477,11 @@ impl FastaReader {
             seq_offsets.push(seq_bytes.len() as u32);
 
             count += 1;
+            self.records_read += 1;
+
+            if seq_bytes.len() >= max_bytes {
+                break;
+            }
         }
 
         if count == 0 {
@@ -187,42 +499,340 @@ impl FastaReader {
         }))
     }
 
+    /// Read up to `max_chunks` pieces of sequence of at most `chunk_size`
+    /// bases each, splitting records instead of loading them whole.
+    ///
+    /// A record yields consecutive chunks tagged with its index in the
+    /// file and the offset of each chunk's first base; the chunk that
+    /// ends the record is flagged `is_last`. A record with no sequence
+    /// yields a single empty chunk. Returns `None` once all records have
+    /// been consumed. Chunking may stop part-way through a record and
+    /// resume on the next call, but `read_batch` may not be called until
+    /// the open record's last chunk has been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if `chunk_size` is zero, or
+    /// `EngineError::Parse` if a record is malformed.
+    pub fn read_sequence_chunks(
+        &mut self,
+        max_chunks: u32,
+        chunk_size: u32,
+    ) -> Result<Option<FastaSequenceChunkBatch>, EngineError> {
+        if chunk_size == 0 {
+            return Err(EngineError::InvalidArgument(
+                "chunk_size must be greater than zero".to_string(),
+            ));
+        }
+
+        let mut batch = FastaSequenceChunkBatch::empty();
+        let mut def_buf = String::new();
+        let mut seq_buf = Vec::with_capacity(chunk_size as usize);
+
+        while batch.count < max_chunks {
+            let (name, start) = if let Some(open) = self.open_record.take() {
+                open
+            } else {
+                def_buf.clear();
+                if self.read_definition(&mut def_buf)? == 0 {
+                    break;
+                }
+                let (name, _) = self.parse_definition(&def_buf)?;
+                (name.as_bytes().to_vec(), 0)
+            };
+
+            seq_buf.clear();
+            let is_last = self.read_sequence_chunk(chunk_size as usize, &mut seq_buf)?;
+            batch.push(self.records_read, start, is_last, &name, &seq_buf);
+
+            if is_last {
+                self.records_read += 1;
+            } else {
+                self.open_record = Some((name, start + seq_buf.len() as u64));
+            }
+        }
+
+        Ok((batch.count > 0).then_some(batch))
+    }
+
+    /// Split a definition line (`>name description`, as returned by
+    /// `read_definition`) into name and description.
+    fn parse_definition<'a>(&self, line: &'a str) -> Result<(&'a str, &'a str), EngineError> {
+        let Some(definition) = line.trim_end().strip_prefix('>') else {
+            let message = format!("record must start with '>', found '{}'", line.trim_end());
+            return Err(ParseError::new(
+                RecordFormat::Fasta,
+                ParseErrorKind::BadHeader,
+                self.records_read + 1,
+                message,
+            )
+            .field("definition")
+            .into());
+        };
+        Ok(match definition.find(char::is_whitespace) {
+            Some(pos) => (&definition[..pos], definition[pos..].trim_start()),
+            None => (definition, ""),
+        })
+    }
+
     fn read_definition(&mut self, buf: &mut String) -> Result<usize, EngineError> {
         let result = match &mut self.inner {
-            FastaReaderInner::PlainFile(r) => r.read_definition(buf),
-            FastaReaderInner::GzipFile(r) => r.read_definition(buf),
-            FastaReaderInner::PlainBytes(r) => r.read_definition(buf),
-            FastaReaderInner::GzipBytes(r) => r.read_definition(buf),
+            FastaReaderInner::PlainFile(r) => read_definition(r, buf),
+            FastaReaderInner::GzipFile(r) => read_definition(r, buf),
+            FastaReaderInner::BgzfFile(r) => read_definition(r, buf),
+            FastaReaderInner::PlainBytes(r) => read_definition(r, buf),
+            FastaReaderInner::GzipBytes(r) => read_definition(r, buf),
+            FastaReaderInner::BgzfBytes(r) => read_definition(r, buf),
+            FastaReaderInner::ZstdFile(r) => read_definition(r, buf),
+            FastaReaderInner::ZstdBytes(r) => read_definition(r, buf),
+            FastaReaderInner::Stream(r) => read_definition(r, buf),
         };
-        result.map_err(|e| EngineError::Io(format!("FASTA definition read error: {e}")))
+        result.map_err(|e| {
+            ParseError::from_io(RecordFormat::Fasta, self.records_read + 1, &e)
+                .field("definition")
+                .into()
+        })
     }
 
     fn read_sequence(&mut self, buf: &mut Vec<u8>) -> Result<usize, EngineError> {
         let result = match &mut self.inner {
             FastaReaderInner::PlainFile(r) => r.read_sequence(buf),
             FastaReaderInner::GzipFile(r) => r.read_sequence(buf),
+            FastaReaderInner::BgzfFile(r) => r.read_sequence(buf),
             FastaReaderInner::PlainBytes(r) => r.read_sequence(buf),
             FastaReaderInner::GzipBytes(r) => r.read_sequence(buf),
+            FastaReaderInner::BgzfBytes(r) => r.read_sequence(buf),
+            FastaReaderInner::ZstdFile(r) => r.read_sequence(buf),
+            FastaReaderInner::ZstdBytes(r) => r.read_sequence(buf),
+            FastaReaderInner::Stream(r) => r.read_sequence(buf),
+        };
+        result.map_err(|e| {
+            ParseError::from_io(RecordFormat::Fasta, self.records_read + 1, &e)
+                .field("sequence")
+                .into()
+        })
+    }
+
+    /// Read up to `max_bases` of the current record's sequence, returning
+    /// whether the record's sequence is exhausted.
+    fn read_sequence_chunk(
+        &mut self,
+        max_bases: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<bool, EngineError> {
+        let result = match &mut self.inner {
+            FastaReaderInner::PlainFile(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::GzipFile(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::BgzfFile(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::PlainBytes(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::GzipBytes(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::BgzfBytes(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::ZstdFile(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::ZstdBytes(r) => fill_sequence_chunk(r, max_bases, buf),
+            FastaReaderInner::Stream(r) => fill_sequence_chunk(r, max_bases, buf),
         };
-        result.map_err(|e| EngineError::Io(format!("FASTA sequence read error: {e}")))
+        result.map_err(|e| {
+            ParseError::from_io(RecordFormat::Fasta, self.records_read + 1, &e)
+                .field("sequence")
+                .into()
+        })
+    }
+}
+
+fn input<R: BufRead>(reader: R, parsed: &ByteCounter) -> Input<R> {
+    fasta::io::Reader::new(Counted::new(reader, parsed))
+}
+
+/// Read a definition line as noodles does, but decode it here so bad
+/// UTF-8 surfaces as a `FromUtf8Error` rather than a bare I/O message.
+fn read_definition<R: BufRead>(
+    reader: &mut fasta::io::Reader<R>,
+    buf: &mut String,
+) -> std::io::Result<usize> {
+    let mut line = Vec::new();
+    let n = reader.get_mut().read_until(b'\n', &mut line)?;
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    let line = String::from_utf8(line)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    buf.push_str(&line);
+    Ok(n)
+}
+
+/// Copy up to `max_bases` of sequence (newlines removed) into `buf`, then
+/// peek to see whether the sequence continues.
+fn fill_sequence_chunk<R: BufRead>(
+    reader: &mut fasta::io::Reader<R>,
+    max_bases: usize,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<bool> {
+    let mut sequence = reader.sequence_reader();
+    while buf.len() < max_bases {
+        let src = sequence.fill_buf()?;
+        if src.is_empty() {
+            return Ok(true);
+        }
+        let n = src.len().min(max_bases - buf.len());
+        buf.extend_from_slice(&src[..n]);
+        sequence.consume(n);
     }
+    Ok(sequence.fill_buf()?.is_empty())
 }
 
-enum WriterInner {
-    PlainFile(BufWriter<File>),
-    GzipFile(GzEncoder<BufWriter<File>>),
-    PlainBytes(Vec<u8>),
-    GzipBytes(GzEncoder<Vec<u8>>),
+/// Push-based FASTA parser for input that arrives in chunks.
+///
+/// Feed chunks with `push_chunk` and call `end` once the input is done.
+/// A record is complete once the next `>` line arrives, so each call
+/// returns the records before the one still being read; `end` returns
+/// the last. Gzip and zstd input is detected from the first bytes and
+/// decoded incrementally.
+pub struct FastaChunkReader {
+    decoder: ChunkDecoder,
+    /// Decoded bytes not yet split into lines.
+    pending: Vec<u8>,
+    /// Name, description and sequence so far of the open record.
+    current: Option<(Vec<u8>, Vec<u8>, Vec<u8>)>,
+    ended: bool,
+    /// Decoded bytes and lines drained from `pending`, for locating
+    /// parse errors.
+    bytes_parsed: u64,
+    lines_parsed: u64,
+}
+
+impl Default for FastaChunkReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FastaChunkReader {
+    pub fn new() -> Self {
+        Self {
+            decoder: ChunkDecoder::new(),
+            pending: Vec::new(),
+            current: None,
+            ended: false,
+            bytes_parsed: 0,
+            lines_parsed: 0,
+        }
+    }
+
+    /// Add the next chunk of input and return the records it completed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the chunk cannot be decompressed or
+    /// `end` was already called, or `EngineError::Parse` if the input does
+    /// not start with a `>` definition line.
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<Option<FastaBatch>, EngineError> {
+        if self.ended {
+            return Err(EngineError::Io(
+                "FASTA chunk pushed after end of input".to_string(),
+            ));
+        }
+        self.decoder
+            .push(chunk, &mut self.pending)
+            .map_err(|e| EngineError::Io(format!("FASTA decompression error: {e}")))?;
+        self.parse_lines(false)
+    }
+
+    /// Mark the end of input and return the remaining records.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the compressed stream is incomplete.
+    pub fn end(&mut self) -> Result<Option<FastaBatch>, EngineError> {
+        if self.ended {
+            return Ok(None);
+        }
+        self.ended = true;
+        self.decoder
+            .finish(&mut self.pending)
+            .map_err(|e| EngineError::Io(format!("FASTA decompression error: {e}")))?;
+        self.parse_lines(true)
+    }
+
+    fn parse_lines(&mut self, at_end: bool) -> Result<Option<FastaBatch>, EngineError> {
+        let mut batch = FastaBatch::empty();
+        let mut pos = 0;
+        let mut lines_done = 0;
+
+        while let Some(([(start, end)], next)) = next_lines::<1>(&self.pending, pos, at_end) {
+            let line = &self.pending[start..end];
+            if let Some(definition) = line.strip_prefix(b">") {
+                if let Some((name, description, sequence)) = self.current.take() {
+                    batch.push(&name, &description, &sequence);
+                }
+                let (name, description) = split_definition(definition);
+                self.current = Some((name.to_vec(), description.to_vec(), Vec::new()));
+            } else if let Some((_, _, sequence)) = &mut self.current {
+                sequence.extend_from_slice(line);
+            } else if !line.is_empty() {
+                let message = format!(
+                    "input must start with '>', found '{}'",
+                    String::from_utf8_lossy(line)
+                );
+                return Err(ParseError::new(
+                    RecordFormat::Fasta,
+                    ParseErrorKind::BadHeader,
+                    1,
+                    message,
+                )
+                .at(
+                    Some(self.bytes_parsed + start as u64),
+                    Some(self.lines_parsed + lines_done + 1),
+                )
+                .field("definition")
+                .into());
+            }
+            pos = next;
+            lines_done += 1;
+        }
+        self.pending.drain(..pos);
+        self.bytes_parsed += pos as u64;
+        self.lines_parsed += lines_done;
+
+        if at_end {
+            if let Some((name, description, sequence)) = self.current.take() {
+                batch.push(&name, &description, &sequence);
+            }
+        }
+
+        Ok((batch.count > 0).then_some(batch))
+    }
+}
+
+/// Output of `FastaWriter::finish_with_sidecars`.
+///
+/// `data` is the written output in bytes mode and `None` in file mode.
+/// For BGZF output, `fai` and `gzi` hold the `.fai` and `.gzi` index
+/// contents; in file mode they have also been written next to the
+/// output as `<path>.fai` and `<path>.gzi`.
+pub struct FastaWriterOutput {
+    pub data: Option<Vec<u8>>,
+    pub fai: Option<Vec<u8>>,
+    pub gzi: Option<Vec<u8>>,
 }
 
 /// Stateful FASTA batch writer.
 ///
 /// Accepts batches of records in the same struct-of-arrays layout that
 /// `FastaReader` produces. Sequences are wrapped at `line_width`
-/// characters (default 80). Optionally gzip-compresses the output.
+/// characters (default 80). Output may be gzip-, BGZF- or zstd-compressed,
+/// with gzip and BGZF optionally compressed in parallel blocks. For BGZF
+/// output the writer tracks record and block offsets, from which `finish`
+/// builds the `.fai` and `.gzi` sidecars that `samtools faidx` expects.
 pub struct FastaWriter {
-    inner: WriterInner,
+    inner: Output,
     line_width: usize,
+    path: Option<String>,
+    /// Uncompressed bytes written so far, for `.fai` offsets.
+    position: u64,
+    fai_records: Vec<fai::Record>,
 }
 
 impl FastaWriter {
@@ -230,35 +840,39 @@ impl FastaWriter {
     ///
     /// # Errors
     ///
-    /// Returns `EngineError::Io` if the file cannot be created.
-    pub fn open_to_path(path: &str, compress: bool, line_width: u32) -> Result<Self, EngineError> {
-        let file = File::create(path)
-            .map_err(|e| EngineError::Io(format!("failed to create '{path}': {e}")))?;
-        let buf = BufWriter::new(file);
-
-        let inner = if compress {
-            WriterInner::GzipFile(GzEncoder::new(buf, flate2::Compression::default()))
-        } else {
-            WriterInner::PlainFile(buf)
-        };
-
-        Ok(Self {
-            inner,
-            line_width: line_width as usize,
-        })
+    /// Returns `EngineError::Io` if the file cannot be created, or
+    /// `EngineError::InvalidArgument` if a compression level is out of
+    /// range.
+    pub fn open_to_path(
+        path: &str,
+        compression: CompressionMode,
+        line_width: u32,
+    ) -> Result<Self, EngineError> {
+        let inner = Output::open_to_path(path, compression)?;
+        Ok(Self::new(inner, line_width, Some(path.to_string())))
     }
 
     /// Open a writer to an in-memory buffer.
-    pub fn open_to_bytes(compress: bool, line_width: u32) -> Self {
-        let inner = if compress {
-            WriterInner::GzipBytes(GzEncoder::new(Vec::new(), flate2::Compression::default()))
-        } else {
-            WriterInner::PlainBytes(Vec::new())
-        };
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a compression level is
+    /// out of range.
+    pub fn open_to_bytes(
+        compression: CompressionMode,
+        line_width: u32,
+    ) -> Result<Self, EngineError> {
+        let inner = Output::open_to_bytes(compression)?;
+        Ok(Self::new(inner, line_width, None))
+    }
 
+    fn new(inner: Output, line_width: u32, path: Option<String>) -> Self {
         Self {
             inner,
             line_width: line_width as usize,
+            path,
+            position: 0,
+            fai_records: Vec::new(),
         }
     }
 
@@ -287,7 +901,9 @@ impl FastaWriter {
             let seq = &batch.sequence_data[seq_start..seq_end];
 
             let lw = self.line_width;
-            let w = self.writer();
+            self.record_fai_entry(name, desc, seq.len());
+
+            let w = &mut self.inner;
             w.write_all(b">").map_err(map_err)?;
             w.write_all(name).map_err(map_err)?;
             if !desc.is_empty() {
@@ -314,39 +930,855 @@ impl FastaWriter {
     /// Flush and close the writer.
     ///
     /// For file mode, returns `None`. For bytes mode, returns the
-    /// accumulated output bytes.
+    /// accumulated output bytes. BGZF file output also gets its `.fai`
+    /// and `.gzi` sidecars written; use `finish_with_sidecars` to obtain
+    /// them in bytes mode.
     ///
     /// # Errors
     ///
     /// Returns `EngineError::Io` if flushing fails.
     pub fn finish(self) -> Result<Option<Vec<u8>>, EngineError> {
-        match self.inner {
-            WriterInner::PlainFile(mut w) => {
-                w.flush()
-                    .map_err(|e| EngineError::Io(format!("flush error: {e}")))?;
-                Ok(None)
+        self.finish_with_sidecars().map(|output| output.data)
+    }
+
+    /// Flush and close the writer, returning the BGZF index sidecars.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if flushing fails or a sidecar cannot be
+    /// written.
+    pub fn finish_with_sidecars(self) -> Result<FastaWriterOutput, EngineError> {
+        let fai = fai::Index::from(self.fai_records);
+        let path = self.path;
+
+        let (data, gzi) = self.inner.finish_with_gzi()?;
+        let Some(gzi) = gzi else {
+            return Ok(FastaWriterOutput {
+                data,
+                fai: None,
+                gzi: None,
+            });
+        };
+        let (fai, gzi) = sidecars(&fai, &gzi)?;
+        if data.is_none() {
+            write_file_sidecars(&path.unwrap_or_default(), &fai, &gzi)?;
+        }
+        Ok(FastaWriterOutput {
+            data,
+            fai: Some(fai),
+            gzi: Some(gzi),
+        })
+    }
+
+    /// Record the `.fai` entry for a record about to be written and
+    /// advance the uncompressed position past it.
+    fn record_fai_entry(&mut self, name: &[u8], desc: &[u8], seq_len: usize) {
+        let mut header_len = 1 + name.len() as u64 + 1;
+        if !desc.is_empty() {
+            header_len += 1 + desc.len() as u64;
+        }
+
+        let seq_len = seq_len as u64;
+        let line_bases = if self.line_width == 0 {
+            seq_len
+        } else {
+            seq_len.min(self.line_width as u64)
+        };
+        let line_count = if line_bases == 0 {
+            1
+        } else {
+            seq_len.div_ceil(line_bases)
+        };
+
+        let offset = self.position + header_len;
+        self.fai_records.push(fai::Record::new(
+            name,
+            seq_len,
+            offset,
+            line_bases,
+            line_bases + 1,
+        ));
+        self.position = offset + seq_len + line_count;
+    }
+}
+
+/// One sequence entry of a `.fai` index.
+///
+/// `offset` is the byte offset of the first base; each sequence line
+/// holds `line_bases` bases in `line_width` bytes (including the line
+/// terminator).
+pub struct FastaIndexEntry {
+    pub name: String,
+    pub length: u64,
+    pub offset: u64,
+    pub line_bases: u64,
+    pub line_width: u64,
+}
+
+enum IndexSource {
+    File(BufReader<File>),
+    Bytes(Cursor<Vec<u8>>),
+}
+
+/// A `.fai`-indexed FASTA file supporting random-access region fetches.
+///
+/// The index is either built in one pass over the FASTA or loaded from
+/// an existing `.fai`. Fetches seek straight to the first requested base
+/// and read only the bytes covering the region. Gzip and BGZF input are
+/// not supported.
+pub struct FastaIndex {
+    index: fai::Index,
+    names: HashMap<Vec<u8>, usize>,
+    source: IndexSource,
+}
+
+impl FastaIndex {
+    /// Build an index by scanning a FASTA file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be read, or
+    /// `EngineError::InvalidArgument` if it is compressed or its lines
+    /// are not uniformly wrapped within a record.
+    pub fn build_from_path(path: &str) -> Result<Self, EngineError> {
+        let file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        let index = build_index(BufReader::new(file))?;
+
+        let file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        Ok(Self::new(index, IndexSource::File(BufReader::new(file))))
+    }
+
+    /// Build an index by scanning an in-memory FASTA buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the buffer is compressed
+    /// or its lines are not uniformly wrapped within a record.
+    pub fn build_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
+        let index = build_index(&bytes[..])?;
+        Ok(Self::new(index, IndexSource::Bytes(Cursor::new(bytes))))
+    }
+
+    /// Open a FASTA file with an existing `.fai` index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if either file cannot be opened, or
+    /// `EngineError::InvalidArgument` if the index cannot be parsed.
+    pub fn load(path: &str, index_path: &str) -> Result<Self, EngineError> {
+        let index_bytes = std::fs::read(index_path)
+            .map_err(|e| EngineError::Io(format!("failed to read '{index_path}': {e}")))?;
+        let index = parse_index(&index_bytes)?;
+
+        let file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        Ok(Self::new(index, IndexSource::File(BufReader::new(file))))
+    }
+
+    /// Wrap an in-memory FASTA buffer and the contents of its `.fai`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the index cannot be
+    /// parsed.
+    pub fn load_from_bytes(bytes: Vec<u8>, index_bytes: &[u8]) -> Result<Self, EngineError> {
+        let index = parse_index(index_bytes)?;
+        Ok(Self::new(index, IndexSource::Bytes(Cursor::new(bytes))))
+    }
+
+    fn new(index: fai::Index, source: IndexSource) -> Self {
+        let names = index
+            .as_ref()
+            .iter()
+            .enumerate()
+            .map(|(i, record)| (record.name().to_vec(), i))
+            .collect();
+
+        Self {
+            index,
+            names,
+            source,
+        }
+    }
+
+    /// The index entries, in file order.
+    pub fn entries(&self) -> Vec<FastaIndexEntry> {
+        self.index
+            .as_ref()
+            .iter()
+            .map(|record| FastaIndexEntry {
+                name: String::from_utf8_lossy(record.name()).into_owned(),
+                length: record.length(),
+                offset: record.offset(),
+                line_bases: record.line_bases(),
+                line_width: record.line_width(),
+            })
+            .collect()
+    }
+
+    /// Serialize the index in `.fai` format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if serialization fails.
+    pub fn index_bytes(&self) -> Result<Vec<u8>, EngineError> {
+        let mut writer = fai::io::Writer::new(Vec::new());
+        writer
+            .write_index(&self.index)
+            .map_err(|e| EngineError::Io(format!("FASTA index write error: {e}")))?;
+        Ok(writer.into_inner())
+    }
+
+    /// Write the index in `.fai` format to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be written.
+    pub fn write_index(&self, path: &str) -> Result<(), EngineError> {
+        let bytes = self.index_bytes()?;
+        std::fs::write(path, bytes)
+            .map_err(|e| EngineError::Io(format!("failed to write '{path}': {e}")))
+    }
+
+    /// Fetch subsequences for a list of regions.
+    ///
+    /// Regions use samtools syntax: `name` (whole sequence), `name:start`
+    /// (to the end), or `name:start-end`, 1-based and inclusive; an end
+    /// past the sequence is clamped. Each region becomes one record whose
+    /// name is the region string. With `reverse_complement`, sequences
+    /// are reverse-complemented and names get a `/rc` suffix, matching
+    /// `samtools faidx -i`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a region is malformed or
+    /// names an unknown sequence, or `EngineError::Io` on read failure.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn fetch(
+        &mut self,
+        regions: &[String],
+        reverse_complement: bool,
+    ) -> Result<FastaBatch, EngineError> {
+        let mut name_data: Vec<u8> = Vec::new();
+        let mut name_offsets: Vec<u32> = Vec::with_capacity(regions.len() + 1);
+        let mut sequence_data: Vec<u8> = Vec::new();
+        let mut sequence_offsets: Vec<u32> = Vec::with_capacity(regions.len() + 1);
+        name_offsets.push(0);
+        sequence_offsets.push(0);
+
+        let mut raw = Vec::new();
+        let mut seq = Vec::new();
+
+        for region in regions {
+            let (record, start, end) = self.resolve_region(region)?;
+            let (record_offset, line_bases, line_width) =
+                (record.offset(), record.line_bases(), record.line_width());
+
+            seq.clear();
+            if end > start {
+                let first = base_offset(record_offset, line_bases, line_width, start);
+                let last = base_offset(record_offset, line_bases, line_width, end - 1);
+                raw.resize((last - first + 1) as usize, 0);
+                self.read_at(first, &mut raw).map_err(|e| {
+                    EngineError::Io(format!("FASTA fetch error for '{region}': {e}"))
+                })?;
+                seq.extend(raw.iter().copied().filter(|&b| b != b'\n' && b != b'\r'));
+            }
+
+            name_data.extend_from_slice(region.as_bytes());
+            if reverse_complement {
+                name_data.extend_from_slice(b"/rc");
+                let start = sequence_data.len();
+                sequence_data.resize(start + seq.len(), 0);
+                transform::reverse_complement(&seq, &mut sequence_data[start..], false);
+            } else {
+                sequence_data.extend_from_slice(&seq);
             }
-            WriterInner::GzipFile(w) => {
-                w.finish()
-                    .map_err(|e| EngineError::Io(format!("gzip finish error: {e}")))?;
-                Ok(None)
+
+            name_offsets.push(name_data.len() as u32);
+            sequence_offsets.push(sequence_data.len() as u32);
+        }
+
+        Ok(FastaBatch {
+            count: regions.len() as u32,
+            name_data,
+            name_offsets,
+            description_data: Vec::new(),
+            description_offsets: vec![0; regions.len() + 1],
+            sequence_data,
+            sequence_offsets,
+        })
+    }
+
+    /// Resolve a region to its index record and 0-based half-open range.
+    fn resolve_region(&self, region: &str) -> Result<(fai::Record, u64, u64), EngineError> {
+        let lookup = |name: &str| {
+            self.names
+                .get(name.as_bytes())
+                .map(|&i| self.index.as_ref()[i].clone())
+        };
+
+        if let Some(record) = lookup(region) {
+            let length = record.length();
+            return Ok((record, 0, length));
+        }
+
+        let invalid = |reason: &str| {
+            EngineError::InvalidArgument(format!("invalid region '{region}': {reason}"))
+        };
+
+        let (name, range) = region
+            .rsplit_once(':')
+            .ok_or_else(|| invalid("unknown sequence name"))?;
+        let record = lookup(name).ok_or_else(|| invalid("unknown sequence name"))?;
+
+        let parse = |s: &str| {
+            s.replace(',', "")
+                .parse::<u64>()
+                .map_err(|_| invalid("malformed coordinates"))
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, "")) => (parse(start)?, record.length()),
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(range)?, record.length()),
+        };
+
+        if start == 0 || start > end {
+            return Err(invalid("start must be at least 1 and not after end"));
+        }
+        if start > record.length() {
+            return Err(invalid("start is past the end of the sequence"));
+        }
+
+        let end = end.min(record.length());
+        Ok((record, start - 1, end))
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+        match &mut self.source {
+            IndexSource::File(r) => {
+                r.seek(SeekFrom::Start(offset))?;
+                r.read_exact(buf)
+            }
+            IndexSource::Bytes(r) => {
+                r.seek(SeekFrom::Start(offset))?;
+                r.read_exact(buf)
+            }
+        }
+    }
+}
+
+/// Write the sidecars of a finished BGZF file next to it as
+/// `<path>.fai` and `<path>.gzi`.
+fn write_file_sidecars(path: &str, fai: &[u8], gzi: &[u8]) -> Result<(), EngineError> {
+    for (ext, contents) in [("fai", fai), ("gzi", gzi)] {
+        let sidecar = format!("{path}.{ext}");
+        std::fs::write(&sidecar, contents)
+            .map_err(|e| EngineError::Io(format!("failed to write '{sidecar}': {e}")))?;
+    }
+    Ok(())
+}
+
+/// Serialize the `.fai` and the `.gzi`.
+fn sidecars(fai: &fai::Index, gzi: &gzi::Index) -> Result<(Vec<u8>, Vec<u8>), EngineError> {
+    let mut fai_writer = fai::io::Writer::new(Vec::new());
+    fai_writer
+        .write_index(fai)
+        .map_err(|e| EngineError::Io(format!("FASTA index write error: {e}")))?;
+
+    let mut gzi_writer = gzi::io::Writer::new(Vec::new());
+    gzi_writer
+        .write_index(gzi)
+        .map_err(|e| EngineError::Io(format!("GZI write error: {e}")))?;
+
+    Ok((fai_writer.into_inner(), gzi_writer.into_inner()))
+}
+
+/// Byte offset of the 0-based base `pos` within a wrapped record.
+fn base_offset(offset: u64, line_bases: u64, line_width: u64, pos: u64) -> u64 {
+    if line_bases == 0 {
+        return offset;
+    }
+    offset + (pos / line_bases) * line_width + pos % line_bases
+}
+
+fn build_index<R: std::io::BufRead>(mut reader: R) -> Result<fai::Index, EngineError> {
+    let magic = reader
+        .fill_buf()
+        .map_err(|e| EngineError::Io(format!("FASTA read error: {e}")))?;
+    if codec::detect(magic) != InputCompression::None {
+        return Err(EngineError::InvalidArgument(
+            "FASTA indexing requires uncompressed input".to_string(),
+        ));
+    }
+
+    let mut indexer = fasta::io::Indexer::new(reader);
+    let mut records = Vec::new();
+    while let Some(record) = indexer
+        .index_record()
+        .map_err(|e| EngineError::InvalidArgument(format!("FASTA index error: {e}")))?
+    {
+        records.push(record);
+    }
+
+    Ok(fai::Index::from(records))
+}
+
+fn parse_index(bytes: &[u8]) -> Result<fai::Index, EngineError> {
+    fai::io::Reader::new(bytes)
+        .read_index()
+        .map_err(|e| EngineError::InvalidArgument(format!("failed to read FASTA index: {e}")))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const FASTA: &[u8] =
+        b">chr1 first\nACGTACGTAC\nGGGGCCCCAA\nTT\n>chr2\nAAAACCCC\n>chr3\nacgtn\n";
+
+    fn names(batch: &FastaBatch) -> Vec<&[u8]> {
+        batch
+            .name_offsets
+            .windows(2)
+            .map(|w| &batch.name_data[w[0] as usize..w[1] as usize])
+            .collect()
+    }
+
+    fn sequences(batch: &FastaBatch) -> Vec<&[u8]> {
+        batch
+            .sequence_offsets
+            .windows(2)
+            .map(|w| &batch.sequence_data[w[0] as usize..w[1] as usize])
+            .collect()
+    }
+
+    #[test]
+    fn index_matches_samtools_layout() {
+        let index = FastaIndex::build_from_bytes(FASTA.to_vec()).unwrap();
+        assert_eq!(
+            String::from_utf8(index.index_bytes().unwrap()).unwrap(),
+            "chr1\t22\t12\t10\t11\nchr2\t8\t43\t8\t9\nchr3\t5\t58\t5\t6\n"
+        );
+        let entries = index.entries();
+        assert_eq!(entries[0].name, "chr1");
+        assert_eq!(entries[0].length, 22);
+    }
+
+    #[test]
+    fn fetch_spans_line_breaks() {
+        let mut index = FastaIndex::build_from_bytes(FASTA.to_vec()).unwrap();
+        let regions: Vec<String> = ["chr1:9-13", "chr2", "chr1:21", "chr1:20-100", "chr3:2-3"]
+            .iter()
+            .map(|&r| r.to_string())
+            .collect();
+        let batch = index.fetch(&regions, false).unwrap();
+
+        assert_eq!(batch.count, 5);
+        assert_eq!(names(&batch)[0], b"chr1:9-13");
+        assert_eq!(
+            sequences(&batch),
+            vec![&b"ACGGG"[..], b"AAAACCCC", b"TT", b"ATT", b"cg"]
+        );
+        assert_eq!(batch.description_offsets, vec![0; 6]);
+    }
+
+    #[test]
+    fn fetch_reverse_complement() {
+        let mut index = FastaIndex::build_from_bytes(FASTA.to_vec()).unwrap();
+        let batch = index.fetch(&["chr1:1-4".to_string()], true).unwrap();
+        assert_eq!(names(&batch), vec![&b"chr1:1-4/rc"[..]]);
+        assert_eq!(sequences(&batch), vec![&b"ACGT"[..]]);
+
+        let batch = index.fetch(&["chr2:3-6".to_string()], true).unwrap();
+        assert_eq!(sequences(&batch), vec![&b"GGTT"[..]]);
+    }
+
+    #[test]
+    fn invalid_regions_are_rejected() {
+        let mut index = FastaIndex::build_from_bytes(FASTA.to_vec()).unwrap();
+        for region in [
+            "chrX",
+            "chrX:1-2",
+            "chr1:0-5",
+            "chr1:5-2",
+            "chr1:30-40",
+            "chr1:a-b",
+        ] {
+            assert!(
+                index.fetch(&[region.to_string()], false).is_err(),
+                "{region}"
+            );
+        }
+    }
+
+    #[test]
+    fn load_existing_index_from_path() {
+        let dir = crate::test_dir("faidx");
+        let fasta_path = dir.join("ref.fa");
+        let index_path = dir.join("ref.fa.fai");
+        std::fs::write(&fasta_path, FASTA).unwrap();
+        let fasta_path = fasta_path.to_str().unwrap();
+        let index_path = index_path.to_str().unwrap();
+
+        let built = FastaIndex::build_from_path(fasta_path).unwrap();
+        built.write_index(index_path).unwrap();
+
+        let mut loaded = FastaIndex::load(fasta_path, index_path).unwrap();
+        let batch = loaded.fetch(&["chr1:10-12".to_string()], false).unwrap();
+        assert_eq!(sequences(&batch), vec![&b"CGG"[..]]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compressed_input_is_rejected() {
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::Gzip, 80).unwrap();
+        writer
+            .write_batch(&FastaBatch {
+                count: 1,
+                name_data: b"a".to_vec(),
+                name_offsets: vec![0, 1],
+                description_data: Vec::new(),
+                description_offsets: vec![0, 0],
+                sequence_data: b"ACGT".to_vec(),
+                sequence_offsets: vec![0, 4],
+            })
+            .unwrap();
+        let gz = writer.finish().unwrap().unwrap();
+        assert!(FastaIndex::build_from_bytes(gz).is_err());
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn large_batch() -> FastaBatch {
+        let mut batch = FastaBatch {
+            count: 0,
+            name_data: Vec::new(),
+            name_offsets: vec![0],
+            description_data: Vec::new(),
+            description_offsets: vec![0],
+            sequence_data: Vec::new(),
+            sequence_offsets: vec![0],
+        };
+        for (i, len) in [30_000usize, 0, 70_000, 45].into_iter().enumerate() {
+            batch
+                .name_data
+                .extend_from_slice(format!("seq{i}").as_bytes());
+            batch.name_offsets.push(batch.name_data.len() as u32);
+            if i == 2 {
+                batch
+                    .description_data
+                    .extend_from_slice(b"some description");
             }
-            WriterInner::PlainBytes(v) => Ok(Some(v)),
-            WriterInner::GzipBytes(w) => {
-                let bytes = w
-                    .finish()
-                    .map_err(|e| EngineError::Io(format!("gzip finish error: {e}")))?;
-                Ok(Some(bytes))
+            batch
+                .description_offsets
+                .push(batch.description_data.len() as u32);
+            batch
+                .sequence_data
+                .extend((0..len).map(|j| b"ACGTN"[(i + j) % 5]));
+            batch
+                .sequence_offsets
+                .push(batch.sequence_data.len() as u32);
+            batch.count += 1;
+        }
+        batch
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn bgzf_output_emits_matching_sidecars() {
+        let batch = large_batch();
+
+        let mut plain = FastaWriter::open_to_bytes(CompressionMode::None, 60).unwrap();
+        plain.write_batch(&batch).unwrap();
+        let plain = plain.finish().unwrap().unwrap();
+
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::Bgzf, 60).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let output = writer.finish_with_sidecars().unwrap();
+        let compressed = output.data.unwrap();
+
+        let mut decompressed = Vec::new();
+        bgzf::io::Reader::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, plain);
+
+        // The .fai must agree with what samtools-style indexing of the
+        // plain text produces, except for the empty record it rejects.
+        let fai = String::from_utf8(output.fai.unwrap()).unwrap();
+        assert!(fai.starts_with("seq0\t30000\t6\t60\t61\n"));
+        let mut index = FastaIndex::load_from_bytes(plain.clone(), fai.as_bytes()).unwrap();
+        let fetched = index
+            .fetch(&["seq2:100-159".to_string(), "seq3".to_string()], false)
+            .unwrap();
+        let expected: Vec<u8> = (99..159).map(|j| b"ACGTN"[(2 + j) % 5]).collect();
+        assert_eq!(sequences(&fetched)[0], &expected[..]);
+        assert_eq!(sequences(&fetched)[1].len(), 45);
+
+        let gzi = gzi::io::Reader::new(&output.gzi.unwrap()[..])
+            .read_index()
+            .unwrap();
+        assert!(!gzi.as_ref().is_empty());
+        let mut reader = bgzf::io::Reader::new(Cursor::new(compressed));
+        for pos in [0u64, 65_280, 70_000, plain.len() as u64 - 10] {
+            reader.seek(gzi.query(pos).unwrap()).unwrap();
+            let mut buf = [0u8; 10];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf[..], &plain[pos as usize..pos as usize + 10]);
+        }
+    }
+
+    #[test]
+    fn bgzf_file_output_writes_sidecars() {
+        let dir = crate::test_dir("bgzf");
+        let path = dir.join("out.fa.gz");
+        let path = path.to_str().unwrap();
+
+        let mut writer = FastaWriter::open_to_path(path, CompressionMode::Bgzf, 80).unwrap();
+        writer.write_batch(&large_batch()).unwrap();
+        assert!(writer.finish().unwrap().is_none());
+
+        assert!(std::fs::metadata(format!("{path}.fai")).is_ok());
+        assert!(std::fs::metadata(format!("{path}.gzi")).is_ok());
+
+        let mut reader = FastaReader::open_from_path(path).unwrap();
+        assert_eq!(reader.read_batch(10).unwrap().unwrap().count, 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_output_round_trips_from_bytes_and_path() {
+        let batch = large_batch();
+
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::Zstd(19), 60).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let output = writer.finish_with_sidecars().unwrap();
+        assert!(output.fai.is_none() && output.gzi.is_none());
+        let compressed = output.data.unwrap();
+        assert_eq!(&compressed[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+
+        let mut reader = FastaReader::open_from_bytes(compressed.clone()).unwrap();
+        let round_tripped = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(round_tripped.sequence_data, batch.sequence_data);
+
+        let dir = crate::test_dir("zstd");
+        let path = dir.join("in.fa.zst");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, &compressed).unwrap();
+
+        let mut reader = FastaReader::open_from_path(path).unwrap();
+        assert_eq!(reader.read_batch(10).unwrap().unwrap().count, 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_input_cannot_be_indexed() {
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::Zstd(0), 60).unwrap();
+        writer.write_batch(&large_batch()).unwrap();
+        let compressed = writer.finish().unwrap().unwrap();
+        assert!(FastaIndex::build_from_bytes(compressed).is_err());
+    }
+
+    #[test]
+    fn out_of_range_zstd_level_is_rejected() {
+        assert!(FastaWriter::open_to_bytes(CompressionMode::Zstd(99), 60).is_err());
+    }
+
+    #[test]
+    fn parallel_bgzf_output_matches_serial_sidecars() {
+        let batch = large_batch();
+
+        let mut serial = FastaWriter::open_to_bytes(CompressionMode::Bgzf, 60).unwrap();
+        serial.write_batch(&batch).unwrap();
+        let serial = serial.finish_with_sidecars().unwrap();
+
+        let mut parallel =
+            FastaWriter::open_to_bytes(CompressionMode::ParallelBgzf(6), 60).unwrap();
+        parallel.write_batch(&batch).unwrap();
+        let parallel = parallel.finish_with_sidecars().unwrap();
+
+        assert_eq!(parallel.fai, serial.fai);
+        assert!(parallel.gzi.is_some());
+
+        let mut reader = FastaReader::open_from_bytes(parallel.data.unwrap()).unwrap();
+        let round_tripped = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(round_tripped.sequence_data, batch.sequence_data);
+    }
+
+    #[test]
+    fn parallel_gzip_output_has_no_sidecars() {
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::ParallelGzip(1), 60).unwrap();
+        writer.write_batch(&large_batch()).unwrap();
+        let output = writer.finish_with_sidecars().unwrap();
+        assert!(output.fai.is_none() && output.gzi.is_none());
+
+        let mut reader = FastaReader::open_from_bytes(output.data.unwrap()).unwrap();
+        assert_eq!(reader.read_batch(10).unwrap().unwrap().count, 4);
+        assert!(FastaWriter::open_to_bytes(CompressionMode::ParallelGzip(10), 60).is_err());
+    }
+
+    #[test]
+    fn chunk_reader_returns_records_as_they_complete() {
+        let input = b">chr1 first\nACGT\nAC\n\n>chr2\r\nGG\r\nTT\r\n>chr3\nA";
+        let mut reader = FastaChunkReader::new();
+        let mut names = Vec::new();
+        let mut sequences = Vec::new();
+        for piece in input.chunks(4) {
+            if let Some(batch) = reader.push_chunk(piece).unwrap() {
+                names.extend(batch.name_data);
+                sequences.extend(batch.sequence_data);
             }
         }
+        assert_eq!(names, b"chr1chr2", "chr3 is still open before end");
+
+        let last = reader.end().unwrap().unwrap();
+        assert_eq!(last.name_data, b"chr3");
+        sequences.extend(last.sequence_data);
+        assert_eq!(sequences, b"ACGTACGGTTA");
+        assert!(reader.end().unwrap().is_none());
     }
 
-    fn writer(&mut self) -> &mut dyn std::io::Write {
-        match &mut self.inner {
-            WriterInner::PlainFile(w) => w,
-            WriterInner::GzipFile(w) => w,
-            WriterInner::PlainBytes(w) => w,
-            WriterInner::GzipBytes(w) => w,
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn chunk_reader_decodes_zstd_and_rejects_headerless_input() {
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::Zstd(0), 60).unwrap();
+        writer.write_batch(&large_batch()).unwrap();
+        let compressed = writer.finish().unwrap().unwrap();
+
+        let mut reader = FastaChunkReader::new();
+        let mut count = 0;
+        for piece in compressed.chunks(100) {
+            count += reader.push_chunk(piece).unwrap().map_or(0, |b| b.count);
+        }
+        count += reader.end().unwrap().map_or(0, |b| b.count);
+        assert_eq!(count, 4);
+
+        let mut reader = FastaChunkReader::new();
+        assert!(reader.push_chunk(b"ACGT\n").is_err());
+    }
+
+    #[test]
+    fn bounded_batches_stop_at_the_byte_budget() {
+        let input = b">a\nACGT\nACGT\n>b\nAC\n>c\nGG\n";
+        let mut reader = FastaReader::open_from_bytes(input.to_vec()).unwrap();
+        let batch = reader.read_batch_bounded(10, 8).unwrap().unwrap();
+        assert_eq!(batch.count, 1);
+        assert_eq!(batch.sequence_data, b"ACGTACGT");
+        let batch = reader.read_batch_bounded(10, 8).unwrap().unwrap();
+        assert_eq!(batch.count, 2);
+    }
+
+    #[test]
+    fn sequence_chunks_split_records_across_calls() {
+        let input = b">chr1 first\nACGTA\nCGTAC\nGT\n>empty\n>chr2\nTT\n";
+        let mut reader = FastaReader::open_from_bytes(input.to_vec()).unwrap();
+        assert!(reader.read_sequence_chunks(1, 0).is_err());
+
+        let batch = reader.read_sequence_chunks(2, 4).unwrap().unwrap();
+        assert_eq!(batch.sequence_data, b"ACGTACGT");
+        assert_eq!(batch.chunk_starts, vec![0, 4]);
+        assert_eq!(batch.is_last, vec![0, 0]);
+        assert!(reader.read_batch(1).is_err());
+
+        let batch = reader.read_sequence_chunks(10, 4).unwrap().unwrap();
+        assert_eq!(batch.count, 3);
+        assert_eq!(batch.record_indices, vec![0, 1, 2]);
+        assert_eq!(batch.chunk_starts, vec![8, 0, 0]);
+        assert_eq!(batch.is_last, vec![1, 1, 1]);
+        assert_eq!(batch.name_data, b"chr1emptychr2");
+        assert_eq!(batch.sequence_offsets, vec![0, 4, 4, 6]);
+        assert!(reader.read_sequence_chunks(10, 4).unwrap().is_none());
+    }
+
+    #[test]
+    fn checkpoints_resume_plain_and_bgzf_input() {
+        let batch = large_batch();
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::None, 60).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let plain = writer.finish().unwrap().unwrap();
+        let mut writer = FastaWriter::open_to_bytes(CompressionMode::Bgzf, 60).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let bgzf = writer.finish().unwrap().unwrap();
+        let half = batch.count / 2;
+
+        for data in [plain.clone(), bgzf] {
+            let mut reader = FastaReader::open_from_bytes(data.clone()).unwrap();
+            reader.read_batch(half).unwrap().unwrap();
+            let checkpoint =
+                ReaderCheckpoint::from_bytes(&reader.checkpoint().unwrap().to_bytes()).unwrap();
+            let rest = reader.read_batch(batch.count).unwrap().unwrap();
+            assert!(reader.read_batch(1).unwrap().is_none());
+            assert_eq!(reader.records_read(), u64::from(batch.count));
+            assert_eq!(
+                reader.bytes_consumed(),
+                BytesConsumed {
+                    compressed: data.len() as u64,
+                    uncompressed: plain.len() as u64,
+                }
+            );
+
+            let mut resumed = FastaReader::open_from_bytes_at(data, &checkpoint).unwrap();
+            let resumed_rest = resumed.read_batch(batch.count).unwrap().unwrap();
+            assert_eq!(resumed_rest.name_data, rest.name_data);
+            assert_eq!(resumed_rest.sequence_data, rest.sequence_data);
+        }
+
+        let mut reader = FastaReader::open_from_bytes(b">a\nACGTACGT\n".to_vec()).unwrap();
+        reader.read_sequence_chunks(1, 4).unwrap().unwrap();
+        assert!(reader.checkpoint().is_err());
+        let fastq = crate::fastq::FastqReader::open_from_bytes(Vec::new())
+            .unwrap()
+            .checkpoint();
+        assert!(FastaReader::open_from_bytes_at(plain, &fastq).is_err());
+    }
+
+    #[test]
+    fn parse_errors_name_the_failing_record() {
+        let mut reader = FastaReader::open_from_bytes(b">a\nAC\n>b\xff\nGT\n".to_vec()).unwrap();
+        let Err(EngineError::Parse(err)) = reader.read_batch(10) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.kind, ParseErrorKind::InvalidUtf8);
+        assert_eq!((err.record, err.field), (2, Some("definition")));
+
+        let mut reader = FastaReader::open_from_bytes(b"AC\n>a\nGT\n".to_vec()).unwrap();
+        let Err(EngineError::Parse(err)) = reader.read_batch(10) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((err.kind, err.record), (ParseErrorKind::BadHeader, 1));
+
+        let mut reader = FastaChunkReader::new();
+        reader.push_chunk(b"\n\n").unwrap();
+        let Err(EngineError::Parse(err)) = reader.push_chunk(b"ACGT\n") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.kind, ParseErrorKind::BadHeader);
+        assert_eq!((err.line, err.byte_offset), (Some(3), Some(2)));
+    }
+
+    #[test]
+    fn generic_reader_accepts_buffered_sources() {
+        let mut reader =
+            FastaReader::open_from_buf_reader(Box::new(&b">a\nAC\nGT\n>b\nTT\n"[..])).unwrap();
+        let batch = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(batch.sequence_data, b"ACGTTT");
+
+        #[cfg(feature = "zstd")]
+        {
+            let mut writer = FastaWriter::open_to_bytes(CompressionMode::Zstd(0), 60).unwrap();
+            writer.write_batch(&large_batch()).unwrap();
+            let compressed = writer.finish().unwrap().unwrap();
+            let mut reader =
+                FastaReader::open_from_reader(Box::new(Cursor::new(compressed))).unwrap();
+            assert_eq!(reader.read_batch(10).unwrap().unwrap().count, 4);
         }
     }
 }
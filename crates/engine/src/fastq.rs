@@ -2,17 +2,34 @@
 //!
 //! Provides a stateful reader that owns a noodles FASTQ reader and
 //! returns batches of parsed records in a struct-of-arrays layout.
-//! Handles gzip-compressed input transparently via flate2.
+//! Handles gzip-compressed input (including multi-member and BGZF
+//! streams) via flate2 and Zstandard input via zstd, detected from the
+//! leading magic bytes. The threaded constructors inflate BGZF blocks in
+//! parallel and decode other input on a read-ahead thread.
+//!
+//! `PairedFastqReader` reads R1/R2 mates in lockstep, from two inputs or
+//! one interleaved input, and checks that their names agree.
+//! `FastqChunkReader` parses input pushed in chunks, such as a stream
+//! from the browser, without buffering the whole file.
 
 use std::{
     fs::File,
-    io::{BufReader, BufWriter, Cursor, Read, Seek, Write},
+    io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write},
 };
 
-use flate2::{read::GzDecoder, write::GzEncoder};
+use flate2::read::MultiGzDecoder;
+use noodles_bgzf as bgzf;
 use noodles_fastq as fastq;
 
-use crate::EngineError;
+use crate::{
+    classify,
+    codec::{
+        self, BoxBufRead, ByteCounter, ChunkDecoder, Counted, CountedBytes, InputCompression,
+        Output, ReadAhead, ZstdDecoder,
+    },
+    field_at, BytesConsumed, CompressionMode, EngineError, ParseError, ParseErrorKind,
+    ReaderCheckpoint, RecordFault, RecordFormat,
+};
 
 /// A batch of parsed FASTQ records in struct-of-arrays layout.
 #[derive(Debug)]
@@ -32,78 +49,352 @@ pub struct FastqBatch {
     pub quality_offsets: Vec<u32>,
 }
 
+impl FastqBatch {
+    pub(crate) fn empty() -> Self {
+        Self {
+            count: 0,
+            name_data: Vec::new(),
+            name_offsets: vec![0],
+            description_data: Vec::new(),
+            description_offsets: vec![0],
+            sequence_data: Vec::new(),
+            sequence_offsets: vec![0],
+            quality_data: Vec::new(),
+            quality_offsets: vec![0],
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn push(
+        &mut self,
+        name: &[u8],
+        description: &[u8],
+        sequence: &[u8],
+        quality: &[u8],
+    ) {
+        self.name_data.extend_from_slice(name);
+        self.name_offsets.push(self.name_data.len() as u32);
+        self.description_data.extend_from_slice(description);
+        self.description_offsets
+            .push(self.description_data.len() as u32);
+        self.sequence_data.extend_from_slice(sequence);
+        self.sequence_offsets.push(self.sequence_data.len() as u32);
+        self.quality_data.extend_from_slice(quality);
+        self.quality_offsets.push(self.quality_data.len() as u32);
+        self.count += 1;
+    }
+}
+
 enum ReaderInner {
-    Plain(fastq::io::Reader<BufReader<File>>),
-    Gzip(fastq::io::Reader<BufReader<GzDecoder<File>>>),
-    PlainBytes(fastq::io::Reader<BufReader<Cursor<Vec<u8>>>>),
-    GzipBytes(fastq::io::Reader<BufReader<GzDecoder<Cursor<Vec<u8>>>>>),
+    Plain(fastq::io::Reader<BufReader<Counted<File>>>),
+    Gzip(fastq::io::Reader<BufReader<MultiGzDecoder<Counted<File>>>>),
+    Bgzf(fastq::io::Reader<bgzf::io::Reader<Counted<File>>>),
+    PlainBytes(fastq::io::Reader<BufReader<CountedBytes>>),
+    GzipBytes(fastq::io::Reader<BufReader<MultiGzDecoder<CountedBytes>>>),
+    BgzfBytes(fastq::io::Reader<bgzf::io::Reader<CountedBytes>>),
+    Zstd(fastq::io::Reader<BufReader<ZstdDecoder<Counted<File>>>>),
+    ZstdBytes(fastq::io::Reader<BufReader<ZstdDecoder<CountedBytes>>>),
+    ParallelBgzf(fastq::io::Reader<bgzf::io::MultithreadedReader<Box<dyn Read + Send>>>),
+    ReadAhead(fastq::io::Reader<ReadAhead>),
+    Stream(fastq::io::Reader<BoxBufRead>),
 }
 
 /// Stateful FASTQ file reader.
 ///
 /// Wraps a noodles FASTQ reader with optional gzip decompression.
-/// Records are read lazily via `read_batch`.
+/// Records are read lazily via `read_batch`. The `_threaded`
+/// constructors move decompression off the calling thread.
+///
+/// `bytes_consumed` reports progress through the input, and
+/// `checkpoint` saves a position that the `_at` constructors resume
+/// from.
 pub struct FastqReader {
     inner: ReaderInner,
     record_buf: fastq::Record,
+    records_read: u64,
+    /// Decompressed bytes consumed by the records read so far.
+    bytes_read: u64,
+    /// Raw bytes pulled from the input.
+    source: ByteCounter,
 }
 
 impl FastqReader {
     /// Open a FASTQ file by path.
     ///
-    /// Gzip compression is detected from the first two bytes (gzip magic).
+    /// Gzip and Zstandard compression are detected from the leading magic
+    /// bytes.
     ///
     /// # Errors
     ///
     /// Returns `EngineError::Io` if the file cannot be opened or read.
     pub fn open_from_path(path: &str) -> Result<Self, EngineError> {
-        let mut file = File::open(path)
+        let file = File::open(path)
             .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        let source = ByteCounter::default();
+        let mut file = Counted::new(file, &source);
 
-        let mut magic = [0u8; 2];
-        let n = file
-            .read(&mut magic)
+        let mut header = Vec::with_capacity(codec::BGZF_HEADER_LEN);
+        (&mut file)
+            .take(codec::BGZF_HEADER_LEN as u64)
+            .read_to_end(&mut header)
             .map_err(|e| EngineError::Io(format!("failed to read from '{path}': {e}")))?;
 
-        file.seek(std::io::SeekFrom::Start(0))
+        file.seek(SeekFrom::Start(0))
             .map_err(|e| EngineError::Io(format!("failed to seek in '{path}': {e}")))?;
 
-        let is_gzip = n >= 2 && magic[0] == 0x1f && magic[1] == 0x8b;
-
-        let inner = if is_gzip {
-            ReaderInner::Gzip(fastq::io::Reader::new(BufReader::new(GzDecoder::new(file))))
-        } else {
-            ReaderInner::Plain(fastq::io::Reader::new(BufReader::new(file)))
+        let inner = match codec::detect(&header) {
+            InputCompression::Gzip if codec::is_bgzf(&header) => {
+                ReaderInner::Bgzf(fastq::io::Reader::new(bgzf::io::Reader::new(file)))
+            }
+            InputCompression::Gzip => ReaderInner::Gzip(fastq::io::Reader::new(BufReader::new(
+                MultiGzDecoder::new(file),
+            ))),
+            InputCompression::Zstd => {
+                let decoder = codec::zstd_decoder(file).map_err(|e| {
+                    EngineError::Io(format!("failed to start zstd stream for '{path}': {e}"))
+                })?;
+                ReaderInner::Zstd(fastq::io::Reader::new(BufReader::new(decoder)))
+            }
+            InputCompression::None => {
+                ReaderInner::Plain(fastq::io::Reader::new(BufReader::new(file)))
+            }
         };
 
-        Ok(Self {
-            inner,
-            record_buf: fastq::Record::default(),
-        })
+        Ok(Self::from_inner(inner, source))
     }
 
     /// Open a FASTQ dataset from an in-memory buffer.
     ///
-    /// Gzip compression is detected from the first two bytes.
+    /// Gzip and Zstandard compression are detected from the leading magic
+    /// bytes.
     ///
     /// # Errors
     ///
     /// Returns `EngineError::Io` if the buffer cannot be parsed.
     pub fn open_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
-        let is_gzip = bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b;
+        let compression = codec::detect(&bytes);
+        let is_bgzf = codec::is_bgzf(&bytes[..bytes.len().min(codec::BGZF_HEADER_LEN)]);
+        let source = ByteCounter::default();
+        let input = Counted::new(Cursor::new(bytes), &source);
+
+        let inner = match compression {
+            InputCompression::Gzip if is_bgzf => {
+                ReaderInner::BgzfBytes(fastq::io::Reader::new(bgzf::io::Reader::new(input)))
+            }
+            InputCompression::Gzip => ReaderInner::GzipBytes(fastq::io::Reader::new(
+                BufReader::new(MultiGzDecoder::new(input)),
+            )),
+            InputCompression::Zstd => {
+                let decoder = codec::zstd_decoder(input)
+                    .map_err(|e| EngineError::Io(format!("failed to start zstd stream: {e}")))?;
+                ReaderInner::ZstdBytes(fastq::io::Reader::new(BufReader::new(decoder)))
+            }
+            InputCompression::None => {
+                ReaderInner::PlainBytes(fastq::io::Reader::new(BufReader::new(input)))
+            }
+        };
+
+        Ok(Self::from_inner(inner, source))
+    }
+
+    /// Open a FASTQ file by path and resume at a checkpoint taken by an
+    /// earlier reader over the same file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the checkpoint is not a
+    /// FASTQ checkpoint, or `EngineError::Io` if the file cannot be read
+    /// up to it.
+    pub fn open_from_path_at(
+        path: &str,
+        checkpoint: &ReaderCheckpoint,
+    ) -> Result<Self, EngineError> {
+        let mut reader = Self::open_from_path(path)?;
+        reader.resume(checkpoint)?;
+        Ok(reader)
+    }
+
+    /// Open a FASTQ dataset from an in-memory buffer and resume at a
+    /// checkpoint, as for `open_from_path_at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the checkpoint is not a
+    /// FASTQ checkpoint, or `EngineError::Io` if the buffer ends before
+    /// it.
+    pub fn open_from_bytes_at(
+        bytes: Vec<u8>,
+        checkpoint: &ReaderCheckpoint,
+    ) -> Result<Self, EngineError> {
+        let mut reader = Self::open_from_bytes(bytes)?;
+        reader.resume(checkpoint)?;
+        Ok(reader)
+    }
+
+    /// Open a FASTQ stream from any reader, such as stdin, a pipe or a
+    /// socket.
+    ///
+    /// The reader is buffered internally; gzip and Zstandard compression
+    /// are detected from its leading bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the leading bytes cannot be read.
+    pub fn open_from_reader(reader: Box<dyn Read + Send>) -> Result<Self, EngineError> {
+        Self::open_from_buf_reader(Box::new(BufReader::new(reader)))
+    }
 
-        let inner = if is_gzip {
-            ReaderInner::GzipBytes(fastq::io::Reader::new(BufReader::new(GzDecoder::new(
-                Cursor::new(bytes),
-            ))))
+    /// Open a FASTQ stream from a buffered reader without adding another
+    /// layer of buffering to plain input.
+    ///
+    /// Compression is detected from the stream's leading bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the leading bytes cannot be read.
+    pub fn open_from_buf_reader(reader: Box<dyn BufRead + Send>) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        let reader = codec::decode_buf_read(Box::new(Counted::new(reader, &source)))
+            .map_err(|e| EngineError::Io(format!("failed to read FASTQ stream: {e}")))?;
+        Ok(Self::from_inner(
+            ReaderInner::Stream(fastq::io::Reader::new(reader)),
+            source,
+        ))
+    }
+
+    /// Open a FASTQ file by path, decompressing off the calling thread.
+    ///
+    /// BGZF input is inflated block-by-block on `threads` workers (`0`
+    /// means one per core). Other gzip, zstd and plain input is decoded
+    /// on a single background thread that reads ahead of the parser.
+    /// Records still come out of `read_batch` in file order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be opened or read.
+    pub fn open_from_path_threaded(path: &str, threads: u32) -> Result<Self, EngineError> {
+        let mut file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+
+        let mut header = Vec::with_capacity(codec::BGZF_HEADER_LEN);
+        (&mut file)
+            .take(codec::BGZF_HEADER_LEN as u64)
+            .read_to_end(&mut header)
+            .map_err(|e| EngineError::Io(format!("failed to read from '{path}': {e}")))?;
+
+        file.seek(std::io::SeekFrom::Start(0))
+            .map_err(|e| EngineError::Io(format!("failed to seek in '{path}': {e}")))?;
+
+        Self::open_threaded(&header, Box::new(file), threads)
+    }
+
+    /// Open a FASTQ dataset from an in-memory buffer, decompressing off
+    /// the calling thread as described for `open_from_path_threaded`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if a decoder cannot be started.
+    pub fn open_from_bytes_threaded(bytes: Vec<u8>, threads: u32) -> Result<Self, EngineError> {
+        let header = bytes[..bytes.len().min(codec::BGZF_HEADER_LEN)].to_vec();
+        Self::open_threaded(&header, Box::new(Cursor::new(bytes)), threads)
+    }
+
+    fn open_threaded(
+        header: &[u8],
+        source: Box<dyn Read + Send>,
+        threads: u32,
+    ) -> Result<Self, EngineError> {
+        let counter = ByteCounter::default();
+        let source: Box<dyn Read + Send> = Box::new(Counted::new(source, &counter));
+        let inner = if codec::is_bgzf(header) {
+            ReaderInner::ParallelBgzf(fastq::io::Reader::new(
+                bgzf::io::MultithreadedReader::with_worker_count(
+                    codec::worker_count(threads),
+                    source,
+                ),
+            ))
         } else {
-            ReaderInner::PlainBytes(fastq::io::Reader::new(BufReader::new(Cursor::new(bytes))))
+            let reader = match codec::detect(header) {
+                InputCompression::Gzip => ReadAhead::spawn(MultiGzDecoder::new(source)),
+                InputCompression::Zstd => {
+                    ReadAhead::spawn(codec::zstd_decoder(source).map_err(|e| {
+                        EngineError::Io(format!("failed to start zstd stream: {e}"))
+                    })?)
+                }
+                InputCompression::None => ReadAhead::spawn(source),
+            };
+            ReaderInner::ReadAhead(fastq::io::Reader::new(reader))
         };
 
-        Ok(Self {
+        Ok(Self::from_inner(inner, counter))
+    }
+
+    fn from_inner(inner: ReaderInner, source: ByteCounter) -> Self {
+        Self {
             inner,
             record_buf: fastq::Record::default(),
-        })
+            records_read: 0,
+            bytes_read: 0,
+            source,
+        }
+    }
+
+    /// Number of records returned so far, counting any skipped by
+    /// resuming at a checkpoint.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Progress through the input, as raw and decompressed bytes.
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        BytesConsumed {
+            compressed: self.source.get(),
+            uncompressed: self.bytes_read,
+        }
+    }
+
+    /// Save the reader's position after the last record returned.
+    ///
+    /// BGZF input records the block's virtual offset, so resuming seeks
+    /// straight to it; other input resumes from the decompressed byte
+    /// offset.
+    pub fn checkpoint(&self) -> ReaderCheckpoint {
+        let virtual_offset = match &self.inner {
+            ReaderInner::Bgzf(r) => Some(r.get_ref().virtual_position()),
+            ReaderInner::BgzfBytes(r) => Some(r.get_ref().virtual_position()),
+            ReaderInner::ParallelBgzf(r) => Some(r.get_ref().virtual_position()),
+            _ => None,
+        };
+        ReaderCheckpoint::new(
+            RecordFormat::Fastq,
+            self.records_read,
+            self.bytes_read,
+            virtual_offset.map(u64::from),
+        )
+    }
+
+    fn resume(&mut self, checkpoint: &ReaderCheckpoint) -> Result<(), EngineError> {
+        checkpoint.expect_format(RecordFormat::Fastq)?;
+        let offset = checkpoint.uncompressed_offset();
+        let result = match (&mut self.inner, checkpoint.virtual_offset()) {
+            (ReaderInner::Bgzf(r), Some(pos)) => r.get_mut().seek(pos.into()).map(drop),
+            (ReaderInner::BgzfBytes(r), Some(pos)) => r.get_mut().seek(pos.into()).map(drop),
+            (ReaderInner::Plain(r), _) => r.get_mut().seek(SeekFrom::Start(offset)).map(drop),
+            (ReaderInner::PlainBytes(r), _) => r.get_mut().seek(SeekFrom::Start(offset)).map(drop),
+            (ReaderInner::Gzip(r), _) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::Bgzf(r), None) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::GzipBytes(r), _) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::BgzfBytes(r), None) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::Zstd(r), _) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::ZstdBytes(r), _) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::ParallelBgzf(r), _) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::ReadAhead(r), _) => codec::skip_to(r.get_mut(), offset),
+            (ReaderInner::Stream(r), _) => codec::skip_to(r.get_mut(), offset),
+        };
+        result.map_err(|e| EngineError::Io(format!("failed to resume FASTQ input: {e}")))?;
+        self.records_read = checkpoint.records_read();
+        self.bytes_read = offset;
+        Ok(())
     }
 
     /// Read the next batch of FASTQ records.
@@ -113,10 +404,30 @@ impl FastqReader {
     ///
     /// # Errors
     ///
-    /// Returns `EngineError::Io` if a record cannot be parsed.
-    #[allow(clippy::cast_possible_truncation)]
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number, line and byte offset in the decompressed input.
     pub fn read_batch(&mut self, max_records: u32) -> Result<Option<FastqBatch>, EngineError> {
+        self.read_batch_bounded(max_records, u32::MAX)
+    }
+
+    /// Read the next batch of FASTQ records, stopping early once the
+    /// batch holds `max_bytes` of sequence and quality data.
+    ///
+    /// The record that crosses the budget is kept, so a batch always
+    /// holds at least one record and a single long read is never split.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number, line and byte offset in the decompressed input.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn read_batch_bounded(
+        &mut self,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> Result<Option<FastqBatch>, EngineError> {
         let max = max_records as usize;
+        let max_bytes = max_bytes as usize;
 
         let mut name_bytes: Vec<u8> = Vec::with_capacity(max * 32);
         let mut name_offsets: Vec<u32> = Vec::with_capacity(max + 1);
@@ -124,10 +435,10 @@ impl FastqReader {
         let mut desc_bytes: Vec<u8> = Vec::with_capacity(max * 32);
         let mut desc_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
-        let mut seq_bytes: Vec<u8> = Vec::with_capacity(max * 150);
+        let mut seq_bytes: Vec<u8> = Vec::with_capacity((max * 150).min(max_bytes / 2));
         let mut seq_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
-        let mut qual_bytes: Vec<u8> = Vec::with_capacity(max * 150);
+        let mut qual_bytes: Vec<u8> = Vec::with_capacity((max * 150).min(max_bytes / 2));
         let mut qual_offsets: Vec<u32> = Vec::with_capacity(max + 1);
 
         name_offsets.push(0);
@@ -138,7 +449,10 @@ impl FastqReader {
         let mut count: u32 = 0;
 
         for _ in 0..max {
-            let bytes_read = self.read_one_record()?;
+            let bytes_read = match self.read_one_record() {
+                Ok(n) => n,
+                Err(e) => return Err(self.parse_error(&e).into()),
+            };
             if bytes_read == 0 {
                 break;
             }
@@ -149,12 +463,16 @@ impl FastqReader {
 
             if seq.len() != qual.len() {
                 let name = String::from_utf8_lossy(record.definition().name());
-                return Err(EngineError::InvalidArgument(format!(
-                    "FASTQ record '{}': sequence length ({}) != quality length ({})",
+                let message = format!(
+                    "record '{}': sequence length ({}) != quality length ({})",
                     name,
                     seq.len(),
                     qual.len()
-                )));
+                );
+                return Err(self
+                    .located(ParseErrorKind::LengthMismatch, message)
+                    .field("quality")
+                    .into());
             }
 
             let def = record.definition();
@@ -172,6 +490,12 @@ impl FastqReader {
             qual_offsets.push(qual_bytes.len() as u32);
 
             count += 1;
+            self.records_read += 1;
+            self.bytes_read += bytes_read as u64;
+
+            if seq_bytes.len() + qual_bytes.len() >= max_bytes {
+                break;
+            }
         }
 
         if count == 0 {
@@ -191,67 +515,114 @@ impl FastqReader {
         }))
     }
 
-    fn read_one_record(&mut self) -> Result<usize, EngineError> {
-        let result = match &mut self.inner {
-            ReaderInner::Plain(r) => r.read_record(&mut self.record_buf),
-            ReaderInner::Gzip(r) => r.read_record(&mut self.record_buf),
-            ReaderInner::PlainBytes(r) => r.read_record(&mut self.record_buf),
-            ReaderInner::GzipBytes(r) => r.read_record(&mut self.record_buf),
-        };
-        result.map_err(|e| EngineError::Io(format!("FASTQ read error: {e}")))
+    fn read_one_record(&mut self) -> std::io::Result<usize> {
+        match &mut self.inner {
+            ReaderInner::Plain(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::Gzip(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::Bgzf(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::PlainBytes(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::GzipBytes(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::BgzfBytes(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::Zstd(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::ZstdBytes(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::ParallelBgzf(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::ReadAhead(r) => read_record(r, &mut self.record_buf),
+            ReaderInner::Stream(r) => read_record(r, &mut self.record_buf),
+        }
+    }
+
+    /// A parse error positioned at the start of the record being read.
+    /// Lines are counted assuming four lines per record.
+    fn located(&self, kind: ParseErrorKind, message: String) -> ParseError {
+        ParseError::new(RecordFormat::Fastq, kind, self.records_read + 1, message)
+            .at(Some(self.bytes_read), Some(self.records_read * 4 + 1))
+    }
+
+    /// Classify an error from `read_record`. The `@` is checked before
+    /// noodles parses the record, so other invalid data is the `+` line.
+    fn parse_error(&self, e: &std::io::Error) -> ParseError {
+        let error = ParseError::from_io(RecordFormat::Fastq, self.records_read + 1, e)
+            .at(Some(self.bytes_read), Some(self.records_read * 4 + 1));
+        if e.get_ref()
+            .and_then(|e| e.downcast_ref::<NamePrefixError>())
+            .is_some()
+        {
+            ParseError {
+                kind: ParseErrorKind::BadHeader,
+                ..error
+            }
+            .field("name")
+        } else if error.kind == ParseErrorKind::InvalidRecord
+            && e.kind() == std::io::ErrorKind::InvalidData
+        {
+            error.field("separator")
+        } else {
+            error
+        }
+    }
+}
+
+/// A record that does not start with `@`.
+#[derive(Debug)]
+struct NamePrefixError(u8);
+
+impl std::fmt::Display for NamePrefixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid name prefix {:?}", char::from(self.0))
     }
 }
 
-enum WriterInner {
-    PlainFile(BufWriter<File>),
-    GzipFile(GzEncoder<BufWriter<File>>),
-    PlainBytes(Vec<u8>),
-    GzipBytes(GzEncoder<Vec<u8>>),
+impl std::error::Error for NamePrefixError {}
+
+/// Read one record after checking the byte that starts it.
+fn read_record<R: BufRead>(
+    reader: &mut fastq::io::Reader<R>,
+    record: &mut fastq::Record,
+) -> std::io::Result<usize> {
+    if let Some(&first) = reader.get_mut().fill_buf()?.first() {
+        if first != b'@' {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                NamePrefixError(first),
+            ));
+        }
+    }
+    reader.read_record(record)
 }
 
 /// Stateful FASTQ batch writer.
 ///
 /// Accepts batches of records in the same struct-of-arrays layout that
-/// `FastqReader` produces. Optionally gzip-compresses the output.
-/// Call `finish()` to flush and close — for bytes mode this returns
-/// the accumulated output.
+/// `FastqReader` produces. Output may be gzip-, BGZF- or
+/// zstd-compressed, with gzip and BGZF optionally compressed in parallel
+/// blocks. Call `finish()` to flush and close — for bytes
+/// mode this returns the accumulated output.
 pub struct FastqWriter {
-    inner: WriterInner,
+    inner: Output,
 }
 
 impl FastqWriter {
     /// Open a writer to a file path.
     ///
-    /// If `compress` is true, output is gzip-compressed.
-    ///
     /// # Errors
     ///
-    /// Returns `EngineError::Io` if the file cannot be created.
-    pub fn open_to_path(path: &str, compress: bool) -> Result<Self, EngineError> {
-        let file = File::create(path)
-            .map_err(|e| EngineError::Io(format!("failed to create '{path}': {e}")))?;
-        let buf = BufWriter::new(file);
-
-        let inner = if compress {
-            WriterInner::GzipFile(GzEncoder::new(buf, flate2::Compression::default()))
-        } else {
-            WriterInner::PlainFile(buf)
-        };
-
+    /// Returns `EngineError::Io` if the file cannot be created, or
+    /// `EngineError::InvalidArgument` if a compression level is out of
+    /// range.
+    pub fn open_to_path(path: &str, compression: CompressionMode) -> Result<Self, EngineError> {
+        let inner = Output::open_to_path(path, compression)?;
         Ok(Self { inner })
     }
 
     /// Open a writer to an in-memory buffer.
     ///
-    /// If `compress` is true, output is gzip-compressed.
-    pub fn open_to_bytes(compress: bool) -> Self {
-        let inner = if compress {
-            WriterInner::GzipBytes(GzEncoder::new(Vec::new(), flate2::Compression::default()))
-        } else {
-            WriterInner::PlainBytes(Vec::new())
-        };
-
-        Self { inner }
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a compression level is
+    /// out of range.
+    pub fn open_to_bytes(compression: CompressionMode) -> Result<Self, EngineError> {
+        let inner = Output::open_to_bytes(compression)?;
+        Ok(Self { inner })
     }
 
     /// Write a batch of FASTQ records.
@@ -281,7 +652,7 @@ impl FastqWriter {
             let seq = &batch.sequence_data[seq_start..seq_end];
             let qual = &batch.quality_data[qual_start..qual_end];
 
-            let w = self.writer();
+            let w = &mut self.inner;
             w.write_all(b"@").map_err(map_err)?;
             w.write_all(name).map_err(map_err)?;
             if !desc.is_empty() {
@@ -307,34 +678,417 @@ impl FastqWriter {
     ///
     /// Returns `EngineError::Io` if flushing fails.
     pub fn finish(self) -> Result<Option<Vec<u8>>, EngineError> {
-        match self.inner {
-            WriterInner::PlainFile(mut w) => {
-                w.flush()
-                    .map_err(|e| EngineError::Io(format!("flush error: {e}")))?;
-                Ok(None)
+        self.inner.finish()
+    }
+}
+
+/// A batch of read pairs in struct-of-arrays layout.
+///
+/// `r1` and `r2` hold the mates in matching order and `pair_id` holds the
+/// shared read name (mate suffix stripped), in the layout
+/// `merge_paired_reads_batch` takes.
+#[derive(Debug)]
+pub struct PairedFastqBatch {
+    pub count: u32,
+
+    pub pair_id_data: Vec<u8>,
+    pub pair_id_offsets: Vec<u32>,
+
+    pub r1: FastqBatch,
+    pub r2: FastqBatch,
+}
+
+enum PairedSource {
+    Split(Box<FastqReader>, Box<FastqReader>),
+    Interleaved(Box<FastqReader>),
+}
+
+/// Reads R1/R2 mates in lockstep, from two files or one interleaved file.
+///
+/// Mate names must agree once a trailing `/1` or `/2` is removed (Casava
+/// comments are already split off into the description). A mismatch or
+/// one side running out early stops the reader with an error naming the
+/// offending pair.
+pub struct PairedFastqReader {
+    source: PairedSource,
+    pairs_read: u64,
+}
+
+impl PairedFastqReader {
+    /// Open separate R1 and R2 files by path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if either file cannot be opened or read.
+    pub fn open_from_paths(r1_path: &str, r2_path: &str) -> Result<Self, EngineError> {
+        Ok(Self::new(PairedSource::Split(
+            Box::new(FastqReader::open_from_path(r1_path)?),
+            Box::new(FastqReader::open_from_path(r2_path)?),
+        )))
+    }
+
+    /// Open separate R1 and R2 datasets from in-memory buffers.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if either buffer cannot be parsed.
+    pub fn open_from_bytes(r1: Vec<u8>, r2: Vec<u8>) -> Result<Self, EngineError> {
+        Ok(Self::new(PairedSource::Split(
+            Box::new(FastqReader::open_from_bytes(r1)?),
+            Box::new(FastqReader::open_from_bytes(r2)?),
+        )))
+    }
+
+    /// Open an interleaved file, where each R1 record is followed by its
+    /// R2 mate.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be opened or read.
+    pub fn open_interleaved_from_path(path: &str) -> Result<Self, EngineError> {
+        Ok(Self::new(PairedSource::Interleaved(Box::new(
+            FastqReader::open_from_path(path)?,
+        ))))
+    }
+
+    /// Open an interleaved dataset from an in-memory buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the buffer cannot be parsed.
+    pub fn open_interleaved_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
+        Ok(Self::new(PairedSource::Interleaved(Box::new(
+            FastqReader::open_from_bytes(bytes)?,
+        ))))
+    }
+
+    fn new(source: PairedSource) -> Self {
+        Self {
+            source,
+            pairs_read: 0,
+        }
+    }
+
+    /// Read the next batch of up to `max_pairs` read pairs, or `None` once
+    /// both sides are exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if a record is malformed, or
+    /// `EngineError::InvalidArgument` if the mates fall out of sync.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn read_batch(&mut self, max_pairs: u32) -> Result<Option<PairedFastqBatch>, EngineError> {
+        let (r1, r2) = match &mut self.source {
+            PairedSource::Split(r1, r2) => {
+                match (r1.read_batch(max_pairs)?, r2.read_batch(max_pairs)?) {
+                    (None, None) => return Ok(None),
+                    (Some(r1), Some(r2)) if r1.count == r2.count => (r1, r2),
+                    (r1, r2) => {
+                        let r1_count = r1.map_or(0, |b| b.count);
+                        let r2_count = r2.map_or(0, |b| b.count);
+                        let (longer, shorter) = if r1_count > r2_count {
+                            ("R1", "R2")
+                        } else {
+                            ("R2", "R1")
+                        };
+                        return Err(EngineError::InvalidArgument(format!(
+                            "paired FASTQ out of sync: {shorter} ended after {} pairs but {longer} has more records",
+                            self.pairs_read + u64::from(r1_count.min(r2_count))
+                        )));
+                    }
+                }
             }
-            WriterInner::GzipFile(w) => {
-                w.finish()
-                    .map_err(|e| EngineError::Io(format!("gzip finish error: {e}")))?;
-                Ok(None)
+            PairedSource::Interleaved(reader) => {
+                // Cap first so the record count stays even at the limit.
+                let max_records = max_pairs.min(u32::MAX / 2) * 2;
+                let Some(batch) = reader.read_batch(max_records)? else {
+                    return Ok(None);
+                };
+                if batch.count % 2 != 0 {
+                    return Err(EngineError::InvalidArgument(format!(
+                        "interleaved FASTQ out of sync: pair {} has no R2 record",
+                        self.pairs_read + u64::from(batch.count / 2) + 1
+                    )));
+                }
+                deinterleave(&batch)
             }
-            WriterInner::PlainBytes(v) => Ok(Some(v)),
-            WriterInner::GzipBytes(w) => {
-                let bytes = w
-                    .finish()
-                    .map_err(|e| EngineError::Io(format!("gzip finish error: {e}")))?;
-                Ok(Some(bytes))
+        };
+
+        let mut pair_id_data = Vec::with_capacity(r1.name_data.len());
+        let mut pair_id_offsets = Vec::with_capacity(r1.count as usize + 1);
+        pair_id_offsets.push(0);
+
+        for i in 0..r1.count as usize {
+            let name1 = field_at(&r1.name_data, &r1.name_offsets, i);
+            let name2 = field_at(&r2.name_data, &r2.name_offsets, i);
+            let id = pair_id(name1);
+            if id != pair_id(name2) {
+                return Err(EngineError::InvalidArgument(format!(
+                    "paired FASTQ out of sync at pair {}: '{}' and '{}' do not match",
+                    self.pairs_read + i as u64 + 1,
+                    String::from_utf8_lossy(name1),
+                    String::from_utf8_lossy(name2)
+                )));
             }
+            pair_id_data.extend_from_slice(id);
+            pair_id_offsets.push(pair_id_data.len() as u32);
         }
+
+        self.pairs_read += u64::from(r1.count);
+
+        Ok(Some(PairedFastqBatch {
+            count: r1.count,
+            pair_id_data,
+            pair_id_offsets,
+            r1,
+            r2,
+        }))
+    }
+}
+
+/// Strip a trailing `/1` or `/2` mate suffix, and anything after the
+/// first whitespace, from a read name.
+fn pair_id(name: &[u8]) -> &[u8] {
+    let name = name
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .map_or(name, |end| &name[..end]);
+    match name {
+        [id @ .., b'/', b'1' | b'2'] => id,
+        _ => name,
     }
+}
 
-    fn writer(&mut self) -> &mut dyn std::io::Write {
-        match &mut self.inner {
-            WriterInner::PlainFile(w) => w,
-            WriterInner::GzipFile(w) => w,
-            WriterInner::PlainBytes(w) => w,
-            WriterInner::GzipBytes(w) => w,
+/// Split an interleaved batch with an even record count into mates.
+fn deinterleave(batch: &FastqBatch) -> (FastqBatch, FastqBatch) {
+    let mut halves = [FastqBatch::empty(), FastqBatch::empty()];
+    for i in 0..batch.count as usize {
+        halves[i % 2].push(
+            field_at(&batch.name_data, &batch.name_offsets, i),
+            field_at(&batch.description_data, &batch.description_offsets, i),
+            field_at(&batch.sequence_data, &batch.sequence_offsets, i),
+            field_at(&batch.quality_data, &batch.quality_offsets, i),
+        );
+    }
+    let [r1, r2] = halves;
+    (r1, r2)
+}
+
+/// Push-based FASTQ parser for input that arrives in chunks.
+///
+/// Feed chunks with `push_chunk` and call `end` once the input is done.
+/// Each call returns the records its bytes completed; a record split
+/// across chunks is held until the rest arrives. Gzip and zstd input is
+/// detected from the first bytes and decoded incrementally.
+pub struct FastqChunkReader {
+    decoder: ChunkDecoder,
+    /// Decoded bytes not yet parsed into complete records.
+    pending: Vec<u8>,
+    ended: bool,
+    /// Records, decoded bytes and lines drained from `pending`, for
+    /// locating parse errors.
+    records_parsed: u64,
+    bytes_parsed: u64,
+    lines_parsed: u64,
+}
+
+impl Default for FastqChunkReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FastqChunkReader {
+    pub fn new() -> Self {
+        Self {
+            decoder: ChunkDecoder::new(),
+            pending: Vec::new(),
+            ended: false,
+            records_parsed: 0,
+            bytes_parsed: 0,
+            lines_parsed: 0,
+        }
+    }
+
+    /// Add the next chunk of input and return the records it completed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the chunk cannot be decompressed or
+    /// `end` was already called, or `EngineError::Parse` if a record is
+    /// malformed.
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<Option<FastqBatch>, EngineError> {
+        if self.ended {
+            return Err(EngineError::Io(
+                "FASTQ chunk pushed after end of input".to_string(),
+            ));
+        }
+        self.decoder
+            .push(chunk, &mut self.pending)
+            .map_err(|e| EngineError::Io(format!("FASTQ decompression error: {e}")))?;
+        self.parse_records(false)
+    }
+
+    /// Mark the end of input and return the remaining records.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the compressed stream is incomplete,
+    /// or `EngineError::Parse` if the input ends mid-record.
+    pub fn end(&mut self) -> Result<Option<FastqBatch>, EngineError> {
+        if self.ended {
+            return Ok(None);
         }
+        self.ended = true;
+        self.decoder
+            .finish(&mut self.pending)
+            .map_err(|e| EngineError::Io(format!("FASTQ decompression error: {e}")))?;
+        self.parse_records(true)
+    }
+
+    fn parse_records(&mut self, at_end: bool) -> Result<Option<FastqBatch>, EngineError> {
+        let mut batch = FastqBatch::empty();
+        let mut pos = 0;
+        let mut lines_done = 0;
+
+        loop {
+            let (start, blank) = skip_blank_lines(&self.pending, pos);
+            pos = start;
+            lines_done += blank;
+            let Some((lines, next)) = next_lines::<4>(&self.pending, pos, at_end) else {
+                break;
+            };
+            let [header, sequence, plus, quality] =
+                lines.map(|(start, end)| &self.pending[start..end]);
+            let (name, description) = check_record(header, sequence, plus, quality).map_err(
+                |(kind, field, message)| {
+                    ParseError::new(
+                        RecordFormat::Fastq,
+                        kind,
+                        self.records_parsed + u64::from(batch.count) + 1,
+                        message,
+                    )
+                    .at(
+                        Some(self.bytes_parsed + pos as u64),
+                        Some(self.lines_parsed + lines_done + 1),
+                    )
+                    .field(field)
+                },
+            )?;
+
+            batch.push(name, description, sequence, quality);
+            pos = next;
+            lines_done += 4;
+        }
+
+        if at_end && pos < self.pending.len() {
+            let error = ParseError::new(
+                RecordFormat::Fastq,
+                ParseErrorKind::TruncatedRecord,
+                self.records_parsed + u64::from(batch.count) + 1,
+                "input ends with a truncated record".to_string(),
+            )
+            .at(
+                Some(self.bytes_parsed + pos as u64),
+                Some(self.lines_parsed + lines_done + 1),
+            );
+            return Err(error.into());
+        }
+        self.pending.drain(..pos);
+        self.records_parsed += u64::from(batch.count);
+        self.bytes_parsed += pos as u64;
+        self.lines_parsed += lines_done;
+
+        Ok((batch.count > 0).then_some(batch))
+    }
+}
+
+/// Skip blank lines (bare `\n` or `\r\n`) starting at `pos`, returning the
+/// position of the next non-blank byte and the number of lines skipped.
+pub(crate) fn skip_blank_lines(data: &[u8], mut pos: usize) -> (usize, u64) {
+    let mut lines = 0;
+    while let Some(&b) = data.get(pos) {
+        match b {
+            b'\n' => lines += 1,
+            b'\r' => {}
+            _ => break,
+        }
+        pos += 1;
+    }
+    (pos, lines)
+}
+
+/// Validate the four lines of a FASTQ record and split its header into
+/// name and description. The caller locates any failure.
+pub(crate) fn check_record<'a>(
+    header: &'a [u8],
+    sequence: &[u8],
+    plus: &[u8],
+    quality: &[u8],
+) -> Result<(&'a [u8], &'a [u8]), RecordFault> {
+    let Some(definition) = header.strip_prefix(b"@") else {
+        let message = format!(
+            "record must start with '@', found '{}'",
+            String::from_utf8_lossy(header)
+        );
+        return Err((ParseErrorKind::BadHeader, "name", message));
+    };
+    let (name, description) = split_definition(definition);
+    if !plus.starts_with(b"+") {
+        let message = format!(
+            "record '{}': expected '+' separator line",
+            String::from_utf8_lossy(name)
+        );
+        return Err((ParseErrorKind::InvalidRecord, "separator", message));
+    }
+    if sequence.len() != quality.len() {
+        let message = format!(
+            "record '{}': sequence length ({}) != quality length ({})",
+            String::from_utf8_lossy(name),
+            sequence.len(),
+            quality.len()
+        );
+        return Err((ParseErrorKind::LengthMismatch, "quality", message));
+    }
+    Ok((name, description))
+}
+
+/// Locate the next `N` lines starting at `pos`, as `(start, end)` ranges
+/// with line endings excluded, plus the position after the last line.
+/// Returns `None` until all `N` lines are complete; at end of input the
+/// final line may lack its newline.
+pub(crate) fn next_lines<const N: usize>(
+    data: &[u8],
+    mut pos: usize,
+    at_end: bool,
+) -> Option<([(usize, usize); N], usize)> {
+    let mut lines = [(0, 0); N];
+    for line in &mut lines {
+        if pos >= data.len() {
+            return None;
+        }
+        let (end, next) = match classify::find_newline(&data[pos..]) {
+            Some(i) => (pos + i, pos + i + 1),
+            None if at_end => (data.len(), data.len()),
+            None => return None,
+        };
+        let end = if end > pos && data[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        };
+        *line = (pos, end);
+        pos = next;
+    }
+    Some((lines, pos))
+}
+
+/// Split a definition line (without its marker) into name and
+/// description at the first space or tab.
+pub(crate) fn split_definition(definition: &[u8]) -> (&[u8], &[u8]) {
+    match definition.iter().position(|&b| b == b' ' || b == b'\t') {
+        Some(i) => (&definition[..i], &definition[i + 1..]),
+        None => (definition, &[]),
     }
 }
 
@@ -378,9 +1132,351 @@ mod tests {
         assert!(result.is_err(), "should error on length mismatch");
     }
 
+    fn parse_error(err: EngineError) -> ParseError {
+        match err {
+            EngineError::Parse(e) => *e,
+            other => panic!("expected a parse error, got {other}"),
+        }
+    }
+
+    #[test]
+    fn parse_errors_locate_the_failing_record() {
+        let input = b"@r1\nACGT\n+\nIIII\n@r2\nACG\n+\nII\n";
+        let mut reader = FastqReader::open_from_bytes(input.to_vec()).unwrap();
+        let err = parse_error(reader.read_batch(100).unwrap_err());
+        assert_eq!(err.kind, ParseErrorKind::LengthMismatch);
+        assert_eq!(err.record, 2);
+        assert_eq!(err.line, Some(5));
+        assert_eq!(err.byte_offset, Some(16));
+        assert_eq!(err.field, Some("quality"));
+
+        let input = b"@r1\nA\n+\nI\nr2\nA\n+\nI\n";
+        let mut reader = FastqReader::open_from_bytes(input.to_vec()).unwrap();
+        let err = parse_error(reader.read_batch(100).unwrap_err());
+        assert_eq!(err.kind, ParseErrorKind::BadHeader);
+        assert_eq!((err.record, err.byte_offset), (2, Some(10)));
+        assert_eq!(err.field, Some("name"));
+
+        let input = b"@r1\nA\n-\nI\n";
+        let mut reader = FastqReader::open_from_bytes(input.to_vec()).unwrap();
+        let err = parse_error(reader.read_batch(100).unwrap_err());
+        assert_eq!(err.kind, ParseErrorKind::InvalidRecord);
+        assert_eq!(err.field, Some("separator"));
+
+        let input = b"@r1\nACGT\n";
+        let mut reader = FastqReader::open_from_bytes(input.to_vec()).unwrap();
+        let err = parse_error(reader.read_batch(100).unwrap_err());
+        assert_eq!(err.kind, ParseErrorKind::TruncatedRecord);
+        assert_eq!(
+            err.to_string(),
+            "FASTQ record 1 (line 1, byte 0): failed to fill whole buffer"
+        );
+    }
+
+    #[test]
+    fn bounded_batches_stop_at_the_byte_budget() {
+        let long = "A".repeat(1000);
+        let input = format!(
+            "@a\nACGTA\n+\nIIIII\n@b\n{long}\n+\n{}\n@c\nAC\n+\nII\n",
+            "I".repeat(1000)
+        );
+        let mut reader = FastqReader::open_from_bytes(input.into_bytes()).unwrap();
+        let batch = reader.read_batch_bounded(100, 50).unwrap().unwrap();
+        assert_eq!(batch.count, 2);
+        assert_eq!(batch.sequence_offsets, vec![0, 5, 1005]);
+        let batch = reader.read_batch_bounded(100, 0).unwrap().unwrap();
+        assert_eq!(batch.count, 1);
+        assert!(reader.read_batch_bounded(100, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn checkpoints_resume_plain_gzip_and_bgzf_input() {
+        let mut plain = Vec::new();
+        for i in 0..3000 {
+            let record = format!("@read{i}\n{}\n+\n{}\n", "ACGT".repeat(12), "I".repeat(48));
+            plain.extend_from_slice(record.as_bytes());
+        }
+        let all = FastqReader::open_from_bytes(plain.clone())
+            .unwrap()
+            .read_batch(3000)
+            .unwrap()
+            .unwrap();
+        let encode = |mode| {
+            let mut writer = FastqWriter::open_to_bytes(mode).unwrap();
+            writer.write_batch(&all).unwrap();
+            writer.finish().unwrap().unwrap()
+        };
+        let inputs = [
+            plain.clone(),
+            encode(CompressionMode::Gzip),
+            encode(CompressionMode::Bgzf),
+        ];
+
+        for data in inputs {
+            let mut reader = FastqReader::open_from_bytes(data.clone()).unwrap();
+            reader.read_batch(1000).unwrap().unwrap();
+            let checkpoint = ReaderCheckpoint::from_bytes(&reader.checkpoint().to_bytes()).unwrap();
+            let rest = reader.read_batch(3000).unwrap().unwrap();
+            assert!(reader.read_batch(1).unwrap().is_none());
+            assert_eq!(reader.records_read(), 3000);
+            assert_eq!(
+                reader.bytes_consumed(),
+                BytesConsumed {
+                    compressed: data.len() as u64,
+                    uncompressed: plain.len() as u64,
+                }
+            );
+
+            let mut resumed = FastqReader::open_from_bytes_at(data.clone(), &checkpoint).unwrap();
+            assert_eq!(resumed.records_read(), 1000);
+            let resumed_rest = resumed.read_batch(3000).unwrap().unwrap();
+            assert_eq!(resumed_rest.name_data, rest.name_data);
+            assert_eq!(resumed_rest.sequence_data, rest.sequence_data);
+            assert_eq!(resumed.records_read(), 3000);
+        }
+
+        let bgzf = encode(CompressionMode::Bgzf);
+        let mut threaded = FastqReader::open_from_bytes_threaded(bgzf.clone(), 2).unwrap();
+        threaded.read_batch(1500).unwrap().unwrap();
+        let checkpoint = threaded.checkpoint();
+        let mut resumed = FastqReader::open_from_bytes_at(bgzf, &checkpoint).unwrap();
+        assert_eq!(resumed.read_batch(3000).unwrap().unwrap().count, 1500);
+    }
+
     #[test]
     fn empty_input_returns_none() {
         let mut reader = FastqReader::open_from_bytes(Vec::new()).unwrap();
         assert!(reader.read_batch(100).unwrap().is_none());
     }
+
+    /// `count` reads of `read_len` bases, parsed from generated FASTQ text.
+    fn synthetic_batch(count: u32, read_len: usize) -> FastqBatch {
+        let mut text = Vec::new();
+        for i in 0..count {
+            text.extend_from_slice(format!("@read{i}\n").as_bytes());
+            text.extend(std::iter::repeat_n(b"ACGT"[i as usize % 4], read_len));
+            text.extend_from_slice(b"\n+\n");
+            text.extend(std::iter::repeat_n(b'I', read_len));
+            text.push(b'\n');
+        }
+        let mut reader = FastqReader::open_from_bytes(text).unwrap();
+        reader.read_batch(count).unwrap().unwrap()
+    }
+
+    #[test]
+    fn bgzf_output_round_trips_across_blocks() {
+        let count = 2000;
+        let batch = synthetic_batch(count, 50);
+
+        let mut writer = FastqWriter::open_to_bytes(CompressionMode::Bgzf).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let bytes = writer.finish().unwrap().unwrap();
+        assert_eq!(&bytes[12..14], b"BC", "expected a BGZF extra subfield");
+
+        let mut reader = FastqReader::open_from_bytes(bytes).unwrap();
+        let round_tripped = reader.read_batch(count).unwrap().unwrap();
+        assert_eq!(round_tripped.count, count);
+        assert_eq!(round_tripped.name_data, batch.name_data);
+        assert_eq!(round_tripped.sequence_data, batch.sequence_data);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_output_round_trips_including_concatenated_frames() {
+        let input = b"@read1\nATCG\n+\nIIII\n@read2\nGCTA\n+\nJJJJ\n";
+        let mut reader = FastqReader::open_from_bytes(input.to_vec()).unwrap();
+        let batch = reader.read_batch(10).unwrap().unwrap();
+
+        let mut bytes = Vec::new();
+        for _ in 0..2 {
+            let mut writer = FastqWriter::open_to_bytes(CompressionMode::Zstd(3)).unwrap();
+            writer.write_batch(&batch).unwrap();
+            bytes.extend(writer.finish().unwrap().unwrap());
+        }
+
+        let mut reader = FastqReader::open_from_bytes(bytes).unwrap();
+        let round_tripped = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(round_tripped.count, 4);
+        assert_eq!(round_tripped.name_data, b"read1read2read1read2");
+        assert_eq!(round_tripped.quality_data, b"IIIIJJJJIIIIJJJJ");
+    }
+
+    #[test]
+    fn threaded_reader_matches_serial_reader() {
+        let batch = synthetic_batch(5000, 100);
+
+        let zstd = cfg!(feature = "zstd").then_some(CompressionMode::Zstd(0));
+        for mode in [
+            CompressionMode::Bgzf,
+            CompressionMode::Gzip,
+            CompressionMode::None,
+        ]
+        .into_iter()
+        .chain(zstd)
+        {
+            let mut writer = FastqWriter::open_to_bytes(mode).unwrap();
+            writer.write_batch(&batch).unwrap();
+            let bytes = writer.finish().unwrap().unwrap();
+
+            let mut reader = FastqReader::open_from_bytes_threaded(bytes, 4).unwrap();
+            let mut names = Vec::new();
+            let mut count = 0;
+            while let Some(b) = reader.read_batch(700).unwrap() {
+                count += b.count;
+                names.extend_from_slice(&b.name_data);
+            }
+            assert_eq!(count, batch.count, "{mode:?}");
+            assert_eq!(names, batch.name_data, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn paired_reader_matches_mates_from_split_inputs() {
+        let r1 = b"@frag1/1\nACGT\n+\nIIII\n@frag2 1:N:0:ATCACG\nGGCC\n+\nJJJJ\n";
+        let r2 = b"@frag1/2\nTTTT\n+\nKKKK\n@frag2 2:N:0:ATCACG\nAAAA\n+\nLLLL\n";
+        let mut reader = PairedFastqReader::open_from_bytes(r1.to_vec(), r2.to_vec()).unwrap();
+
+        let batch = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(batch.count, 2);
+        assert_eq!(batch.pair_id_data, b"frag1frag2");
+        assert_eq!(batch.pair_id_offsets, vec![0, 5, 10]);
+        assert_eq!(batch.r1.sequence_data, b"ACGTGGCC");
+        assert_eq!(batch.r2.sequence_data, b"TTTTAAAA");
+        assert!(reader.read_batch(10).unwrap().is_none());
+    }
+
+    #[test]
+    fn paired_reader_splits_interleaved_input() {
+        let input = b"@a/1\nAC\n+\nII\n@a/2\nGT\n+\nJJ\n@b/1\nCC\n+\nKK\n@b/2\nGG\n+\nLL\n";
+        let mut reader = PairedFastqReader::open_interleaved_from_bytes(input.to_vec()).unwrap();
+
+        let first = reader.read_batch(1).unwrap().unwrap();
+        assert_eq!(first.pair_id_data, b"a");
+        assert_eq!(first.r1.quality_data, b"II");
+        assert_eq!(first.r2.quality_data, b"JJ");
+
+        let second = reader.read_batch(1).unwrap().unwrap();
+        assert_eq!(second.pair_id_data, b"b");
+        assert_eq!(second.r2.name_data, b"b/2");
+        assert!(reader.read_batch(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn paired_reader_reports_desync() {
+        let r1 = b"@a/1\nAC\n+\nII\n@b/1\nAC\n+\nII\n";
+        let r2 = b"@a/2\nAC\n+\nII\n@c/2\nAC\n+\nII\n";
+        let mut reader = PairedFastqReader::open_from_bytes(r1.to_vec(), r2.to_vec()).unwrap();
+        let err = reader.read_batch(10).unwrap_err().to_string();
+        assert!(err.contains("pair 2") && err.contains("'b/1'"), "{err}");
+
+        let r2 = b"@a/2\nAC\n+\nII\n";
+        let mut reader = PairedFastqReader::open_from_bytes(r1.to_vec(), r2.to_vec()).unwrap();
+        let err = reader.read_batch(10).unwrap_err().to_string();
+        assert!(err.contains("R2 ended after 1 pairs"), "{err}");
+
+        let interleaved = b"@a/1\nAC\n+\nII\n@a/2\nAC\n+\nII\n@b/1\nAC\n+\nII\n";
+        let mut reader =
+            PairedFastqReader::open_interleaved_from_bytes(interleaved.to_vec()).unwrap();
+        let err = reader.read_batch(10).unwrap_err().to_string();
+        assert!(err.contains("pair 2 has no R2"), "{err}");
+    }
+
+    #[test]
+    fn chunk_reader_matches_whole_buffer_parse() {
+        let input = b"@r1 desc one\nACGT\n+\nIIII\n@r2\r\nGGCCAA\r\n+r2\r\nJJJJJJ\r\n@r3\nT\n+\nK";
+        let mut reader = FastqChunkReader::new();
+        let mut names = Vec::new();
+        let mut sequences = Vec::new();
+        let mut descriptions = Vec::new();
+        let mut count = 0;
+        for piece in input.chunks(5) {
+            if let Some(batch) = reader.push_chunk(piece).unwrap() {
+                count += batch.count;
+                names.extend(batch.name_data);
+                sequences.extend(batch.sequence_data);
+                descriptions.extend(batch.description_data);
+            }
+        }
+        let last = reader.end().unwrap().unwrap();
+        assert_eq!(last.count, 1, "only the unterminated record waits for end");
+        count += last.count;
+        names.extend(last.name_data);
+        sequences.extend(last.sequence_data);
+
+        assert_eq!(count, 3);
+        assert_eq!(names, b"r1r2r3");
+        assert_eq!(descriptions, b"desc one");
+        assert_eq!(sequences, b"ACGTGGCCAAT");
+        assert!(reader.push_chunk(b"@x").is_err());
+    }
+
+    #[test]
+    fn chunk_reader_decodes_gzip_across_chunks() {
+        let records = b"@a\nAC\n+\nII\n".repeat(1000);
+        let mut writer = FastqWriter::open_to_bytes(CompressionMode::Gzip).unwrap();
+        let mut source = FastqReader::open_from_bytes(records).unwrap();
+        writer
+            .write_batch(&source.read_batch(1000).unwrap().unwrap())
+            .unwrap();
+        let compressed = writer.finish().unwrap().unwrap();
+
+        let mut reader = FastqChunkReader::new();
+        let mut count = 0;
+        for piece in compressed.chunks(7) {
+            count += reader.push_chunk(piece).unwrap().map_or(0, |b| b.count);
+        }
+        count += reader.end().unwrap().map_or(0, |b| b.count);
+        assert_eq!(count, 1000);
+    }
+
+    #[test]
+    fn chunk_reader_rejects_truncated_record() {
+        let mut reader = FastqChunkReader::new();
+        assert!(reader.push_chunk(b"@a\nACGT\n+\n").unwrap().is_none());
+        assert!(reader.end().is_err());
+    }
+
+    #[test]
+    fn chunk_reader_locates_errors_across_chunks() {
+        let mut reader = FastqChunkReader::new();
+        assert_eq!(
+            reader
+                .push_chunk(b"@r1\nAC\n+\nII\n\n@r2\nAC")
+                .unwrap()
+                .unwrap()
+                .count,
+            1
+        );
+        let err = parse_error(reader.push_chunk(b"\n-\nII\n").unwrap_err());
+        assert_eq!(err.kind, ParseErrorKind::InvalidRecord);
+        assert_eq!(err.field, Some("separator"));
+        assert_eq!(
+            (err.record, err.line, err.byte_offset),
+            (2, Some(6), Some(13))
+        );
+
+        let mut reader = FastqChunkReader::new();
+        reader.push_chunk(b"@r1\nAC\n+\nII\n@r2\nAC\n").unwrap();
+        let err = parse_error(reader.end().unwrap_err());
+        assert_eq!(err.kind, ParseErrorKind::TruncatedRecord);
+        assert_eq!((err.record, err.line), (2, Some(5)));
+    }
+
+    #[test]
+    fn generic_reader_decodes_gzip_from_chained_sources() {
+        let mut writer = FastqWriter::open_to_bytes(CompressionMode::Gzip).unwrap();
+        let mut source =
+            FastqReader::open_from_bytes(b"@a\nAC\n+\nII\n@b\nGT\n+\nJJ\n".to_vec()).unwrap();
+        writer
+            .write_batch(&source.read_batch(10).unwrap().unwrap())
+            .unwrap();
+        let compressed = writer.finish().unwrap().unwrap();
+        let (head, tail) = compressed.split_at(5);
+
+        let chained = Cursor::new(head.to_vec()).chain(Cursor::new(tail.to_vec()));
+        let mut reader = FastqReader::open_from_reader(Box::new(chained)).unwrap();
+        let batch = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(batch.name_data, b"ab");
+        assert!(reader.read_batch(10).unwrap().is_none());
+    }
 }
@@ -12,18 +12,28 @@
 #![allow(clippy::must_use_candidate)]
 
 pub mod alignment;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod bed;
 pub mod classify;
+mod codec;
+pub mod detect;
 pub mod fasta;
 pub mod fastq;
 pub mod grep;
+pub mod gtf;
 pub mod hash;
+pub mod mapped;
 pub mod metrics;
 pub mod paired_merge;
+pub mod prefetch;
 pub mod quality;
 #[cfg(feature = "native-sequence-sort")]
 pub mod sequence_sort;
+mod text;
 pub mod transform;
 pub mod translate;
+pub mod variant;
 
 use rayon::prelude::*;
 
@@ -36,6 +46,8 @@ pub enum EngineError {
     InvalidArgument(String),
     /// A file or stream I/O operation failed.
     Io(String),
+    /// A record or header in a sequence or alignment file is malformed.
+    Parse(Box<ParseError>),
 }
 
 impl std::fmt::Display for EngineError {
@@ -45,12 +57,310 @@ impl std::fmt::Display for EngineError {
                 write!(f, "[validation] {msg}")
             }
             Self::Io(msg) => write!(f, "[io] {msg}"),
+            Self::Parse(e) => write!(f, "[validation] {e}"),
         }
     }
 }
 
 impl std::error::Error for EngineError {}
 
+impl From<ParseError> for EngineError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(Box::new(e))
+    }
+}
+
+/// Record file format, as reported by `ParseError` and `detect`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    Fastq,
+    Fasta,
+    Sam,
+    Bam,
+    Cram,
+    Bed,
+    Gtf,
+    Gff,
+    Vcf,
+    Bcf,
+}
+
+impl RecordFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fastq => "FASTQ",
+            Self::Fasta => "FASTA",
+            Self::Sam => "SAM",
+            Self::Bam => "BAM",
+            Self::Cram => "CRAM",
+            Self::Bed => "BED",
+            Self::Gtf => "GTF",
+            Self::Gff => "GFF3",
+            Self::Vcf => "VCF",
+            Self::Bcf => "BCF",
+        }
+    }
+}
+
+/// A malformed record's error kind, offending field and message, for the
+/// caller to locate in a `ParseError`.
+pub(crate) type RecordFault = (ParseErrorKind, &'static str, String);
+
+/// What went wrong in a `ParseError`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input ended part-way through a record.
+    TruncatedRecord,
+    /// A FASTQ sequence and its quality string differ in length.
+    LengthMismatch,
+    /// A file header, or a record's definition line, is malformed.
+    BadHeader,
+    /// Text that must be UTF-8 is not.
+    InvalidUtf8,
+    /// Any other malformed record.
+    InvalidRecord,
+}
+
+impl ParseErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TruncatedRecord => "truncated_record",
+            Self::LengthMismatch => "length_mismatch",
+            Self::BadHeader => "bad_header",
+            Self::InvalidUtf8 => "invalid_utf8",
+            Self::InvalidRecord => "invalid_record",
+        }
+    }
+
+    /// Classify an I/O error raised while parsing.
+    fn from_io(e: &std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            return Self::TruncatedRecord;
+        }
+        let mut source: Option<&(dyn std::error::Error + 'static)> = e.get_ref().map(|e| e as _);
+        while let Some(err) = source {
+            if err.is::<std::str::Utf8Error>() || err.is::<std::string::FromUtf8Error>() {
+                return Self::InvalidUtf8;
+            }
+            source = err.source();
+        }
+        Self::InvalidRecord
+    }
+}
+
+/// A malformed record, located well enough to find it in a large file.
+#[derive(Debug)]
+pub struct ParseError {
+    pub format: RecordFormat,
+    pub kind: ParseErrorKind,
+    /// 1-based number of the failing record; `0` for file header errors.
+    pub record: u64,
+    /// Offset of the record's first byte in the decompressed input, when
+    /// the reader tracks it.
+    pub byte_offset: Option<u64>,
+    /// 1-based line of the record's first line, for text formats.
+    pub line: Option<u64>,
+    /// The field that failed, when the failure is specific to one.
+    pub field: Option<&'static str>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(
+        format: RecordFormat,
+        kind: ParseErrorKind,
+        record: u64,
+        message: String,
+    ) -> Self {
+        Self {
+            format,
+            kind,
+            record,
+            byte_offset: None,
+            line: None,
+            field: None,
+            message,
+        }
+    }
+
+    /// A parse error for an I/O error raised by an underlying reader,
+    /// classified by `ParseErrorKind::from_io`.
+    pub(crate) fn from_io(format: RecordFormat, record: u64, e: &std::io::Error) -> Self {
+        Self::new(format, ParseErrorKind::from_io(e), record, e.to_string())
+    }
+
+    /// A file header that failed to parse.
+    pub(crate) fn header(format: RecordFormat, context: &str, e: &std::io::Error) -> Self {
+        let kind = match ParseErrorKind::from_io(e) {
+            ParseErrorKind::InvalidUtf8 => ParseErrorKind::InvalidUtf8,
+            _ => ParseErrorKind::BadHeader,
+        };
+        Self::new(
+            format,
+            kind,
+            0,
+            format!("failed to read header from {context}: {e}"),
+        )
+    }
+
+    pub(crate) fn at(mut self, byte_offset: Option<u64>, line: Option<u64>) -> Self {
+        self.byte_offset = byte_offset;
+        self.line = line;
+        self
+    }
+
+    pub(crate) fn field(mut self, field: &'static str) -> Self {
+        self.field = Some(field);
+        self
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.record == 0 {
+            write!(f, "{} header", self.format.as_str())?;
+        } else {
+            write!(f, "{} record {}", self.format.as_str(), self.record)?;
+        }
+        match (self.line, self.byte_offset) {
+            (Some(line), Some(offset)) => write!(f, " (line {line}, byte {offset})")?,
+            (Some(line), None) => write!(f, " (line {line})")?,
+            (None, Some(offset)) => write!(f, " (byte {offset})")?,
+            (None, None) => {}
+        }
+        if let Some(field) = self.field {
+            write!(f, ", field {field}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// How far a reader has got through its input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BytesConsumed {
+    /// Bytes pulled from the file or buffer as stored, including any
+    /// read-ahead buffering. Compare against the input's size for a
+    /// progress bar.
+    pub compressed: u64,
+    /// Decompressed bytes taken by the records returned so far. Equal
+    /// to `compressed` only for uncompressed input read to the end.
+    pub uncompressed: u64,
+}
+
+const CHECKPOINT_MAGIC: [u8; 4] = *b"GTCK";
+const CHECKPOINT_VERSION: u8 = 1;
+const CHECKPOINT_LEN: usize = 31;
+
+/// Saved position of a reader between records, for resuming a long job
+/// after it is interrupted.
+///
+/// Taken with `checkpoint()` on a reader and passed to its `_at`
+/// constructors. BGZF input resumes by seeking to a virtual offset and
+/// plain input by seeking to a byte offset; gzip and Zstandard streams
+/// have no random access, so they are decompressed again up to the
+/// saved position. `to_bytes` gives an opaque form for storing next to
+/// a job's output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReaderCheckpoint {
+    format: RecordFormat,
+    records_read: u64,
+    uncompressed_offset: u64,
+    virtual_offset: Option<u64>,
+}
+
+impl ReaderCheckpoint {
+    pub(crate) fn new(
+        format: RecordFormat,
+        records_read: u64,
+        uncompressed_offset: u64,
+        virtual_offset: Option<u64>,
+    ) -> Self {
+        Self {
+            format,
+            records_read,
+            uncompressed_offset,
+            virtual_offset,
+        }
+    }
+
+    /// Number of records read before the checkpoint was taken.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    pub(crate) fn uncompressed_offset(&self) -> u64 {
+        self.uncompressed_offset
+    }
+
+    pub(crate) fn virtual_offset(&self) -> Option<u64> {
+        self.virtual_offset
+    }
+
+    /// Reject a checkpoint taken by a reader of another format.
+    pub(crate) fn expect_format(&self, format: RecordFormat) -> Result<(), EngineError> {
+        if self.format == format {
+            Ok(())
+        } else {
+            Err(EngineError::InvalidArgument(format!(
+                "checkpoint was taken from a {} reader, not {}",
+                self.format.as_str(),
+                format.as_str()
+            )))
+        }
+    }
+
+    /// Serialise the checkpoint to a fixed-size opaque buffer.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(CHECKPOINT_LEN);
+        out.extend_from_slice(&CHECKPOINT_MAGIC);
+        out.push(CHECKPOINT_VERSION);
+        out.push(self.format as u8);
+        out.push(u8::from(self.virtual_offset.is_some()));
+        out.extend_from_slice(&self.records_read.to_le_bytes());
+        out.extend_from_slice(&self.uncompressed_offset.to_le_bytes());
+        out.extend_from_slice(&self.virtual_offset.unwrap_or(0).to_le_bytes());
+        out
+    }
+
+    /// Parse a buffer produced by `to_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the buffer is not a
+    /// checkpoint of a supported version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EngineError> {
+        let invalid = || EngineError::InvalidArgument("not a reader checkpoint".to_string());
+        if bytes.len() != CHECKPOINT_LEN || bytes[..4] != CHECKPOINT_MAGIC {
+            return Err(invalid());
+        }
+        if bytes[4] != CHECKPOINT_VERSION {
+            return Err(EngineError::InvalidArgument(format!(
+                "unsupported checkpoint version {}",
+                bytes[4]
+            )));
+        }
+        let format = match bytes[5] {
+            0 => RecordFormat::Fastq,
+            1 => RecordFormat::Fasta,
+            2 => RecordFormat::Sam,
+            3 => RecordFormat::Bam,
+            4 => RecordFormat::Cram,
+            _ => return Err(invalid()),
+        };
+        let word = |at: usize| {
+            let mut le = [0u8; 8];
+            le.copy_from_slice(&bytes[at..at + 8]);
+            u64::from_le_bytes(le)
+        };
+        let virtual_offset = match bytes[6] {
+            0 => None,
+            1 => Some(word(23)),
+            _ => return Err(invalid()),
+        };
+        Ok(Self::new(format, word(7), word(15), virtual_offset))
+    }
+}
+
 /// The result of a batch transform operation.
 #[derive(Debug)]
 pub struct TransformResult {
@@ -97,6 +407,29 @@ pub enum ValidationMode {
     Protein,
 }
 
+/// Output compression for the FASTA and FASTQ writers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionMode {
+    /// Uncompressed text.
+    #[default]
+    None,
+    /// A single gzip member.
+    Gzip,
+    /// Blocked gzip (BGZF). Readable by any gzip decoder, and seekable
+    /// by samtools, tabix and other htslib tools.
+    Bgzf,
+    /// A Zstandard stream at the given level. `0` selects the library
+    /// default; negative levels trade ratio for speed. Needs the `zstd`
+    /// feature; writers reject it otherwise.
+    Zstd(i32),
+    /// Concatenated gzip members compressed in parallel on the rayon
+    /// pool, pigz-style, at the given deflate level (0-9).
+    ParallelGzip(i32),
+    /// BGZF compressed in parallel on the rayon pool at the given
+    /// deflate level (0-9).
+    ParallelBgzf(i32),
+}
+
 impl ValidationMode {
     fn to_classify_mode(self) -> classify::ValidMode {
         match self {
@@ -109,7 +442,7 @@ impl ValidationMode {
     }
 }
 
-fn validate_offsets(offsets: &[u32], data_len: usize) -> Result<(), EngineError> {
+pub(crate) fn validate_offsets(offsets: &[u32], data_len: usize) -> Result<(), EngineError> {
     if let Some(&last) = offsets.last() {
         if last as usize > data_len {
             return Err(EngineError::InvalidOffsets(format!(
@@ -132,6 +465,13 @@ fn num_sequences(offsets: &[u32]) -> usize {
     offsets.len().saturating_sub(1)
 }
 
+/// Record `index` of a packed byte column and its offsets.
+pub(crate) fn field_at<'a>(data: &'a [u8], offsets: &[u32], index: usize) -> &'a [u8] {
+    let start = offsets[index] as usize;
+    let end = offsets[index + 1] as usize;
+    &data[start..end]
+}
+
 /// Search a batch of sequences for a pattern within a given edit distance.
 ///
 /// Returns a `Vec<u8>` of length `num_sequences` where each byte is 1 if
@@ -150,24 +490,37 @@ pub fn grep_batch(
 ) -> Result<Vec<u8>, EngineError> {
     validate_offsets(offsets, sequences.len())?;
 
-    let n = num_sequences(offsets);
-    let results: Vec<u8> = (0..n)
+    Ok(grep_each(
+        num_sequences(offsets),
+        |i| field_at(sequences, offsets, i),
+        pattern,
+        max_edits,
+        case_insensitive,
+        search_both_strands,
+    ))
+}
+
+/// `grep_batch` over `n` sequences supplied by an accessor, so batches
+/// that borrow their records (such as memory-mapped ones) can be searched
+/// without packing them first.
+pub(crate) fn grep_each<'a>(
+    n: usize,
+    sequence: impl Fn(usize) -> &'a [u8] + Sync,
+    pattern: &[u8],
+    max_edits: u32,
+    case_insensitive: bool,
+    search_both_strands: bool,
+) -> Vec<u8> {
+    (0..n)
         .into_par_iter()
         .map_init(
             || {
                 let mode = grep::SearchMode::from_flags(case_insensitive, search_both_strands);
                 grep::SearchContext::new(pattern, max_edits, &mode)
             },
-            |ctx, i| {
-                let start = offsets[i] as usize;
-                let end = offsets[i + 1] as usize;
-                let seq = &sequences[start..end];
-                u8::from(ctx.contains_match(seq))
-            },
+            |ctx, i| u8::from(ctx.contains_match(sequence(i))),
         )
-        .collect();
-
-    Ok(results)
+        .collect()
 }
 
 /// Find all pattern matches with positions and edit distances in a batch.
@@ -560,27 +913,33 @@ pub fn sequence_metrics_batch(
         ));
     }
 
-    let n = num_sequences(seq_offsets);
+    Ok(sequence_metrics_each(
+        num_sequences(seq_offsets),
+        |i| field_at(sequences, seq_offsets, i),
+        |i| field_at(quality_data, qual_offsets, i),
+        metric_flags,
+        ascii_offset,
+    ))
+}
+
+/// `sequence_metrics_batch` over `n` records supplied by accessors.
+pub(crate) fn sequence_metrics_each<'a>(
+    n: usize,
+    sequence: impl Fn(usize) -> &'a [u8] + Sync,
+    quality: impl Fn(usize) -> &'a [u8] + Sync,
+    metric_flags: u32,
+    ascii_offset: u8,
+) -> metrics::SequenceMetricsResult {
     let needs_qual = metric_flags
         & (metrics::METRIC_AVG_QUAL | metrics::METRIC_MIN_QUAL | metrics::METRIC_MAX_QUAL)
         != 0;
 
     let rows: Vec<_> = (0..n)
         .into_par_iter()
-        .map(|i| {
-            let seq_start = seq_offsets[i] as usize;
-            let seq_end = seq_offsets[i + 1] as usize;
-            let seq = &sequences[seq_start..seq_end];
-
-            let qual_start = qual_offsets[i] as usize;
-            let qual_end = qual_offsets[i + 1] as usize;
-            let qual = &quality_data[qual_start..qual_end];
-
-            metrics::compute_row(seq, qual, needs_qual)
-        })
+        .map(|i| metrics::compute_row(sequence(i), quality(i), needs_qual))
         .collect();
 
-    Ok(metrics::materialize(&rows, metric_flags, ascii_offset))
+    metrics::materialize(&rows, metric_flags, ascii_offset)
 }
 
 /// Translate a packed batch of nucleotide sequences into proteins.
@@ -667,20 +1026,43 @@ pub fn hash_batch(
 ) -> Result<Vec<u8>, EngineError> {
     validate_offsets(offsets, sequences.len())?;
 
-    let n = num_sequences(offsets);
+    Ok(hash_each(
+        num_sequences(offsets),
+        |i| field_at(sequences, offsets, i),
+        case_insensitive,
+    ))
+}
+
+/// `hash_batch` over `n` sequences supplied by an accessor.
+pub(crate) fn hash_each<'a>(
+    n: usize,
+    sequence: impl Fn(usize) -> &'a [u8] + Sync,
+    case_insensitive: bool,
+) -> Vec<u8> {
     let mut out = vec![0u8; n * 16];
 
     out.par_chunks_exact_mut(16)
         .enumerate()
         .for_each(|(i, slot)| {
-            let start = offsets[i] as usize;
-            let end = offsets[i + 1] as usize;
-            let seq = &sequences[start..end];
-            let h = hash::hash_one(seq, case_insensitive);
+            let h = hash::hash_one(sequence(i), case_insensitive);
             slot.copy_from_slice(&h.to_le_bytes());
         });
 
-    Ok(out)
+    out
+}
+
+/// A scratch directory for file-backed tests, unique to the process and
+/// test thread so parallel tests do not collide.
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+pub(crate) fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "genotype-{name}-{}-{:?}",
+        std::process::id(),
+        std::thread::current().id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
 }
 
 #[cfg(test)]
@@ -1243,4 +1625,25 @@ mod tests {
         let r2 = hash_batch(&d2, &o2, true).unwrap();
         assert_eq!(extract_hash(&r1, 0), extract_hash(&r2, 0));
     }
+
+    // ── ReaderCheckpoint ─────────────────────────────────────────
+
+    #[test]
+    fn checkpoint_bytes_round_trip_and_reject_garbage() {
+        let checkpoint = ReaderCheckpoint::new(RecordFormat::Bam, 12, 3456, Some(789 << 16));
+        let bytes = checkpoint.to_bytes();
+        assert_eq!(ReaderCheckpoint::from_bytes(&bytes).unwrap(), checkpoint);
+
+        let plain = ReaderCheckpoint::new(RecordFormat::Sam, 1, 2, None);
+        assert_eq!(
+            ReaderCheckpoint::from_bytes(&plain.to_bytes()).unwrap(),
+            plain
+        );
+
+        assert!(ReaderCheckpoint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 99;
+        assert!(ReaderCheckpoint::from_bytes(&wrong_version).is_err());
+        assert!(checkpoint.expect_format(RecordFormat::Sam).is_err());
+    }
 }
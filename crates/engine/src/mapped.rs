@@ -0,0 +1,568 @@
+//! Memory-mapped, zero-copy FASTQ and FASTA readers.
+//!
+//! `FastqReader` and `FastaReader` stream their input through a
+//! `BufReader` and copy every field into freshly allocated batch buffers.
+//! For uncompressed files on disk that copy is avoidable: the readers here
+//! map the file and hand out batches whose fields are spans into the
+//! mapping. Line boundaries are found with `classify::find_newline`, so
+//! computing a batch's offsets is a vectorised scan rather than a parse.
+//!
+//! A mapped batch borrows its reader, so it must be dropped before the
+//! next batch is read. Scanning workloads (`grep`, `hash`,
+//! `sequence_metrics`) run directly on the borrowed spans; `to_batch`
+//! materialises an owned `FastqBatch` or `FastaBatch` when one is needed.
+
+use std::{borrow::Cow, fs::File, ops::Range};
+
+use memmap2::Mmap;
+
+use crate::{
+    codec::{self, InputCompression},
+    fasta::FastaBatch,
+    fastq::{self, next_lines, skip_blank_lines, split_definition, FastqBatch},
+    grep_each, hash_each, metrics, sequence_metrics_each, EngineError, ParseError, ParseErrorKind,
+    RecordFormat,
+};
+
+/// Map `path` read-only, rejecting compressed input.
+fn map_plain(path: &str, reader: &str) -> Result<Mmap, EngineError> {
+    let file =
+        File::open(path).map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+    // SAFETY: the mapping is read-only and private to this reader. Another
+    // process truncating or rewriting the file while it is mapped can
+    // change or invalidate the bytes underneath us; like other mmap-based
+    // tools we treat input files as immutable for the reader's lifetime.
+    let map = unsafe { Mmap::map(&file) }
+        .map_err(|e| EngineError::Io(format!("failed to map '{path}': {e}")))?;
+
+    if codec::detect(&map[..map.len().min(codec::MAGIC_LEN)]) != InputCompression::None {
+        return Err(EngineError::InvalidArgument(format!(
+            "{reader}: '{path}' is compressed; memory-mapped reading needs uncompressed input"
+        )));
+    }
+    Ok(map)
+}
+
+/// Memory-mapped reader over an uncompressed FASTQ file.
+pub struct MappedFastqReader {
+    map: Mmap,
+    pos: usize,
+    records_read: u64,
+    lines_read: u64,
+}
+
+/// A batch of FASTQ records borrowed from a `MappedFastqReader`.
+///
+/// Each field is a span into the mapped file; nothing is copied until
+/// `to_batch` is called.
+pub struct MappedFastqBatch<'a> {
+    data: &'a [u8],
+    names: Vec<Range<usize>>,
+    descriptions: Vec<Range<usize>>,
+    sequences: Vec<Range<usize>>,
+    qualities: Vec<Range<usize>>,
+}
+
+impl MappedFastqReader {
+    /// Map an uncompressed FASTQ file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be opened or mapped,
+    /// or `EngineError::InvalidArgument` if it is gzip- or
+    /// zstd-compressed.
+    pub fn open(path: &str) -> Result<Self, EngineError> {
+        Ok(Self {
+            map: map_plain(path, "MappedFastqReader")?,
+            pos: 0,
+            records_read: 0,
+            lines_read: 0,
+        })
+    }
+
+    /// Number of records returned so far.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Bytes of the file consumed by the records returned so far.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// Read the next batch of up to `max_records` records, or `None` at
+    /// end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number, line and byte offset in the file.
+    pub fn read_batch(
+        &mut self,
+        max_records: u32,
+    ) -> Result<Option<MappedFastqBatch<'_>>, EngineError> {
+        let data: &[u8] = &self.map;
+        let mut batch = MappedFastqBatch {
+            data,
+            names: Vec::new(),
+            descriptions: Vec::new(),
+            sequences: Vec::new(),
+            qualities: Vec::new(),
+        };
+
+        while batch.names.len() < max_records as usize {
+            let (start, blank) = skip_blank_lines(data, self.pos);
+            self.pos = start;
+            self.lines_read += blank;
+            let located = |kind, message| {
+                ParseError::new(RecordFormat::Fastq, kind, self.records_read + 1, message)
+                    .at(Some(start as u64), Some(self.lines_read + 1))
+            };
+
+            let Some((lines, next)) = next_lines::<4>(data, start, true) else {
+                if start < data.len() {
+                    let message = "input ends with a truncated record".to_string();
+                    return Err(located(ParseErrorKind::TruncatedRecord, message).into());
+                }
+                break;
+            };
+            let [header, sequence, plus, quality] = lines;
+            let (name, description) = fastq::check_record(
+                &data[header.0..header.1],
+                &data[sequence.0..sequence.1],
+                &data[plus.0..plus.1],
+                &data[quality.0..quality.1],
+            )
+            .map_err(|(kind, field, message)| located(kind, message).field(field))?;
+
+            let name_start = header.0 + 1;
+            batch.names.push(name_start..name_start + name.len());
+            batch
+                .descriptions
+                .push(header.1 - description.len()..header.1);
+            batch.sequences.push(sequence.0..sequence.1);
+            batch.qualities.push(quality.0..quality.1);
+
+            self.pos = next;
+            self.lines_read += 4;
+            self.records_read += 1;
+        }
+
+        Ok((!batch.names.is_empty()).then_some(batch))
+    }
+}
+
+impl MappedFastqBatch<'_> {
+    /// Number of records in the batch.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn count(&self) -> u32 {
+        self.names.len() as u32
+    }
+
+    /// Record `i`'s name, up to the first whitespace of its header.
+    pub fn name(&self, i: usize) -> &[u8] {
+        &self.data[self.names[i].clone()]
+    }
+
+    /// Record `i`'s header text after the name, empty if there is none.
+    pub fn description(&self, i: usize) -> &[u8] {
+        &self.data[self.descriptions[i].clone()]
+    }
+
+    /// Record `i`'s sequence line.
+    pub fn sequence(&self, i: usize) -> &[u8] {
+        &self.data[self.sequences[i].clone()]
+    }
+
+    /// Record `i`'s quality line, as written.
+    pub fn quality(&self, i: usize) -> &[u8] {
+        &self.data[self.qualities[i].clone()]
+    }
+
+    /// Copy the batch into an owned `FastqBatch`.
+    pub fn to_batch(&self) -> FastqBatch {
+        let mut batch = FastqBatch::empty();
+        for i in 0..self.names.len() {
+            batch.push(
+                self.name(i),
+                self.description(i),
+                self.sequence(i),
+                self.quality(i),
+            );
+        }
+        batch
+    }
+
+    /// `grep_batch` over the batch's sequences, without copying them.
+    pub fn grep(
+        &self,
+        pattern: &[u8],
+        max_edits: u32,
+        case_insensitive: bool,
+        search_both_strands: bool,
+    ) -> Vec<u8> {
+        grep_each(
+            self.sequences.len(),
+            |i| self.sequence(i),
+            pattern,
+            max_edits,
+            case_insensitive,
+            search_both_strands,
+        )
+    }
+
+    /// `hash_batch` over the batch's sequences, without copying them.
+    pub fn hash(&self, case_insensitive: bool) -> Vec<u8> {
+        hash_each(self.sequences.len(), |i| self.sequence(i), case_insensitive)
+    }
+
+    /// `sequence_metrics_batch` over the batch, without copying it.
+    pub fn sequence_metrics(
+        &self,
+        metric_flags: u32,
+        ascii_offset: u8,
+    ) -> metrics::SequenceMetricsResult {
+        sequence_metrics_each(
+            self.sequences.len(),
+            |i| self.sequence(i),
+            |i| self.quality(i),
+            metric_flags,
+            ascii_offset,
+        )
+    }
+}
+
+/// Memory-mapped reader over an uncompressed FASTA file.
+pub struct MappedFastaReader {
+    map: Mmap,
+    pos: usize,
+    records_read: u64,
+    lines_read: u64,
+}
+
+/// A batch of FASTA records borrowed from a `MappedFastaReader`.
+///
+/// A sequence's span runs from its first to its last non-empty line, so
+/// single-line sequences are returned without copying and wrapped ones
+/// are joined on demand.
+pub struct MappedFastaBatch<'a> {
+    data: &'a [u8],
+    names: Vec<Range<usize>>,
+    descriptions: Vec<Range<usize>>,
+    sequences: Vec<Range<usize>>,
+}
+
+impl MappedFastaReader {
+    /// Map an uncompressed FASTA file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be opened or mapped,
+    /// or `EngineError::InvalidArgument` if it is gzip- or
+    /// zstd-compressed.
+    pub fn open(path: &str) -> Result<Self, EngineError> {
+        Ok(Self {
+            map: map_plain(path, "MappedFastaReader")?,
+            pos: 0,
+            records_read: 0,
+            lines_read: 0,
+        })
+    }
+
+    /// Number of records returned so far.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Bytes of the file consumed by the records returned so far.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// Read the next batch of up to `max_records` records, or `None` at
+    /// end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if the file does not start with a `>`
+    /// definition line.
+    pub fn read_batch(
+        &mut self,
+        max_records: u32,
+    ) -> Result<Option<MappedFastaBatch<'_>>, EngineError> {
+        let data: &[u8] = &self.map;
+        let mut batch = MappedFastaBatch {
+            data,
+            names: Vec::new(),
+            descriptions: Vec::new(),
+            sequences: Vec::new(),
+        };
+
+        while batch.names.len() < max_records as usize {
+            let (start, blank) = skip_blank_lines(data, self.pos);
+            self.pos = start;
+            self.lines_read += blank;
+            let Some(([(line_start, line_end)], mut next)) = next_lines::<1>(data, start, true)
+            else {
+                break;
+            };
+            let line = &data[line_start..line_end];
+            let Some(definition) = line.strip_prefix(b">") else {
+                let message = format!(
+                    "input must start with '>', found '{}'",
+                    String::from_utf8_lossy(line)
+                );
+                return Err(ParseError::new(
+                    RecordFormat::Fasta,
+                    ParseErrorKind::BadHeader,
+                    self.records_read + 1,
+                    message,
+                )
+                .at(Some(start as u64), Some(self.lines_read + 1))
+                .field("definition")
+                .into());
+            };
+            let (name, description) = split_definition(definition);
+            batch
+                .names
+                .push(line_start + 1..line_start + 1 + name.len());
+            batch
+                .descriptions
+                .push(line_end - description.len()..line_end);
+            self.lines_read += 1;
+
+            let mut sequence = next..next;
+            while let Some(([(start, end)], after)) = next_lines::<1>(data, next, true) {
+                if data[start..end].starts_with(b">") {
+                    break;
+                }
+                if start < end {
+                    if sequence.is_empty() {
+                        sequence.start = start;
+                    }
+                    sequence.end = end;
+                }
+                next = after;
+                self.lines_read += 1;
+            }
+            batch.sequences.push(sequence);
+
+            self.pos = next;
+            self.records_read += 1;
+        }
+
+        Ok((!batch.names.is_empty()).then_some(batch))
+    }
+}
+
+impl MappedFastaBatch<'_> {
+    /// Number of records in the batch.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn count(&self) -> u32 {
+        self.names.len() as u32
+    }
+
+    /// Record `i`'s name, up to the first whitespace of its definition line.
+    pub fn name(&self, i: usize) -> &[u8] {
+        &self.data[self.names[i].clone()]
+    }
+
+    /// Record `i`'s definition text after the name, empty if there is none.
+    pub fn description(&self, i: usize) -> &[u8] {
+        &self.data[self.descriptions[i].clone()]
+    }
+
+    /// The record's sequence, borrowed when it sits on one line and
+    /// joined into an owned buffer when it is wrapped.
+    pub fn sequence(&self, i: usize) -> Cow<'_, [u8]> {
+        let span = &self.data[self.sequences[i].clone()];
+        if crate::classify::find_newline(span).is_none() {
+            return Cow::Borrowed(span);
+        }
+        Cow::Owned(
+            span.iter()
+                .copied()
+                .filter(|&b| b != b'\n' && b != b'\r')
+                .collect(),
+        )
+    }
+
+    /// Copy the batch into an owned `FastaBatch`.
+    pub fn to_batch(&self) -> FastaBatch {
+        let mut batch = FastaBatch::empty();
+        for i in 0..self.names.len() {
+            batch.push(self.name(i), self.description(i), &self.sequence(i));
+        }
+        batch
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        fasta::FastaReader,
+        fastq::FastqReader,
+        grep_batch, hash_batch,
+        metrics::{METRIC_GC, METRIC_LENGTH, METRIC_MIN_QUAL},
+        sequence_metrics_batch,
+    };
+
+    const FASTQ: &[u8] =
+        b"@r1 first read\nACGTACGT\n+\nIIIIIIII\n@r2\nGGGCCC\n+r2\n!!!!!!\r\n@r3\tx\nTTAA\n+\n####";
+
+    const FASTA: &[u8] =
+        b">chr1 one\nACGT\nAC\n\n>chr2\nGGGG\n>empty\n>chr3 three\r\nTTTT\r\nAAAA\r\n";
+
+    fn temp_file(name: &str, contents: &[u8]) -> (std::path::PathBuf, String) {
+        let dir = crate::test_dir("mapped");
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+        (dir, path_str)
+    }
+
+    #[test]
+    fn fastq_batches_match_the_streaming_reader() {
+        let (dir, path) = temp_file("reads.fq", FASTQ);
+        let expected = FastqReader::open_from_bytes(FASTQ.to_vec())
+            .unwrap()
+            .read_batch(10)
+            .unwrap()
+            .unwrap();
+
+        let mut reader = MappedFastqReader::open(&path).unwrap();
+        let first = reader.read_batch(2).unwrap().unwrap();
+        assert_eq!(first.count(), 2);
+        assert_eq!(first.name(0), b"r1");
+        assert_eq!(first.description(0), b"first read");
+        assert_eq!(first.quality(1), b"!!!!!!");
+        let mut owned = first.to_batch();
+        let second = reader.read_batch(2).unwrap().unwrap().to_batch();
+        assert_eq!(second.count, 1);
+        assert!(reader.read_batch(2).unwrap().is_none());
+        assert_eq!(reader.records_read(), 3);
+        assert_eq!(reader.bytes_consumed(), FASTQ.len() as u64);
+
+        owned.push(
+            &second.name_data,
+            &second.description_data,
+            &second.sequence_data,
+            &second.quality_data,
+        );
+        assert_eq!(owned.name_data, expected.name_data);
+        assert_eq!(owned.description_data, expected.description_data);
+        assert_eq!(owned.sequence_data, expected.sequence_data);
+        assert_eq!(owned.sequence_offsets, expected.sequence_offsets);
+        assert_eq!(owned.quality_data, expected.quality_data);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fastq_scans_match_the_packed_batch_functions() {
+        let (dir, path) = temp_file("scan.fq", FASTQ);
+        let mut reader = MappedFastqReader::open(&path).unwrap();
+        let mapped = reader.read_batch(10).unwrap().unwrap();
+        let packed = mapped.to_batch();
+        let seqs = &packed.sequence_data;
+        let offsets = &packed.sequence_offsets;
+
+        assert_eq!(
+            mapped.grep(b"ccc", 0, true, false),
+            grep_batch(seqs, offsets, b"ccc", 0, true, false).unwrap()
+        );
+        assert_eq!(
+            mapped.hash(false),
+            hash_batch(seqs, offsets, false).unwrap()
+        );
+        let flags = METRIC_LENGTH | METRIC_GC | METRIC_MIN_QUAL;
+        let expected = sequence_metrics_batch(
+            seqs,
+            offsets,
+            &packed.quality_data,
+            &packed.quality_offsets,
+            flags,
+            33,
+        )
+        .unwrap();
+        let actual = mapped.sequence_metrics(flags, 33);
+        assert_eq!(actual.lengths, expected.lengths);
+        assert_eq!(actual.gc, expected.gc);
+        assert_eq!(actual.min_qual, expected.min_qual);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fastq_errors_are_located_and_compressed_input_is_rejected() {
+        let (dir, path) = temp_file("bad.fq", b"@r1\nAC\n+\nII\n@r2\nACG\n+\nII\n");
+        let mut reader = MappedFastqReader::open(&path).unwrap();
+        let Err(EngineError::Parse(error)) = reader.read_batch(10) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.kind, ParseErrorKind::LengthMismatch);
+        assert_eq!(error.record, 2);
+        assert_eq!(error.line, Some(5));
+        assert_eq!(error.byte_offset, Some(12));
+
+        let (_, truncated) = temp_file("truncated.fq", b"@r1\nAC\n+\n");
+        let mut reader = MappedFastqReader::open(&truncated).unwrap();
+        let Err(EngineError::Parse(error)) = reader.read_batch(10) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.kind, ParseErrorKind::TruncatedRecord);
+
+        let (_, gzipped) = temp_file("reads.fq.gz", &[0x1f, 0x8b, 8, 0]);
+        assert!(matches!(
+            MappedFastqReader::open(&gzipped),
+            Err(EngineError::InvalidArgument(_))
+        ));
+
+        let (_, empty) = temp_file("empty.fq", b"");
+        assert!(MappedFastqReader::open(&empty)
+            .unwrap()
+            .read_batch(10)
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fasta_batches_borrow_single_line_sequences() {
+        let (dir, path) = temp_file("ref.fa", FASTA);
+        let expected = FastaReader::open_from_bytes(FASTA.to_vec())
+            .unwrap()
+            .read_batch(10)
+            .unwrap()
+            .unwrap();
+
+        let mut reader = MappedFastaReader::open(&path).unwrap();
+        let batch = reader.read_batch(10).unwrap().unwrap();
+        assert_eq!(batch.count(), 4);
+        assert_eq!(batch.description(0), b"one");
+        assert!(matches!(batch.sequence(0), Cow::Owned(_)));
+        assert!(matches!(batch.sequence(1), Cow::Borrowed(b"GGGG")));
+        assert_eq!(&*batch.sequence(2), b"");
+        assert_eq!(&*batch.sequence(3), b"TTTTAAAA");
+
+        let owned = batch.to_batch();
+        assert_eq!(owned.name_data, expected.name_data);
+        assert_eq!(owned.description_data, expected.description_data);
+        assert_eq!(owned.sequence_data, expected.sequence_data);
+        assert_eq!(owned.sequence_offsets, expected.sequence_offsets);
+        assert!(reader.read_batch(10).unwrap().is_none());
+
+        let (_, bad) = temp_file("bad.fa", b"\nACGT\n>chr1\n");
+        let Err(EngineError::Parse(error)) = MappedFastaReader::open(&bad).unwrap().read_batch(1)
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.kind, ParseErrorKind::BadHeader);
+        assert_eq!(error.line, Some(2));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+//! Background batch prefetching for the FASTQ, FASTA and alignment readers.
+//!
+//! `read_batch` decompresses, parses and assembles each batch on the
+//! caller's thread. `Prefetcher` moves a reader onto a worker thread that
+//! keeps up to `depth` batches ready in a bounded queue, so parsing the
+//! next batch overlaps with whatever the caller does with the current one.
+//! Batches come out in input order; a parse error ends the stream after
+//! the batches that preceded it.
+//!
+//! Dropping a `Prefetcher` closes the queue and joins the worker, which
+//! stops after finishing at most the batch it is parsing. Threads are not
+//! available on `wasm32`, so the wasm adapter does not expose this.
+
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    alignment::{AlignmentBatch, AlignmentReader},
+    fasta::{FastaBatch, FastaReader},
+    fastq::{FastqBatch, FastqReader},
+    EngineError,
+};
+
+/// A reader that can run on a `Prefetcher` thread.
+pub trait BatchReader: Send + 'static {
+    type Batch: Send + 'static;
+
+    /// Read the next batch of up to `max_records` records.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reader's own error for malformed or unreadable input.
+    fn next_batch(&mut self, max_records: u32) -> Result<Option<Self::Batch>, EngineError>;
+}
+
+impl BatchReader for FastqReader {
+    type Batch = FastqBatch;
+
+    fn next_batch(&mut self, max_records: u32) -> Result<Option<FastqBatch>, EngineError> {
+        self.read_batch(max_records)
+    }
+}
+
+impl BatchReader for FastaReader {
+    type Batch = FastaBatch;
+
+    fn next_batch(&mut self, max_records: u32) -> Result<Option<FastaBatch>, EngineError> {
+        self.read_batch(max_records)
+    }
+}
+
+impl BatchReader for AlignmentReader {
+    type Batch = AlignmentBatch;
+
+    fn next_batch(&mut self, max_records: u32) -> Result<Option<AlignmentBatch>, EngineError> {
+        self.read_batch(max_records)
+    }
+}
+
+type BatchResult<R> = Result<Option<<R as BatchReader>::Batch>, EngineError>;
+
+/// Runs a `BatchReader` on a background thread, keeping parsed batches
+/// ready in a bounded queue.
+pub struct Prefetcher<R: BatchReader> {
+    receiver: Option<Receiver<BatchResult<R>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<R: BatchReader> Prefetcher<R> {
+    /// Move `reader` onto a worker thread that reads batches of up to
+    /// `max_records` records, keeping at most `depth` of them queued.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if `depth` is zero, or
+    /// `EngineError::Io` if the thread cannot be started.
+    pub fn spawn(mut reader: R, max_records: u32, depth: u32) -> Result<Self, EngineError> {
+        if depth == 0 {
+            return Err(EngineError::InvalidArgument(
+                "prefetch: depth must be at least 1".to_owned(),
+            ));
+        }
+        let (sender, receiver) = mpsc::sync_channel(depth as usize);
+        let handle = thread::Builder::new()
+            .name("genotype-prefetch".to_owned())
+            .spawn(move || loop {
+                let message = reader.next_batch(max_records);
+                let done = !matches!(message, Ok(Some(_)));
+                // A send error means the prefetcher was dropped; stop reading.
+                if sender.send(message).is_err() || done {
+                    break;
+                }
+            })
+            .map_err(|e| EngineError::Io(format!("failed to start prefetch thread: {e}")))?;
+
+        Ok(Self {
+            receiver: Some(receiver),
+            handle: Some(handle),
+        })
+    }
+
+    /// Take the next prefetched batch, waiting for the worker if none is
+    /// ready yet. Returns `None` once the input is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reader's error for the batch that failed, or
+    /// `EngineError::Io` if the worker thread panicked.
+    pub fn read_batch(&mut self) -> BatchResult<R> {
+        let Some(receiver) = &self.receiver else {
+            return Ok(None);
+        };
+        match receiver.recv() {
+            Ok(Ok(Some(batch))) => Ok(Some(batch)),
+            Ok(last) => {
+                self.join()?;
+                last
+            }
+            // The worker hung up without a final message, so it panicked.
+            Err(_) => self.join().map(|()| None),
+        }
+    }
+
+    fn join(&mut self) -> Result<(), EngineError> {
+        self.receiver = None;
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| EngineError::Io("prefetch thread panicked".to_owned())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: BatchReader> Drop for Prefetcher<R> {
+    fn drop(&mut self) {
+        // Dropping the receiver makes the worker's next send fail, so the
+        // join waits for at most one batch of parsing.
+        let _ = self.join();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn fastq(records: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..records {
+            data.extend_from_slice(format!("@r{i}\nACGT\n+\nIIII\n").as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn prefetched_batches_match_direct_reads() {
+        let data = fastq(25);
+        let mut direct = FastqReader::open_from_bytes(data.clone()).unwrap();
+        let reader = FastqReader::open_from_bytes(data).unwrap();
+        let mut prefetcher = Prefetcher::spawn(reader, 10, 2).unwrap();
+
+        while let Some(expected) = direct.read_batch(10).unwrap() {
+            let batch = prefetcher.read_batch().unwrap().unwrap();
+            assert_eq!(batch.count, expected.count);
+            assert_eq!(batch.name_data, expected.name_data);
+        }
+        assert!(prefetcher.read_batch().unwrap().is_none());
+        assert!(prefetcher.read_batch().unwrap().is_none());
+    }
+
+    #[test]
+    fn errors_end_the_stream_after_earlier_batches() {
+        let mut data = fastq(4);
+        data.extend_from_slice(b"not a record\n");
+        let reader = FastqReader::open_from_bytes(data).unwrap();
+        let mut prefetcher = Prefetcher::spawn(reader, 2, 1).unwrap();
+
+        assert_eq!(prefetcher.read_batch().unwrap().unwrap().count, 2);
+        assert_eq!(prefetcher.read_batch().unwrap().unwrap().count, 2);
+        assert!(matches!(
+            prefetcher.read_batch(),
+            Err(EngineError::Parse(_))
+        ));
+        assert!(prefetcher.read_batch().unwrap().is_none());
+    }
+
+    #[test]
+    fn dropping_mid_stream_stops_the_worker() {
+        let reader =
+            FastaReader::open_from_bytes(b">a\nAC\n>b\nGT\n>c\nTT\n".repeat(1000)).unwrap();
+        let mut prefetcher = Prefetcher::spawn(reader, 1, 1).unwrap();
+        assert_eq!(prefetcher.read_batch().unwrap().unwrap().count, 1);
+        drop(prefetcher);
+
+        let reader = FastqReader::open_from_bytes(Vec::new()).unwrap();
+        assert!(matches!(
+            Prefetcher::spawn(reader, 1, 0),
+            Err(EngineError::InvalidArgument(_))
+        ));
+    }
+}
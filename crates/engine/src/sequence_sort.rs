@@ -20,7 +20,7 @@ use spillover_bio::{
     sort::{Builder, Reverse as ReverseOrder, SequenceQualityKey, ILLUMINA_ORDER},
 };
 
-use crate::{fastq::FastqBatch, validate_offsets, EngineError};
+use crate::{fastq::FastqBatch, field_at, validate_offsets, EngineError};
 
 type SortCodec = DryIceCodec<TwoBitExactCodec, RawQualityCodec, RawNameCodec>;
 type SortError = MergeError<dryice::DryIceError>;
@@ -291,12 +291,6 @@ fn validate_batch(
     Ok(())
 }
 
-fn field_at<'a>(data: &'a [u8], offsets: &[u32], index: usize) -> &'a [u8] {
-    let start = offsets[index] as usize;
-    let end = offsets[index + 1] as usize;
-    &data[start..end]
-}
-
 fn encode_definition(name: &[u8], description: &[u8]) -> Result<Vec<u8>, EngineError> {
     let name_len = u32::try_from(name.len()).map_err(|_| {
         EngineError::InvalidArgument("sequence sort: record name exceeds u32 length".to_owned())
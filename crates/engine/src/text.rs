@@ -0,0 +1,36 @@
+//! Field parsing and packing helpers shared by the BED and GTF readers.
+
+use std::str::FromStr;
+
+use crate::{ParseErrorKind, RecordFault};
+
+/// Append `value` as the next CSR row of `data`/`offsets`.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn push_packed<T: Copy>(data: &mut Vec<T>, offsets: &mut Vec<u32>, value: &[T]) {
+    data.extend_from_slice(value);
+    offsets.push(data.len() as u32);
+}
+
+pub(crate) fn invalid(field: &'static str, message: String) -> RecordFault {
+    (ParseErrorKind::InvalidRecord, field, message)
+}
+
+pub(crate) fn parse_number<T: FromStr>(field: &[u8]) -> Option<T> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+/// Parse `field` with `parse`, describing what was `expected` on failure.
+pub(crate) fn parse_field<T>(
+    field: &[u8],
+    name: &'static str,
+    expected: &str,
+    parse: impl FnOnce(&[u8]) -> Option<T>,
+) -> Result<T, RecordFault> {
+    parse(field).ok_or_else(|| {
+        let message = format!(
+            "{name} must be {expected}, found '{}'",
+            String::from_utf8_lossy(field)
+        );
+        invalid(name, message)
+    })
+}
@@ -0,0 +1,1637 @@
+//! VCF and BCF batch reader and VCF writer.
+//!
+//! `VariantReader` decodes VCF (plain, gzip, BGZF or zstd) and BCF into
+//! struct-of-arrays batches: CHROM, POS, ID, REF, ALT, QUAL and FILTER
+//! columns, typed columns for selected INFO fields, and optionally a
+//! per-sample genotype matrix. Records are read through noodles'
+//! format-agnostic `variant::Record` view, so VCF and BCF batches are
+//! identical for the same data.
+//!
+//! BGZF-compressed VCF and BCF opened with a tabix (`.tbi`) or CSI
+//! (`.csi`) index can be restricted to a genomic region with `query`.
+//!
+//! `VcfWriter` writes the same batches back out as VCF text under a
+//! caller-supplied header, tabix-indexing BGZF output on `finish`.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, Write},
+};
+
+use noodles_bcf as bcf;
+use noodles_bgzf::{self as bgzf, gzi};
+use noodles_core::{region::Interval, Position, Region};
+use noodles_csi::{
+    self as csi, binning_index::index::reference_sequence::bin::Chunk, BinningIndex,
+};
+use noodles_tabix as tabix;
+use noodles_vcf::{
+    self as vcf,
+    header::record::value::map::info::Type as InfoType,
+    variant::record::{
+        info::field::{value::Array, Value},
+        samples::series::{value::genotype::Phasing, Value as SampleValue},
+        AlternateBases, Filters, Ids, Info, ReferenceBases, Samples,
+    },
+    variant::Record,
+};
+
+use crate::{
+    codec::{self, BoxBufRead, ByteCounter, Counted, Output},
+    BytesConsumed, CompressionMode, EngineError, ParseError, RecordFormat,
+};
+
+/// Integer INFO array element for a missing (`.`) value, as in BCF.
+pub const MISSING_INT: i32 = i32::MIN;
+
+/// Value type of an extracted INFO column, from its header definition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InfoKind {
+    Int,
+    Float,
+    /// Presence only; the column holds no values.
+    Flag,
+    /// `String` and `Character` fields, rendered as VCF text.
+    String,
+}
+
+impl InfoKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Flag => "flag",
+            Self::String => "string",
+        }
+    }
+
+    fn from_header(ty: InfoType) -> Self {
+        match ty {
+            InfoType::Integer => Self::Int,
+            InfoType::Float => Self::Float,
+            InfoType::Flag => Self::Flag,
+            InfoType::Character | InfoType::String => Self::String,
+        }
+    }
+}
+
+/// A single INFO field extracted across a batch.
+///
+/// `present[i]` is 1 when record `i` carries the field. `offsets` is a
+/// CSR index into the storage matching `kind`: `int_values` (with
+/// `MISSING_INT` for `.` elements), `float_values` (`NaN` for `.`), or
+/// `string_data`, where multiple values are joined with commas. Absent
+/// fields and flags have no values.
+#[derive(Debug)]
+pub struct InfoColumn {
+    pub key: String,
+    pub kind: InfoKind,
+    pub present: Vec<u8>,
+    pub offsets: Vec<u32>,
+    pub int_values: Vec<i32>,
+    pub float_values: Vec<f64>,
+    pub string_data: Vec<u8>,
+}
+
+/// Genotype calls for every sample of every record in a batch.
+///
+/// Cells are record-major: cell `i * sample_count + j` holds sample `j`
+/// of record `i`. Each cell's allele indices are the CSR range
+/// `allele_offsets[cell]..allele_offsets[cell + 1]` of `alleles`, with
+/// -1 for a missing (`.`) allele; a sample without a GT value has none.
+#[derive(Debug)]
+pub struct GenotypeMatrix {
+    pub sample_count: u32,
+    pub allele_offsets: Vec<u32>,
+    pub alleles: Vec<i32>,
+    /// 1 where the call is phased (`0|1`); haploid calls are unphased.
+    pub phased: Vec<u8>,
+}
+
+/// A batch of variant records in struct-of-arrays layout.
+#[derive(Debug)]
+pub struct VariantBatch {
+    pub count: u32,
+    /// `Vcf` or `Bcf`.
+    pub format: RecordFormat,
+
+    pub chrom_data: Vec<u8>,
+    pub chrom_offsets: Vec<u32>,
+    /// 1-based; 0 for telomeric records.
+    pub positions: Vec<u64>,
+    /// IDs joined with `;`; empty for `.`.
+    pub id_data: Vec<u8>,
+    pub id_offsets: Vec<u32>,
+    pub ref_data: Vec<u8>,
+    pub ref_offsets: Vec<u32>,
+    /// Alternate alleles joined with `,`; empty for `.`.
+    pub alt_data: Vec<u8>,
+    pub alt_offsets: Vec<u32>,
+    /// `NaN` for a missing quality.
+    pub qualities: Vec<f64>,
+    /// Filters joined with `;`; empty for `.`.
+    pub filter_data: Vec<u8>,
+    pub filter_offsets: Vec<u32>,
+
+    /// One column per field requested via `set_info_fields`, in request
+    /// order.
+    pub info: Vec<InfoColumn>,
+    /// Present when enabled via `set_genotypes`.
+    pub genotypes: Option<GenotypeMatrix>,
+}
+
+impl VariantBatch {
+    fn empty(format: RecordFormat, info: &[(String, InfoKind)], samples: Option<u32>) -> Self {
+        Self {
+            count: 0,
+            format,
+            chrom_data: Vec::new(),
+            chrom_offsets: vec![0],
+            positions: Vec::new(),
+            id_data: Vec::new(),
+            id_offsets: vec![0],
+            ref_data: Vec::new(),
+            ref_offsets: vec![0],
+            alt_data: Vec::new(),
+            alt_offsets: vec![0],
+            qualities: Vec::new(),
+            filter_data: Vec::new(),
+            filter_offsets: vec![0],
+            info: info
+                .iter()
+                .map(|(key, kind)| InfoColumn {
+                    key: key.clone(),
+                    kind: *kind,
+                    present: Vec::new(),
+                    offsets: vec![0],
+                    int_values: Vec::new(),
+                    float_values: Vec::new(),
+                    string_data: Vec::new(),
+                })
+                .collect(),
+            genotypes: samples.map(|sample_count| GenotypeMatrix {
+                sample_count,
+                allele_offsets: vec![0],
+                alleles: Vec::new(),
+                phased: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// Raw input that BGZF sources can seek in for region queries.
+trait SeekRead: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> SeekRead for T {}
+
+/// A BGZF reader over seekable input, counting raw bytes below and
+/// decompressed bytes above.
+type BgzfInput = Counted<bgzf::io::Reader<Counted<Box<dyn SeekRead>>>>;
+
+enum ReaderInner {
+    /// BGZF VCF over a file or buffer; can be queried.
+    BgzfVcf(vcf::io::Reader<BgzfInput>),
+    /// BCF over a file or buffer; can be queried.
+    BgzfBcf(bcf::io::Reader<BgzfInput>),
+    /// Plain, gzip or zstd VCF, or any stream.
+    StreamVcf(vcf::io::Reader<Counted<BoxBufRead>>),
+    /// BCF from a stream or a non-BGZF source.
+    StreamBcf(bcf::io::Reader<Counted<BoxBufRead>>),
+}
+
+impl ReaderInner {
+    fn bgzf(&mut self) -> Option<&mut bgzf::io::Reader<Counted<Box<dyn SeekRead>>>> {
+        match self {
+            Self::BgzfVcf(r) => Some(r.get_mut().get_mut()),
+            Self::BgzfBcf(r) => Some(r.get_mut().get_mut()),
+            Self::StreamVcf(_) | Self::StreamBcf(_) => None,
+        }
+    }
+}
+
+/// Position within an active region query, as in the alignment reader.
+struct QueryState {
+    chunks: Vec<Chunk>,
+    next_chunk: usize,
+    chunk_end: Option<bgzf::VirtualPosition>,
+    reference_sequence_name: Vec<u8>,
+    interval: Interval,
+}
+
+/// Stateful VCF/BCF batch reader.
+pub struct VariantReader {
+    inner: ReaderInner,
+    header: vcf::Header,
+    format: RecordFormat,
+    vcf_record: vcf::Record,
+    bcf_record: bcf::Record,
+    index: Option<Box<dyn BinningIndex + Send>>,
+    query: Option<QueryState>,
+    info_fields: Vec<(String, InfoKind)>,
+    genotypes: bool,
+    /// Records read since open, or since the current query started.
+    records_read: u64,
+    /// VCF header line count, for reporting record line numbers.
+    header_lines: u64,
+    /// Raw bytes pulled from the input.
+    source: ByteCounter,
+    /// Decompressed bytes the parser has consumed.
+    decoded: ByteCounter,
+}
+
+impl VariantReader {
+    /// Open a VCF or BCF file by path.
+    ///
+    /// Compression and format are detected from the leading bytes. The
+    /// header is read immediately; records are read lazily via
+    /// `read_batch`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if the file cannot be opened, or
+    /// `EngineError::Parse` if the header cannot be parsed.
+    pub fn open_from_path(path: &str) -> Result<Self, EngineError> {
+        let file = File::open(path)
+            .map_err(|e| EngineError::Io(format!("failed to open '{path}': {e}")))?;
+        Self::open_seekable(Box::new(BufReader::new(file)), path)
+    }
+
+    /// Open a VCF or BCF dataset from an in-memory buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if the header cannot be parsed.
+    pub fn open_from_bytes(bytes: Vec<u8>) -> Result<Self, EngineError> {
+        Self::open_seekable(Box::new(Cursor::new(bytes)), "buffer")
+    }
+
+    /// Open a VCF or BCF stream from any reader, such as stdin, a pipe
+    /// or a socket. Stream readers cannot `query`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if the header cannot be read.
+    pub fn open_from_reader(reader: Box<dyn Read + Send>) -> Result<Self, EngineError> {
+        Self::open_from_buf_reader(Box::new(BufReader::new(reader)))
+    }
+
+    /// Open a VCF or BCF stream from a buffered reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if the header cannot be read.
+    pub fn open_from_buf_reader(reader: Box<dyn BufRead + Send>) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        Self::open_stream(Box::new(Counted::new(reader, &source)), source, "stream")
+    }
+
+    /// Open an indexed BGZF VCF or BCF file by path.
+    ///
+    /// `index_path` may point to a tabix or CSI index; the format is
+    /// detected from its magic bytes. Until `query` is called, records
+    /// are read sequentially from the start of the file as usual.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the file is not BGZF
+    /// compressed or the index cannot be parsed, or `EngineError::Io` if
+    /// either file cannot be read.
+    pub fn open_indexed(path: &str, index_path: &str) -> Result<Self, EngineError> {
+        let index_bytes = std::fs::read(index_path)
+            .map_err(|e| EngineError::Io(format!("failed to read '{index_path}': {e}")))?;
+        Self::open_from_path(path)?.with_index(&index_bytes, path)
+    }
+
+    /// Open an indexed BGZF VCF or BCF dataset from in-memory data and
+    /// index buffers, as for `open_indexed`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the data is not BGZF
+    /// compressed or the index cannot be parsed.
+    pub fn open_indexed_from_bytes(
+        bytes: Vec<u8>,
+        index_bytes: &[u8],
+    ) -> Result<Self, EngineError> {
+        Self::open_from_bytes(bytes)?.with_index(index_bytes, "the buffer")
+    }
+
+    fn with_index(mut self, index_bytes: &[u8], context: &str) -> Result<Self, EngineError> {
+        if self.inner.bgzf().is_none() {
+            return Err(EngineError::InvalidArgument(format!(
+                "indexed queries require BGZF-compressed VCF or BCF, but {context} is not"
+            )));
+        }
+        self.index = Some(read_index(index_bytes)?);
+        Ok(self)
+    }
+
+    fn open_seekable(raw: Box<dyn SeekRead>, context: &str) -> Result<Self, EngineError> {
+        let source = ByteCounter::default();
+        let mut raw = Counted::new(raw, &source);
+
+        let mut magic = [0u8; codec::BGZF_HEADER_LEN];
+        let n = raw
+            .read(&mut magic)
+            .and_then(|n| raw.seek(io::SeekFrom::Start(0)).map(|_| n))
+            .map_err(|e| EngineError::Io(format!("failed to read {context}: {e}")))?;
+        if !codec::is_bgzf(&magic[..n]) {
+            return Self::open_stream(Box::new(BufReader::new(raw)), source, context);
+        }
+
+        let decoded = ByteCounter::default();
+        let mut reader = Counted::new(bgzf::io::Reader::new(raw), &decoded);
+        let is_bcf = reader
+            .fill_buf()
+            .map_err(|e| EngineError::Io(format!("failed to read {context}: {e}")))?
+            .starts_with(b"BCF");
+        let (inner, header, format) = if is_bcf {
+            let mut reader = bcf::io::Reader::from(reader);
+            let header = reader
+                .read_header()
+                .map_err(|e| ParseError::header(RecordFormat::Bcf, context, &e))?;
+            (ReaderInner::BgzfBcf(reader), header, RecordFormat::Bcf)
+        } else {
+            let mut reader = vcf::io::Reader::new(reader);
+            let header = reader
+                .read_header()
+                .map_err(|e| ParseError::header(RecordFormat::Vcf, context, &e))?;
+            (ReaderInner::BgzfVcf(reader), header, RecordFormat::Vcf)
+        };
+        Ok(Self::from_parts(inner, header, format, source, decoded))
+    }
+
+    fn open_stream(
+        reader: BoxBufRead,
+        source: ByteCounter,
+        context: &str,
+    ) -> Result<Self, EngineError> {
+        let decoded = ByteCounter::default();
+        let (magic, reader) = codec::decode_buf_read(reader)
+            .and_then(|r| codec::peek_magic(r, b"BCF".len()))
+            .map_err(|e| EngineError::Io(format!("failed to read {context}: {e}")))?;
+        let reader = Counted::new(reader, &decoded);
+        let is_bcf = magic == b"BCF";
+        let (inner, header, format) = if is_bcf {
+            let mut reader = bcf::io::Reader::from(reader);
+            let header = reader
+                .read_header()
+                .map_err(|e| ParseError::header(RecordFormat::Bcf, context, &e))?;
+            (ReaderInner::StreamBcf(reader), header, RecordFormat::Bcf)
+        } else {
+            let mut reader = vcf::io::Reader::new(reader);
+            let header = reader
+                .read_header()
+                .map_err(|e| ParseError::header(RecordFormat::Vcf, context, &e))?;
+            (ReaderInner::StreamVcf(reader), header, RecordFormat::Vcf)
+        };
+        Ok(Self::from_parts(inner, header, format, source, decoded))
+    }
+
+    fn from_parts(
+        inner: ReaderInner,
+        header: vcf::Header,
+        format: RecordFormat,
+        source: ByteCounter,
+        decoded: ByteCounter,
+    ) -> Self {
+        let header_lines = match format {
+            RecordFormat::Vcf => count_header_lines(&header),
+            _ => 0,
+        };
+        Self {
+            inner,
+            header,
+            format,
+            vcf_record: vcf::Record::default(),
+            bcf_record: bcf::Record::default(),
+            index: None,
+            query: None,
+            info_fields: Vec::new(),
+            genotypes: false,
+            records_read: 0,
+            header_lines,
+            source,
+            decoded,
+        }
+    }
+
+    /// `RecordFormat::Vcf` or `RecordFormat::Bcf`.
+    pub fn format(&self) -> RecordFormat {
+        self.format
+    }
+
+    /// Sample names from the header, in column order.
+    pub fn sample_names(&self) -> Vec<String> {
+        self.header.sample_names().iter().cloned().collect()
+    }
+
+    /// The header as VCF text, from `##fileformat` through the `#CHROM`
+    /// line, e.g. to open a `VcfWriter` with.
+    pub fn header_text(&self) -> String {
+        let mut writer = vcf::io::Writer::new(Vec::new());
+        // Writing a parsed header into memory does not fail.
+        let _ = writer.write_header(&self.header);
+        String::from_utf8_lossy(writer.get_ref()).into_owned()
+    }
+
+    /// Configure which INFO fields `read_batch` extracts.
+    ///
+    /// By default no INFO fields are read. Each requested key yields an
+    /// `InfoColumn` in every subsequent batch, in request order, typed
+    /// from its `##INFO` header definition.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if a key has no `##INFO`
+    /// definition in the header.
+    pub fn set_info_fields(&mut self, keys: &[String]) -> Result<(), EngineError> {
+        self.info_fields = keys
+            .iter()
+            .map(|key| {
+                let info = self.header.infos().get(key).ok_or_else(|| {
+                    EngineError::InvalidArgument(format!(
+                        "INFO field '{key}' is not defined in the header"
+                    ))
+                })?;
+                Ok((key.clone(), InfoKind::from_header(info.ty())))
+            })
+            .collect::<Result<_, EngineError>>()?;
+        Ok(())
+    }
+
+    /// Enable or disable the per-sample genotype matrix in subsequent
+    /// batches. Off by default.
+    pub fn set_genotypes(&mut self, enabled: bool) {
+        self.genotypes = enabled;
+    }
+
+    /// Restrict subsequent reads to records overlapping `region`.
+    ///
+    /// `region` uses samtools syntax (`chr2`, `chr2:1000`, or
+    /// `chr2:1000-5000`, 1-based and inclusive). `read_batch` then
+    /// returns only overlapping records and yields `None` once the
+    /// region is exhausted. Calling `query` again starts a new region.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the reader was not
+    /// opened with an index, the region cannot be parsed, or the
+    /// reference sequence is not in the index (VCF) or header (BCF).
+    pub fn query(&mut self, region: &str) -> Result<(), EngineError> {
+        let index = self.index.as_ref().ok_or_else(|| {
+            EngineError::InvalidArgument(
+                "region queries require a reader opened with an index".to_string(),
+            )
+        })?;
+
+        let region: Region = region
+            .parse()
+            .map_err(|e| EngineError::InvalidArgument(format!("invalid region '{region}': {e}")))?;
+
+        let reference_sequence_id = match self.format {
+            RecordFormat::Bcf => std::str::from_utf8(region.name())
+                .ok()
+                .and_then(|name| self.header.string_maps().contigs().get_index_of(name)),
+            _ => index.header().and_then(|header| {
+                header
+                    .reference_sequence_names()
+                    .get_index_of(region.name())
+            }),
+        }
+        .ok_or_else(|| {
+            EngineError::InvalidArgument(format!(
+                "reference sequence '{}' is not in the index",
+                String::from_utf8_lossy(region.name())
+            ))
+        })?;
+
+        let chunks = index
+            .query(reference_sequence_id, region.interval())
+            .map_err(|e| EngineError::Io(format!("index query error: {e}")))?;
+
+        self.query = Some(QueryState {
+            chunks,
+            next_chunk: 0,
+            chunk_end: None,
+            reference_sequence_name: region.name().to_vec(),
+            interval: region.interval(),
+        });
+        self.records_read = 0;
+
+        Ok(())
+    }
+
+    /// Number of records returned so far, or since the current query
+    /// started.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    /// Progress through the input, as raw and decompressed bytes.
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        BytesConsumed {
+            compressed: self.source.get(),
+            uncompressed: self.decoded.get(),
+        }
+    }
+
+    /// Read the next batch of up to `max_records` records, or `None` at
+    /// end of input (or of the queried region).
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Parse` if a record is malformed, locating it
+    /// by record number (and line, for VCF outside of queries).
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<VariantBatch>, EngineError> {
+        let samples = self
+            .genotypes
+            .then(|| self.header.sample_names().len() as u32);
+        let mut batch = VariantBatch::empty(self.format, &self.info_fields, samples);
+
+        while batch.count < max_records {
+            match self.read_record() {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Err(self.parse_error(&e).into()),
+            }
+            let record: &dyn Record = match self.format {
+                RecordFormat::Bcf => &self.bcf_record,
+                _ => &self.vcf_record,
+            };
+            if let Err(e) = push_record(&mut batch, &self.header, record) {
+                return Err(self.parse_error(&e).into());
+            }
+            self.records_read += 1;
+        }
+
+        Ok((batch.count > 0).then_some(batch))
+    }
+
+    fn read_record(&mut self) -> io::Result<usize> {
+        loop {
+            if let Some(query) = &mut self.query {
+                let Some(bgzf) = self.inner.bgzf() else {
+                    return Ok(0);
+                };
+                let in_chunk = query
+                    .chunk_end
+                    .is_some_and(|end| bgzf.virtual_position() < end);
+                if !in_chunk {
+                    let Some(chunk) = query.chunks.get(query.next_chunk) else {
+                        return Ok(0);
+                    };
+                    bgzf.seek(chunk.start())?;
+                    query.chunk_end = Some(chunk.end());
+                    query.next_chunk += 1;
+                    continue;
+                }
+            }
+
+            let n = match &mut self.inner {
+                ReaderInner::BgzfVcf(r) => r.read_record(&mut self.vcf_record)?,
+                ReaderInner::StreamVcf(r) => r.read_record(&mut self.vcf_record)?,
+                ReaderInner::BgzfBcf(r) => r.read_record(&mut self.bcf_record)?,
+                ReaderInner::StreamBcf(r) => r.read_record(&mut self.bcf_record)?,
+            };
+
+            let Some(query) = &mut self.query else {
+                return Ok(n);
+            };
+            if n == 0 {
+                query.next_chunk = query.chunks.len();
+                query.chunk_end = None;
+                return Ok(0);
+            }
+            let record: &dyn Record = match self.format {
+                RecordFormat::Bcf => &self.bcf_record,
+                _ => &self.vcf_record,
+            };
+            if intersects(
+                &self.header,
+                record,
+                &query.reference_sequence_name,
+                query.interval,
+            )? {
+                return Ok(n);
+            }
+        }
+    }
+
+    /// A parse error for the record being read. VCF records carry their
+    /// line number outside of region queries.
+    fn parse_error(&self, e: &io::Error) -> ParseError {
+        let record = self.records_read + 1;
+        let error = ParseError::from_io(self.format, record, e);
+        match self.format {
+            RecordFormat::Vcf => {
+                let line = self.query.is_none().then_some(self.header_lines + record);
+                error.at(None, line)
+            }
+            _ => error,
+        }
+    }
+}
+
+/// Number of lines the header takes up when written as VCF text.
+#[allow(clippy::naive_bytecount)]
+fn count_header_lines(header: &vcf::Header) -> u64 {
+    let mut buf = Vec::new();
+    if vcf::io::Writer::new(&mut buf).write_header(header).is_err() {
+        return 0;
+    }
+    buf.iter().filter(|&&b| b == b'\n').count() as u64
+}
+
+fn read_index(bytes: &[u8]) -> Result<Box<dyn BinningIndex + Send>, EngineError> {
+    let mut magic = [0u8; 4];
+    bgzf::io::Reader::new(bytes)
+        .read_exact(&mut magic)
+        .map_err(|e| EngineError::InvalidArgument(format!("failed to read index: {e}")))?;
+    match &magic {
+        b"TBI\x01" => {
+            let index = tabix::io::Reader::new(bytes).read_index().map_err(|e| {
+                EngineError::InvalidArgument(format!("failed to read tabix index: {e}"))
+            })?;
+            Ok(Box::new(index))
+        }
+        b"CSI\x01" => {
+            let index = csi::io::Reader::new(bytes).read_index().map_err(|e| {
+                EngineError::InvalidArgument(format!("failed to read CSI index: {e}"))
+            })?;
+            Ok(Box::new(index))
+        }
+        _ => Err(EngineError::InvalidArgument(
+            "unrecognized index format (expected tabix or CSI)".to_string(),
+        )),
+    }
+}
+
+fn intersects(
+    header: &vcf::Header,
+    record: &dyn Record,
+    reference_sequence_name: &[u8],
+    interval: Interval,
+) -> io::Result<bool> {
+    if record.reference_sequence_name(header)?.as_bytes() != reference_sequence_name {
+        return Ok(false);
+    }
+    if interval.start().is_none() && interval.end().is_none() {
+        return Ok(true);
+    }
+    let Some(start) = record.variant_start().transpose()? else {
+        return Ok(false);
+    };
+    let end = record.variant_end(header)?;
+    Ok(interval.intersects((start..=end).into()))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn close_row(data: &[u8], offsets: &mut Vec<u32>) {
+    offsets.push(data.len() as u32);
+}
+
+/// Append `values` to `data` joined by `separator`.
+fn push_joined<'a>(
+    data: &mut Vec<u8>,
+    separator: u8,
+    values: impl Iterator<Item = io::Result<&'a str>>,
+) -> io::Result<()> {
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            data.push(separator);
+        }
+        data.extend_from_slice(value?.as_bytes());
+    }
+    Ok(())
+}
+
+fn push_record(
+    batch: &mut VariantBatch,
+    header: &vcf::Header,
+    record: &dyn Record,
+) -> io::Result<()> {
+    batch
+        .chrom_data
+        .extend_from_slice(record.reference_sequence_name(header)?.as_bytes());
+    close_row(&batch.chrom_data, &mut batch.chrom_offsets);
+    let position = record.variant_start().transpose()?;
+    batch
+        .positions
+        .push(position.map_or(0, |p| usize::from(p) as u64));
+    push_joined(&mut batch.id_data, b';', record.ids().iter().map(Ok))?;
+    close_row(&batch.id_data, &mut batch.id_offsets);
+    for base in record.reference_bases().iter() {
+        batch.ref_data.push(base?);
+    }
+    close_row(&batch.ref_data, &mut batch.ref_offsets);
+    push_joined(&mut batch.alt_data, b',', record.alternate_bases().iter())?;
+    close_row(&batch.alt_data, &mut batch.alt_offsets);
+    let quality = record.quality_score().transpose()?;
+    batch.qualities.push(quality.map_or(f64::NAN, f64::from));
+    push_joined(&mut batch.filter_data, b';', record.filters().iter(header))?;
+    close_row(&batch.filter_data, &mut batch.filter_offsets);
+
+    if !batch.info.is_empty() {
+        let info = record.info();
+        for column in &mut batch.info {
+            let value = info.get(header, &column.key).transpose()?;
+            column.present.push(u8::from(value.is_some()));
+            push_info(column, value.flatten())?;
+        }
+    }
+    if let Some(matrix) = &mut batch.genotypes {
+        push_genotypes(matrix, header, record)?;
+    }
+
+    batch.count += 1;
+    Ok(())
+}
+
+fn push_info(column: &mut InfoColumn, value: Option<Value<'_>>) -> io::Result<()> {
+    let len = match (column.kind, value) {
+        (InfoKind::Flag, _) | (_, None) => None,
+        (InfoKind::Int, Some(Value::Integer(n))) => {
+            column.int_values.push(n);
+            Some(column.int_values.len())
+        }
+        (InfoKind::Int, Some(Value::Array(Array::Integer(values)))) => {
+            for value in values.iter() {
+                column.int_values.push(value?.unwrap_or(MISSING_INT));
+            }
+            Some(column.int_values.len())
+        }
+        (InfoKind::Float, Some(Value::Float(x))) => {
+            column.float_values.push(f64::from(x));
+            Some(column.float_values.len())
+        }
+        (InfoKind::Float, Some(Value::Array(Array::Float(values)))) => {
+            for value in values.iter() {
+                column.float_values.push(value?.map_or(f64::NAN, f64::from));
+            }
+            Some(column.float_values.len())
+        }
+        (InfoKind::String, Some(Value::String(s))) => {
+            column.string_data.extend_from_slice(s.as_bytes());
+            Some(column.string_data.len())
+        }
+        (InfoKind::String, Some(Value::Character(c))) => {
+            let mut buf = [0; 4];
+            column
+                .string_data
+                .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            Some(column.string_data.len())
+        }
+        (InfoKind::String, Some(Value::Array(Array::String(values)))) => {
+            let values = values
+                .iter()
+                .map(|v| v.map(|v| v.unwrap_or_else(|| ".".into())));
+            for (i, value) in values.enumerate() {
+                if i > 0 {
+                    column.string_data.push(b',');
+                }
+                column.string_data.extend_from_slice(value?.as_bytes());
+            }
+            Some(column.string_data.len())
+        }
+        (InfoKind::String, Some(Value::Array(Array::Character(values)))) => {
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    column.string_data.push(b',');
+                }
+                let mut buf = [0; 4];
+                let value = value?.unwrap_or('.');
+                column
+                    .string_data
+                    .extend_from_slice(value.encode_utf8(&mut buf).as_bytes());
+            }
+            Some(column.string_data.len())
+        }
+        (kind, Some(value)) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "INFO field {} is declared {} but holds {value:?}",
+                    column.key,
+                    kind.as_str()
+                ),
+            ));
+        }
+    };
+    let end = match len {
+        Some(len) => len,
+        None => column.offsets.last().copied().unwrap_or(0) as usize,
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    column.offsets.push(end as u32);
+    Ok(())
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn push_genotypes(
+    matrix: &mut GenotypeMatrix,
+    header: &vcf::Header,
+    record: &dyn Record,
+) -> io::Result<()> {
+    let samples = record.samples()?;
+    let series = samples.select(header, "GT").transpose()?;
+    for i in 0..matrix.sample_count as usize {
+        let value = series
+            .as_ref()
+            .and_then(|series| series.get(header, i))
+            .flatten()
+            .transpose()?;
+        let mut phased = false;
+        if let Some(SampleValue::Genotype(genotype)) = value {
+            // The first allele's phasing is implicit before VCF 4.4, so a
+            // call is phased when it has several alleles and every later
+            // one is phased.
+            let mut n = 0;
+            for allele in genotype.iter() {
+                let (position, phasing) = allele?;
+                phased = if n == 0 {
+                    true
+                } else {
+                    phased && phasing == Phasing::Phased
+                };
+                matrix
+                    .alleles
+                    .push(position.map_or(-1, |p| i32::try_from(p).unwrap_or(i32::MAX)));
+                n += 1;
+            }
+            phased &= n > 1;
+        }
+        matrix.phased.push(u8::from(phased));
+        matrix.allele_offsets.push(matrix.alleles.len() as u32);
+    }
+    Ok(())
+}
+
+/// Output of `VcfWriter::finish_with_index`.
+///
+/// For BGZF output, `tbi` holds the tabix index contents; in file mode it
+/// has also been written next to the output as `<path>.tbi`.
+pub struct VcfWriterOutput {
+    pub data: Option<Vec<u8>>,
+    pub tbi: Option<Vec<u8>>,
+}
+
+/// Stateful VCF batch writer.
+///
+/// Writes the header on open, then one line per record of batches in the
+/// layout `VariantReader` produces. INFO columns must be defined in the
+/// header, and when the header declares samples each batch must carry a
+/// genotype matrix for them, written as a `GT`-only FORMAT column.
+/// Output may be gzip-, BGZF- or zstd-compressed, with gzip and BGZF
+/// optionally compressed in parallel blocks. For BGZF output each
+/// record's interval is noted as it is written, and `finish` builds a
+/// tabix index from them so the output can be opened with
+/// `VariantReader::open_indexed`.
+pub struct VcfWriter {
+    inner: Output,
+    header: vcf::Header,
+    path: Option<String>,
+    line: Vec<u8>,
+    /// Uncompressed bytes written so far.
+    position: u64,
+    /// Intervals to index, for BGZF output only.
+    tabix: Option<TabixRecords>,
+    record: vcf::Record,
+}
+
+/// Tabix entries noted while writing BGZF output.
+#[derive(Default)]
+struct TabixRecords {
+    names: Vec<String>,
+    /// Index into `names`, start, end, and the record's uncompressed span.
+    records: Vec<(usize, Position, Position, u64, u64)>,
+    /// Set once a telomeric record is written; those cannot be indexed.
+    telomeric: bool,
+}
+
+impl TabixRecords {
+    /// Note the interval of the record `line` holds, written at `start`.
+    fn push(
+        &mut self,
+        header: &vcf::Header,
+        record: &mut vcf::Record,
+        line: &[u8],
+        start: u64,
+    ) -> io::Result<()> {
+        vcf::io::Reader::new(line).read_record(record)?;
+        let Some(variant_start) = record.variant_start().transpose()? else {
+            self.telomeric = true;
+            return Ok(());
+        };
+        let variant_end = record.variant_end(header)?;
+        let name = record.reference_sequence_name();
+        if self.names.last().is_none_or(|last| last != name) {
+            self.names.push(name.to_owned());
+        }
+        let end = start + line.len() as u64;
+        self.records
+            .push((self.names.len() - 1, variant_start, variant_end, start, end));
+        Ok(())
+    }
+
+    /// Build the `.tbi` contents, placing records through the output's
+    /// `.gzi`.
+    fn build(&self, gzi: &gzi::Index) -> Result<Vec<u8>, EngineError> {
+        let map_err = |e: io::Error| EngineError::Io(format!("tabix index error: {e}"));
+        if self.telomeric {
+            return Err(EngineError::Io(
+                "tabix index error: telomeric records cannot be indexed".to_owned(),
+            ));
+        }
+        let mut indexer = tabix::index::Indexer::default();
+        indexer.set_header(csi::binning_index::index::header::Builder::vcf().build());
+        for &(name, start, end, chunk_start, chunk_end) in &self.records {
+            let chunk = Chunk::new(
+                gzi.query(chunk_start).map_err(map_err)?,
+                gzi.query(chunk_end).map_err(map_err)?,
+            );
+            indexer
+                .add_record(&self.names[name], start, end, chunk)
+                .map_err(map_err)?;
+        }
+
+        let mut writer = tabix::io::Writer::new(Vec::new());
+        writer.write_index(&indexer.build()).map_err(map_err)?;
+        writer.into_inner().finish().map_err(map_err)
+    }
+}
+
+impl VcfWriter {
+    /// Open a writer to a file path.
+    ///
+    /// `header` is VCF header text, from `##fileformat` through the
+    /// `#CHROM` line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the header cannot be
+    /// parsed or a compression level is out of range, or
+    /// `EngineError::Io` if the file cannot be created.
+    pub fn open_to_path(
+        path: &str,
+        header: &str,
+        compression: CompressionMode,
+    ) -> Result<Self, EngineError> {
+        let header = parse_header(header)?;
+        let inner = Output::open_to_path(path, compression)?;
+        Self::with_header(inner, header, Some(path.to_owned()))
+    }
+
+    /// Open a writer to an in-memory buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if the header cannot be
+    /// parsed or a compression level is out of range.
+    pub fn open_to_bytes(header: &str, compression: CompressionMode) -> Result<Self, EngineError> {
+        let header = parse_header(header)?;
+        let inner = Output::open_to_bytes(compression)?;
+        Self::with_header(inner, header, None)
+    }
+
+    fn with_header(
+        inner: Output,
+        header: vcf::Header,
+        path: Option<String>,
+    ) -> Result<Self, EngineError> {
+        let mut writer = Self {
+            tabix: inner.is_bgzf().then(TabixRecords::default),
+            inner,
+            header,
+            path,
+            line: Vec::new(),
+            position: 0,
+            record: vcf::Record::default(),
+        };
+        let mut text = vcf::io::Writer::new(Vec::new());
+        text.write_header(&writer.header)
+            .map_err(|e| EngineError::InvalidArgument(format!("invalid VCF header: {e}")))?;
+        writer
+            .inner
+            .write_all(text.get_ref())
+            .map_err(|e| EngineError::Io(format!("VCF write error: {e}")))?;
+        writer.position = text.get_ref().len() as u64;
+        Ok(writer)
+    }
+
+    /// Write a batch of records, one VCF line each.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::InvalidArgument` if an INFO column is not
+    /// defined in the header, the genotype matrix does not match the
+    /// header's samples, or a column or its offsets do not match
+    /// `count`, all checked before anything is written, or
+    /// `EngineError::Io` if writing fails.
+    pub fn write_batch(&mut self, batch: &VariantBatch) -> Result<(), EngineError> {
+        self.check_batch(batch)?;
+        for i in 0..batch.count as usize {
+            self.line.clear();
+            format_record(batch, i, &mut self.line)?;
+            if let Some(tabix) = &mut self.tabix {
+                tabix
+                    .push(&self.header, &mut self.record, &self.line, self.position)
+                    .map_err(|e| EngineError::Io(format!("tabix index error: {e}")))?;
+            }
+            self.inner
+                .write_all(&self.line)
+                .map_err(|e| EngineError::Io(format!("VCF write error: {e}")))?;
+            self.position += self.line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn check_batch(&self, batch: &VariantBatch) -> Result<(), EngineError> {
+        if let Some(column) = batch
+            .info
+            .iter()
+            .find(|column| !self.header.infos().contains_key(&column.key))
+        {
+            return Err(EngineError::InvalidArgument(format!(
+                "VcfWriter: INFO field '{}' is not defined in the header",
+                column.key
+            )));
+        }
+        let samples = self.header.sample_names().len();
+        let matrix_samples = batch.genotypes.as_ref().map(|m| m.sample_count as usize);
+        if samples > 0 && matrix_samples != Some(samples)
+            || samples == 0 && matrix_samples > Some(0)
+        {
+            return Err(EngineError::InvalidArgument(format!(
+                "VcfWriter: the header declares {samples} samples but the batch has {}",
+                matrix_samples.map_or_else(|| "no genotypes".to_owned(), |n| format!("{n}")),
+            )));
+        }
+
+        let count = batch.count as usize;
+        check_rows(batch.chrom_data.len(), &batch.chrom_offsets, "chrom", count)?;
+        check_len(batch.positions.len(), "position", count)?;
+        check_rows(batch.id_data.len(), &batch.id_offsets, "id", count)?;
+        check_rows(batch.ref_data.len(), &batch.ref_offsets, "ref", count)?;
+        check_rows(batch.alt_data.len(), &batch.alt_offsets, "alt", count)?;
+        check_len(batch.qualities.len(), "quality", count)?;
+        check_rows(
+            batch.filter_data.len(),
+            &batch.filter_offsets,
+            "filter",
+            count,
+        )?;
+        for column in &batch.info {
+            check_len(column.present.len(), &column.key, count)?;
+            let values = match column.kind {
+                InfoKind::Flag => continue,
+                InfoKind::Int => column.int_values.len(),
+                InfoKind::Float => column.float_values.len(),
+                InfoKind::String => column.string_data.len(),
+            };
+            check_rows(values, &column.offsets, &column.key, count)?;
+        }
+        if let Some(matrix) = &batch.genotypes {
+            let cells = count * matrix.sample_count as usize;
+            check_rows(
+                matrix.alleles.len(),
+                &matrix.allele_offsets,
+                "genotype",
+                cells,
+            )?;
+            check_len(matrix.phased.len(), "phased", cells)?;
+        }
+        Ok(())
+    }
+
+    /// Flush and close the writer.
+    ///
+    /// For file mode, returns `None`. For bytes mode, returns the
+    /// accumulated output bytes. BGZF file output also gets its `.tbi`
+    /// written; use `finish_with_index` to obtain it in bytes mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if flushing or indexing fails.
+    pub fn finish(self) -> Result<Option<Vec<u8>>, EngineError> {
+        self.finish_with_index().map(|output| output.data)
+    }
+
+    /// Flush and close the writer, returning the tabix index for BGZF
+    /// output.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EngineError::Io` if flushing fails or the index cannot be
+    /// built or written.
+    pub fn finish_with_index(self) -> Result<VcfWriterOutput, EngineError> {
+        let (data, gzi) = self.inner.finish_with_gzi()?;
+        let (Some(gzi), Some(tabix)) = (gzi, self.tabix) else {
+            return Ok(VcfWriterOutput { data, tbi: None });
+        };
+        let tbi = tabix.build(&gzi)?;
+        if data.is_none() {
+            let sidecar = format!("{}.tbi", self.path.unwrap_or_default());
+            std::fs::write(&sidecar, &tbi)
+                .map_err(|e| EngineError::Io(format!("failed to write '{sidecar}': {e}")))?;
+        }
+        Ok(VcfWriterOutput {
+            data,
+            tbi: Some(tbi),
+        })
+    }
+}
+
+fn parse_header(text: &str) -> Result<vcf::Header, EngineError> {
+    text.parse()
+        .map_err(|e| EngineError::InvalidArgument(format!("invalid VCF header: {e}")))
+}
+
+/// Check that a per-record column has one value for each of `count`
+/// records.
+fn check_len(len: usize, name: &str, count: usize) -> Result<(), EngineError> {
+    if len == count {
+        return Ok(());
+    }
+    Err(EngineError::InvalidArgument(format!(
+        "VcfWriter: the {name} column has {len} values for {count} records"
+    )))
+}
+
+/// Check that a CSR column has `count` rows, each within its `len`
+/// values.
+fn check_rows(len: usize, offsets: &[u32], name: &str, count: usize) -> Result<(), EngineError> {
+    let valid = offsets.len() == count + 1
+        && offsets.windows(2).all(|w| w[0] <= w[1])
+        && offsets.last().is_some_and(|&end| end as usize <= len);
+    if valid {
+        return Ok(());
+    }
+    Err(EngineError::InvalidArgument(format!(
+        "VcfWriter: the {name} offsets do not describe {count} rows of its {len} values"
+    )))
+}
+
+/// CSR row `i` of a packed column, or an error naming it.
+fn row<'a, T>(
+    values: &'a [T],
+    offsets: &[u32],
+    name: &str,
+    i: usize,
+) -> Result<&'a [T], EngineError> {
+    offsets
+        .get(i)
+        .zip(offsets.get(i + 1))
+        .and_then(|(&start, &end)| values.get(start as usize..end as usize))
+        .ok_or_else(|| {
+            EngineError::InvalidArgument(format!(
+                "VcfWriter: record {i} is out of range of the {name} column"
+            ))
+        })
+}
+
+/// Element `i` of a per-record column, or an error naming it.
+fn value<T: Copy>(values: &[T], name: &str, i: usize) -> Result<T, EngineError> {
+    values.get(i).copied().ok_or_else(|| {
+        EngineError::InvalidArgument(format!(
+            "VcfWriter: record {i} is out of range of the {name} column"
+        ))
+    })
+}
+
+/// Append `bytes`, or `.` if empty.
+fn push_or_missing(out: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.is_empty() {
+        out.push(b'.');
+    } else {
+        out.extend_from_slice(bytes);
+    }
+}
+
+/// Format a float as VCF does, at the single precision VCF and BCF store.
+#[allow(clippy::cast_possible_truncation)]
+fn push_float(out: &mut Vec<u8>, x: f64) {
+    if x.is_nan() {
+        out.push(b'.');
+    } else {
+        out.extend_from_slice((x as f32).to_string().as_bytes());
+    }
+}
+
+/// Format record `i` of `batch` as a VCF line.
+fn format_record(batch: &VariantBatch, i: usize, out: &mut Vec<u8>) -> Result<(), EngineError> {
+    out.extend_from_slice(row(&batch.chrom_data, &batch.chrom_offsets, "chrom", i)?);
+    out.push(b'\t');
+    out.extend_from_slice(
+        value(&batch.positions, "position", i)?
+            .to_string()
+            .as_bytes(),
+    );
+    out.push(b'\t');
+    push_or_missing(out, row(&batch.id_data, &batch.id_offsets, "id", i)?);
+    out.push(b'\t');
+    push_or_missing(out, row(&batch.ref_data, &batch.ref_offsets, "ref", i)?);
+    out.push(b'\t');
+    push_or_missing(out, row(&batch.alt_data, &batch.alt_offsets, "alt", i)?);
+    out.push(b'\t');
+    push_float(out, value(&batch.qualities, "quality", i)?);
+    out.push(b'\t');
+    push_or_missing(
+        out,
+        row(&batch.filter_data, &batch.filter_offsets, "filter", i)?,
+    );
+    out.push(b'\t');
+    format_info(batch, i, out)?;
+    // A sites-only file has no FORMAT column, even with genotypes on.
+    if let Some(matrix) = batch.genotypes.as_ref().filter(|m| m.sample_count > 0) {
+        format_genotypes(matrix, i, out)?;
+    }
+    out.push(b'\n');
+    Ok(())
+}
+
+fn format_info(batch: &VariantBatch, i: usize, out: &mut Vec<u8>) -> Result<(), EngineError> {
+    let start = out.len();
+    for column in &batch.info {
+        if value(&column.present, &column.key, i)? == 0 {
+            continue;
+        }
+        if out.len() > start {
+            out.push(b';');
+        }
+        out.extend_from_slice(column.key.as_bytes());
+        let key = column.key.as_str();
+        match column.kind {
+            InfoKind::Flag => {}
+            InfoKind::Int => {
+                out.push(b'=');
+                let values = row(&column.int_values, &column.offsets, key, i)?;
+                push_list(out, values, |out, &n| {
+                    if n == MISSING_INT {
+                        out.push(b'.');
+                    } else {
+                        out.extend_from_slice(n.to_string().as_bytes());
+                    }
+                });
+            }
+            InfoKind::Float => {
+                out.push(b'=');
+                let values = row(&column.float_values, &column.offsets, key, i)?;
+                push_list(out, values, |out, &x| push_float(out, x));
+            }
+            InfoKind::String => {
+                out.push(b'=');
+                let text = row(&column.string_data, &column.offsets, key, i)?;
+                if text.is_empty() {
+                    out.push(b'.');
+                }
+                // Commas separate values, so only the other reserved
+                // characters are percent-encoded.
+                for &b in text {
+                    if matches!(b, b';' | b'=' | b'%' | b'\t' | b'\r' | b'\n') {
+                        out.extend_from_slice(format!("%{b:02X}").as_bytes());
+                    } else {
+                        out.push(b);
+                    }
+                }
+            }
+        }
+    }
+    if out.len() == start {
+        out.push(b'.');
+    }
+    Ok(())
+}
+
+/// Append comma-separated `values`, or `.` if there are none.
+fn push_list<T>(out: &mut Vec<u8>, values: &[T], mut push: impl FnMut(&mut Vec<u8>, &T)) {
+    if values.is_empty() {
+        out.push(b'.');
+    }
+    for (j, value) in values.iter().enumerate() {
+        if j > 0 {
+            out.push(b',');
+        }
+        push(out, value);
+    }
+}
+
+fn format_genotypes(
+    matrix: &GenotypeMatrix,
+    i: usize,
+    out: &mut Vec<u8>,
+) -> Result<(), EngineError> {
+    out.extend_from_slice(b"\tGT");
+    let samples = matrix.sample_count as usize;
+    for cell in i * samples..(i + 1) * samples {
+        let alleles = row(&matrix.alleles, &matrix.allele_offsets, "genotype", cell)?;
+        let separator = if value(&matrix.phased, "phased", cell)? == 0 {
+            b'/'
+        } else {
+            b'|'
+        };
+        out.push(b'\t');
+        if alleles.is_empty() {
+            out.push(b'.');
+        }
+        for (j, &allele) in alleles.iter().enumerate() {
+            if j > 0 {
+                out.push(separator);
+            }
+            if allele < 0 {
+                out.push(b'.');
+            } else {
+                out.extend_from_slice(allele.to_string().as_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::Write;
+
+    use noodles_vcf::variant::io::Write as _;
+
+    use super::*;
+
+    const VCF: &[u8] = b"##fileformat=VCFv4.3\n\
+##contig=<ID=chr1,length=100000>\n\
+##contig=<ID=chr2,length=100000>\n\
+##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n\
+##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP\">\n\
+##INFO=<ID=GENE,Number=.,Type=String,Description=\"Gene\">\n\
+##FILTER=<ID=q10,Description=\"Low quality\">\n\
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ts1\ts2\n\
+chr1\t100\trs1\tA\tG\t50\tPASS\tDP=12;AF=0.5;DB;GENE=ABC\tGT\t0|1\t1/1\n\
+chr1\t5000\t.\tAC\tA,ACC\t.\tq10\tAF=0.1,.\tGT\t./.\t0/2\n\
+chr2\t995\trs3;rs4\tT\t.\t7.5\t.\tDP=3\tGT\t1\t0\n\
+chr2\t3000\t.\tG\tC\t.\t.\t.\tGT\t0|0\t0|1\n";
+
+    fn text(data: &[u8], offsets: &[u32], i: usize) -> String {
+        String::from_utf8(data[offsets[i] as usize..offsets[i + 1] as usize].to_vec()).unwrap()
+    }
+
+    fn read_all(reader: &mut VariantReader) -> VariantBatch {
+        reader.read_batch(100).unwrap().unwrap()
+    }
+
+    fn configured(reader: &mut VariantReader) {
+        let keys = ["DP", "AF", "DB", "GENE"].map(str::to_owned);
+        reader.set_info_fields(&keys).unwrap();
+        reader.set_genotypes(true);
+    }
+
+    fn bgzf(data: &[u8]) -> Vec<u8> {
+        let mut writer = bgzf::io::Writer::new(Vec::new());
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn reads_fixed_columns_info_and_genotypes() {
+        let mut reader = VariantReader::open_from_bytes(VCF.to_vec()).unwrap();
+        configured(&mut reader);
+        assert_eq!(reader.sample_names(), vec!["s1", "s2"]);
+        let batch = read_all(&mut reader);
+
+        assert_eq!(batch.count, 4);
+        assert_eq!(batch.format, RecordFormat::Vcf);
+        assert_eq!(batch.positions, vec![100, 5000, 995, 3000]);
+        assert_eq!(text(&batch.chrom_data, &batch.chrom_offsets, 2), "chr2");
+        assert_eq!(text(&batch.id_data, &batch.id_offsets, 1), "");
+        assert_eq!(text(&batch.id_data, &batch.id_offsets, 2), "rs3;rs4");
+        assert_eq!(text(&batch.ref_data, &batch.ref_offsets, 1), "AC");
+        assert_eq!(text(&batch.alt_data, &batch.alt_offsets, 1), "A,ACC");
+        assert_eq!(text(&batch.alt_data, &batch.alt_offsets, 2), "");
+        assert!((batch.qualities[2] - 7.5).abs() < f64::EPSILON);
+        assert!(batch.qualities[1].is_nan());
+        assert_eq!(text(&batch.filter_data, &batch.filter_offsets, 0), "PASS");
+        assert_eq!(text(&batch.filter_data, &batch.filter_offsets, 3), "");
+
+        let [dp, af, db, gene] = &batch.info[..] else {
+            panic!("expected four INFO columns");
+        };
+        assert_eq!(dp.kind, InfoKind::Int);
+        assert_eq!(dp.present, vec![1, 0, 1, 0]);
+        assert_eq!(dp.int_values, vec![12, 3]);
+        assert_eq!(dp.offsets, vec![0, 1, 1, 2, 2]);
+        assert_eq!(af.kind, InfoKind::Float);
+        assert_eq!(af.offsets, vec![0, 1, 3, 3, 3]);
+        assert!((af.float_values[1] - 0.1).abs() < 1e-6 && af.float_values[2].is_nan());
+        assert_eq!(db.kind, InfoKind::Flag);
+        assert_eq!(db.present, vec![1, 0, 0, 0]);
+        assert_eq!(text(&gene.string_data, &gene.offsets, 0), "ABC");
+
+        let gt = batch.genotypes.unwrap();
+        assert_eq!(gt.sample_count, 2);
+        assert_eq!(gt.allele_offsets, vec![0, 2, 4, 6, 8, 9, 10, 12, 14]);
+        assert_eq!(gt.alleles, vec![0, 1, 1, 1, -1, -1, 0, 2, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(gt.phased, vec![1, 0, 0, 0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn bcf_and_bgzf_vcf_match_plain_vcf() {
+        let mut plain = VariantReader::open_from_bytes(VCF.to_vec()).unwrap();
+        configured(&mut plain);
+        let expected = read_all(&mut plain);
+
+        let mut reader = vcf::io::Reader::new(VCF);
+        let header = reader.read_header().unwrap();
+        let mut writer = bcf::io::Writer::new(Vec::new());
+        writer.write_variant_header(&header).unwrap();
+        for record in reader.records() {
+            writer
+                .write_variant_record(&header, &record.unwrap())
+                .unwrap();
+        }
+        let mut bgzf_writer = writer.into_inner();
+        bgzf_writer.try_finish().unwrap();
+        let bcf_bytes = bgzf_writer.into_inner();
+
+        for data in [bcf_bytes, bgzf(VCF)] {
+            let mut reader = VariantReader::open_from_bytes(data).unwrap();
+            configured(&mut reader);
+            let batch = read_all(&mut reader);
+            assert_eq!(batch.chrom_data, expected.chrom_data);
+            assert_eq!(batch.positions, expected.positions);
+            assert_eq!(batch.id_data, expected.id_data);
+            assert_eq!(batch.alt_data, expected.alt_data);
+            assert_eq!(batch.filter_data, expected.filter_data);
+            assert_eq!(batch.info[0].int_values, expected.info[0].int_values);
+            assert_eq!(batch.info[2].present, expected.info[2].present);
+            let gt = batch.genotypes.unwrap();
+            let expected_gt = expected.genotypes.as_ref().unwrap();
+            assert_eq!(gt.alleles, expected_gt.alleles);
+            assert_eq!(gt.phased, expected_gt.phased);
+        }
+    }
+
+    #[test]
+    fn tabix_query_returns_only_overlapping_records() {
+        let data = bgzf(VCF);
+        let path = crate::test_dir("variant").join("sample.vcf.gz");
+        std::fs::write(&path, &data).unwrap();
+        let index = vcf::fs::index(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        let mut index_bytes = Vec::new();
+        let mut writer = tabix::io::Writer::new(&mut index_bytes);
+        writer.write_index(&index).unwrap();
+        writer.try_finish().unwrap();
+        drop(writer);
+
+        let mut reader = VariantReader::open_indexed_from_bytes(data, &index_bytes).unwrap();
+        reader.query("chr2:1000-5000").unwrap();
+        let batch = read_all(&mut reader);
+        assert_eq!(batch.positions, vec![3000]);
+        assert!(reader.read_batch(100).unwrap().is_none());
+
+        reader.query("chr1").unwrap();
+        assert_eq!(read_all(&mut reader).positions, vec![100, 5000]);
+        assert!(reader.query("chrX").is_err());
+
+        let mut unindexed = VariantReader::open_from_bytes(VCF.to_vec()).unwrap();
+        assert!(unindexed.query("chr1").is_err());
+        assert!(VariantReader::open_indexed_from_bytes(VCF.to_vec(), &index_bytes).is_err());
+    }
+
+    #[test]
+    fn malformed_records_are_located() {
+        let mut data = VCF.to_vec();
+        data.extend_from_slice(b"chr2\tx\t.\tG\tC\t.\t.\t.\tGT\t0\t0\n");
+        let mut reader = VariantReader::open_from_bytes(data).unwrap();
+        assert_eq!(reader.read_batch(4).unwrap().unwrap().count, 4);
+        let Err(EngineError::Parse(error)) = reader.read_batch(4) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.format, RecordFormat::Vcf);
+        assert_eq!(error.record, 5);
+        assert_eq!(error.line, Some(15));
+
+        assert!(matches!(
+            reader.set_info_fields(&["XX".to_owned()]),
+            Err(EngineError::InvalidArgument(_))
+        ));
+    }
+
+    fn round_trip(compression: CompressionMode) -> (VariantBatch, VcfWriterOutput) {
+        let mut reader = VariantReader::open_from_bytes(VCF.to_vec()).unwrap();
+        configured(&mut reader);
+        let batch = read_all(&mut reader);
+        let mut writer = VcfWriter::open_to_bytes(&reader.header_text(), compression).unwrap();
+        writer.write_batch(&batch).unwrap();
+        (batch, writer.finish_with_index().unwrap())
+    }
+
+    #[test]
+    fn sites_only_files_round_trip_without_a_format_column() {
+        let sites = b"##fileformat=VCFv4.3\n\
+##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr1\t100\trs1\tA\tG\t50\tPASS\tDP=12\n\
+chr2\t3000\t.\tG\tC\t.\t.\t.\n";
+        let mut reader = VariantReader::open_from_bytes(sites.to_vec()).unwrap();
+        reader.set_info_fields(&["DP".to_owned()]).unwrap();
+        reader.set_genotypes(true);
+        let batch = read_all(&mut reader);
+        assert_eq!(batch.genotypes.as_ref().unwrap().sample_count, 0);
+
+        let mut writer =
+            VcfWriter::open_to_bytes(&reader.header_text(), CompressionMode::None).unwrap();
+        writer.write_batch(&batch).unwrap();
+        let text = writer.finish().unwrap().unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            String::from_utf8_lossy(sites)
+        );
+    }
+
+    #[test]
+    fn writer_round_trips_batches() {
+        let (expected, output) = round_trip(CompressionMode::None);
+        assert!(output.tbi.is_none());
+        let text = output.data.unwrap();
+        let lines: Vec<&[u8]> = text.split(|&b| b == b'\n').collect();
+        assert_eq!(
+            lines[lines.len() - 4],
+            b"chr1\t5000\t.\tAC\tA,ACC\t.\tq10\tAF=0.1,.\tGT\t./.\t0/2"
+        );
+
+        let mut reader = VariantReader::open_from_bytes(text).unwrap();
+        configured(&mut reader);
+        let batch = read_all(&mut reader);
+        assert_eq!(batch.chrom_data, expected.chrom_data);
+        assert_eq!(batch.positions, expected.positions);
+        assert_eq!(batch.id_offsets, expected.id_offsets);
+        assert_eq!(batch.alt_data, expected.alt_data);
+        assert_eq!(batch.filter_data, expected.filter_data);
+        for (column, expected) in batch.info.iter().zip(&expected.info) {
+            assert_eq!(column.present, expected.present);
+            assert_eq!(column.offsets, expected.offsets);
+            assert_eq!(column.int_values, expected.int_values);
+            assert_eq!(column.string_data, expected.string_data);
+        }
+        let gt = batch.genotypes.unwrap();
+        assert_eq!(gt.alleles, expected.genotypes.as_ref().unwrap().alleles);
+        assert_eq!(gt.phased, expected.genotypes.as_ref().unwrap().phased);
+    }
+
+    #[test]
+    fn bgzf_writer_output_is_tabix_indexed() {
+        for compression in [CompressionMode::Bgzf, CompressionMode::ParallelBgzf(6)] {
+            let (_, output) = round_trip(compression);
+            let (data, tbi) = (output.data.unwrap(), output.tbi.unwrap());
+            let mut reader = VariantReader::open_indexed_from_bytes(data, &tbi).unwrap();
+            reader.query("chr2:1000-5000").unwrap();
+            assert_eq!(read_all(&mut reader).positions, vec![3000]);
+        }
+        let (_, output) = round_trip(CompressionMode::Gzip);
+        assert!(output.tbi.is_none());
+    }
+
+    #[test]
+    fn writer_rejects_batches_that_do_not_match_the_header() {
+        let header = "##fileformat=VCFv4.3\n\
+##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+        let mut reader = VariantReader::open_from_bytes(VCF.to_vec()).unwrap();
+        configured(&mut reader);
+        let mut batch = read_all(&mut reader);
+
+        let mut writer = VcfWriter::open_to_bytes(header, CompressionMode::None).unwrap();
+        assert!(matches!(
+            writer.write_batch(&batch),
+            Err(EngineError::InvalidArgument(_))
+        ));
+        batch.info.truncate(1);
+        assert!(writer.write_batch(&batch).is_err());
+        batch.genotypes = None;
+        // A short column late in the batch must not leave the records
+        // before it written.
+        let quality = batch.qualities.pop().unwrap();
+        assert!(writer.write_batch(&batch).is_err());
+        batch.qualities.push(quality);
+        let end = batch.alt_offsets.pop().unwrap();
+        assert!(writer.write_batch(&batch).is_err());
+        batch.alt_offsets.push(end);
+        batch.info[0].present.pop();
+        assert!(writer.write_batch(&batch).is_err());
+        batch.info[0].present.push(0);
+        writer.write_batch(&batch).unwrap();
+        let text = writer.finish().unwrap().unwrap();
+        let expected = format!(
+            "{header}\
+chr1\t100\trs1\tA\tG\t50\tPASS\tDP=12\n\
+chr1\t5000\t.\tAC\tA,ACC\t.\tq10\t.\n\
+chr2\t995\trs3;rs4\tT\t.\t7.5\t.\tDP=3\n\
+chr2\t3000\t.\tG\tC\t.\t.\t.\n"
+        );
+        assert_eq!(String::from_utf8(text).unwrap(), expected);
+        assert!(VcfWriter::open_to_bytes("#CHROM\tPOS\n", CompressionMode::None).is_err());
+    }
+}
@@ -1,9 +1,11 @@
-//! Napi wrapper for the engine's alignment reader.
+//! Napi wrapper for the engine's alignment reader and writer.
 
 use genotype_engine as engine;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+use crate::{checkpoint_arg, reader_err, saturating_i64, BytesConsumed};
+
 #[napi(object)]
 pub struct ReferenceSequenceInfo {
     pub name: String,
@@ -24,9 +26,64 @@ pub struct AlignmentBatch {
     pub cigar_offsets: Vec<u32>,
     pub rname_data: Buffer,
     pub rname_offsets: Vec<u32>,
+    pub rnext_data: Buffer,
+    pub rnext_offsets: Vec<u32>,
     pub flags: Vec<u16>,
     pub positions: Vec<i32>,
     pub mapping_qualities: Buffer,
+    pub next_positions: Vec<i32>,
+    pub template_lengths: Vec<i32>,
+    pub tags: Vec<AuxTagColumn>,
+    pub raw_tag_data: Buffer,
+    pub raw_tag_offsets: Vec<u32>,
+}
+
+#[napi(string_enum = "snake_case")]
+pub enum AuxTagKind {
+    Int,
+    Float,
+    String,
+    IntArray,
+    FloatArray,
+}
+
+impl From<engine::alignment::AuxTagKind> for AuxTagKind {
+    fn from(kind: engine::alignment::AuxTagKind) -> Self {
+        match kind {
+            engine::alignment::AuxTagKind::Int => Self::Int,
+            engine::alignment::AuxTagKind::Float => Self::Float,
+            engine::alignment::AuxTagKind::String => Self::String,
+            engine::alignment::AuxTagKind::IntArray => Self::IntArray,
+            engine::alignment::AuxTagKind::FloatArray => Self::FloatArray,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct AuxTagColumn {
+    pub tag: String,
+    pub kind: AuxTagKind,
+    pub present: Buffer,
+    pub int_values: Vec<i64>,
+    pub float_values: Vec<f64>,
+    pub string_data: Buffer,
+    pub string_offsets: Vec<u32>,
+    pub array_offsets: Vec<u32>,
+}
+
+impl From<engine::alignment::AuxTagColumn> for AuxTagColumn {
+    fn from(c: engine::alignment::AuxTagColumn) -> Self {
+        Self {
+            tag: c.tag,
+            kind: c.kind.into(),
+            present: c.present.into(),
+            int_values: c.int_values,
+            float_values: c.float_values,
+            string_data: c.string_data.into(),
+            string_offsets: c.string_offsets,
+            array_offsets: c.array_offsets,
+        }
+    }
 }
 
 impl From<engine::alignment::AlignmentBatch> for AlignmentBatch {
@@ -44,9 +101,31 @@ impl From<engine::alignment::AlignmentBatch> for AlignmentBatch {
             cigar_offsets: b.cigar_offsets,
             rname_data: b.rname_data.into(),
             rname_offsets: b.rname_offsets,
+            rnext_data: b.rnext_data.into(),
+            rnext_offsets: b.rnext_offsets,
             flags: b.flags,
             positions: b.positions,
             mapping_qualities: b.mapping_qualities.into(),
+            next_positions: b.next_positions,
+            template_lengths: b.template_lengths,
+            tags: b.tags.into_iter().map(Into::into).collect(),
+            raw_tag_data: b.raw_tag_data.into(),
+            raw_tag_offsets: b.raw_tag_offsets,
+        }
+    }
+}
+
+#[napi(string_enum = "lowercase")]
+pub enum AlignmentFormat {
+    Bam,
+    Sam,
+}
+
+impl From<AlignmentFormat> for engine::alignment::AlignmentOutputFormat {
+    fn from(format: AlignmentFormat) -> Self {
+        match format {
+            AlignmentFormat::Bam => Self::Bam,
+            AlignmentFormat::Sam => Self::Sam,
         }
     }
 }
@@ -65,25 +144,134 @@ fn engine_err(e: engine::EngineError) -> napi::Error {
 impl AlignmentReader {
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open(path: String) -> napi::Result<Self> {
-        let inner =
-            engine::alignment::AlignmentReader::open_from_path(&path).map_err(engine_err)?;
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner = engine::alignment::AlignmentReader::open_from_path(&path)
+            .map_err(|e| reader_err(env, e))?;
         Ok(Self { inner })
     }
 
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open_bytes(data: Buffer) -> napi::Result<Self> {
+    pub fn open_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
         let inner = engine::alignment::AlignmentReader::open_from_bytes(data.to_vec())
-            .map_err(engine_err)?;
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_at(env: Env, path: String, checkpoint: Buffer) -> napi::Result<Self> {
+        let checkpoint = checkpoint_arg(&checkpoint)?;
+        let inner = engine::alignment::AlignmentReader::open_from_path_at(&path, &checkpoint)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes_at(env: Env, data: Buffer, checkpoint: Buffer) -> napi::Result<Self> {
+        let checkpoint = checkpoint_arg(&checkpoint)?;
+        let inner =
+            engine::alignment::AlignmentReader::open_from_bytes_at(data.to_vec(), &checkpoint)
+                .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_cram(env: Env, path: String, reference_path: String) -> napi::Result<Self> {
+        let inner = engine::alignment::AlignmentReader::open_cram(&path, &reference_path)
+            .map_err(|e| reader_err(env, e))?;
         Ok(Self { inner })
     }
 
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_cram_bytes(
+        env: Env,
+        data: Buffer,
+        reference: Buffer,
+        reference_index: Buffer,
+    ) -> napi::Result<Self> {
+        let inner = engine::alignment::AlignmentReader::open_cram_from_bytes(
+            data.to_vec(),
+            reference.to_vec(),
+            &reference_index,
+        )
+        .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_indexed(env: Env, path: String, index_path: String) -> napi::Result<Self> {
+        let inner = engine::alignment::AlignmentReader::open_indexed(&path, &index_path)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_indexed_bytes(env: Env, data: Buffer, index: Buffer) -> napi::Result<Self> {
+        let inner =
+            engine::alignment::AlignmentReader::open_indexed_from_bytes(data.to_vec(), &index)
+                .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn query(&mut self, region: String) -> napi::Result<()> {
+        self.inner.query(&region).map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn set_tag_options(&mut self, tags: Vec<String>, raw: bool) -> napi::Result<()> {
+        self.inner
+            .set_tag_options(&engine::alignment::AuxTagOptions { tags, raw })
+            .map_err(engine_err)
+    }
+
     #[napi]
-    pub fn read_batch(&mut self, max_records: u32) -> napi::Result<Option<AlignmentBatch>> {
+    pub fn read_batch(
+        &mut self,
+        env: Env,
+        max_records: u32,
+    ) -> napi::Result<Option<AlignmentBatch>> {
         self.inner
             .read_batch(max_records)
             .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn read_batch_bounded(
+        &mut self,
+        env: Env,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> napi::Result<Option<AlignmentBatch>> {
+        self.inner
+            .read_batch_bounded(max_records, max_bytes)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+
+    #[napi]
+    pub fn checkpoint(&self) -> napi::Result<Buffer> {
+        self.inner
+            .checkpoint()
+            .map(|c| c.to_bytes().into())
             .map_err(engine_err)
     }
 
@@ -104,3 +292,131 @@ impl AlignmentReader {
             .collect()
     }
 }
+
+#[napi]
+pub struct PrefetchingAlignmentReader {
+    inner: engine::prefetch::Prefetcher<engine::alignment::AlignmentReader>,
+}
+
+#[napi]
+impl PrefetchingAlignmentReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String, max_records: u32, depth: u32) -> napi::Result<Self> {
+        let reader = engine::alignment::AlignmentReader::open_from_path(&path)
+            .map_err(|e| reader_err(env, e))?;
+        let inner =
+            engine::prefetch::Prefetcher::spawn(reader, max_records, depth).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, data: Buffer, max_records: u32, depth: u32) -> napi::Result<Self> {
+        let reader = engine::alignment::AlignmentReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        let inner =
+            engine::prefetch::Prefetcher::spawn(reader, max_records, depth).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env) -> napi::Result<Option<AlignmentBatch>> {
+        self.inner
+            .read_batch()
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+}
+
+#[napi]
+pub struct AlignmentWriter {
+    inner: Option<engine::alignment::AlignmentWriter>,
+}
+
+#[napi]
+impl AlignmentWriter {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(path: String, header_text: String, format: AlignmentFormat) -> napi::Result<Self> {
+        let inner =
+            engine::alignment::AlignmentWriter::open_to_path(&path, &header_text, format.into())
+                .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(header_text: String, format: AlignmentFormat) -> napi::Result<Self> {
+        let inner = engine::alignment::AlignmentWriter::open_to_bytes(&header_text, format.into())
+            .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    #[napi]
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_batch(
+        &mut self,
+        qname_data: &[u8],
+        qname_offsets: &[u32],
+        sequence_data: &[u8],
+        sequence_offsets: &[u32],
+        quality_data: &[u8],
+        quality_offsets: &[u32],
+        cigar_data: &[u8],
+        cigar_offsets: &[u32],
+        rname_data: &[u8],
+        rname_offsets: &[u32],
+        rnext_data: &[u8],
+        rnext_offsets: &[u32],
+        flags: Vec<u16>,
+        positions: Vec<i32>,
+        mapping_qualities: &[u8],
+        next_positions: Vec<i32>,
+        template_lengths: Vec<i32>,
+        raw_tag_data: &[u8],
+        raw_tag_offsets: &[u32],
+        count: u32,
+    ) -> napi::Result<()> {
+        let w = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        let batch = engine::alignment::AlignmentBatch {
+            count,
+            format: "",
+            qname_data: qname_data.to_vec(),
+            qname_offsets: qname_offsets.to_vec(),
+            sequence_data: sequence_data.to_vec(),
+            sequence_offsets: sequence_offsets.to_vec(),
+            quality_data: quality_data.to_vec(),
+            quality_offsets: quality_offsets.to_vec(),
+            cigar_data: cigar_data.to_vec(),
+            cigar_offsets: cigar_offsets.to_vec(),
+            rname_data: rname_data.to_vec(),
+            rname_offsets: rname_offsets.to_vec(),
+            rnext_data: rnext_data.to_vec(),
+            rnext_offsets: rnext_offsets.to_vec(),
+            flags,
+            positions,
+            mapping_qualities: mapping_qualities.to_vec(),
+            next_positions,
+            template_lengths,
+            tags: Vec::new(),
+            raw_tag_data: raw_tag_data.to_vec(),
+            raw_tag_offsets: raw_tag_offsets.to_vec(),
+        };
+        w.write_batch(&batch).map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn finish(&mut self) -> napi::Result<Option<Buffer>> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        w.finish()
+            .map(|opt| opt.map(Into::into))
+            .map_err(engine_err)
+    }
+}
@@ -0,0 +1,174 @@
+//! Conversions for the engine's Arrow IPC export.
+
+use genotype_engine as engine;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{
+    alignment::{AlignmentBatch, AuxTagColumn, AuxTagKind},
+    engine_err,
+    fasta::FastaBatch,
+    fastq::FastqBatch,
+    SequenceMetricsResult,
+};
+
+/// Serialize a converted batch, with `metrics` appended as extra columns.
+fn to_ipc(
+    mut table: engine::arrow::ArrowTable,
+    format: ArrowIpcFormat,
+    metrics: Option<SequenceMetricsResult>,
+) -> napi::Result<Buffer> {
+    if let Some(metrics) = metrics {
+        table.append_metrics(metrics.into()).map_err(engine_err)?;
+    }
+    table
+        .to_ipc(format.into())
+        .map(Into::into)
+        .map_err(engine_err)
+}
+
+#[napi]
+pub fn fastq_batch_to_arrow(
+    batch: FastqBatch,
+    format: ArrowIpcFormat,
+    metrics: Option<SequenceMetricsResult>,
+) -> napi::Result<Buffer> {
+    let table = engine::arrow::ArrowTable::from_fastq(batch.into()).map_err(engine_err)?;
+    to_ipc(table, format, metrics)
+}
+
+#[napi]
+pub fn fasta_batch_to_arrow(
+    batch: FastaBatch,
+    format: ArrowIpcFormat,
+    metrics: Option<SequenceMetricsResult>,
+) -> napi::Result<Buffer> {
+    let table = engine::arrow::ArrowTable::from_fasta(batch.into()).map_err(engine_err)?;
+    to_ipc(table, format, metrics)
+}
+
+#[napi]
+pub fn alignment_batch_to_arrow(
+    batch: AlignmentBatch,
+    format: ArrowIpcFormat,
+    metrics: Option<SequenceMetricsResult>,
+) -> napi::Result<Buffer> {
+    let table = engine::arrow::ArrowTable::from_alignment(batch.into()).map_err(engine_err)?;
+    to_ipc(table, format, metrics)
+}
+
+#[napi(string_enum = "lowercase")]
+pub enum ArrowIpcFormat {
+    Stream,
+    File,
+}
+
+impl From<ArrowIpcFormat> for engine::arrow::IpcFormat {
+    fn from(format: ArrowIpcFormat) -> Self {
+        match format {
+            ArrowIpcFormat::Stream => Self::Stream,
+            ArrowIpcFormat::File => Self::File,
+        }
+    }
+}
+
+impl From<SequenceMetricsResult> for engine::metrics::SequenceMetricsResult {
+    fn from(r: SequenceMetricsResult) -> Self {
+        Self {
+            lengths: r.lengths,
+            gc: r.gc,
+            at: r.at,
+            gc_skew: r.gc_skew,
+            at_skew: r.at_skew,
+            entropy: r.entropy,
+            alphabet_mask: r.alphabet_mask,
+            avg_qual: r.avg_qual,
+            min_qual: r.min_qual,
+            max_qual: r.max_qual,
+        }
+    }
+}
+
+impl From<AuxTagColumn> for engine::alignment::AuxTagColumn {
+    fn from(c: AuxTagColumn) -> Self {
+        Self {
+            tag: c.tag,
+            kind: match c.kind {
+                AuxTagKind::Int => engine::alignment::AuxTagKind::Int,
+                AuxTagKind::Float => engine::alignment::AuxTagKind::Float,
+                AuxTagKind::String => engine::alignment::AuxTagKind::String,
+                AuxTagKind::IntArray => engine::alignment::AuxTagKind::IntArray,
+                AuxTagKind::FloatArray => engine::alignment::AuxTagKind::FloatArray,
+            },
+            present: c.present.into(),
+            int_values: c.int_values,
+            float_values: c.float_values,
+            string_data: c.string_data.into(),
+            string_offsets: c.string_offsets,
+            array_offsets: c.array_offsets,
+        }
+    }
+}
+
+impl From<FastqBatch> for engine::fastq::FastqBatch {
+    fn from(batch: FastqBatch) -> Self {
+        Self {
+            count: batch.count,
+            name_data: batch.name_data.into(),
+            name_offsets: batch.name_offsets,
+            description_data: batch.description_data.into(),
+            description_offsets: batch.description_offsets,
+            sequence_data: batch.sequence_data.into(),
+            sequence_offsets: batch.sequence_offsets,
+            quality_data: batch.quality_data.into(),
+            quality_offsets: batch.quality_offsets,
+        }
+    }
+}
+
+impl From<FastaBatch> for engine::fasta::FastaBatch {
+    fn from(batch: FastaBatch) -> Self {
+        Self {
+            count: batch.count,
+            name_data: batch.name_data.into(),
+            name_offsets: batch.name_offsets,
+            description_data: batch.description_data.into(),
+            description_offsets: batch.description_offsets,
+            sequence_data: batch.sequence_data.into(),
+            sequence_offsets: batch.sequence_offsets,
+        }
+    }
+}
+
+impl From<AlignmentBatch> for engine::alignment::AlignmentBatch {
+    fn from(batch: AlignmentBatch) -> Self {
+        Self {
+            count: batch.count,
+            format: match batch.format.as_str() {
+                "bam" => "bam",
+                "cram" => "cram",
+                _ => "sam",
+            },
+            qname_data: batch.qname_data.into(),
+            qname_offsets: batch.qname_offsets,
+            sequence_data: batch.sequence_data.into(),
+            sequence_offsets: batch.sequence_offsets,
+            quality_data: batch.quality_data.into(),
+            quality_offsets: batch.quality_offsets,
+            cigar_data: batch.cigar_data.into(),
+            cigar_offsets: batch.cigar_offsets,
+            rname_data: batch.rname_data.into(),
+            rname_offsets: batch.rname_offsets,
+            rnext_data: batch.rnext_data.into(),
+            rnext_offsets: batch.rnext_offsets,
+            flags: batch.flags,
+            positions: batch.positions,
+            mapping_qualities: batch.mapping_qualities.into(),
+            next_positions: batch.next_positions,
+            template_lengths: batch.template_lengths,
+            tags: batch.tags.into_iter().map(Into::into).collect(),
+            raw_tag_data: batch.raw_tag_data.into(),
+            raw_tag_offsets: batch.raw_tag_offsets,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Napi wrapper for the engine's BED reader and writer.
+
+use genotype_engine as engine;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{
+    compression_mode, engine_err, reader_err, saturating_i64, BytesConsumed, CompressionMode,
+};
+
+#[napi(object)]
+pub struct BedBatch {
+    pub count: u32,
+    pub field_counts: Buffer,
+    pub chrom_data: Buffer,
+    pub chrom_offsets: Vec<u32>,
+    pub starts: Vec<i64>,
+    pub ends: Vec<i64>,
+    pub name_data: Option<Buffer>,
+    pub name_offsets: Option<Vec<u32>>,
+    pub scores: Option<Vec<f64>>,
+    pub strands: Option<Buffer>,
+    pub thick_starts: Option<Vec<i64>>,
+    pub thick_ends: Option<Vec<i64>>,
+    pub item_rgbs: Option<Vec<u32>>,
+    pub block_offsets: Option<Vec<u32>>,
+    pub block_sizes: Option<Vec<u32>>,
+    pub block_starts: Option<Vec<u32>>,
+}
+
+fn to_i64(values: Vec<u64>) -> Vec<i64> {
+    values.into_iter().map(saturating_i64).collect()
+}
+
+fn to_u64(values: &[i64], name: &str) -> napi::Result<Vec<u64>> {
+    values
+        .iter()
+        .map(|&v| {
+            u64::try_from(v).map_err(|_| {
+                napi::Error::from_reason(format!("BedWriter: {name} must be non-negative"))
+            })
+        })
+        .collect()
+}
+
+impl From<engine::bed::BedBatch> for BedBatch {
+    fn from(b: engine::bed::BedBatch) -> Self {
+        Self {
+            count: b.count,
+            field_counts: b.field_counts.into(),
+            chrom_data: b.chrom_data.into(),
+            chrom_offsets: b.chrom_offsets,
+            starts: to_i64(b.starts),
+            ends: to_i64(b.ends),
+            name_data: b.name_data.map(Into::into),
+            name_offsets: b.name_offsets,
+            scores: b.scores,
+            strands: b.strands.map(Into::into),
+            thick_starts: b.thick_starts.map(to_i64),
+            thick_ends: b.thick_ends.map(to_i64),
+            item_rgbs: b.item_rgbs,
+            block_offsets: b.block_offsets,
+            block_sizes: b.block_sizes,
+            block_starts: b.block_starts,
+        }
+    }
+}
+
+impl TryFrom<&BedBatch> for engine::bed::BedBatch {
+    type Error = napi::Error;
+
+    fn try_from(b: &BedBatch) -> napi::Result<Self> {
+        Ok(Self {
+            count: b.count,
+            field_counts: b.field_counts.to_vec(),
+            chrom_data: b.chrom_data.to_vec(),
+            chrom_offsets: b.chrom_offsets.clone(),
+            starts: to_u64(&b.starts, "starts")?,
+            ends: to_u64(&b.ends, "ends")?,
+            name_data: b.name_data.as_ref().map(|d| d.to_vec()),
+            name_offsets: b.name_offsets.clone(),
+            scores: b.scores.clone(),
+            strands: b.strands.as_ref().map(|d| d.to_vec()),
+            thick_starts: b
+                .thick_starts
+                .as_deref()
+                .map(|v| to_u64(v, "thickStarts"))
+                .transpose()?,
+            thick_ends: b
+                .thick_ends
+                .as_deref()
+                .map(|v| to_u64(v, "thickEnds"))
+                .transpose()?,
+            item_rgbs: b.item_rgbs.clone(),
+            block_offsets: b.block_offsets.clone(),
+            block_sizes: b.block_sizes.clone(),
+            block_starts: b.block_starts.clone(),
+        })
+    }
+}
+
+#[napi]
+pub struct BedReader {
+    inner: engine::bed::BedReader,
+}
+
+#[napi]
+impl BedReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner =
+            engine::bed::BedReader::open_from_path(&path).map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
+        let inner = engine::bed::BedReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<BedBatch>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+}
+
+#[napi]
+pub struct BedWriter {
+    inner: Option<engine::bed::BedWriter>,
+}
+
+#[napi]
+impl BedWriter {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(
+        path: String,
+        compression: CompressionMode,
+        level: Option<i32>,
+    ) -> napi::Result<Self> {
+        let inner =
+            engine::bed::BedWriter::open_to_path(&path, compression_mode(compression, level))
+                .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    #[napi(factory)]
+    pub fn open_bytes(compression: CompressionMode, level: Option<i32>) -> napi::Result<Self> {
+        let inner = engine::bed::BedWriter::open_to_bytes(compression_mode(compression, level))
+            .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn write_batch(&mut self, batch: BedBatch) -> napi::Result<()> {
+        let w = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        let batch = engine::bed::BedBatch::try_from(&batch)?;
+        w.write_batch(&batch).map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn finish(&mut self) -> napi::Result<Option<Buffer>> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        w.finish()
+            .map(|opt| opt.map(Into::into))
+            .map_err(engine_err)
+    }
+}
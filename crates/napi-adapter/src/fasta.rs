@@ -1,9 +1,13 @@
-//! Napi wrapper for the engine's FASTA reader.
+//! Napi wrapper for the engine's FASTA reader, writer and index.
 
 use genotype_engine as engine;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+use crate::{
+    checkpoint_arg, compression_mode, reader_err, saturating_i64, BytesConsumed, CompressionMode,
+};
+
 #[napi(object)]
 pub struct FastaBatch {
     pub count: u32,
@@ -29,6 +33,34 @@ impl From<engine::fasta::FastaBatch> for FastaBatch {
     }
 }
 
+#[napi(object)]
+pub struct FastaSequenceChunkBatch {
+    pub count: u32,
+    pub record_indices: Vec<i64>,
+    pub chunk_starts: Vec<i64>,
+    pub is_last: Buffer,
+    pub name_data: Buffer,
+    pub name_offsets: Vec<u32>,
+    pub sequence_data: Buffer,
+    pub sequence_offsets: Vec<u32>,
+}
+
+impl From<engine::fasta::FastaSequenceChunkBatch> for FastaSequenceChunkBatch {
+    #[allow(clippy::cast_possible_wrap)]
+    fn from(b: engine::fasta::FastaSequenceChunkBatch) -> Self {
+        Self {
+            count: b.count,
+            record_indices: b.record_indices.into_iter().map(|i| i as i64).collect(),
+            chunk_starts: b.chunk_starts.into_iter().map(|s| s as i64).collect(),
+            is_last: b.is_last.into(),
+            name_data: b.name_data.into(),
+            name_offsets: b.name_offsets,
+            sequence_data: b.sequence_data.into(),
+            sequence_offsets: b.sequence_offsets,
+        }
+    }
+}
+
 #[napi]
 pub struct FastaReader {
     inner: engine::fasta::FastaReader,
@@ -43,28 +75,192 @@ fn engine_err(e: engine::EngineError) -> napi::Error {
 impl FastaReader {
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open(path: String) -> napi::Result<Self> {
-        let inner = engine::fasta::FastaReader::open_from_path(&path).map_err(engine_err)?;
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner =
+            engine::fasta::FastaReader::open_from_path(&path).map_err(|e| reader_err(env, e))?;
         Ok(Self { inner })
     }
 
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open_bytes(data: Buffer) -> napi::Result<Self> {
-        let inner =
-            engine::fasta::FastaReader::open_from_bytes(data.to_vec()).map_err(engine_err)?;
+    pub fn open_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
+        let inner = engine::fasta::FastaReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_at(env: Env, path: String, checkpoint: Buffer) -> napi::Result<Self> {
+        let checkpoint = checkpoint_arg(&checkpoint)?;
+        let inner = engine::fasta::FastaReader::open_from_path_at(&path, &checkpoint)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes_at(env: Env, data: Buffer, checkpoint: Buffer) -> napi::Result<Self> {
+        let checkpoint = checkpoint_arg(&checkpoint)?;
+        let inner = engine::fasta::FastaReader::open_from_bytes_at(data.to_vec(), &checkpoint)
+            .map_err(|e| reader_err(env, e))?;
         Ok(Self { inner })
     }
 
     #[napi]
-    pub fn read_batch(&mut self, max_records: u32) -> napi::Result<Option<FastaBatch>> {
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<FastaBatch>> {
         self.inner
             .read_batch(max_records)
             .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn read_batch_bounded(
+        &mut self,
+        env: Env,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> napi::Result<Option<FastaBatch>> {
+        self.inner
+            .read_batch_bounded(max_records, max_bytes)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn read_sequence_chunks(
+        &mut self,
+        env: Env,
+        max_chunks: u32,
+        chunk_size: u32,
+    ) -> napi::Result<Option<FastaSequenceChunkBatch>> {
+        self.inner
+            .read_sequence_chunks(max_chunks, chunk_size)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+
+    #[napi]
+    pub fn checkpoint(&self) -> napi::Result<Buffer> {
+        self.inner
+            .checkpoint()
+            .map(|c| c.to_bytes().into())
             .map_err(engine_err)
     }
 }
 
+#[napi]
+pub struct PrefetchingFastaReader {
+    inner: engine::prefetch::Prefetcher<engine::fasta::FastaReader>,
+}
+
+#[napi]
+impl PrefetchingFastaReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String, max_records: u32, depth: u32) -> napi::Result<Self> {
+        let reader =
+            engine::fasta::FastaReader::open_from_path(&path).map_err(|e| reader_err(env, e))?;
+        let inner =
+            engine::prefetch::Prefetcher::spawn(reader, max_records, depth).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, data: Buffer, max_records: u32, depth: u32) -> napi::Result<Self> {
+        let reader = engine::fasta::FastaReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        let inner =
+            engine::prefetch::Prefetcher::spawn(reader, max_records, depth).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env) -> napi::Result<Option<FastaBatch>> {
+        self.inner
+            .read_batch()
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+}
+
+#[napi]
+pub struct MappedFastaReader {
+    inner: engine::mapped::MappedFastaReader,
+}
+
+#[napi]
+impl MappedFastaReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner =
+            engine::mapped::MappedFastaReader::open(&path).map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<FastaBatch>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(|batch| batch.to_batch().into()))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> i64 {
+        saturating_i64(self.inner.bytes_consumed())
+    }
+}
+
+#[napi]
+pub struct FastaChunkReader {
+    inner: engine::fasta::FastaChunkReader,
+}
+
+#[napi]
+impl FastaChunkReader {
+    #[napi(factory)]
+    pub fn create() -> Self {
+        Self {
+            inner: engine::fasta::FastaChunkReader::new(),
+        }
+    }
+
+    #[napi]
+    pub fn push_chunk(&mut self, env: Env, chunk: &[u8]) -> napi::Result<Option<FastaBatch>> {
+        self.inner
+            .push_chunk(chunk)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn end(&mut self, env: Env) -> napi::Result<Option<FastaBatch>> {
+        self.inner
+            .end()
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+}
+
 #[napi]
 pub struct FastaWriter {
     inner: Option<engine::fasta::FastaWriter>,
@@ -74,19 +270,33 @@ pub struct FastaWriter {
 impl FastaWriter {
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open(path: String, compress: bool, line_width: u32) -> napi::Result<Self> {
-        let inner = engine::fasta::FastaWriter::open_to_path(&path, compress, line_width)
-            .map_err(engine_err)?;
+    pub fn open(
+        path: String,
+        compression: CompressionMode,
+        line_width: u32,
+        level: Option<i32>,
+    ) -> napi::Result<Self> {
+        let inner = engine::fasta::FastaWriter::open_to_path(
+            &path,
+            compression_mode(compression, level),
+            line_width,
+        )
+        .map_err(engine_err)?;
         Ok(Self { inner: Some(inner) })
     }
 
     #[napi(factory)]
-    pub fn open_bytes(compress: bool, line_width: u32) -> Self {
-        Self {
-            inner: Some(engine::fasta::FastaWriter::open_to_bytes(
-                compress, line_width,
-            )),
-        }
+    pub fn open_bytes(
+        compression: CompressionMode,
+        line_width: u32,
+        level: Option<i32>,
+    ) -> napi::Result<Self> {
+        let inner = engine::fasta::FastaWriter::open_to_bytes(
+            compression_mode(compression, level),
+            line_width,
+        )
+        .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
     }
 
     #[napi]
@@ -127,4 +337,112 @@ impl FastaWriter {
             .map(|opt| opt.map(Into::into))
             .map_err(engine_err)
     }
+
+    #[napi]
+    pub fn finish_with_sidecars(&mut self) -> napi::Result<FastaWriterOutput> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        let output = w.finish_with_sidecars().map_err(engine_err)?;
+        Ok(FastaWriterOutput {
+            data: output.data.map(Into::into),
+            fai: output.fai.map(Into::into),
+            gzi: output.gzi.map(Into::into),
+        })
+    }
+}
+
+#[napi(object)]
+pub struct FastaWriterOutput {
+    pub data: Option<Buffer>,
+    pub fai: Option<Buffer>,
+    pub gzi: Option<Buffer>,
+}
+
+#[napi(object)]
+pub struct FastaIndexEntry {
+    pub name: String,
+    pub length: f64,
+    pub offset: f64,
+    pub line_bases: f64,
+    pub line_width: f64,
+}
+
+#[napi]
+pub struct FastaIndex {
+    inner: engine::fasta::FastaIndex,
+}
+
+#[napi]
+impl FastaIndex {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn build(path: String) -> napi::Result<Self> {
+        let inner = engine::fasta::FastaIndex::build_from_path(&path).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn build_bytes(data: Buffer) -> napi::Result<Self> {
+        let inner =
+            engine::fasta::FastaIndex::build_from_bytes(data.to_vec()).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn load(path: String, index_path: String) -> napi::Result<Self> {
+        let inner = engine::fasta::FastaIndex::load(&path, &index_path).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn load_bytes(data: Buffer, index: Buffer) -> napi::Result<Self> {
+        let inner = engine::fasta::FastaIndex::load_from_bytes(data.to_vec(), &index)
+            .map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn entries(&self) -> Vec<FastaIndexEntry> {
+        self.inner
+            .entries()
+            .into_iter()
+            .map(|e| FastaIndexEntry {
+                name: e.name,
+                length: e.length as f64,
+                offset: e.offset as f64,
+                line_bases: e.line_bases as f64,
+                line_width: e.line_width as f64,
+            })
+            .collect()
+    }
+
+    #[napi]
+    pub fn index_bytes(&self) -> napi::Result<Buffer> {
+        self.inner.index_bytes().map(Into::into).map_err(engine_err)
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn write_index(&self, path: String) -> napi::Result<()> {
+        self.inner.write_index(&path).map_err(engine_err)
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn fetch(
+        &mut self,
+        regions: Vec<String>,
+        reverse_complement: bool,
+    ) -> napi::Result<FastaBatch> {
+        self.inner
+            .fetch(&regions, reverse_complement)
+            .map(Into::into)
+            .map_err(engine_err)
+    }
 }
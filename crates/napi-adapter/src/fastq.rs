@@ -1,9 +1,14 @@
-//! Napi wrapper for the engine's FASTQ reader.
+//! Napi wrapper for the engine's FASTQ readers and writer.
 
 use genotype_engine as engine;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+use crate::{
+    checkpoint_arg, compression_mode, reader_err, saturating_i64, BytesConsumed, CompressionMode,
+    SequenceMetricsResult,
+};
+
 #[napi(object)]
 pub struct FastqBatch {
     pub count: u32,
@@ -47,25 +52,311 @@ fn engine_err(e: engine::EngineError) -> napi::Error {
 impl FastqReader {
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open(path: String) -> napi::Result<Self> {
-        let inner = engine::fastq::FastqReader::open_from_path(&path).map_err(engine_err)?;
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner =
+            engine::fastq::FastqReader::open_from_path(&path).map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
+        let inner = engine::fastq::FastqReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_at(env: Env, path: String, checkpoint: Buffer) -> napi::Result<Self> {
+        let checkpoint = checkpoint_arg(&checkpoint)?;
+        let inner = engine::fastq::FastqReader::open_from_path_at(&path, &checkpoint)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes_at(env: Env, data: Buffer, checkpoint: Buffer) -> napi::Result<Self> {
+        let checkpoint = checkpoint_arg(&checkpoint)?;
+        let inner = engine::fastq::FastqReader::open_from_bytes_at(data.to_vec(), &checkpoint)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_threaded(env: Env, path: String, threads: u32) -> napi::Result<Self> {
+        let inner = engine::fastq::FastqReader::open_from_path_threaded(&path, threads)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes_threaded(env: Env, data: Buffer, threads: u32) -> napi::Result<Self> {
+        let inner = engine::fastq::FastqReader::open_from_bytes_threaded(data.to_vec(), threads)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<FastqBatch>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn read_batch_bounded(
+        &mut self,
+        env: Env,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> napi::Result<Option<FastqBatch>> {
+        self.inner
+            .read_batch_bounded(max_records, max_bytes)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+
+    #[napi]
+    pub fn checkpoint(&self) -> Buffer {
+        self.inner.checkpoint().to_bytes().into()
+    }
+}
+
+#[napi]
+pub struct PrefetchingFastqReader {
+    inner: engine::prefetch::Prefetcher<engine::fastq::FastqReader>,
+}
+
+#[napi]
+impl PrefetchingFastqReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String, max_records: u32, depth: u32) -> napi::Result<Self> {
+        let reader =
+            engine::fastq::FastqReader::open_from_path(&path).map_err(|e| reader_err(env, e))?;
+        let inner =
+            engine::prefetch::Prefetcher::spawn(reader, max_records, depth).map_err(engine_err)?;
         Ok(Self { inner })
     }
 
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open_bytes(data: Buffer) -> napi::Result<Self> {
+    pub fn open_bytes(env: Env, data: Buffer, max_records: u32, depth: u32) -> napi::Result<Self> {
+        let reader = engine::fastq::FastqReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
         let inner =
-            engine::fastq::FastqReader::open_from_bytes(data.to_vec()).map_err(engine_err)?;
+            engine::prefetch::Prefetcher::spawn(reader, max_records, depth).map_err(engine_err)?;
         Ok(Self { inner })
     }
 
     #[napi]
-    pub fn read_batch(&mut self, max_records: u32) -> napi::Result<Option<FastqBatch>> {
+    pub fn read_batch(&mut self, env: Env) -> napi::Result<Option<FastqBatch>> {
+        self.inner
+            .read_batch()
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+}
+
+#[napi]
+pub struct MappedFastqReader {
+    inner: engine::mapped::MappedFastqReader,
+}
+
+#[napi]
+impl MappedFastqReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner =
+            engine::mapped::MappedFastqReader::open(&path).map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<FastqBatch>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(|batch| batch.to_batch().into()))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn grep_next_batch(
+        &mut self,
+        env: Env,
+        max_records: u32,
+        pattern: &[u8],
+        max_edits: u32,
+        case_insensitive: bool,
+        search_both_strands: bool,
+    ) -> napi::Result<Option<Buffer>> {
         self.inner
             .read_batch(max_records)
+            .map(|opt| {
+                opt.map(|batch| {
+                    batch
+                        .grep(pattern, max_edits, case_insensitive, search_both_strands)
+                        .into()
+                })
+            })
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn hash_next_batch(
+        &mut self,
+        env: Env,
+        max_records: u32,
+        case_insensitive: bool,
+    ) -> napi::Result<Option<Buffer>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(|batch| batch.hash(case_insensitive).into()))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn sequence_metrics_next_batch(
+        &mut self,
+        env: Env,
+        max_records: u32,
+        metric_flags: u32,
+        ascii_offset: u8,
+    ) -> napi::Result<Option<SequenceMetricsResult>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(|batch| batch.sequence_metrics(metric_flags, ascii_offset).into()))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> i64 {
+        saturating_i64(self.inner.bytes_consumed())
+    }
+}
+
+#[napi(object)]
+pub struct PairedFastqBatch {
+    pub count: u32,
+    pub pair_id_data: Buffer,
+    pub pair_id_offsets: Vec<u32>,
+    pub r1: FastqBatch,
+    pub r2: FastqBatch,
+}
+
+impl From<engine::fastq::PairedFastqBatch> for PairedFastqBatch {
+    fn from(b: engine::fastq::PairedFastqBatch) -> Self {
+        Self {
+            count: b.count,
+            pair_id_data: b.pair_id_data.into(),
+            pair_id_offsets: b.pair_id_offsets,
+            r1: b.r1.into(),
+            r2: b.r2.into(),
+        }
+    }
+}
+
+#[napi]
+pub struct PairedFastqReader {
+    inner: engine::fastq::PairedFastqReader,
+}
+
+#[napi]
+impl PairedFastqReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, r1_path: String, r2_path: String) -> napi::Result<Self> {
+        let inner = engine::fastq::PairedFastqReader::open_from_paths(&r1_path, &r2_path)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, r1: Buffer, r2: Buffer) -> napi::Result<Self> {
+        let inner = engine::fastq::PairedFastqReader::open_from_bytes(r1.to_vec(), r2.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_interleaved(env: Env, path: String) -> napi::Result<Self> {
+        let inner = engine::fastq::PairedFastqReader::open_interleaved_from_path(&path)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_interleaved_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
+        let inner = engine::fastq::PairedFastqReader::open_interleaved_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn read_batch(
+        &mut self,
+        env: Env,
+        max_pairs: u32,
+    ) -> napi::Result<Option<PairedFastqBatch>> {
+        self.inner
+            .read_batch(max_pairs)
             .map(|opt| opt.map(Into::into))
-            .map_err(engine_err)
+            .map_err(|e| reader_err(env, e))
+    }
+}
+
+#[napi]
+pub struct FastqChunkReader {
+    inner: engine::fastq::FastqChunkReader,
+}
+
+#[napi]
+impl FastqChunkReader {
+    #[napi(factory)]
+    pub fn create() -> Self {
+        Self {
+            inner: engine::fastq::FastqChunkReader::new(),
+        }
+    }
+
+    #[napi]
+    pub fn push_chunk(&mut self, env: Env, chunk: &[u8]) -> napi::Result<Option<FastqBatch>> {
+        self.inner
+            .push_chunk(chunk)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn end(&mut self, env: Env) -> napi::Result<Option<FastqBatch>> {
+        self.inner
+            .end()
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
     }
 }
 
@@ -78,17 +369,22 @@ pub struct FastqWriter {
 impl FastqWriter {
     #[napi(factory)]
     #[allow(clippy::needless_pass_by_value)]
-    pub fn open(path: String, compress: bool) -> napi::Result<Self> {
+    pub fn open(
+        path: String,
+        compression: CompressionMode,
+        level: Option<i32>,
+    ) -> napi::Result<Self> {
         let inner =
-            engine::fastq::FastqWriter::open_to_path(&path, compress).map_err(engine_err)?;
+            engine::fastq::FastqWriter::open_to_path(&path, compression_mode(compression, level))
+                .map_err(engine_err)?;
         Ok(Self { inner: Some(inner) })
     }
 
     #[napi(factory)]
-    pub fn open_bytes(compress: bool) -> Self {
-        Self {
-            inner: Some(engine::fastq::FastqWriter::open_to_bytes(compress)),
-        }
+    pub fn open_bytes(compression: CompressionMode, level: Option<i32>) -> napi::Result<Self> {
+        let inner = engine::fastq::FastqWriter::open_to_bytes(compression_mode(compression, level))
+            .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
     }
 
     #[napi]
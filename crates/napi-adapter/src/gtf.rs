@@ -0,0 +1,113 @@
+//! Napi wrapper for the engine's GTF/GFF3 reader.
+
+use genotype_engine as engine;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{engine_err, reader_err, saturating_i64, BytesConsumed};
+
+#[napi(object)]
+pub struct GtfAttributeColumn {
+    pub key: String,
+    pub present: Buffer,
+    pub value_data: Buffer,
+    pub value_offsets: Vec<u32>,
+}
+
+impl From<engine::gtf::GtfAttributeColumn> for GtfAttributeColumn {
+    fn from(c: engine::gtf::GtfAttributeColumn) -> Self {
+        Self {
+            key: c.key,
+            present: c.present.into(),
+            value_data: c.value_data.into(),
+            value_offsets: c.value_offsets,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct GtfBatch {
+    pub count: u32,
+    pub format: String,
+    pub seqid_data: Buffer,
+    pub seqid_offsets: Vec<u32>,
+    pub source_data: Buffer,
+    pub source_offsets: Vec<u32>,
+    pub type_data: Buffer,
+    pub type_offsets: Vec<u32>,
+    pub starts: Vec<i64>,
+    pub ends: Vec<i64>,
+    pub scores: Vec<f64>,
+    pub strands: Buffer,
+    pub phases: Buffer,
+    pub attributes: Vec<GtfAttributeColumn>,
+}
+
+impl From<engine::gtf::GtfBatch> for GtfBatch {
+    fn from(b: engine::gtf::GtfBatch) -> Self {
+        Self {
+            count: b.count,
+            format: b.format.as_str().to_owned(),
+            seqid_data: b.seqid_data.into(),
+            seqid_offsets: b.seqid_offsets,
+            source_data: b.source_data.into(),
+            source_offsets: b.source_offsets,
+            type_data: b.type_data.into(),
+            type_offsets: b.type_offsets,
+            starts: b.starts.into_iter().map(saturating_i64).collect(),
+            ends: b.ends.into_iter().map(saturating_i64).collect(),
+            scores: b.scores,
+            strands: b.strands.into(),
+            phases: b.phases.into(),
+            attributes: b.attributes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[napi]
+pub struct GtfReader {
+    inner: engine::gtf::GtfReader,
+}
+
+#[napi]
+impl GtfReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner =
+            engine::gtf::GtfReader::open_from_path(&path).map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
+        let inner = engine::gtf::GtfReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_attribute_keys(&mut self, keys: Vec<String>) -> napi::Result<()> {
+        self.inner.set_attribute_keys(&keys).map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<GtfBatch>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+}
@@ -8,14 +8,20 @@
 #![allow(clippy::must_use_candidate, clippy::missing_errors_doc)]
 
 mod alignment;
+mod arrow;
+mod bed;
 mod fasta;
 mod fastq;
+mod gtf;
 mod sequence_sort;
+mod variant;
 
 use genotype_engine as engine;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+pub use arrow::{alignment_batch_to_arrow, fasta_batch_to_arrow, fastq_batch_to_arrow};
+
 #[napi(object)]
 pub struct TransformResult {
     pub data: Buffer,
@@ -94,6 +100,33 @@ impl From<ValidationMode> for engine::ValidationMode {
     }
 }
 
+#[napi(string_enum = "lowercase")]
+pub enum CompressionMode {
+    None,
+    Gzip,
+    Bgzf,
+    Zstd,
+    #[napi(value = "parallel-gzip")]
+    ParallelGzip,
+    #[napi(value = "parallel-bgzf")]
+    ParallelBgzf,
+}
+
+/// Combine a JS compression mode with its optional level. The level is
+/// used by zstd (default: the library default) and the parallel deflate
+/// modes (default 6); the serial gzip and BGZF modes ignore it.
+#[allow(clippy::needless_pass_by_value)]
+fn compression_mode(mode: CompressionMode, level: Option<i32>) -> engine::CompressionMode {
+    match mode {
+        CompressionMode::None => engine::CompressionMode::None,
+        CompressionMode::Gzip => engine::CompressionMode::Gzip,
+        CompressionMode::Bgzf => engine::CompressionMode::Bgzf,
+        CompressionMode::Zstd => engine::CompressionMode::Zstd(level.unwrap_or(0)),
+        CompressionMode::ParallelGzip => engine::CompressionMode::ParallelGzip(level.unwrap_or(6)),
+        CompressionMode::ParallelBgzf => engine::CompressionMode::ParallelBgzf(level.unwrap_or(6)),
+    }
+}
+
 #[napi(object)]
 pub struct SequenceMetricsResult {
     pub lengths: Option<Vec<u32>>,
@@ -260,6 +293,107 @@ fn engine_err(e: engine::EngineError) -> napi::Error {
     napi::Error::from_reason(e.to_string())
 }
 
+#[napi(object)]
+pub struct FormatDetection {
+    pub compression: String,
+    /// Lowercase, like `compression` and `alphabet`.
+    pub format: Option<String>,
+    pub alphabet: Option<String>,
+    pub quality_encoding: Option<String>,
+}
+
+impl From<engine::detect::Detection> for FormatDetection {
+    fn from(d: engine::detect::Detection) -> Self {
+        Self {
+            compression: d.compression.as_str().to_owned(),
+            format: d.format.map(|f| f.as_str().to_ascii_lowercase()),
+            alphabet: d.alphabet.map(|a| a.as_str().to_owned()),
+            quality_encoding: d.quality_encoding.map(|q| q.as_str().to_owned()),
+        }
+    }
+}
+
+/// Location of a malformed record. Readers throw an `Error` carrying
+/// these fields as properties alongside the usual message.
+#[napi(object)]
+pub struct ParseErrorDetails {
+    pub format: String,
+    pub kind: String,
+    pub record: i64,
+    pub byte_offset: Option<i64>,
+    pub line: Option<i64>,
+    pub field: Option<String>,
+}
+
+impl From<&engine::ParseError> for ParseErrorDetails {
+    fn from(e: &engine::ParseError) -> Self {
+        Self {
+            format: e.format.as_str().to_owned(),
+            kind: e.kind.as_str().to_owned(),
+            record: saturating_i64(e.record),
+            byte_offset: e.byte_offset.map(saturating_i64),
+            line: e.line.map(saturating_i64),
+            field: e.field.map(str::to_owned),
+        }
+    }
+}
+
+/// Like `engine_err`, but parse errors become an `Error` with the
+/// `ParseErrorDetails` fields attached.
+#[allow(clippy::needless_pass_by_value)]
+fn reader_err(env: Env, e: engine::EngineError) -> napi::Error {
+    let engine::EngineError::Parse(parse) = &e else {
+        return engine_err(e);
+    };
+    let details = ParseErrorDetails::from(&**parse);
+    let error = env
+        .create_error(napi::Error::from_reason(e.to_string()))
+        .and_then(|mut error| {
+            error.set_named_property("format", details.format)?;
+            error.set_named_property("kind", details.kind)?;
+            error.set_named_property("record", details.record)?;
+            if let Some(byte_offset) = details.byte_offset {
+                error.set_named_property("byteOffset", byte_offset)?;
+            }
+            if let Some(line) = details.line {
+                error.set_named_property("line", line)?;
+            }
+            if let Some(field) = details.field {
+                error.set_named_property("field", field)?;
+            }
+            Ok(error)
+        });
+    match error {
+        Ok(error) => napi::Error::from(error.to_unknown()),
+        Err(_) => engine_err(e),
+    }
+}
+
+/// Reader progress through its input, in bytes.
+#[napi(object)]
+pub struct BytesConsumed {
+    pub compressed: i64,
+    pub uncompressed: i64,
+}
+
+impl From<engine::BytesConsumed> for BytesConsumed {
+    fn from(b: engine::BytesConsumed) -> Self {
+        Self {
+            compressed: saturating_i64(b.compressed),
+            uncompressed: saturating_i64(b.uncompressed),
+        }
+    }
+}
+
+fn saturating_i64(n: u64) -> i64 {
+    i64::try_from(n).unwrap_or(i64::MAX)
+}
+
+/// Parse a checkpoint buffer returned by a reader's `checkpoint()`.
+fn checkpoint_arg(data: &[u8]) -> napi::Result<engine::ReaderCheckpoint> {
+    engine::ReaderCheckpoint::from_bytes(data).map_err(engine_err)
+}
+
 #[napi]
 pub fn grep_batch(
     sequences: &[u8],
@@ -472,6 +606,19 @@ pub fn translate_batch(
     .map_err(engine_err)
 }
 
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn detect_path(path: String) -> napi::Result<FormatDetection> {
+    engine::detect::from_path(&path)
+        .map(Into::into)
+        .map_err(engine_err)
+}
+
+#[napi]
+pub fn detect_bytes(data: &[u8]) -> FormatDetection {
+    engine::detect::from_bytes(data).into()
+}
+
 #[napi]
 pub fn hash_batch(
     sequences: &[u8],
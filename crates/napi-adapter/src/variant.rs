@@ -0,0 +1,354 @@
+//! Napi wrapper for the engine's VCF/BCF reader and VCF writer.
+
+use genotype_engine as engine;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{
+    compression_mode, engine_err, reader_err, saturating_i64, BytesConsumed, CompressionMode,
+};
+
+#[napi(string_enum = "lowercase")]
+pub enum InfoKind {
+    Int,
+    Float,
+    Flag,
+    String,
+}
+
+impl From<engine::variant::InfoKind> for InfoKind {
+    fn from(kind: engine::variant::InfoKind) -> Self {
+        match kind {
+            engine::variant::InfoKind::Int => Self::Int,
+            engine::variant::InfoKind::Float => Self::Float,
+            engine::variant::InfoKind::Flag => Self::Flag,
+            engine::variant::InfoKind::String => Self::String,
+        }
+    }
+}
+
+impl From<&InfoKind> for engine::variant::InfoKind {
+    fn from(kind: &InfoKind) -> Self {
+        match kind {
+            InfoKind::Int => Self::Int,
+            InfoKind::Float => Self::Float,
+            InfoKind::Flag => Self::Flag,
+            InfoKind::String => Self::String,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct InfoColumn {
+    pub key: String,
+    pub kind: InfoKind,
+    pub present: Buffer,
+    pub offsets: Vec<u32>,
+    pub int_values: Vec<i32>,
+    pub float_values: Vec<f64>,
+    pub string_data: Buffer,
+}
+
+impl From<engine::variant::InfoColumn> for InfoColumn {
+    fn from(c: engine::variant::InfoColumn) -> Self {
+        Self {
+            key: c.key,
+            kind: c.kind.into(),
+            present: c.present.into(),
+            offsets: c.offsets,
+            int_values: c.int_values,
+            float_values: c.float_values,
+            string_data: c.string_data.into(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct GenotypeMatrix {
+    pub sample_count: u32,
+    pub allele_offsets: Vec<u32>,
+    pub alleles: Vec<i32>,
+    pub phased: Buffer,
+}
+
+impl From<engine::variant::GenotypeMatrix> for GenotypeMatrix {
+    fn from(m: engine::variant::GenotypeMatrix) -> Self {
+        Self {
+            sample_count: m.sample_count,
+            allele_offsets: m.allele_offsets,
+            alleles: m.alleles,
+            phased: m.phased.into(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct VariantBatch {
+    pub count: u32,
+    pub format: String,
+    pub chrom_data: Buffer,
+    pub chrom_offsets: Vec<u32>,
+    pub positions: Vec<i64>,
+    pub id_data: Buffer,
+    pub id_offsets: Vec<u32>,
+    pub ref_data: Buffer,
+    pub ref_offsets: Vec<u32>,
+    pub alt_data: Buffer,
+    pub alt_offsets: Vec<u32>,
+    pub qualities: Vec<f64>,
+    pub filter_data: Buffer,
+    pub filter_offsets: Vec<u32>,
+    pub info: Vec<InfoColumn>,
+    pub genotypes: Option<GenotypeMatrix>,
+}
+
+impl From<engine::variant::VariantBatch> for VariantBatch {
+    fn from(b: engine::variant::VariantBatch) -> Self {
+        Self {
+            count: b.count,
+            format: b.format.as_str().to_owned(),
+            chrom_data: b.chrom_data.into(),
+            chrom_offsets: b.chrom_offsets,
+            positions: b.positions.into_iter().map(saturating_i64).collect(),
+            id_data: b.id_data.into(),
+            id_offsets: b.id_offsets,
+            ref_data: b.ref_data.into(),
+            ref_offsets: b.ref_offsets,
+            alt_data: b.alt_data.into(),
+            alt_offsets: b.alt_offsets,
+            qualities: b.qualities,
+            filter_data: b.filter_data.into(),
+            filter_offsets: b.filter_offsets,
+            info: b.info.into_iter().map(Into::into).collect(),
+            genotypes: b.genotypes.map(Into::into),
+        }
+    }
+}
+
+impl From<&InfoColumn> for engine::variant::InfoColumn {
+    fn from(c: &InfoColumn) -> Self {
+        Self {
+            key: c.key.clone(),
+            kind: (&c.kind).into(),
+            present: c.present.to_vec(),
+            offsets: c.offsets.clone(),
+            int_values: c.int_values.clone(),
+            float_values: c.float_values.clone(),
+            string_data: c.string_data.to_vec(),
+        }
+    }
+}
+
+impl From<&GenotypeMatrix> for engine::variant::GenotypeMatrix {
+    fn from(m: &GenotypeMatrix) -> Self {
+        Self {
+            sample_count: m.sample_count,
+            allele_offsets: m.allele_offsets.clone(),
+            alleles: m.alleles.clone(),
+            phased: m.phased.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&VariantBatch> for engine::variant::VariantBatch {
+    type Error = napi::Error;
+
+    fn try_from(b: &VariantBatch) -> napi::Result<Self> {
+        let positions = b
+            .positions
+            .iter()
+            .map(|&v| {
+                u64::try_from(v).map_err(|_| {
+                    napi::Error::from_reason("VcfWriter: positions must be non-negative")
+                })
+            })
+            .collect::<napi::Result<_>>()?;
+        Ok(Self {
+            count: b.count,
+            format: match b.format.as_str() {
+                "BCF" => engine::RecordFormat::Bcf,
+                _ => engine::RecordFormat::Vcf,
+            },
+            chrom_data: b.chrom_data.to_vec(),
+            chrom_offsets: b.chrom_offsets.clone(),
+            positions,
+            id_data: b.id_data.to_vec(),
+            id_offsets: b.id_offsets.clone(),
+            ref_data: b.ref_data.to_vec(),
+            ref_offsets: b.ref_offsets.clone(),
+            alt_data: b.alt_data.to_vec(),
+            alt_offsets: b.alt_offsets.clone(),
+            qualities: b.qualities.clone(),
+            filter_data: b.filter_data.to_vec(),
+            filter_offsets: b.filter_offsets.clone(),
+            info: b.info.iter().map(Into::into).collect(),
+            genotypes: b.genotypes.as_ref().map(Into::into),
+        })
+    }
+}
+
+#[napi]
+pub struct VariantReader {
+    inner: engine::variant::VariantReader,
+}
+
+#[napi]
+impl VariantReader {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(env: Env, path: String) -> napi::Result<Self> {
+        let inner = engine::variant::VariantReader::open_from_path(&path)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(env: Env, data: Buffer) -> napi::Result<Self> {
+        let inner = engine::variant::VariantReader::open_from_bytes(data.to_vec())
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_indexed(env: Env, path: String, index_path: String) -> napi::Result<Self> {
+        let inner = engine::variant::VariantReader::open_indexed(&path, &index_path)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_indexed_bytes(env: Env, data: Buffer, index: Buffer) -> napi::Result<Self> {
+        let inner = engine::variant::VariantReader::open_indexed_from_bytes(data.to_vec(), &index)
+            .map_err(|e| reader_err(env, e))?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn query(&mut self, region: String) -> napi::Result<()> {
+        self.inner.query(&region).map_err(engine_err)
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_info_fields(&mut self, keys: Vec<String>) -> napi::Result<()> {
+        self.inner.set_info_fields(&keys).map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn set_genotypes(&mut self, enabled: bool) {
+        self.inner.set_genotypes(enabled);
+    }
+
+    #[napi]
+    pub fn sample_names(&self) -> Vec<String> {
+        self.inner.sample_names()
+    }
+
+    #[napi]
+    pub fn header_text(&self) -> String {
+        self.inner.header_text()
+    }
+
+    #[napi]
+    pub fn read_batch(&mut self, env: Env, max_records: u32) -> napi::Result<Option<VariantBatch>> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(|e| reader_err(env, e))
+    }
+
+    #[napi]
+    pub fn records_read(&self) -> i64 {
+        saturating_i64(self.inner.records_read())
+    }
+
+    #[napi]
+    pub fn bytes_consumed(&self) -> BytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+}
+
+#[napi(object)]
+pub struct VcfWriterOutput {
+    pub data: Option<Buffer>,
+    pub tbi: Option<Buffer>,
+}
+
+#[napi]
+pub struct VcfWriter {
+    inner: Option<engine::variant::VcfWriter>,
+}
+
+#[napi]
+impl VcfWriter {
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open(
+        path: String,
+        header: String,
+        compression: CompressionMode,
+        level: Option<i32>,
+    ) -> napi::Result<Self> {
+        let inner = engine::variant::VcfWriter::open_to_path(
+            &path,
+            &header,
+            compression_mode(compression, level),
+        )
+        .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    #[napi(factory)]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn open_bytes(
+        header: String,
+        compression: CompressionMode,
+        level: Option<i32>,
+    ) -> napi::Result<Self> {
+        let inner = engine::variant::VcfWriter::open_to_bytes(
+            &header,
+            compression_mode(compression, level),
+        )
+        .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    #[napi]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn write_batch(&mut self, batch: VariantBatch) -> napi::Result<()> {
+        let w = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        let batch = engine::variant::VariantBatch::try_from(&batch)?;
+        w.write_batch(&batch).map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn finish(&mut self) -> napi::Result<Option<Buffer>> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        w.finish()
+            .map(|opt| opt.map(Into::into))
+            .map_err(engine_err)
+    }
+
+    #[napi]
+    pub fn finish_with_index(&mut self) -> napi::Result<VcfWriterOutput> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| napi::Error::from_reason("writer already finished"))?;
+        let output = w.finish_with_index().map_err(engine_err)?;
+        Ok(VcfWriterOutput {
+            data: output.data.map(Into::into),
+            tbi: output.tbi.map(Into::into),
+        })
+    }
+}
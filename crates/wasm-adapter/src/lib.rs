@@ -94,6 +94,73 @@ fn engine_err(e: engine::EngineError) -> JsError {
     JsError::new(&e.to_string())
 }
 
+/// Like `engine_err`, but parse errors become an `Error` carrying the
+/// failing record's `format`, `kind`, `record`, `byteOffset`, `line` and
+/// `field` as properties.
+#[allow(clippy::needless_pass_by_value, clippy::cast_precision_loss)]
+fn reader_err(e: engine::EngineError) -> JsValue {
+    let engine::EngineError::Parse(parse) = &e else {
+        return engine_err(e).into();
+    };
+    let error = js_sys::Error::new(&e.to_string());
+    let number = |n: u64| JsValue::from_f64(n as f64);
+    // Absent locations are left unset, as the napi adapter does.
+    let properties = [
+        ("format", Some(JsValue::from_str(parse.format.as_str()))),
+        ("kind", Some(JsValue::from_str(parse.kind.as_str()))),
+        ("record", Some(number(parse.record))),
+        ("byteOffset", parse.byte_offset.map(number)),
+        ("line", parse.line.map(number)),
+        ("field", parse.field.map(JsValue::from_str)),
+    ];
+    for (name, value) in properties {
+        let Some(value) = value else { continue };
+        // Setting a property on a fresh `Error` cannot fail.
+        let _ = js_sys::Reflect::set(&error, &JsValue::from_str(name), &value);
+    }
+    error.into()
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct FormatDetection {
+    pub compression: String,
+    /// Lowercase, like `compression` and `alphabet`.
+    pub format: Option<String>,
+    pub alphabet: Option<String>,
+    pub quality_encoding: Option<String>,
+}
+
+impl From<engine::detect::Detection> for FormatDetection {
+    fn from(d: engine::detect::Detection) -> Self {
+        Self {
+            compression: d.compression.as_str().to_owned(),
+            format: d.format.map(|f| f.as_str().to_ascii_lowercase()),
+            alphabet: d.alphabet.map(|a| a.as_str().to_owned()),
+            quality_encoding: d.quality_encoding.map(|q| q.as_str().to_owned()),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmBytesConsumed {
+    pub compressed: f64,
+    pub uncompressed: f64,
+}
+
+impl From<engine::BytesConsumed> for WasmBytesConsumed {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(b: engine::BytesConsumed) -> Self {
+        Self {
+            compressed: b.compressed as f64,
+            uncompressed: b.uncompressed as f64,
+        }
+    }
+}
+
+fn checkpoint_arg(data: &[u8]) -> Result<engine::ReaderCheckpoint, JsError> {
+    engine::ReaderCheckpoint::from_bytes(data).map_err(engine_err)
+}
+
 #[wasm_bindgen]
 pub fn grep_batch(
     sequences: &[u8],
@@ -313,6 +380,11 @@ pub fn translate_batch(
     .map_err(engine_err)
 }
 
+#[wasm_bindgen]
+pub fn detect_bytes(data: &[u8]) -> FormatDetection {
+    engine::detect::from_bytes(data).into()
+}
+
 #[wasm_bindgen]
 pub fn hash_batch(
     sequences: &[u8],
@@ -448,9 +520,44 @@ pub struct WasmAlignmentBatch {
     pub cigar_offsets: Vec<u32>,
     pub rname_data: Vec<u8>,
     pub rname_offsets: Vec<u32>,
+    pub rnext_data: Vec<u8>,
+    pub rnext_offsets: Vec<u32>,
     pub flags: Vec<u16>,
     pub positions: Vec<i32>,
     pub mapping_qualities: Vec<u8>,
+    pub next_positions: Vec<i32>,
+    pub template_lengths: Vec<i32>,
+    pub tags: Vec<WasmAuxTagColumn>,
+    pub raw_tag_data: Vec<u8>,
+    pub raw_tag_offsets: Vec<u32>,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct WasmAuxTagColumn {
+    pub tag: String,
+    pub kind: String,
+    pub present: Vec<u8>,
+    pub int_values: Vec<i64>,
+    pub float_values: Vec<f64>,
+    pub string_data: Vec<u8>,
+    pub string_offsets: Vec<u32>,
+    pub array_offsets: Vec<u32>,
+}
+
+impl From<engine::alignment::AuxTagColumn> for WasmAuxTagColumn {
+    fn from(c: engine::alignment::AuxTagColumn) -> Self {
+        Self {
+            tag: c.tag,
+            kind: c.kind.as_str().to_owned(),
+            present: c.present,
+            int_values: c.int_values,
+            float_values: c.float_values,
+            string_data: c.string_data,
+            string_offsets: c.string_offsets,
+            array_offsets: c.array_offsets,
+        }
+    }
 }
 
 impl From<engine::alignment::AlignmentBatch> for WasmAlignmentBatch {
@@ -468,9 +575,16 @@ impl From<engine::alignment::AlignmentBatch> for WasmAlignmentBatch {
             cigar_offsets: b.cigar_offsets,
             rname_data: b.rname_data,
             rname_offsets: b.rname_offsets,
+            rnext_data: b.rnext_data,
+            rnext_offsets: b.rnext_offsets,
             flags: b.flags,
             positions: b.positions,
             mapping_qualities: b.mapping_qualities,
+            next_positions: b.next_positions,
+            template_lengths: b.template_lengths,
+            tags: b.tags.into_iter().map(Into::into).collect(),
+            raw_tag_data: b.raw_tag_data,
+            raw_tag_offsets: b.raw_tag_offsets,
         }
     }
 }
@@ -489,16 +603,82 @@ pub struct WasmAlignmentReader {
 #[wasm_bindgen]
 impl WasmAlignmentReader {
     #[wasm_bindgen(constructor)]
-    pub fn new(data: &[u8]) -> Result<WasmAlignmentReader, JsError> {
+    pub fn new(data: &[u8]) -> Result<WasmAlignmentReader, JsValue> {
         let inner = engine::alignment::AlignmentReader::open_from_bytes(data.to_vec())
-            .map_err(engine_err)?;
+            .map_err(reader_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn open_at(data: &[u8], checkpoint: &[u8]) -> Result<WasmAlignmentReader, JsValue> {
+        let checkpoint = checkpoint_arg(checkpoint)?;
+        let inner =
+            engine::alignment::AlignmentReader::open_from_bytes_at(data.to_vec(), &checkpoint)
+                .map_err(reader_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn cram(
+        data: &[u8],
+        reference: &[u8],
+        reference_index: &[u8],
+    ) -> Result<WasmAlignmentReader, JsValue> {
+        let inner = engine::alignment::AlignmentReader::open_cram_from_bytes(
+            data.to_vec(),
+            reference.to_vec(),
+            reference_index,
+        )
+        .map_err(reader_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn indexed(data: &[u8], index: &[u8]) -> Result<WasmAlignmentReader, JsValue> {
+        let inner =
+            engine::alignment::AlignmentReader::open_indexed_from_bytes(data.to_vec(), index)
+                .map_err(reader_err)?;
         Ok(Self { inner })
     }
 
-    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmAlignmentBatch>, JsError> {
+    pub fn query(&mut self, region: &str) -> Result<(), JsError> {
+        self.inner.query(region).map_err(engine_err)
+    }
+
+    pub fn set_tag_options(&mut self, tags: Vec<String>, raw: bool) -> Result<(), JsError> {
+        self.inner
+            .set_tag_options(&engine::alignment::AuxTagOptions { tags, raw })
+            .map_err(engine_err)
+    }
+
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmAlignmentBatch>, JsValue> {
         self.inner
             .read_batch(max_records)
             .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    pub fn read_batch_bounded(
+        &mut self,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> Result<Option<WasmAlignmentBatch>, JsValue> {
+        self.inner
+            .read_batch_bounded(max_records, max_bytes)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn records_read(&self) -> f64 {
+        self.inner.records_read() as f64
+    }
+
+    pub fn bytes_consumed(&self) -> WasmBytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+
+    pub fn checkpoint(&self) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .checkpoint()
+            .map(|c| c.to_bytes())
             .map_err(engine_err)
     }
 
@@ -518,6 +698,98 @@ impl WasmAlignmentReader {
     }
 }
 
+#[wasm_bindgen]
+pub struct WasmAlignmentWriter {
+    inner: Option<engine::alignment::AlignmentWriter>,
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+impl WasmAlignmentWriter {
+    #[wasm_bindgen(constructor)]
+    pub fn new(header_text: &str, format: &str) -> Result<WasmAlignmentWriter, JsError> {
+        let inner = engine::alignment::AlignmentWriter::open_to_bytes(
+            header_text,
+            parse_alignment_format(format)?,
+        )
+        .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    pub fn write_batch(
+        &mut self,
+        qname_data: &[u8],
+        qname_offsets: Vec<u32>,
+        sequence_data: &[u8],
+        sequence_offsets: Vec<u32>,
+        quality_data: &[u8],
+        quality_offsets: Vec<u32>,
+        cigar_data: &[u8],
+        cigar_offsets: Vec<u32>,
+        rname_data: &[u8],
+        rname_offsets: Vec<u32>,
+        rnext_data: &[u8],
+        rnext_offsets: Vec<u32>,
+        flags: Vec<u16>,
+        positions: Vec<i32>,
+        mapping_qualities: &[u8],
+        next_positions: Vec<i32>,
+        template_lengths: Vec<i32>,
+        raw_tag_data: &[u8],
+        raw_tag_offsets: Vec<u32>,
+        count: u32,
+    ) -> Result<(), JsError> {
+        let w = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        let batch = engine::alignment::AlignmentBatch {
+            count,
+            format: "",
+            qname_data: qname_data.to_vec(),
+            qname_offsets,
+            sequence_data: sequence_data.to_vec(),
+            sequence_offsets,
+            quality_data: quality_data.to_vec(),
+            quality_offsets,
+            cigar_data: cigar_data.to_vec(),
+            cigar_offsets,
+            rname_data: rname_data.to_vec(),
+            rname_offsets,
+            rnext_data: rnext_data.to_vec(),
+            rnext_offsets,
+            flags,
+            positions,
+            mapping_qualities: mapping_qualities.to_vec(),
+            next_positions,
+            template_lengths,
+            tags: Vec::new(),
+            raw_tag_data: raw_tag_data.to_vec(),
+            raw_tag_offsets,
+        };
+        w.write_batch(&batch).map_err(engine_err)
+    }
+
+    pub fn finish(&mut self) -> Result<Option<Vec<u8>>, JsError> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        w.finish().map_err(engine_err)
+    }
+}
+
+fn parse_alignment_format(
+    format: &str,
+) -> Result<engine::alignment::AlignmentOutputFormat, JsError> {
+    match format {
+        "bam" => Ok(engine::alignment::AlignmentOutputFormat::Bam),
+        "sam" => Ok(engine::alignment::AlignmentOutputFormat::Sam),
+        _ => Err(JsError::new(&format!("unknown alignment format: {format}"))),
+    }
+}
+
+#[derive(Clone)]
 #[wasm_bindgen(getter_with_clone)]
 pub struct WasmFastqBatch {
     pub count: u32,
@@ -561,11 +833,91 @@ impl WasmFastqReader {
         Ok(Self { inner })
     }
 
-    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmFastqBatch>, JsError> {
+    pub fn open_at(data: &[u8], checkpoint: &[u8]) -> Result<WasmFastqReader, JsError> {
+        let checkpoint = checkpoint_arg(checkpoint)?;
+        let inner = engine::fastq::FastqReader::open_from_bytes_at(data.to_vec(), &checkpoint)
+            .map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmFastqBatch>, JsValue> {
         self.inner
             .read_batch(max_records)
             .map(|opt| opt.map(Into::into))
-            .map_err(engine_err)
+            .map_err(reader_err)
+    }
+
+    pub fn read_batch_bounded(
+        &mut self,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> Result<Option<WasmFastqBatch>, JsValue> {
+        self.inner
+            .read_batch_bounded(max_records, max_bytes)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn records_read(&self) -> f64 {
+        self.inner.records_read() as f64
+    }
+
+    pub fn bytes_consumed(&self) -> WasmBytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+
+    pub fn checkpoint(&self) -> Vec<u8> {
+        self.inner.checkpoint().to_bytes()
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmPairedFastqBatch {
+    pub count: u32,
+    pub pair_id_data: Vec<u8>,
+    pub pair_id_offsets: Vec<u32>,
+    pub r1: WasmFastqBatch,
+    pub r2: WasmFastqBatch,
+}
+
+impl From<engine::fastq::PairedFastqBatch> for WasmPairedFastqBatch {
+    fn from(b: engine::fastq::PairedFastqBatch) -> Self {
+        Self {
+            count: b.count,
+            pair_id_data: b.pair_id_data,
+            pair_id_offsets: b.pair_id_offsets,
+            r1: b.r1.into(),
+            r2: b.r2.into(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmPairedFastqReader {
+    inner: engine::fastq::PairedFastqReader,
+}
+
+#[wasm_bindgen]
+impl WasmPairedFastqReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new(r1: &[u8], r2: &[u8]) -> Result<WasmPairedFastqReader, JsError> {
+        let inner = engine::fastq::PairedFastqReader::open_from_bytes(r1.to_vec(), r2.to_vec())
+            .map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn interleaved(data: &[u8]) -> Result<WasmPairedFastqReader, JsError> {
+        let inner = engine::fastq::PairedFastqReader::open_interleaved_from_bytes(data.to_vec())
+            .map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn read_batch(&mut self, max_pairs: u32) -> Result<Option<WasmPairedFastqBatch>, JsValue> {
+        self.inner
+            .read_batch(max_pairs)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
     }
 }
 
@@ -594,6 +946,33 @@ impl From<engine::fasta::FastaBatch> for WasmFastaBatch {
     }
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmFastaSequenceChunkBatch {
+    pub count: u32,
+    pub record_indices: Vec<u64>,
+    pub chunk_starts: Vec<u64>,
+    pub is_last: Vec<u8>,
+    pub name_data: Vec<u8>,
+    pub name_offsets: Vec<u32>,
+    pub sequence_data: Vec<u8>,
+    pub sequence_offsets: Vec<u32>,
+}
+
+impl From<engine::fasta::FastaSequenceChunkBatch> for WasmFastaSequenceChunkBatch {
+    fn from(b: engine::fasta::FastaSequenceChunkBatch) -> Self {
+        Self {
+            count: b.count,
+            record_indices: b.record_indices,
+            chunk_starts: b.chunk_starts,
+            is_last: b.is_last,
+            name_data: b.name_data,
+            name_offsets: b.name_offsets,
+            sequence_data: b.sequence_data,
+            sequence_offsets: b.sequence_offsets,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct WasmFastaReader {
     inner: engine::fasta::FastaReader,
@@ -608,14 +987,148 @@ impl WasmFastaReader {
         Ok(Self { inner })
     }
 
-    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmFastaBatch>, JsError> {
+    pub fn open_at(data: &[u8], checkpoint: &[u8]) -> Result<WasmFastaReader, JsError> {
+        let checkpoint = checkpoint_arg(checkpoint)?;
+        let inner = engine::fasta::FastaReader::open_from_bytes_at(data.to_vec(), &checkpoint)
+            .map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmFastaBatch>, JsValue> {
         self.inner
             .read_batch(max_records)
             .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    pub fn read_batch_bounded(
+        &mut self,
+        max_records: u32,
+        max_bytes: u32,
+    ) -> Result<Option<WasmFastaBatch>, JsValue> {
+        self.inner
+            .read_batch_bounded(max_records, max_bytes)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn records_read(&self) -> f64 {
+        self.inner.records_read() as f64
+    }
+
+    pub fn bytes_consumed(&self) -> WasmBytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+
+    pub fn checkpoint(&self) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .checkpoint()
+            .map(|c| c.to_bytes())
+            .map_err(engine_err)
+    }
+
+    pub fn read_sequence_chunks(
+        &mut self,
+        max_chunks: u32,
+        chunk_size: u32,
+    ) -> Result<Option<WasmFastaSequenceChunkBatch>, JsValue> {
+        self.inner
+            .read_sequence_chunks(max_chunks, chunk_size)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmFastaIndexEntry {
+    pub name: String,
+    pub length: f64,
+    pub offset: f64,
+    pub line_bases: f64,
+    pub line_width: f64,
+}
+
+#[wasm_bindgen]
+pub struct WasmFastaIndex {
+    inner: engine::fasta::FastaIndex,
+}
+
+#[wasm_bindgen]
+impl WasmFastaIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8]) -> Result<WasmFastaIndex, JsError> {
+        let inner =
+            engine::fasta::FastaIndex::build_from_bytes(data.to_vec()).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn with_index(data: &[u8], index: &[u8]) -> Result<WasmFastaIndex, JsError> {
+        let inner =
+            engine::fasta::FastaIndex::load_from_bytes(data.to_vec(), index).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn entries(&self) -> Vec<WasmFastaIndexEntry> {
+        self.inner
+            .entries()
+            .into_iter()
+            .map(|e| WasmFastaIndexEntry {
+                name: e.name,
+                length: e.length as f64,
+                offset: e.offset as f64,
+                line_bases: e.line_bases as f64,
+                line_width: e.line_width as f64,
+            })
+            .collect()
+    }
+
+    pub fn index_bytes(&self) -> Result<Vec<u8>, JsError> {
+        self.inner.index_bytes().map_err(engine_err)
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn fetch(
+        &mut self,
+        regions: Vec<String>,
+        reverse_complement: bool,
+    ) -> Result<WasmFastaBatch, JsError> {
+        self.inner
+            .fetch(&regions, reverse_complement)
+            .map(Into::into)
             .map_err(engine_err)
     }
 }
 
+#[derive(Default)]
+#[wasm_bindgen]
+pub struct WasmFastqChunkReader {
+    inner: engine::fastq::FastqChunkReader,
+}
+
+#[wasm_bindgen]
+impl WasmFastqChunkReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmFastqChunkReader {
+        Self::default()
+    }
+
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<Option<WasmFastqBatch>, JsValue> {
+        self.inner
+            .push_chunk(chunk)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    pub fn end(&mut self) -> Result<Option<WasmFastqBatch>, JsValue> {
+        self.inner
+            .end()
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+}
+
 #[wasm_bindgen]
 pub struct WasmFastqWriter {
     inner: Option<engine::fastq::FastqWriter>,
@@ -625,10 +1138,11 @@ pub struct WasmFastqWriter {
 #[allow(clippy::too_many_arguments)]
 impl WasmFastqWriter {
     #[wasm_bindgen(constructor)]
-    pub fn new(compress: bool) -> WasmFastqWriter {
-        Self {
-            inner: Some(engine::fastq::FastqWriter::open_to_bytes(compress)),
-        }
+    pub fn new(compression: &str, level: Option<i32>) -> Result<WasmFastqWriter, JsError> {
+        let inner =
+            engine::fastq::FastqWriter::open_to_bytes(parse_compression_mode(compression, level)?)
+                .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
     }
 
     pub fn write_batch(
@@ -670,6 +1184,34 @@ impl WasmFastqWriter {
     }
 }
 
+#[derive(Default)]
+#[wasm_bindgen]
+pub struct WasmFastaChunkReader {
+    inner: engine::fasta::FastaChunkReader,
+}
+
+#[wasm_bindgen]
+impl WasmFastaChunkReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmFastaChunkReader {
+        Self::default()
+    }
+
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<Option<WasmFastaBatch>, JsValue> {
+        self.inner
+            .push_chunk(chunk)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    pub fn end(&mut self) -> Result<Option<WasmFastaBatch>, JsValue> {
+        self.inner
+            .end()
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+}
+
 #[wasm_bindgen]
 pub struct WasmFastaWriter {
     inner: Option<engine::fasta::FastaWriter>,
@@ -679,12 +1221,17 @@ pub struct WasmFastaWriter {
 #[allow(clippy::too_many_arguments)]
 impl WasmFastaWriter {
     #[wasm_bindgen(constructor)]
-    pub fn new(compress: bool, line_width: u32) -> WasmFastaWriter {
-        Self {
-            inner: Some(engine::fasta::FastaWriter::open_to_bytes(
-                compress, line_width,
-            )),
-        }
+    pub fn new(
+        compression: &str,
+        line_width: u32,
+        level: Option<i32>,
+    ) -> Result<WasmFastaWriter, JsError> {
+        let inner = engine::fasta::FastaWriter::open_to_bytes(
+            parse_compression_mode(compression, level)?,
+            line_width,
+        )
+        .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
     }
 
     pub fn write_batch(
@@ -720,4 +1267,759 @@ impl WasmFastaWriter {
             .ok_or_else(|| JsError::new("writer already finished"))?;
         w.finish().map_err(engine_err)
     }
+
+    pub fn finish_with_sidecars(&mut self) -> Result<WasmFastaWriterOutput, JsError> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        let output = w.finish_with_sidecars().map_err(engine_err)?;
+        Ok(WasmFastaWriterOutput {
+            data: output.data,
+            fai: output.fai,
+            gzi: output.gzi,
+        })
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmFastaWriterOutput {
+    pub data: Option<Vec<u8>>,
+    pub fai: Option<Vec<u8>>,
+    pub gzi: Option<Vec<u8>>,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Default)]
+pub struct WasmBedBatch {
+    pub count: u32,
+    pub field_counts: Vec<u8>,
+    pub chrom_data: Vec<u8>,
+    pub chrom_offsets: Vec<u32>,
+    pub starts: Vec<f64>,
+    pub ends: Vec<f64>,
+    pub name_data: Option<Vec<u8>>,
+    pub name_offsets: Option<Vec<u32>>,
+    pub scores: Option<Vec<f64>>,
+    pub strands: Option<Vec<u8>>,
+    pub thick_starts: Option<Vec<f64>>,
+    pub thick_ends: Option<Vec<f64>>,
+    pub item_rgbs: Option<Vec<u32>>,
+    pub block_offsets: Option<Vec<u32>>,
+    pub block_sizes: Option<Vec<u32>>,
+    pub block_starts: Option<Vec<u32>>,
+}
+
+#[wasm_bindgen]
+impl WasmBedBatch {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmBedBatch {
+        Self::default()
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn to_f64(values: Vec<u64>) -> Vec<f64> {
+    values.into_iter().map(|v| v as f64).collect()
+}
+
+/// Largest integer a JS number holds exactly.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+fn to_u64(values: &[f64], name: &str) -> Result<Vec<u64>, JsError> {
+    values
+        .iter()
+        .map(|&v| {
+            if (0.0..=MAX_SAFE_INTEGER).contains(&v) && v.fract() == 0.0 {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                Ok(v as u64)
+            } else {
+                Err(JsError::new(&format!(
+                    "BedWriter: {name} must be non-negative integers"
+                )))
+            }
+        })
+        .collect()
+}
+
+impl From<engine::bed::BedBatch> for WasmBedBatch {
+    fn from(b: engine::bed::BedBatch) -> Self {
+        Self {
+            count: b.count,
+            field_counts: b.field_counts,
+            chrom_data: b.chrom_data,
+            chrom_offsets: b.chrom_offsets,
+            starts: to_f64(b.starts),
+            ends: to_f64(b.ends),
+            name_data: b.name_data,
+            name_offsets: b.name_offsets,
+            scores: b.scores,
+            strands: b.strands,
+            thick_starts: b.thick_starts.map(to_f64),
+            thick_ends: b.thick_ends.map(to_f64),
+            item_rgbs: b.item_rgbs,
+            block_offsets: b.block_offsets,
+            block_sizes: b.block_sizes,
+            block_starts: b.block_starts,
+        }
+    }
+}
+
+impl TryFrom<&WasmBedBatch> for engine::bed::BedBatch {
+    type Error = JsError;
+
+    fn try_from(b: &WasmBedBatch) -> Result<Self, JsError> {
+        Ok(Self {
+            count: b.count,
+            field_counts: b.field_counts.clone(),
+            chrom_data: b.chrom_data.clone(),
+            chrom_offsets: b.chrom_offsets.clone(),
+            starts: to_u64(&b.starts, "starts")?,
+            ends: to_u64(&b.ends, "ends")?,
+            name_data: b.name_data.clone(),
+            name_offsets: b.name_offsets.clone(),
+            scores: b.scores.clone(),
+            strands: b.strands.clone(),
+            thick_starts: b
+                .thick_starts
+                .as_deref()
+                .map(|v| to_u64(v, "thick_starts"))
+                .transpose()?,
+            thick_ends: b
+                .thick_ends
+                .as_deref()
+                .map(|v| to_u64(v, "thick_ends"))
+                .transpose()?,
+            item_rgbs: b.item_rgbs.clone(),
+            block_offsets: b.block_offsets.clone(),
+            block_sizes: b.block_sizes.clone(),
+            block_starts: b.block_starts.clone(),
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmBedReader {
+    inner: engine::bed::BedReader,
+}
+
+#[wasm_bindgen]
+impl WasmBedReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8]) -> Result<WasmBedReader, JsError> {
+        let inner = engine::bed::BedReader::open_from_bytes(data.to_vec()).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmBedBatch>, JsValue> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn records_read(&self) -> f64 {
+        self.inner.records_read() as f64
+    }
+
+    pub fn bytes_consumed(&self) -> WasmBytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmBedWriter {
+    inner: Option<engine::bed::BedWriter>,
+}
+
+#[wasm_bindgen]
+impl WasmBedWriter {
+    #[wasm_bindgen(constructor)]
+    pub fn new(compression: &str, level: Option<i32>) -> Result<WasmBedWriter, JsError> {
+        let inner =
+            engine::bed::BedWriter::open_to_bytes(parse_compression_mode(compression, level)?)
+                .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    pub fn write_batch(&mut self, batch: &WasmBedBatch) -> Result<(), JsError> {
+        let w = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        let batch = engine::bed::BedBatch::try_from(batch)?;
+        w.write_batch(&batch).map_err(engine_err)
+    }
+
+    pub fn finish(&mut self) -> Result<Option<Vec<u8>>, JsError> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        w.finish().map_err(engine_err)
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct WasmGtfAttributeColumn {
+    pub key: String,
+    pub present: Vec<u8>,
+    pub value_data: Vec<u8>,
+    pub value_offsets: Vec<u32>,
+}
+
+impl From<engine::gtf::GtfAttributeColumn> for WasmGtfAttributeColumn {
+    fn from(c: engine::gtf::GtfAttributeColumn) -> Self {
+        Self {
+            key: c.key,
+            present: c.present,
+            value_data: c.value_data,
+            value_offsets: c.value_offsets,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmGtfBatch {
+    pub count: u32,
+    pub format: String,
+    pub seqid_data: Vec<u8>,
+    pub seqid_offsets: Vec<u32>,
+    pub source_data: Vec<u8>,
+    pub source_offsets: Vec<u32>,
+    pub type_data: Vec<u8>,
+    pub type_offsets: Vec<u32>,
+    pub starts: Vec<f64>,
+    pub ends: Vec<f64>,
+    pub scores: Vec<f64>,
+    pub strands: Vec<u8>,
+    pub phases: Vec<u8>,
+    pub attributes: Vec<WasmGtfAttributeColumn>,
+}
+
+impl From<engine::gtf::GtfBatch> for WasmGtfBatch {
+    fn from(b: engine::gtf::GtfBatch) -> Self {
+        Self {
+            count: b.count,
+            format: b.format.as_str().to_owned(),
+            seqid_data: b.seqid_data,
+            seqid_offsets: b.seqid_offsets,
+            source_data: b.source_data,
+            source_offsets: b.source_offsets,
+            type_data: b.type_data,
+            type_offsets: b.type_offsets,
+            starts: to_f64(b.starts),
+            ends: to_f64(b.ends),
+            scores: b.scores,
+            strands: b.strands,
+            phases: b.phases,
+            attributes: b.attributes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmGtfReader {
+    inner: engine::gtf::GtfReader,
+}
+
+#[wasm_bindgen]
+impl WasmGtfReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8]) -> Result<WasmGtfReader, JsError> {
+        let inner = engine::gtf::GtfReader::open_from_bytes(data.to_vec()).map_err(engine_err)?;
+        Ok(Self { inner })
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_attribute_keys(&mut self, keys: Vec<String>) -> Result<(), JsError> {
+        self.inner.set_attribute_keys(&keys).map_err(engine_err)
+    }
+
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmGtfBatch>, JsValue> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn records_read(&self) -> f64 {
+        self.inner.records_read() as f64
+    }
+
+    pub fn bytes_consumed(&self) -> WasmBytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Default)]
+pub struct WasmInfoColumn {
+    pub key: String,
+    pub kind: String,
+    pub present: Vec<u8>,
+    pub offsets: Vec<u32>,
+    pub int_values: Vec<i32>,
+    pub float_values: Vec<f64>,
+    pub string_data: Vec<u8>,
+}
+
+impl From<engine::variant::InfoColumn> for WasmInfoColumn {
+    fn from(c: engine::variant::InfoColumn) -> Self {
+        Self {
+            key: c.key,
+            kind: c.kind.as_str().to_owned(),
+            present: c.present,
+            offsets: c.offsets,
+            int_values: c.int_values,
+            float_values: c.float_values,
+            string_data: c.string_data,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WasmInfoColumn {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmInfoColumn {
+        Self::default()
+    }
+}
+
+impl TryFrom<&WasmInfoColumn> for engine::variant::InfoColumn {
+    type Error = JsError;
+
+    fn try_from(c: &WasmInfoColumn) -> Result<Self, JsError> {
+        let kind = match c.kind.as_str() {
+            "int" => engine::variant::InfoKind::Int,
+            "float" => engine::variant::InfoKind::Float,
+            "flag" => engine::variant::InfoKind::Flag,
+            "string" => engine::variant::InfoKind::String,
+            other => return Err(JsError::new(&format!("unknown INFO kind: {other}"))),
+        };
+        Ok(Self {
+            key: c.key.clone(),
+            kind,
+            present: c.present.clone(),
+            offsets: c.offsets.clone(),
+            int_values: c.int_values.clone(),
+            float_values: c.float_values.clone(),
+            string_data: c.string_data.clone(),
+        })
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Default)]
+pub struct WasmGenotypeMatrix {
+    pub sample_count: u32,
+    pub allele_offsets: Vec<u32>,
+    pub alleles: Vec<i32>,
+    pub phased: Vec<u8>,
+}
+
+impl From<engine::variant::GenotypeMatrix> for WasmGenotypeMatrix {
+    fn from(m: engine::variant::GenotypeMatrix) -> Self {
+        Self {
+            sample_count: m.sample_count,
+            allele_offsets: m.allele_offsets,
+            alleles: m.alleles,
+            phased: m.phased,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WasmGenotypeMatrix {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmGenotypeMatrix {
+        Self::default()
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Default)]
+pub struct WasmVariantBatch {
+    pub count: u32,
+    pub format: String,
+    pub chrom_data: Vec<u8>,
+    pub chrom_offsets: Vec<u32>,
+    pub positions: Vec<f64>,
+    pub id_data: Vec<u8>,
+    pub id_offsets: Vec<u32>,
+    pub ref_data: Vec<u8>,
+    pub ref_offsets: Vec<u32>,
+    pub alt_data: Vec<u8>,
+    pub alt_offsets: Vec<u32>,
+    pub qualities: Vec<f64>,
+    pub filter_data: Vec<u8>,
+    pub filter_offsets: Vec<u32>,
+    pub info: Vec<WasmInfoColumn>,
+    pub genotypes: Option<WasmGenotypeMatrix>,
+}
+
+impl From<engine::variant::VariantBatch> for WasmVariantBatch {
+    fn from(b: engine::variant::VariantBatch) -> Self {
+        Self {
+            count: b.count,
+            format: b.format.as_str().to_owned(),
+            chrom_data: b.chrom_data,
+            chrom_offsets: b.chrom_offsets,
+            positions: to_f64(b.positions),
+            id_data: b.id_data,
+            id_offsets: b.id_offsets,
+            ref_data: b.ref_data,
+            ref_offsets: b.ref_offsets,
+            alt_data: b.alt_data,
+            alt_offsets: b.alt_offsets,
+            qualities: b.qualities,
+            filter_data: b.filter_data,
+            filter_offsets: b.filter_offsets,
+            info: b.info.into_iter().map(Into::into).collect(),
+            genotypes: b.genotypes.map(Into::into),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WasmVariantBatch {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmVariantBatch {
+        Self::default()
+    }
+}
+
+impl TryFrom<&WasmVariantBatch> for engine::variant::VariantBatch {
+    type Error = JsError;
+
+    fn try_from(b: &WasmVariantBatch) -> Result<Self, JsError> {
+        Ok(Self {
+            count: b.count,
+            format: match b.format.as_str() {
+                "BCF" => engine::RecordFormat::Bcf,
+                _ => engine::RecordFormat::Vcf,
+            },
+            chrom_data: b.chrom_data.clone(),
+            chrom_offsets: b.chrom_offsets.clone(),
+            positions: to_u64(&b.positions, "positions")
+                .map_err(|_| JsError::new("VcfWriter: positions must be non-negative integers"))?,
+            id_data: b.id_data.clone(),
+            id_offsets: b.id_offsets.clone(),
+            ref_data: b.ref_data.clone(),
+            ref_offsets: b.ref_offsets.clone(),
+            alt_data: b.alt_data.clone(),
+            alt_offsets: b.alt_offsets.clone(),
+            qualities: b.qualities.clone(),
+            filter_data: b.filter_data.clone(),
+            filter_offsets: b.filter_offsets.clone(),
+            info: b
+                .info
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            genotypes: b
+                .genotypes
+                .as_ref()
+                .map(|m| engine::variant::GenotypeMatrix {
+                    sample_count: m.sample_count,
+                    allele_offsets: m.allele_offsets.clone(),
+                    alleles: m.alleles.clone(),
+                    phased: m.phased.clone(),
+                }),
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmVariantReader {
+    inner: engine::variant::VariantReader,
+}
+
+#[wasm_bindgen]
+impl WasmVariantReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8]) -> Result<WasmVariantReader, JsValue> {
+        let inner =
+            engine::variant::VariantReader::open_from_bytes(data.to_vec()).map_err(reader_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn indexed(data: &[u8], index: &[u8]) -> Result<WasmVariantReader, JsValue> {
+        let inner = engine::variant::VariantReader::open_indexed_from_bytes(data.to_vec(), index)
+            .map_err(reader_err)?;
+        Ok(Self { inner })
+    }
+
+    pub fn query(&mut self, region: &str) -> Result<(), JsError> {
+        self.inner.query(region).map_err(engine_err)
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn set_info_fields(&mut self, keys: Vec<String>) -> Result<(), JsError> {
+        self.inner.set_info_fields(&keys).map_err(engine_err)
+    }
+
+    pub fn set_genotypes(&mut self, enabled: bool) {
+        self.inner.set_genotypes(enabled);
+    }
+
+    pub fn sample_names(&self) -> Vec<String> {
+        self.inner.sample_names()
+    }
+
+    pub fn header_text(&self) -> String {
+        self.inner.header_text()
+    }
+
+    pub fn read_batch(&mut self, max_records: u32) -> Result<Option<WasmVariantBatch>, JsValue> {
+        self.inner
+            .read_batch(max_records)
+            .map(|opt| opt.map(Into::into))
+            .map_err(reader_err)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn records_read(&self) -> f64 {
+        self.inner.records_read() as f64
+    }
+
+    pub fn bytes_consumed(&self) -> WasmBytesConsumed {
+        self.inner.bytes_consumed().into()
+    }
+}
+
+#[wasm_bindgen]
+pub struct WasmVcfWriter {
+    inner: Option<engine::variant::VcfWriter>,
+}
+
+#[wasm_bindgen]
+impl WasmVcfWriter {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        header: &str,
+        compression: &str,
+        level: Option<i32>,
+    ) -> Result<WasmVcfWriter, JsError> {
+        let inner = engine::variant::VcfWriter::open_to_bytes(
+            header,
+            parse_compression_mode(compression, level)?,
+        )
+        .map_err(engine_err)?;
+        Ok(Self { inner: Some(inner) })
+    }
+
+    pub fn write_batch(&mut self, batch: &WasmVariantBatch) -> Result<(), JsError> {
+        let w = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        let batch = engine::variant::VariantBatch::try_from(batch)?;
+        w.write_batch(&batch).map_err(engine_err)
+    }
+
+    pub fn finish(&mut self) -> Result<Option<Vec<u8>>, JsError> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        w.finish().map_err(engine_err)
+    }
+
+    pub fn finish_with_index(&mut self) -> Result<WasmVcfWriterOutput, JsError> {
+        let w = self
+            .inner
+            .take()
+            .ok_or_else(|| JsError::new("writer already finished"))?;
+        let output = w.finish_with_index().map_err(engine_err)?;
+        Ok(WasmVcfWriterOutput {
+            data: output.data,
+            tbi: output.tbi,
+        })
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmVcfWriterOutput {
+    pub data: Option<Vec<u8>>,
+    pub tbi: Option<Vec<u8>>,
+}
+
+impl From<&WasmFastqBatch> for engine::fastq::FastqBatch {
+    fn from(b: &WasmFastqBatch) -> Self {
+        Self {
+            count: b.count,
+            name_data: b.name_data.clone(),
+            name_offsets: b.name_offsets.clone(),
+            description_data: b.description_data.clone(),
+            description_offsets: b.description_offsets.clone(),
+            sequence_data: b.sequence_data.clone(),
+            sequence_offsets: b.sequence_offsets.clone(),
+            quality_data: b.quality_data.clone(),
+            quality_offsets: b.quality_offsets.clone(),
+        }
+    }
+}
+
+impl From<&WasmFastaBatch> for engine::fasta::FastaBatch {
+    fn from(b: &WasmFastaBatch) -> Self {
+        Self {
+            count: b.count,
+            name_data: b.name_data.clone(),
+            name_offsets: b.name_offsets.clone(),
+            description_data: b.description_data.clone(),
+            description_offsets: b.description_offsets.clone(),
+            sequence_data: b.sequence_data.clone(),
+            sequence_offsets: b.sequence_offsets.clone(),
+        }
+    }
+}
+
+impl TryFrom<&WasmAuxTagColumn> for engine::alignment::AuxTagColumn {
+    type Error = JsError;
+
+    fn try_from(c: &WasmAuxTagColumn) -> Result<Self, JsError> {
+        let kind = match c.kind.as_str() {
+            "int" => engine::alignment::AuxTagKind::Int,
+            "float" => engine::alignment::AuxTagKind::Float,
+            "string" => engine::alignment::AuxTagKind::String,
+            "int_array" => engine::alignment::AuxTagKind::IntArray,
+            "float_array" => engine::alignment::AuxTagKind::FloatArray,
+            other => return Err(JsError::new(&format!("unknown tag kind: {other}"))),
+        };
+        Ok(Self {
+            tag: c.tag.clone(),
+            kind,
+            present: c.present.clone(),
+            int_values: c.int_values.clone(),
+            float_values: c.float_values.clone(),
+            string_data: c.string_data.clone(),
+            string_offsets: c.string_offsets.clone(),
+            array_offsets: c.array_offsets.clone(),
+        })
+    }
+}
+
+impl TryFrom<&WasmAlignmentBatch> for engine::alignment::AlignmentBatch {
+    type Error = JsError;
+
+    fn try_from(b: &WasmAlignmentBatch) -> Result<Self, JsError> {
+        Ok(Self {
+            count: b.count,
+            format: match b.format.as_str() {
+                "bam" => "bam",
+                "cram" => "cram",
+                _ => "sam",
+            },
+            qname_data: b.qname_data.clone(),
+            qname_offsets: b.qname_offsets.clone(),
+            sequence_data: b.sequence_data.clone(),
+            sequence_offsets: b.sequence_offsets.clone(),
+            quality_data: b.quality_data.clone(),
+            quality_offsets: b.quality_offsets.clone(),
+            cigar_data: b.cigar_data.clone(),
+            cigar_offsets: b.cigar_offsets.clone(),
+            rname_data: b.rname_data.clone(),
+            rname_offsets: b.rname_offsets.clone(),
+            rnext_data: b.rnext_data.clone(),
+            rnext_offsets: b.rnext_offsets.clone(),
+            flags: b.flags.clone(),
+            positions: b.positions.clone(),
+            mapping_qualities: b.mapping_qualities.clone(),
+            next_positions: b.next_positions.clone(),
+            template_lengths: b.template_lengths.clone(),
+            tags: b
+                .tags
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            raw_tag_data: b.raw_tag_data.clone(),
+            raw_tag_offsets: b.raw_tag_offsets.clone(),
+        })
+    }
+}
+
+impl From<SequenceMetricsResult> for engine::metrics::SequenceMetricsResult {
+    fn from(r: SequenceMetricsResult) -> Self {
+        Self {
+            lengths: r.lengths,
+            gc: r.gc,
+            at: r.at,
+            gc_skew: r.gc_skew,
+            at_skew: r.at_skew,
+            entropy: r.entropy,
+            alphabet_mask: r.alphabet_mask,
+            avg_qual: r.avg_qual,
+            min_qual: r.min_qual,
+            max_qual: r.max_qual,
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+fn arrow_ipc(
+    mut table: engine::arrow::ArrowTable,
+    format: &str,
+    metrics: Option<SequenceMetricsResult>,
+) -> Result<Vec<u8>, JsError> {
+    let format = match format {
+        "stream" => engine::arrow::IpcFormat::Stream,
+        "file" => engine::arrow::IpcFormat::File,
+        _ => return Err(JsError::new(&format!("unknown IPC format: {format}"))),
+    };
+    if let Some(metrics) = metrics {
+        table.append_metrics(metrics.into()).map_err(engine_err)?;
+    }
+    table.to_ipc(format).map_err(engine_err)
+}
+
+#[cfg(feature = "arrow")]
+#[wasm_bindgen]
+pub fn fastq_batch_to_arrow(
+    batch: &WasmFastqBatch,
+    format: &str,
+    metrics: Option<SequenceMetricsResult>,
+) -> Result<Vec<u8>, JsError> {
+    let table = engine::arrow::ArrowTable::from_fastq(batch.into()).map_err(engine_err)?;
+    arrow_ipc(table, format, metrics)
+}
+
+#[cfg(feature = "arrow")]
+#[wasm_bindgen]
+pub fn fasta_batch_to_arrow(
+    batch: &WasmFastaBatch,
+    format: &str,
+    metrics: Option<SequenceMetricsResult>,
+) -> Result<Vec<u8>, JsError> {
+    let table = engine::arrow::ArrowTable::from_fasta(batch.into()).map_err(engine_err)?;
+    arrow_ipc(table, format, metrics)
+}
+
+#[cfg(feature = "arrow")]
+#[wasm_bindgen]
+pub fn alignment_batch_to_arrow(
+    batch: &WasmAlignmentBatch,
+    format: &str,
+    metrics: Option<SequenceMetricsResult>,
+) -> Result<Vec<u8>, JsError> {
+    let table = engine::arrow::ArrowTable::from_alignment(batch.try_into()?).map_err(engine_err)?;
+    arrow_ipc(table, format, metrics)
+}
+
+fn parse_compression_mode(
+    mode: &str,
+    level: Option<i32>,
+) -> Result<engine::CompressionMode, JsError> {
+    match mode {
+        "none" => Ok(engine::CompressionMode::None),
+        "gzip" => Ok(engine::CompressionMode::Gzip),
+        "bgzf" => Ok(engine::CompressionMode::Bgzf),
+        "zstd" => Ok(engine::CompressionMode::Zstd(level.unwrap_or(0))),
+        "parallel-gzip" => Ok(engine::CompressionMode::ParallelGzip(level.unwrap_or(6))),
+        "parallel-bgzf" => Ok(engine::CompressionMode::ParallelBgzf(level.unwrap_or(6))),
+        _ => Err(JsError::new(&format!("unknown compression mode: {mode}"))),
+    }
 }
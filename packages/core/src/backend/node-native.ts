@@ -195,9 +195,13 @@ interface NativeAlignmentBatch {
   cigarOffsets: number[];
   rnameData: Buffer;
   rnameOffsets: number[];
+  rnextData: Buffer;
+  rnextOffsets: number[];
   flags: number[];
   positions: number[];
   mappingQualities: Buffer;
+  nextPositions: number[];
+  templateLengths: number[];
 }
 
 interface NativeAlignmentReader {
@@ -257,10 +261,18 @@ interface NativeFastaModule {
   };
 }
 
+type NativeCompressionMode =
+  | "none"
+  | "gzip"
+  | "bgzf"
+  | "zstd"
+  | "parallel-gzip"
+  | "parallel-bgzf";
+
 interface NativeFastqWriterModule {
   FastqWriter: {
-    open(path: string, compress: boolean): NativeFastqWriter;
-    openBytes(compress: boolean): NativeFastqWriter;
+    open(path: string, compression: NativeCompressionMode): NativeFastqWriter;
+    openBytes(compression: NativeCompressionMode): NativeFastqWriter;
   };
 }
 
@@ -303,8 +315,8 @@ interface NativeFastqSequenceSorterModule {
 
 interface NativeFastaWriterModule {
   FastaWriter: {
-    open(path: string, compress: boolean, lineWidth: number): NativeFastaWriter;
-    openBytes(compress: boolean, lineWidth: number): NativeFastaWriter;
+    open(path: string, compression: NativeCompressionMode, lineWidth: number): NativeFastaWriter;
+    openBytes(compression: NativeCompressionMode, lineWidth: number): NativeFastaWriter;
   };
 }
 
@@ -389,9 +401,13 @@ function wrapAlignmentReader(reader: NativeAlignmentReader): AlignmentReaderHand
         cigarOffsets: Uint32Array.from(batch.cigarOffsets),
         rnameData: batch.rnameData,
         rnameOffsets: Uint32Array.from(batch.rnameOffsets),
+        rnextData: batch.rnextData,
+        rnextOffsets: Uint32Array.from(batch.rnextOffsets),
         flags: Uint16Array.from(batch.flags),
         positions: Int32Array.from(batch.positions),
         mappingQualities: batch.mappingQualities,
+        nextPositions: Int32Array.from(batch.nextPositions),
+        templateLengths: Int32Array.from(batch.templateLengths),
       };
     },
     async headerText(): Promise<string> {
@@ -766,8 +782,12 @@ function buildFromKernel(
         : Effect.try({
             try: () =>
               path !== null
-                ? wrapFastqWriter(fastqWriterMod.FastqWriter.open(path, compress))
-                : wrapFastqWriter(fastqWriterMod.FastqWriter.openBytes(compress)),
+                ? wrapFastqWriter(
+                    fastqWriterMod.FastqWriter.open(path, compress ? "gzip" : "none")
+                  )
+                : wrapFastqWriter(
+                    fastqWriterMod.FastqWriter.openBytes(compress ? "gzip" : "none")
+                  ),
             catch: (e) => classifyEngineError("createFastqWriter", e),
           }),
     createFastaWriter: (path, compress, lineWidth) =>
@@ -776,8 +796,12 @@ function buildFromKernel(
         : Effect.try({
             try: () =>
               path !== null
-                ? wrapFastaWriter(fastaWriterMod.FastaWriter.open(path, compress, lineWidth))
-                : wrapFastaWriter(fastaWriterMod.FastaWriter.openBytes(compress, lineWidth)),
+                ? wrapFastaWriter(
+                    fastaWriterMod.FastaWriter.open(path, compress ? "gzip" : "none", lineWidth)
+                  )
+                : wrapFastaWriter(
+                    fastaWriterMod.FastaWriter.openBytes(compress ? "gzip" : "none", lineWidth)
+                  ),
             catch: (e) => classifyEngineError("createFastaWriter", e),
           }),
     createFastqSequenceSorter: (options: FastqSequenceSortOptions) =>
@@ -53,9 +53,13 @@ export interface AlignmentBatch {
   cigarOffsets: Uint32Array;
   rnameData: Uint8Array;
   rnameOffsets: Uint32Array;
+  rnextData: Uint8Array;
+  rnextOffsets: Uint32Array;
   flags: Uint16Array;
   positions: Int32Array;
   mappingQualities: Uint8Array;
+  nextPositions: Int32Array;
+  templateLengths: Int32Array;
 }
 
 /**
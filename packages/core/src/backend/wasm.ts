@@ -364,9 +364,13 @@ function buildFromModule(wasm: WasmModule) {
                 cigarOffsets: batch.cigar_offsets,
                 rnameData: batch.rname_data,
                 rnameOffsets: batch.rname_offsets,
+                rnextData: batch.rnext_data,
+                rnextOffsets: batch.rnext_offsets,
                 flags: batch.flags,
                 positions: batch.positions,
                 mappingQualities: batch.mapping_qualities,
+                nextPositions: batch.next_positions,
+                templateLengths: batch.template_lengths,
               };
               batch.free();
               return result;
@@ -474,7 +478,7 @@ function buildFromModule(wasm: WasmModule) {
     createFastqWriter: (_path, compress) =>
       Effect.try({
         try: (): FastqWriterHandle => {
-          const writer = new wasm.WasmFastqWriter(compress);
+          const writer = new wasm.WasmFastqWriter(compress ? "gzip" : "none");
           return {
             async writeBatch(batch: FastqBatch) {
               writer.write_batch(
@@ -504,7 +508,7 @@ function buildFromModule(wasm: WasmModule) {
     createFastaWriter: (_path, compress, lineWidth) =>
       Effect.try({
         try: (): FastaWriterHandle => {
-          const writer = new wasm.WasmFastaWriter(compress, lineWidth);
+          const writer = new wasm.WasmFastaWriter(compress ? "gzip" : "none", lineWidth);
           return {
             async writeBatch(batch: FastaBatch) {
               writer.write_batch(
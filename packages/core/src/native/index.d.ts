@@ -3,28 +3,101 @@
 export declare class AlignmentReader {
   static open(path: string): AlignmentReader
   static openBytes(data: Buffer): AlignmentReader
+  static openAt(path: string, checkpoint: Buffer): AlignmentReader
+  static openBytesAt(data: Buffer, checkpoint: Buffer): AlignmentReader
+  static openCram(path: string, referencePath: string): AlignmentReader
+  static openCramBytes(data: Buffer, reference: Buffer, referenceIndex: Buffer): AlignmentReader
+  static openIndexed(path: string, indexPath: string): AlignmentReader
+  static openIndexedBytes(data: Buffer, index: Buffer): AlignmentReader
+  query(region: string): void
+  setTagOptions(tags: Array<string>, raw: boolean): void
   readBatch(maxRecords: number): AlignmentBatch | null
+  readBatchBounded(maxRecords: number, maxBytes: number): AlignmentBatch | null
+  recordsRead(): number
+  bytesConsumed(): BytesConsumed
+  checkpoint(): Buffer
   headerText(): string
   referenceSequences(): Array<ReferenceSequenceInfo>
 }
 
+export declare class AlignmentWriter {
+  static open(path: string, headerText: string, format: AlignmentFormat): AlignmentWriter
+  static openBytes(headerText: string, format: AlignmentFormat): AlignmentWriter
+  writeBatch(qnameData: Uint8Array, qnameOffsets: Uint32Array, sequenceData: Uint8Array, sequenceOffsets: Uint32Array, qualityData: Uint8Array, qualityOffsets: Uint32Array, cigarData: Uint8Array, cigarOffsets: Uint32Array, rnameData: Uint8Array, rnameOffsets: Uint32Array, rnextData: Uint8Array, rnextOffsets: Uint32Array, flags: Array<number>, positions: Array<number>, mappingQualities: Uint8Array, nextPositions: Array<number>, templateLengths: Array<number>, rawTagData: Uint8Array, rawTagOffsets: Uint32Array, count: number): void
+  finish(): Buffer | null
+}
+
+export declare class BedReader {
+  static open(path: string): BedReader
+  static openBytes(data: Buffer): BedReader
+  readBatch(maxRecords: number): BedBatch | null
+  recordsRead(): number
+  bytesConsumed(): BytesConsumed
+}
+
+export declare class BedWriter {
+  static open(path: string, compression: CompressionMode, level?: number | undefined | null): BedWriter
+  static openBytes(compression: CompressionMode, level?: number | undefined | null): BedWriter
+  writeBatch(batch: BedBatch): void
+  finish(): Buffer | null
+}
+
+export declare class FastaChunkReader {
+  static create(): FastaChunkReader
+  pushChunk(chunk: Uint8Array): FastaBatch | null
+  end(): FastaBatch | null
+}
+
+export declare class FastaIndex {
+  static build(path: string): FastaIndex
+  static buildBytes(data: Buffer): FastaIndex
+  static load(path: string, indexPath: string): FastaIndex
+  static loadBytes(data: Buffer, index: Buffer): FastaIndex
+  entries(): Array<FastaIndexEntry>
+  indexBytes(): Buffer
+  writeIndex(path: string): void
+  fetch(regions: Array<string>, reverseComplement: boolean): FastaBatch
+}
+
 export declare class FastaReader {
   static open(path: string): FastaReader
   static openBytes(data: Buffer): FastaReader
+  static openAt(path: string, checkpoint: Buffer): FastaReader
+  static openBytesAt(data: Buffer, checkpoint: Buffer): FastaReader
   readBatch(maxRecords: number): FastaBatch | null
+  readBatchBounded(maxRecords: number, maxBytes: number): FastaBatch | null
+  readSequenceChunks(maxChunks: number, chunkSize: number): FastaSequenceChunkBatch | null
+  recordsRead(): number
+  bytesConsumed(): BytesConsumed
+  checkpoint(): Buffer
 }
 
 export declare class FastaWriter {
-  static open(path: string, compress: boolean, lineWidth: number): FastaWriter
-  static openBytes(compress: boolean, lineWidth: number): FastaWriter
+  static open(path: string, compression: CompressionMode, lineWidth: number, level?: number | undefined | null): FastaWriter
+  static openBytes(compression: CompressionMode, lineWidth: number, level?: number | undefined | null): FastaWriter
   writeBatch(nameData: Uint8Array, nameOffsets: Uint32Array, descriptionData: Uint8Array, descriptionOffsets: Uint32Array, sequenceData: Uint8Array, sequenceOffsets: Uint32Array, count: number): void
   finish(): Buffer | null
+  finishWithSidecars(): FastaWriterOutput
+}
+
+export declare class FastqChunkReader {
+  static create(): FastqChunkReader
+  pushChunk(chunk: Uint8Array): FastqBatch | null
+  end(): FastqBatch | null
 }
 
 export declare class FastqReader {
   static open(path: string): FastqReader
   static openBytes(data: Buffer): FastqReader
+  static openAt(path: string, checkpoint: Buffer): FastqReader
+  static openBytesAt(data: Buffer, checkpoint: Buffer): FastqReader
+  static openThreaded(path: string, threads: number): FastqReader
+  static openBytesThreaded(data: Buffer, threads: number): FastqReader
   readBatch(maxRecords: number): FastqBatch | null
+  readBatchBounded(maxRecords: number, maxBytes: number): FastqBatch | null
+  recordsRead(): number
+  bytesConsumed(): BytesConsumed
+  checkpoint(): Buffer
 }
 
 export declare class FastqSequenceSorter {
@@ -35,12 +108,87 @@ export declare class FastqSequenceSorter {
 }
 
 export declare class FastqWriter {
-  static open(path: string, compress: boolean): FastqWriter
-  static openBytes(compress: boolean): FastqWriter
+  static open(path: string, compression: CompressionMode, level?: number | undefined | null): FastqWriter
+  static openBytes(compression: CompressionMode, level?: number | undefined | null): FastqWriter
   writeBatch(nameData: Uint8Array, nameOffsets: Uint32Array, descriptionData: Uint8Array, descriptionOffsets: Uint32Array, sequenceData: Uint8Array, sequenceOffsets: Uint32Array, qualityData: Uint8Array, qualityOffsets: Uint32Array, count: number): void
   finish(): Buffer | null
 }
 
+export declare class GtfReader {
+  static open(path: string): GtfReader
+  static openBytes(data: Buffer): GtfReader
+  setAttributeKeys(keys: Array<string>): void
+  readBatch(maxRecords: number): GtfBatch | null
+  recordsRead(): number
+  bytesConsumed(): BytesConsumed
+}
+
+export declare class MappedFastaReader {
+  static open(path: string): MappedFastaReader
+  readBatch(maxRecords: number): FastaBatch | null
+  recordsRead(): number
+  bytesConsumed(): number
+}
+
+export declare class MappedFastqReader {
+  static open(path: string): MappedFastqReader
+  readBatch(maxRecords: number): FastqBatch | null
+  grepNextBatch(maxRecords: number, pattern: Uint8Array, maxEdits: number, caseInsensitive: boolean, searchBothStrands: boolean): Buffer | null
+  hashNextBatch(maxRecords: number, caseInsensitive: boolean): Buffer | null
+  sequenceMetricsNextBatch(maxRecords: number, metricFlags: number, asciiOffset: number): SequenceMetricsResult | null
+  recordsRead(): number
+  bytesConsumed(): number
+}
+
+export declare class PairedFastqReader {
+  static open(r1Path: string, r2Path: string): PairedFastqReader
+  static openBytes(r1: Buffer, r2: Buffer): PairedFastqReader
+  static openInterleaved(path: string): PairedFastqReader
+  static openInterleavedBytes(data: Buffer): PairedFastqReader
+  readBatch(maxPairs: number): PairedFastqBatch | null
+}
+
+export declare class PrefetchingAlignmentReader {
+  static open(path: string, maxRecords: number, depth: number): PrefetchingAlignmentReader
+  static openBytes(data: Buffer, maxRecords: number, depth: number): PrefetchingAlignmentReader
+  readBatch(): AlignmentBatch | null
+}
+
+export declare class PrefetchingFastaReader {
+  static open(path: string, maxRecords: number, depth: number): PrefetchingFastaReader
+  static openBytes(data: Buffer, maxRecords: number, depth: number): PrefetchingFastaReader
+  readBatch(): FastaBatch | null
+}
+
+export declare class PrefetchingFastqReader {
+  static open(path: string, maxRecords: number, depth: number): PrefetchingFastqReader
+  static openBytes(data: Buffer, maxRecords: number, depth: number): PrefetchingFastqReader
+  readBatch(): FastqBatch | null
+}
+
+export declare class VariantReader {
+  static open(path: string): VariantReader
+  static openBytes(data: Buffer): VariantReader
+  static openIndexed(path: string, indexPath: string): VariantReader
+  static openIndexedBytes(data: Buffer, index: Buffer): VariantReader
+  query(region: string): void
+  setInfoFields(keys: Array<string>): void
+  setGenotypes(enabled: boolean): void
+  sampleNames(): Array<string>
+  headerText(): string
+  readBatch(maxRecords: number): VariantBatch | null
+  recordsRead(): number
+  bytesConsumed(): BytesConsumed
+}
+
+export declare class VcfWriter {
+  static open(path: string, header: string, compression: CompressionMode, level?: number | undefined | null): VcfWriter
+  static openBytes(header: string, compression: CompressionMode, level?: number | undefined | null): VcfWriter
+  writeBatch(batch: VariantBatch): void
+  finish(): Buffer | null
+  finishWithIndex(): VcfWriterOutput
+}
+
 export interface AlignmentBatch {
   count: number
   format: string
@@ -54,9 +202,71 @@ export interface AlignmentBatch {
   cigarOffsets: Array<number>
   rnameData: Buffer
   rnameOffsets: Array<number>
+  rnextData: Buffer
+  rnextOffsets: Array<number>
   flags: Array<number>
   positions: Array<number>
   mappingQualities: Buffer
+  nextPositions: Array<number>
+  templateLengths: Array<number>
+  tags: Array<AuxTagColumn>
+  rawTagData: Buffer
+  rawTagOffsets: Array<number>
+}
+
+export declare function alignmentBatchToArrow(batch: AlignmentBatch, format: ArrowIpcFormat, metrics?: SequenceMetricsResult | undefined | null): Buffer
+
+export declare const enum AlignmentFormat {
+  Bam = 'bam',
+  Sam = 'sam'
+}
+
+export declare const enum ArrowIpcFormat {
+  Stream = 'stream',
+  File = 'file'
+}
+
+export interface AuxTagColumn {
+  tag: string
+  kind: AuxTagKind
+  present: Buffer
+  intValues: Array<number>
+  floatValues: Array<number>
+  stringData: Buffer
+  stringOffsets: Array<number>
+  arrayOffsets: Array<number>
+}
+
+export declare const enum AuxTagKind {
+  Int = 'int',
+  Float = 'float',
+  String = 'string',
+  IntArray = 'int_array',
+  FloatArray = 'float_array'
+}
+
+export interface BedBatch {
+  count: number
+  fieldCounts: Buffer
+  chromData: Buffer
+  chromOffsets: Array<number>
+  starts: Array<number>
+  ends: Array<number>
+  nameData?: Buffer
+  nameOffsets?: Array<number>
+  scores?: Array<number>
+  strands?: Buffer
+  thickStarts?: Array<number>
+  thickEnds?: Array<number>
+  itemRgbs?: Array<number>
+  blockOffsets?: Array<number>
+  blockSizes?: Array<number>
+  blockStarts?: Array<number>
+}
+
+export interface BytesConsumed {
+  compressed: number
+  uncompressed: number
 }
 
 export declare function checkValidBatch(sequences: Uint8Array, offsets: Uint32Array, mode: ValidationMode): Buffer
@@ -67,6 +277,19 @@ export interface ClassifyResult {
   counts: Array<number>
 }
 
+export declare const enum CompressionMode {
+  None = 'none',
+  Gzip = 'gzip',
+  Bgzf = 'bgzf',
+  Zstd = 'zstd',
+  ParallelGzip = 'parallel-gzip',
+  ParallelBgzf = 'parallel-bgzf'
+}
+
+export declare function detectBytes(data: Uint8Array): FormatDetection
+
+export declare function detectPath(path: string): FormatDetection
+
 export interface FastaBatch {
   count: number
   nameData: Buffer
@@ -77,6 +300,33 @@ export interface FastaBatch {
   sequenceOffsets: Array<number>
 }
 
+export declare function fastaBatchToArrow(batch: FastaBatch, format: ArrowIpcFormat, metrics?: SequenceMetricsResult | undefined | null): Buffer
+
+export interface FastaIndexEntry {
+  name: string
+  length: number
+  offset: number
+  lineBases: number
+  lineWidth: number
+}
+
+export interface FastaSequenceChunkBatch {
+  count: number
+  recordIndices: Array<number>
+  chunkStarts: Array<number>
+  isLast: Buffer
+  nameData: Buffer
+  nameOffsets: Array<number>
+  sequenceData: Buffer
+  sequenceOffsets: Array<number>
+}
+
+export interface FastaWriterOutput {
+  data?: Buffer
+  fai?: Buffer
+  gzi?: Buffer
+}
+
 export interface FastqBatch {
   count: number
   nameData: Buffer
@@ -89,14 +339,79 @@ export interface FastqBatch {
   qualityOffsets: Array<number>
 }
 
+export declare function fastqBatchToArrow(batch: FastqBatch, format: ArrowIpcFormat, metrics?: SequenceMetricsResult | undefined | null): Buffer
+
 export declare function findPatternBatch(sequences: Uint8Array, offsets: Uint32Array, pattern: Uint8Array, maxEdits: number, caseInsensitive: boolean): PatternSearchResult
 
+export interface FormatDetection {
+  compression: string
+  format?: string
+  alphabet?: string
+  qualityEncoding?: string
+}
+
+export interface GenotypeMatrix {
+  sampleCount: number
+  alleleOffsets: Array<number>
+  alleles: Array<number>
+  phased: Buffer
+}
+
 export declare function grepBatch(sequences: Uint8Array, offsets: Uint32Array, pattern: Uint8Array, maxEdits: number, caseInsensitive: boolean, searchBothStrands: boolean): Buffer
 
+export interface GtfAttributeColumn {
+  key: string
+  present: Buffer
+  valueData: Buffer
+  valueOffsets: Array<number>
+}
+
+export interface GtfBatch {
+  count: number
+  format: string
+  seqidData: Buffer
+  seqidOffsets: Array<number>
+  sourceData: Buffer
+  sourceOffsets: Array<number>
+  typeData: Buffer
+  typeOffsets: Array<number>
+  starts: Array<number>
+  ends: Array<number>
+  scores: Array<number>
+  strands: Buffer
+  phases: Buffer
+  attributes: Array<GtfAttributeColumn>
+}
+
 export declare function hashBatch(sequences: Uint8Array, offsets: Uint32Array, caseInsensitive: boolean): Buffer
 
+export interface InfoColumn {
+  key: string
+  kind: InfoKind
+  present: Buffer
+  offsets: Array<number>
+  intValues: Array<number>
+  floatValues: Array<number>
+  stringData: Buffer
+}
+
+export declare const enum InfoKind {
+  Int = 'int',
+  Float = 'float',
+  Flag = 'flag',
+  String = 'string'
+}
+
 export declare function mergePairedReadsBatch(pairIds: Uint8Array, pairIdOffsets: Uint32Array, r1Sequences: Uint8Array, r1SequenceOffsets: Uint32Array, r1Quality: Uint8Array, r1QualityOffsets: Uint32Array, r2Sequences: Uint8Array, r2SequenceOffsets: Uint32Array, r2Quality: Uint8Array, r2QualityOffsets: Uint32Array, options: PairedReadMergeOptions): PairedReadMergeResult
 
+export interface PairedFastqBatch {
+  count: number
+  pairIdData: Buffer
+  pairIdOffsets: Array<number>
+  r1: FastqBatch
+  r2: FastqBatch
+}
+
 export interface PairedReadMergeOptions {
   overlapDiffMax: number
   minOverlap: number
@@ -140,6 +455,15 @@ export declare const enum PairedReadValidationPreset {
   Strict = 'Strict'
 }
 
+export interface ParseErrorDetails {
+  format: string
+  kind: string
+  record: number
+  byteOffset?: number
+  line?: number
+  field?: string
+}
+
 export interface PatternSearchResult {
   starts: Array<number>
   ends: Array<number>
@@ -218,3 +542,27 @@ export declare const enum ValidationMode {
   NormalRna = 'NormalRna',
   Protein = 'Protein'
 }
+
+export interface VariantBatch {
+  count: number
+  format: string
+  chromData: Buffer
+  chromOffsets: Array<number>
+  positions: Array<number>
+  idData: Buffer
+  idOffsets: Array<number>
+  refData: Buffer
+  refOffsets: Array<number>
+  altData: Buffer
+  altOffsets: Array<number>
+  qualities: Array<number>
+  filterData: Buffer
+  filterOffsets: Array<number>
+  info: Array<InfoColumn>
+  genotypes?: GenotypeMatrix
+}
+
+export interface VcfWriterOutput {
+  data?: Buffer
+  tbi?: Buffer
+}